@@ -2,12 +2,11 @@
 
 use std::{
     collections::{BTreeMap, BTreeSet},
-    io::Read,
     ops::Not,
 };
 
 use anyhow::Result;
-use log::{debug, error, warn};
+use log::{debug, error};
 use serde_json::json;
 
 use lancelot::{
@@ -17,7 +16,7 @@ use lancelot::{
     util,
     workspace::{
         config::{empty, Configuration},
-        workspace_from_bytes,
+        workspace_from_bytes, workspaces_from_archive,
     },
     RVA, VA,
 };
@@ -427,37 +426,18 @@ fn _main() -> Result<()> {
 
         println!("# triplet,compiler,library,version,profile,path,function,type,value");
         output_functions_features(&build, "/", &features)?;
-    } else if buf.starts_with(b"!<arch>\n") {
+    } else if lancelot::loader::ar::is_archive(&buf) {
         // archive file
 
         println!("# triplet,compiler,library,version,profile,path,function,type,value");
-        let mut ar = ar::Archive::new(buf.as_slice());
-        while let Some(entry_result) = ar.next_entry() {
-            let mut entry = match entry_result {
-                Ok(entry) => entry,
-                Err(e) => {
-                    warn!("failed to read archive entry: {:?}", e);
-                    continue;
-                }
-            };
-
-            let Ok(path) = String::from_utf8(entry.header().identifier().to_vec()) else {
-                continue;
-            };
-
+        for (path, ws) in workspaces_from_archive(config, &buf)? {
             // fix paths to use forward slashes
             let path = path.replace('\\', "/");
             debug!("ar: path: {}", path);
 
-            let mut sbuf = Vec::with_capacity(entry.header().size() as usize);
-            entry.read_to_end(&mut sbuf)?;
-
-            let features = match extract_buf_features(config.clone(), &sbuf) {
+            let features = match ws.and_then(|ws| extract_workspace_features(&*ws)) {
                 Ok(features) => features,
                 Err(e) => {
-                    // MS may embed some files with COFF magic/machine == 00 00
-                    // such as in libcmt.lib.
-                    // object::coff doesn't parse these, so we skip them.
                     debug!("failed to extract features: {}: {}", path, e);
                     continue;
                 }
@@ -507,22 +487,13 @@ mod tests {
     }
 
     fn ar_first_entry(buf: &[u8]) -> Result<Vec<u8>> {
-        let mut ar = ar::Archive::new(buf);
-        loop {
-            let mut entry = ar.next_entry().unwrap().unwrap();
-
-            let path = String::from_utf8(entry.header().identifier().to_vec()).unwrap();
-            let path = path.replace('\\', "/");
-            debug!("ar: path: {}", path);
+        let member = lancelot::loader::ar::read_archive(buf)?
+            .into_iter()
+            .find(|member| member.kind == lancelot::loader::ar::MemberKind::Coff)
+            .unwrap();
+        debug!("ar: path: {}", member.name.replace('\\', "/"));
 
-            let mut sbuf = Vec::with_capacity(entry.header().size() as usize);
-            entry.read_to_end(&mut sbuf)?;
-
-            if &sbuf[..2] == b"\x00\x00" {
-                continue;
-            }
-            return Ok(sbuf);
-        }
+        Ok(member.buf.to_vec())
     }
 
     #[test]
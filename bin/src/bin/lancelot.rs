@@ -4,10 +4,15 @@ use anyhow::{anyhow, Result};
 use log::{debug, error, info};
 
 use lancelot::{
-    analysis::dis,
+    analysis::{dis, fingerprints::Fingerprints},
+    arch::Arch,
     aspace::AddressSpace,
     util,
-    workspace::{formatter::Formatter, workspace_from_bytes, Workspace},
+    workspace::{
+        config::{self, Configuration},
+        formatter::Formatter,
+        workspace_from_bytes, workspace_from_shellcode, Workspace,
+    },
     RVA, VA,
 };
 
@@ -24,6 +29,17 @@ fn handle_functions(ws: &dyn Workspace) -> Result<()> {
             print!(" thunk")
         }
 
+        if md.flags.intersects(lancelot::workspace::FunctionFlags::TLS_CALLBACK) {
+            print!(" tls_callback")
+        }
+
+        if md
+            .flags
+            .intersects(lancelot::workspace::FunctionFlags::INDIRECT_CALL_TARGET)
+        {
+            print!(" icall_target")
+        }
+
         if let Some(name) = ws.analysis().names.names_by_address.get(va) {
             print!(" {name}");
         }
@@ -65,6 +81,20 @@ fn handle_disassemble(ws: &dyn Workspace, va: VA) -> Result<()> {
     Ok(())
 }
 
+fn handle_fingerprints(buf: &[u8]) -> Result<()> {
+    let fingerprints = Fingerprints::from_bytes(buf)?;
+    for (name, hash) in [
+        ("imphash", &fingerprints.imphash),
+        ("export hash", &fingerprints.export_hash),
+        ("rich hash", &fingerprints.rich_hash),
+    ] {
+        if let Some(hash) = hash {
+            println!("{name}: {hash}");
+        }
+    }
+    Ok(())
+}
+
 fn parse_va(s: &str) -> Result<VA> {
     if s.starts_with("0x") {
         let without_prefix = s.trim_start_matches("0x");
@@ -74,6 +104,48 @@ fn parse_va(s: &str) -> Result<VA> {
     }
 }
 
+/// Load the input as raw code when `--shellcode` is provided,
+/// otherwise detect its format.
+fn load_workspace(
+    config: Box<dyn Configuration>,
+    buf: &[u8],
+    matches: &clap::ArgMatches,
+) -> Result<Box<dyn Workspace>> {
+    let arch = match matches.value_of("shellcode") {
+        None => {
+            let config = match matches.value_of("base") {
+                Some(base) => config::with_base_address(config, parse_va(base)?),
+                None => config,
+            };
+            let config = if matches.is_present("mapped") {
+                config::with_mapped_image(config)
+            } else {
+                config
+            };
+            return workspace_from_bytes(config, buf);
+        }
+        Some("x32") => Arch::X32,
+        Some("x64") => Arch::X64,
+        Some(arch) => return Err(anyhow!("unsupported architecture: {arch}")),
+    };
+
+    let base_address = match matches.value_of("base") {
+        Some(base) => parse_va(base)?,
+        None => 0x0,
+    };
+
+    let entry_points = match matches.values_of("entry") {
+        Some(entries) => entries.map(parse_va).collect::<Result<Vec<RVA>>>()?,
+        None => vec![],
+    };
+
+    debug!(
+        "shellcode: arch: {:?} base: {:#x} entry points: {:#x?}",
+        arch, base_address, entry_points
+    );
+    workspace_from_shellcode(config, buf, arch, base_address, &entry_points)
+}
+
 fn _main() -> Result<()> {
     better_panic::install();
 
@@ -99,6 +171,33 @@ fn _main() -> Result<()> {
                 .takes_value(true)
                 .help("path to configuration directory"),
         )
+        .arg(
+            clap::Arg::new("shellcode")
+                .long("shellcode")
+                .takes_value(true)
+                .possible_values(["x32", "x64"])
+                .help("treat the input as raw code of the given architecture"),
+        )
+        .arg(
+            clap::Arg::new("base")
+                .long("base")
+                .takes_value(true)
+                .help("address at which to load the input (default: preferred base, or 0x0 for raw code)"),
+        )
+        .arg(
+            clap::Arg::new("mapped")
+                .long("mapped")
+                .conflicts_with("shellcode")
+                .help("treat the input as a PE in its memory layout, like one dumped from a process"),
+        )
+        .arg(
+            clap::Arg::new("entry")
+                .long("entry")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .requires("shellcode")
+                .help("offset into the raw code at which a function starts"),
+        )
         .subcommand(
             clap::App::new("functions").about("find functions").arg(
                 clap::Arg::new("input")
@@ -118,6 +217,16 @@ fn _main() -> Result<()> {
                 )
                 .arg(clap::Arg::new("va").required(true).index(2).help("VA of function")),
         )
+        .subcommand(
+            clap::App::new("fingerprints")
+                .about("compute imphash, export hash, and rich hash")
+                .arg(
+                    clap::Arg::new("input")
+                        .required(true)
+                        .index(1)
+                        .help("path to file to analyze"),
+                ),
+        )
         .get_matches();
 
     // --quiet overrides --verbose
@@ -157,7 +266,7 @@ fn _main() -> Result<()> {
     #[cfg(windows)]
     let _ = ansi_term::enable_ansi_support();
 
-    let config = if matches.is_present("configuration") {
+    let config: Box<dyn Configuration> = if matches.is_present("configuration") {
         let path = matches.value_of("configuration").unwrap();
         log::info!("configuration: {}", path);
         Box::new(lancelot::workspace::config::FileSystemConfiguration::from_path(
@@ -168,28 +277,36 @@ fn _main() -> Result<()> {
         lancelot::workspace::config::empty()
     };
 
-    if let Some(matches) = matches.subcommand_matches("functions") {
+    if let Some(submatches) = matches.subcommand_matches("functions") {
         debug!("mode: find functions");
 
-        let filename = matches.value_of("input").unwrap();
+        let filename = submatches.value_of("input").unwrap();
         debug!("input: {}", filename);
 
         let buf = util::read_file(filename)?;
-        let ws = workspace_from_bytes(config, &buf)?;
+        let ws = load_workspace(config, &buf, &matches)?;
 
         handle_functions(&*ws)
-    } else if let Some(matches) = matches.subcommand_matches("disassemble") {
+    } else if let Some(submatches) = matches.subcommand_matches("disassemble") {
         debug!("mode: disassemble");
 
-        let filename = matches.value_of("input").unwrap();
+        let filename = submatches.value_of("input").unwrap();
         debug!("input: {}", filename);
 
-        let va = parse_va(matches.value_of("va").unwrap())?;
+        let va = parse_va(submatches.value_of("va").unwrap())?;
 
         let buf = util::read_file(filename)?;
-        let ws = workspace_from_bytes(config, &buf)?;
+        let ws = load_workspace(config, &buf, &matches)?;
 
         handle_disassemble(&*ws, va)
+    } else if let Some(submatches) = matches.subcommand_matches("fingerprints") {
+        debug!("mode: fingerprints");
+
+        let filename = submatches.value_of("input").unwrap();
+        debug!("input: {}", filename);
+
+        let buf = util::read_file(filename)?;
+        handle_fingerprints(&buf)
     } else {
         Err(anyhow!("SUBCOMMAND required"))
     }
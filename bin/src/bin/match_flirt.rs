@@ -92,7 +92,9 @@ fn _main() -> Result<()> {
     let buf = util::read_file(filename)?;
     let pe = PE::from_bytes(&buf)?;
 
-    let mut functions = lancelot::analysis::pe::find_function_starts(&pe)?;
+    let assembly = lancelot::analysis::pe::clr::Assembly::from_pe(&pe);
+    let load_config = pe.load_config()?;
+    let mut functions = lancelot::analysis::pe::find_function_starts(&pe, assembly.as_ref(), load_config.as_ref())?;
     functions.sort_unstable();
     info!("found {} functions", functions.len());
 
@@ -156,7 +156,7 @@ mod tests {
         let ptrs = find_executable_pointers(&pe.module)?;
         assert!(ptrs.contains(&0x4010E0));
 
-        let existing = crate::analysis::pe::find_function_starts(&pe)?;
+        let existing = crate::analysis::pe::find_function_starts(&pe, None, None)?;
         assert!(!existing.contains(&0x4010E0));
 
         let mut insns: InstructionIndex = Default::default();
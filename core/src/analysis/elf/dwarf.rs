@@ -17,7 +17,7 @@ pub struct DwarfFunction {
 
 pub fn find_dwarf_function_starts(elf: &ELF) -> Result<Vec<VA>> {
     let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
-    let base_address = elf.module.address_space.base_address;
+    let load_bias = elf.load_bias();
     let endian = if goblin_elf.header.endianness()? == goblin::container::Endian::Little {
         RunTimeEndian::Little
     } else {
@@ -28,7 +28,7 @@ pub fn find_dwarf_function_starts(elf: &ELF) -> Result<Vec<VA>> {
     let dwarf = load_dwarf_sections(&goblin_elf, &elf.buf, endian)?;
     
     // find functions
-    let functions = parse_dwarf_functions(&dwarf, base_address)?;
+    let functions = parse_dwarf_functions(&dwarf, load_bias)?;
     
     let mut function_starts: Vec<VA> = functions.iter()
         .map(|f| f.address)
@@ -92,7 +92,7 @@ fn load_dwarf_sections<'a>(goblin_elf: &goblin::elf::Elf, buf: &'a [u8], endian:
     })
 }
 
-fn parse_dwarf_functions(dwarf: &gimli::Dwarf<EndianSlice<RunTimeEndian>>, base_address: VA) -> Result<Vec<DwarfFunction>> {
+fn parse_dwarf_functions(dwarf: &gimli::Dwarf<EndianSlice<RunTimeEndian>>, load_bias: VA) -> Result<Vec<DwarfFunction>> {
     let mut functions = Vec::new();
     
     let mut units = dwarf.units();
@@ -101,7 +101,7 @@ fn parse_dwarf_functions(dwarf: &gimli::Dwarf<EndianSlice<RunTimeEndian>>, base_
         let mut entries = unit.entries();
         while let Some((_, entry)) = entries.next_dfs()? {
             if entry.tag() == gimli::DW_TAG_subprogram {
-                if let Some(func) = parse_function_entry(dwarf, &unit, entry, base_address)? {
+                if let Some(func) = parse_function_entry(dwarf, &unit, entry, load_bias)? {
                     functions.push(func);
                 }
             }
@@ -111,7 +111,7 @@ fn parse_dwarf_functions(dwarf: &gimli::Dwarf<EndianSlice<RunTimeEndian>>, base_
     Ok(functions)
 }
 
-fn parse_function_entry(dwarf: &gimli::Dwarf<EndianSlice<RunTimeEndian>>, unit: &gimli::Unit<EndianSlice<RunTimeEndian>>, entry: &gimli::DebuggingInformationEntry<EndianSlice<RunTimeEndian>>, base_address: VA) -> Result<Option<DwarfFunction>> {
+fn parse_function_entry(dwarf: &gimli::Dwarf<EndianSlice<RunTimeEndian>>, unit: &gimli::Unit<EndianSlice<RunTimeEndian>>, entry: &gimli::DebuggingInformationEntry<EndianSlice<RunTimeEndian>>, load_bias: VA) -> Result<Option<DwarfFunction>> {
     let mut low_pc: Option<u64> = None;
     let mut high_pc: Option<u64> = None;
     let mut high_pc_is_offset = false;
@@ -150,7 +150,7 @@ fn parse_function_entry(dwarf: &gimli::Dwarf<EndianSlice<RunTimeEndian>>, unit:
     }
     
     if let Some(addr) = low_pc {
-        let actual_address = addr + base_address;
+        let actual_address = addr.wrapping_add(load_bias);
         let size = if let Some(hp) = high_pc {
             if high_pc_is_offset {
                 hp
@@ -13,7 +13,7 @@ pub fn find_elf_entrypoint(elf: &ELF) -> Result<Vec<VA>> {
         return Ok(vec![]);
     }
 
-    let entry_point = goblin_elf.header.e_entry + elf.module.address_space.base_address;
+    let entry_point = goblin_elf.header.e_entry.wrapping_add(elf.load_bias());
     debug!("elf: entry point: {entry_point:#x}");
     Ok(vec![entry_point])
 }
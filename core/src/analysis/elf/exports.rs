@@ -16,7 +16,7 @@ pub fn find_elf_exports(elf: &ELF) -> Result<Vec<VA>> {
             && sym.st_shndx != elf::section_header::SHN_UNDEF as usize 
             && sym.st_value != 0 
         {
-            let addr = sym.st_value + elf.module.address_space.base_address;
+            let addr = sym.st_value.wrapping_add(elf.load_bias());
 
             if elf.module.probe_va(addr, Permissions::X) {
                 debug!("elf: found exported function in dynsym: {:#x} (sym value: {:#x})", addr, sym.st_value);
@@ -32,7 +32,7 @@ pub fn find_elf_exports(elf: &ELF) -> Result<Vec<VA>> {
             && sym.st_value != 0 
             && sym.st_bind() == elf::sym::STB_GLOBAL
         {
-            let addr = sym.st_value + elf.module.address_space.base_address;
+            let addr = sym.st_value.wrapping_add(elf.load_bias());
             
             if elf.module.probe_va(addr, Permissions::X) {
                 debug!("elf: found exported function in symtab: {:#x} (sym value: {:#x})", addr, sym.st_value);
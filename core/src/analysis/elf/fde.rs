@@ -32,7 +32,7 @@ pub fn find_fde_function_starts(elf: &ELF, goblin_elf: &elf::Elf) -> Result<Vec<
             debug!("elf: parsing .eh_frame section at offset {:#x}, size {:#x}, addr {:#x}", 
                    section_offset, section_size, section_addr);
 
-            if let Ok(fdes) = parse_eh_frame(eh_frame_data, section_addr, elf.module.address_space.base_address) {
+            if let Ok(fdes) = parse_eh_frame(eh_frame_data, section_addr, elf.load_bias()) {
                 let decoder = dis::get_disassembler(&elf.module)?;
                 
                 for fde in fdes {
@@ -54,7 +54,7 @@ struct FrameDescriptorEntry {
     pc_range: u64,
 }
 // parsing .eh_frame to extract FDEs
-fn parse_eh_frame(data: &[u8], section_addr: u64, base_address: VA) -> Result<Vec<FrameDescriptorEntry>> {
+fn parse_eh_frame(data: &[u8], section_addr: u64, load_bias: VA) -> Result<Vec<FrameDescriptorEntry>> {
     let mut fdes = Vec::new();
     let mut offset = 0;
 
@@ -113,7 +113,7 @@ fn parse_eh_frame(data: &[u8], section_addr: u64, base_address: VA) -> Result<Ve
             offset += 4;
             let field_addr = section_addr + pc_begin_field_offset as u64;
             let pc_begin_abs = (field_addr as i64 + pc_begin_relative) as u64;
-            let pc_begin = pc_begin_abs + base_address;
+            let pc_begin = pc_begin_abs.wrapping_add(load_bias);
 
             if pc_begin != 0 && pc_range != 0 {
                 fdes.push(FrameDescriptorEntry {
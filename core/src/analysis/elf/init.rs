@@ -0,0 +1,403 @@
+//! Find the functions that the runtime calls outside of the regular control
+//! flow: initializers and finalizers, and IFUNC resolvers.
+//!
+//! The loader and libc run these before and after `main`, via:
+//!   - `DT_INIT`/`DT_FINI`, or the `.init`/`.fini` sections,
+//!   - `DT_PREINIT_ARRAY`/`DT_INIT_ARRAY`/`DT_FINI_ARRAY`, or the `.*_array`
+//!     sections,
+//!   - the legacy `.ctors`/`.dtors` lists, and
+//!   - IFUNC resolvers, referenced by IRELATIVE relocations and `STT_GNU_IFUNC`
+//!     symbols.
+//!
+//! Nothing else needs to reference them, so in stripped binaries they're easy
+//! to miss, yet that's where constructors (and sometimes backdoors) live.
+//!
+//! The array entries are read from the loaded module, after relocations
+//! have been applied.
+use std::{collections::BTreeMap, ops::Range};
+
+use anyhow::Result;
+use goblin::elf::{
+    dynamic::{
+        DT_FINI, DT_FINI_ARRAY, DT_FINI_ARRAYSZ, DT_INIT, DT_INIT_ARRAY, DT_INIT_ARRAYSZ, DT_PREINIT_ARRAY,
+        DT_PREINIT_ARRAYSZ,
+    },
+    sym::STT_GNU_IFUNC,
+};
+use log::debug;
+
+use crate::{
+    aspace::AddressSpace,
+    loader::elf::{
+        reloc::{read_relocations, RelocationType},
+        ELF,
+    },
+    module::Permissions,
+    VA,
+};
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum InitFunctionKind {
+    /// `DT_INIT` or `.init`.
+    Init,
+    /// `DT_FINI` or `.fini`.
+    Fini,
+    /// an entry of `DT_PREINIT_ARRAY` or `.preinit_array`.
+    PreinitArray,
+    /// an entry of `DT_INIT_ARRAY` or `.init_array`.
+    InitArray,
+    /// an entry of `DT_FINI_ARRAY` or `.fini_array`.
+    FiniArray,
+    /// an entry of `.ctors`.
+    Ctors,
+    /// an entry of `.dtors`.
+    Dtors,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub struct InitFunction {
+    pub address: VA,
+    pub kind:    InitFunctionKind,
+    /// the index of the entry within its table, or zero for `_init`/`_fini`.
+    pub index:   usize,
+}
+
+impl InitFunction {
+    /// A name for the function, like `_init` or `init_array_0`,
+    /// for when there's no symbol.
+    pub fn name(&self) -> String {
+        match self.kind {
+            InitFunctionKind::Init => "_init".to_string(),
+            InitFunctionKind::Fini => "_fini".to_string(),
+            InitFunctionKind::PreinitArray => format!("preinit_array_{}", self.index),
+            InitFunctionKind::InitArray => format!("init_array_{}", self.index),
+            InitFunctionKind::FiniArray => format!("fini_array_{}", self.index),
+            InitFunctionKind::Ctors => format!("ctors_{}", self.index),
+            InitFunctionKind::Dtors => format!("dtors_{}", self.index),
+        }
+    }
+}
+
+/// Find the region of a table described by the dynamic section,
+/// or by the section headers when there's no dynamic section,
+/// such as in static binaries.
+///
+/// The size from the dynamic section is untrusted,
+/// so the table is clamped to the segment that contains it.
+fn get_table(elf: &ELF, dynamic: &BTreeMap<u64, u64>, tag: u64, size_tag: u64, section: &str) -> Option<Range<VA>> {
+    if let (Some(&address), Some(&size)) = (dynamic.get(&tag), dynamic.get(&size_tag)) {
+        let start = address.wrapping_add(elf.load_bias());
+        let Some(segment) = elf
+            .module
+            .sections
+            .iter()
+            .find(|segment| segment.virtual_range.contains(&start))
+        else {
+            debug!("elf: init: table not mapped: {:#x}", start);
+            return None;
+        };
+
+        let end = match start.checked_add(size) {
+            Some(end) if end <= segment.virtual_range.end => end,
+            _ => {
+                debug!(
+                    "elf: init: table size truncated to its segment: {:#x} {:#x}",
+                    start, size
+                );
+                segment.virtual_range.end
+            }
+        };
+        return Some(start..end);
+    }
+
+    elf.module
+        .get_section_header(section)
+        .map(|header| header.virtual_range.clone())
+}
+
+/// Read the function pointers from a table, by their index,
+/// skipping the `0` and `-1` entries used as terminators and placeholders.
+fn read_table(elf: &ELF, table: Range<VA>) -> Vec<(usize, VA)> {
+    let pointer_size = elf.module.arch.pointer_size() as u64;
+    let invalid = match pointer_size {
+        4 => u32::MAX as u64,
+        _ => u64::MAX,
+    };
+
+    (table.start..table.end)
+        .step_by(pointer_size as usize)
+        .enumerate()
+        .filter_map(|(index, slot)| {
+            let address = elf.module.address_space.read_pointer(elf.module.arch, slot).ok()?;
+            if address == 0 || address == invalid {
+                None
+            } else {
+                Some((index, address))
+            }
+        })
+        .collect()
+}
+
+/// Find the initializers and finalizers, in the order the tables are listed
+/// above. Only entries that point to executable memory are returned.
+pub fn find_elf_init_functions(elf: &ELF) -> Result<Vec<InitFunction>> {
+    let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+
+    let dynamic: BTreeMap<u64, u64> = goblin_elf
+        .dynamic
+        .as_ref()
+        .map(|dynamic| dynamic.dyns.iter().map(|d| (d.d_tag, d.d_val)).collect())
+        .unwrap_or_default();
+
+    let mut functions: Vec<InitFunction> = Default::default();
+
+    for (kind, tag, section) in [
+        (InitFunctionKind::Init, DT_INIT, ".init"),
+        (InitFunctionKind::Fini, DT_FINI, ".fini"),
+    ] {
+        let address = match dynamic.get(&tag) {
+            Some(&address) => Some(address.wrapping_add(elf.load_bias())),
+            None => elf
+                .module
+                .get_section_header(section)
+                .map(|header| header.virtual_range.start),
+        };
+
+        if let Some(address) = address {
+            functions.push(InitFunction {
+                address,
+                kind,
+                index: 0,
+            });
+        }
+    }
+
+    for (kind, tag, size_tag, section) in [
+        (
+            InitFunctionKind::PreinitArray,
+            DT_PREINIT_ARRAY,
+            DT_PREINIT_ARRAYSZ,
+            ".preinit_array",
+        ),
+        (
+            InitFunctionKind::InitArray,
+            DT_INIT_ARRAY,
+            DT_INIT_ARRAYSZ,
+            ".init_array",
+        ),
+        (
+            InitFunctionKind::FiniArray,
+            DT_FINI_ARRAY,
+            DT_FINI_ARRAYSZ,
+            ".fini_array",
+        ),
+    ] {
+        if let Some(table) = get_table(elf, &dynamic, tag, size_tag, section) {
+            for (index, address) in read_table(elf, table) {
+                functions.push(InitFunction { address, kind, index });
+            }
+        }
+    }
+
+    // the legacy lists start with -1 (or a count) and end with 0.
+    for (kind, section) in [(InitFunctionKind::Ctors, ".ctors"), (InitFunctionKind::Dtors, ".dtors")] {
+        if let Some(header) = elf.module.get_section_header(section) {
+            for (index, address) in read_table(elf, header.virtual_range.clone()) {
+                functions.push(InitFunction { address, kind, index });
+            }
+        }
+    }
+
+    Ok(functions
+        .into_iter()
+        .filter(|function| {
+            let is_code = elf.module.probe_va(function.address, Permissions::X);
+            if is_code {
+                debug!("elf: {}: {:#x}", function.name(), function.address);
+            } else {
+                debug!(
+                    "elf: {}: {:#x}: not in executable memory",
+                    function.name(),
+                    function.address
+                );
+            }
+            is_code
+        })
+        .collect())
+}
+
+/// Find the IFUNC resolvers referenced by IRELATIVE relocations,
+/// indexed by the address of the slot they fill.
+///
+/// The loader stores the address of the slot's resolver,
+/// since we can't call it to find the implementation it selects.
+pub fn find_elf_ifunc_slots(elf: &ELF) -> Result<BTreeMap<VA, VA>> {
+    let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+
+    let mut slots: BTreeMap<VA, VA> = Default::default();
+    for relocation in read_relocations(&goblin_elf, elf.module.arch)
+        .into_iter()
+        .filter(|relocation| relocation.type_ == RelocationType::IRelative)
+    {
+        let slot = relocation.address.wrapping_add(elf.load_bias());
+        if !elf.relocations.contains(&slot) {
+            continue;
+        }
+
+        if let Ok(resolver) = elf.module.address_space.read_pointer(elf.module.arch, slot) {
+            if elf.module.probe_va(resolver, Permissions::X) {
+                debug!("elf: IFUNC slot: {slot:#x} -> resolver: {resolver:#x}");
+                slots.insert(slot, resolver);
+            }
+        }
+    }
+
+    Ok(slots)
+}
+
+/// Find the IFUNC resolvers, from IRELATIVE relocations and `STT_GNU_IFUNC`
+/// symbols, whose values are the addresses of their resolvers.
+pub fn find_elf_ifunc_resolvers(elf: &ELF) -> Result<Vec<VA>> {
+    let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+
+    let mut resolvers: Vec<VA> = find_elf_ifunc_slots(elf)?.into_values().collect();
+
+    resolvers.extend(
+        goblin_elf
+            .syms
+            .iter()
+            .chain(goblin_elf.dynsyms.iter())
+            .filter(|sym| sym.st_type() == STT_GNU_IFUNC && sym.st_value != 0)
+            .map(|sym| sym.st_value.wrapping_add(elf.load_bias()))
+            .filter(|&va| elf.module.probe_va(va, Permissions::X)),
+    );
+
+    resolvers.sort_unstable();
+    resolvers.dedup();
+
+    Ok(resolvers)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::loader::elf::tests::{build_pie, PIE_ENTRY, PIE_SLOT};
+
+    pub const INIT: u64 = 0x110;
+    pub const INIT_ARRAY_0: u64 = 0x120;
+    pub const FINI_ARRAY_0: u64 = 0x130;
+    pub const IFUNC_RESOLVER: u64 = 0x140;
+    pub const INIT_ARRAY: u64 = 0x500;
+    pub const FINI_ARRAY: u64 = 0x510;
+    pub const IFUNC_SLOT: u64 = 0x520;
+
+    /// Extend `build_pie` with:
+    ///
+    /// ```text
+    ///   0x110: ret  ; DT_INIT
+    ///   0x120: ret  ; init_array[0]
+    ///   0x130: ret  ; fini_array[0]
+    ///   0x140: ret  ; IFUNC resolver
+    ///   0x500: .init_array: [0x120 (RELATIVE), 0]
+    ///   0x510: .fini_array: [0x130 (RELATIVE)]
+    ///   0x520: GOT slot: R_X86_64_IRELATIVE 0x140
+    /// ```
+    pub fn build_pie_with_init() -> Vec<u8> {
+        let mut buf = build_pie();
+
+        for address in [INIT, INIT_ARRAY_0, FINI_ARRAY_0, IFUNC_RESOLVER] {
+            buf[address as usize] = 0xC3;
+        }
+
+        // PT_DYNAMIC p_filesz and p_memsz
+        buf[0x98..0xA0].copy_from_slice(&0x90u64.to_le_bytes());
+        buf[0xA0..0xA8].copy_from_slice(&0x90u64.to_le_bytes());
+
+        let mut dynamic = vec![];
+        for (tag, value) in [
+            (goblin::elf::dynamic::DT_RELA, 0x300u64),
+            (goblin::elf::dynamic::DT_RELASZ, 0x60),
+            (goblin::elf::dynamic::DT_RELAENT, 0x18),
+            (DT_INIT, INIT),
+            (DT_INIT_ARRAY, INIT_ARRAY),
+            (DT_INIT_ARRAYSZ, 0x10),
+            (DT_FINI_ARRAY, FINI_ARRAY),
+            (DT_FINI_ARRAYSZ, 0x8),
+            (goblin::elf::dynamic::DT_NULL, 0x0),
+        ] {
+            dynamic.write_u64::<LittleEndian>(tag).unwrap();
+            dynamic.write_u64::<LittleEndian>(value).unwrap();
+        }
+        buf[0x200..0x200 + dynamic.len()].copy_from_slice(&dynamic);
+
+        let mut rela = vec![];
+        for (r_type, slot, addend) in [
+            (goblin::elf::reloc::R_X86_64_RELATIVE, PIE_SLOT, PIE_ENTRY),
+            (goblin::elf::reloc::R_X86_64_RELATIVE, INIT_ARRAY, INIT_ARRAY_0),
+            (goblin::elf::reloc::R_X86_64_RELATIVE, FINI_ARRAY, FINI_ARRAY_0),
+            (goblin::elf::reloc::R_X86_64_IRELATIVE, IFUNC_SLOT, IFUNC_RESOLVER),
+        ] {
+            rela.write_u64::<LittleEndian>(slot).unwrap(); // r_offset
+            rela.write_u64::<LittleEndian>(r_type as u64).unwrap(); // r_info
+            rela.write_i64::<LittleEndian>(addend as i64).unwrap(); // r_addend
+        }
+        buf[0x300..0x300 + rela.len()].copy_from_slice(&rela);
+
+        buf
+    }
+
+    #[test]
+    fn init_functions() -> Result<()> {
+        let buf = build_pie_with_init();
+        let mut elf = ELF::from_bytes(&buf)?;
+
+        let functions = find_elf_init_functions(&elf)?;
+        assert_eq!(
+            vec![
+                (INIT, "_init".to_string()),
+                (INIT_ARRAY_0, "init_array_0".to_string()),
+                (FINI_ARRAY_0, "fini_array_0".to_string()),
+            ],
+            functions
+                .iter()
+                .map(|function| (function.address, function.name()))
+                .collect::<Vec<_>>()
+        );
+
+        // the slot is filled with the address of the resolver.
+        assert_eq!(IFUNC_RESOLVER, elf.module.address_space.read_u64(IFUNC_SLOT)?);
+        assert_eq!(
+            BTreeMap::from([(IFUNC_SLOT, IFUNC_RESOLVER)]),
+            find_elf_ifunc_slots(&elf)?
+        );
+        assert_eq!(vec![IFUNC_RESOLVER], find_elf_ifunc_resolvers(&elf)?);
+
+        elf.rebase(0x5555_5555_4000)?;
+        assert_eq!(0x5555_5555_4000 + INIT, find_elf_init_functions(&elf)?[0].address);
+        assert_eq!(
+            BTreeMap::from([(0x5555_5555_4000 + IFUNC_SLOT, 0x5555_5555_4000 + IFUNC_RESOLVER)]),
+            find_elf_ifunc_slots(&elf)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn table_size() -> Result<()> {
+        // DT_INIT_ARRAYSZ, the sixth entry of the dynamic section.
+        let mut buf = build_pie_with_init();
+        buf[0x258..0x260].copy_from_slice(&u64::MAX.to_le_bytes());
+        let elf = ELF::from_bytes(&buf)?;
+
+        // the table is read up to the end of its segment.
+        let functions = find_elf_init_functions(&elf)?;
+        assert!(functions
+            .iter()
+            .any(|function| function.address == INIT_ARRAY_0 && function.kind == InitFunctionKind::InitArray));
+
+        Ok(())
+    }
+}
@@ -29,6 +29,7 @@
 //! - **Symbol Tables**: Function symbols from symtab and dynsym
 //! - **DWARF Debug Info**: Function information from debug sections
 //! - **Entry Points**: Program entry points
+//! - **Initializers**: init/fini arrays, `.ctors`/`.dtors`, and IFUNC resolvers
 //! - **Pattern Matching**: Function prologue patterns
 
 use std::collections::BTreeMap;
@@ -46,8 +47,11 @@ mod symtab;
 mod dwarf;
 pub mod entrypoints;
 pub mod exports;
+pub mod init;
 mod patterns;
 mod call_targets;
+mod pointers;
+pub mod plt;
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub struct ELFImport {
@@ -64,10 +68,25 @@ pub fn get_imports(elf: &ELF) -> Result<BTreeMap<VA, ELFImport>> {
     let goblin_elf = elf::Elf::parse(&elf.buf)?;
 
     // read import libraries and symbols
-    let import_libs = read_import_libraries(&goblin_elf);
+    let import_libs = read_import_libraries(&goblin_elf, &elf.symbol_versions);
+
+    // the PLT and GOT addresses are link-time addresses.
+    let load_bias = elf.load_bias();
+
+    // the PLT stub that jumps through each GOT slot.
+    let mut stubs_by_slot: BTreeMap<VA, VA> = Default::default();
+    for (stub, slot) in plt::find_elf_plt_stubs(elf)? {
+        stubs_by_slot.insert(slot, stub);
+    }
 
     for lib in import_libs {
-        for symbol in lib.symbols {
+        for mut symbol in lib.symbols {
+            symbol.plt_address = symbol.plt_address.map(|address| address.wrapping_add(load_bias));
+            symbol.got_address = symbol.got_address.map(|address| address.wrapping_add(load_bias));
+            if symbol.plt_address.is_none() {
+                symbol.plt_address = symbol.got_address.and_then(|slot| stubs_by_slot.get(&slot).copied());
+            }
+
             // use PLT address if available, otherwise GOT address
             let address = symbol.plt_address
                 .or(symbol.got_address)
@@ -116,6 +135,14 @@ pub fn find_function_starts(elf: &ELF) -> Result<Vec<VA>> {
         function_starts.insert(ep);
     }
 
+    // add initializers, finalizers, and IFUNC resolvers
+    function_starts.extend(
+        init::find_elf_init_functions(elf)?
+            .into_iter()
+            .map(|function| function.address)
+    );
+    function_starts.extend(init::find_elf_ifunc_resolvers(elf)?);
+
     // add call targets
     let decoder = dis::get_disassembler(&elf.module)?;
     function_starts.extend(
@@ -124,6 +151,13 @@ pub fn find_function_starts(elf: &ELF) -> Result<Vec<VA>> {
             .filter(|&va| heuristics::is_probably_code(&elf.module, &decoder, va))
     );
 
+    // add pointers fixed up by dynamic relocations
+    function_starts.extend(
+        pointers::find_elf_relocated_code_pointers(elf)?
+            .into_iter()
+            .filter(|&va| heuristics::is_probably_code(&elf.module, &decoder, va))
+    );
+
     // add patterns
     function_starts.extend(
         patterns::find_function_prologues(elf)?
@@ -15,14 +15,6 @@ pub fn cfg_prune_noret_imports(elf: &ELF, cfg: &mut CFG) -> Result<BTreeSet<VA>>
     let mut noret = elf::get_imports(elf)?
         .values()
         .filter(|imp| {
-            let is_got_entry = imp.symbol.got_address.is_some() && 
-                               (imp.symbol.plt_address.is_none() || 
-                                imp.address == imp.symbol.got_address.unwrap_or(0) + elf.module.address_space.base_address);
-            
-            if !is_got_entry {
-                return false;
-            }
-            
             match (&*imp.library, &imp.symbol.name) {
                 ("libc.so.6", name) if name == "exit" => true,
                 ("libc.so.6", name) if name == "_exit" => true,
@@ -32,7 +24,8 @@ pub fn cfg_prune_noret_imports(elf: &ELF, cfg: &mut CFG) -> Result<BTreeSet<VA>>
                 (_, _) => false,
             }
         })
-        .map(|imp| imp.address)
+        // code calls the PLT stub, or through the GOT slot (`-fno-plt`).
+        .flat_map(|imp| std::iter::once(imp.address).chain(imp.symbol.got_address))
         .collect::<BTreeSet<_>>();
 
     for &noret_import in noret.clone().iter() {
@@ -0,0 +1,605 @@
+//! Resolve PLT stubs and GOT references to the GOT slots of imports.
+//!
+//! On x86-64 a PLT stub jumps through a RIP-relative GOT slot:
+//!
+//! ```text
+//!   jmp [rip+puts@GOTPCREL]
+//! ```
+//!
+//! but on i386, position independent code has no PC-relative addressing,
+//! so the stub jumps relative to the GOT base (`_GLOBAL_OFFSET_TABLE_`),
+//! which the caller must have placed in `ebx`:
+//!
+//! ```text
+//!   jmp [ebx+puts@GOT]
+//! ```
+//!
+//! Function bodies compute the GOT base themselves, via a helper
+//! that returns its return address:
+//!
+//! ```text
+//!   call __x86.get_pc_thunk.bx   ; mov ebx, [esp]; ret
+//!   add  ebx, _GLOBAL_OFFSET_TABLE_ - $
+//!   call [ebx+puts@GOT]          ; with -fno-plt
+//! ```
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use log::debug;
+
+use crate::{arch::Arch, aspace::AddressSpace, loader::elf::ELF, module::Module, VA};
+
+/// The size of the first PLT entry (PLT0), which calls the dynamic resolver
+/// rather than an import, on both i386 and x86-64.
+pub const PLT_HEADER_SIZE: u64 = 16;
+
+/// Find the GOT base used by i386 PIC code (`_GLOBAL_OFFSET_TABLE_`),
+/// which is the start of `.got.plt`, as described by `DT_PLTGOT`.
+pub fn get_elf_got_base(elf: &ELF) -> Result<Option<VA>> {
+    let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+
+    if let Some(dynamic) = goblin_elf.dynamic.as_ref() {
+        if let Some(d) = dynamic.dyns.iter().find(|d| d.d_tag == goblin::elf::dynamic::DT_PLTGOT) {
+            return Ok(Some(d.d_val.wrapping_add(elf.load_bias())));
+        }
+    }
+
+    Ok(elf
+        .module
+        .get_section_header(".got.plt")
+        .or_else(|| elf.module.get_section_header(".got"))
+        .map(|header| header.virtual_range.start))
+}
+
+/// Decode the `jmp` of a PLT stub at the given address to the GOT slot it jumps
+/// through. The stub may be preceded by `endbr32`/`endbr64` and a `bnd` prefix,
+/// as with `-fcf-protection`.
+///
+/// `got_base` is required to resolve i386 PIC stubs, and
+/// `load_bias` to resolve i386 stubs with absolute addresses.
+fn decode_plt_stub(arch: Arch, va: VA, buf: &[u8], got_base: Option<VA>, load_bias: u64) -> Option<VA> {
+    let mut offset = 0usize;
+
+    // endbr64 (F3 0F 1E FA), endbr32 (F3 0F 1E FB)
+    if buf.len() >= 4 && buf[..3] == [0xF3, 0x0F, 0x1E] && (buf[3] == 0xFA || buf[3] == 0xFB) {
+        offset += 4;
+    }
+
+    // bnd
+    if buf.get(offset) == Some(&0xF2) {
+        offset += 1;
+    }
+
+    let insn = &buf[offset.min(buf.len())..];
+    if insn.len() < 6 || insn[0] != 0xFF {
+        return None;
+    }
+    let disp32 = i32::from_le_bytes([insn[2], insn[3], insn[4], insn[5]]) as i64;
+
+    match (arch, insn[1]) {
+        // jmp [rip+disp32]
+        (Arch::X64, 0x25) => Some((va + offset as u64 + 6).wrapping_add(disp32 as u64)),
+        // jmp [disp32]
+        (Arch::X32, 0x25) => Some((disp32 as u32 as u64).wrapping_add(load_bias)),
+        // jmp [ebx+disp32]
+        (Arch::X32, 0xA3) => got_base.map(|base| (base as u32).wrapping_add(disp32 as u32) as VA),
+        // jmp [ebx+disp8]
+        (Arch::X32, 0x63) => got_base.map(|base| (base as u32).wrapping_add(insn[2] as i8 as i32 as u32) as VA),
+        _ => None,
+    }
+}
+
+/// Find the stubs in `.plt`, `.plt.sec`, and `.plt.got`,
+/// indexed by the address of the stub, with the address of the GOT slot they
+/// jump through.
+pub fn find_elf_plt_stubs(elf: &ELF) -> Result<BTreeMap<VA, VA>> {
+    let got_base = get_elf_got_base(elf)?;
+    let load_bias = elf.load_bias();
+
+    let mut stubs: BTreeMap<VA, VA> = Default::default();
+    // the stubs of `.plt.got` are `jmp` and a two byte `nop`,
+    // unless there's an `endbr`, when they're the same size as others.
+    for (name, skip, default_entry_size) in [(".plt", PLT_HEADER_SIZE, 16), (".plt.sec", 0, 16), (".plt.got", 0, 8)] {
+        let Some(section) = elf.module.get_section_header(name) else {
+            continue;
+        };
+
+        let entry_size = if section.entry_size != 0 {
+            section.entry_size
+        } else {
+            default_entry_size
+        };
+
+        let end = section.virtual_range.end;
+        let Some(start) = section.virtual_range.start.checked_add(skip) else {
+            continue;
+        };
+        for va in (start..end).step_by(entry_size as usize) {
+            let Ok(buf) = elf
+                .module
+                .address_space
+                .read_bytes(va, entry_size.min(end - va) as usize)
+            else {
+                continue;
+            };
+
+            if let Some(slot) = decode_plt_stub(elf.module.arch, va, &buf, got_base, load_bias) {
+                debug!("elf: PLT stub: {va:#x} -> GOT slot: {slot:#x}");
+                stubs.insert(va, slot);
+            }
+        }
+    }
+
+    Ok(stubs)
+}
+
+/// The registers in the order of their ModRM encoding.
+const REGISTER_NAMES: [&str; 8] = ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di"];
+
+/// Recognize `mov reg, [esp]; ret` and return the encoding of the register.
+fn decode_pc_thunk(buf: &[u8]) -> Option<u8> {
+    // 8B /r with mod=00, rm=100 (SIB), SIB=esp
+    if buf.len() >= 4 && buf[0] == 0x8B && buf[1] & 0xC7 == 0x04 && buf[2] == 0x24 && buf[3] == 0xC3 {
+        let reg = (buf[1] >> 3) & 0x7;
+        // esp isn't a useful destination
+        if reg != 4 {
+            return Some(reg);
+        }
+    }
+    None
+}
+
+/// The name of the i386 helper that loads its return address into the
+/// register, like `__x86.get_pc_thunk.bx`.
+pub fn get_pc_thunk_name(reg: u8) -> String {
+    format!("__x86.get_pc_thunk.{}", REGISTER_NAMES[reg as usize & 0x7])
+}
+
+/// Find the `__x86.get_pc_thunk.*` helpers among the given functions,
+/// indexed by address, with the encoding of the register they load.
+pub fn find_elf_pc_thunks<'a, T>(module: &Module, functions: T) -> BTreeMap<VA, u8>
+where
+    T: Iterator<Item = &'a VA>,
+{
+    if !matches!(module.arch, Arch::X32) {
+        return Default::default();
+    }
+
+    functions
+        .filter_map(|&va| {
+            let buf = module.address_space.read_bytes(va, 4).ok()?;
+            let reg = decode_pc_thunk(&buf)?;
+            debug!("elf: {}: {va:#x}", get_pc_thunk_name(reg));
+            Some((va, reg))
+        })
+        .collect()
+}
+
+/// Find the instructions that reference GOT slots relative to a GOT base
+/// computed via `__x86.get_pc_thunk.*`, like `call [ebx+puts@GOT]`,
+/// indexed by the address of the instruction, with the address of the slot.
+///
+/// From each `call thunk; add reg, imm` whose result is the GOT base,
+/// the instructions are followed, not into calls, until the register is
+/// overwritten. `ebx` is callee-saved, so it's followed across calls,
+/// while the other registers are not.
+#[cfg(feature = "disassembler")]
+pub fn find_elf_got_references(
+    elf: &ELF,
+    cfg: &crate::analysis::cfg::CFG,
+    pc_thunks: &BTreeMap<VA, u8>,
+) -> Result<BTreeMap<VA, VA>> {
+    use std::collections::BTreeSet;
+
+    use crate::analysis::{
+        cfg::{flow::Flow, read_insn_with_cache, CachingPageReader},
+        dis::{self, zydis, Target},
+    };
+
+    const REGISTERS: [zydis::Register; 8] = [
+        zydis::Register::EAX,
+        zydis::Register::ECX,
+        zydis::Register::EDX,
+        zydis::Register::EBX,
+        zydis::Register::ESP,
+        zydis::Register::EBP,
+        zydis::Register::ESI,
+        zydis::Register::EDI,
+    ];
+
+    let mut references: BTreeMap<VA, VA> = Default::default();
+    if pc_thunks.is_empty() {
+        return Ok(references);
+    }
+    let Some(got_base) = get_elf_got_base(elf)? else {
+        return Ok(references);
+    };
+
+    let module = &elf.module;
+    let decoder = dis::get_disassembler(module)?;
+    let mut reader: CachingPageReader = Default::default();
+
+    for (&va, insn) in cfg.insns.insns_by_address.iter() {
+        let Some(&reg) = insn.successors.iter().find_map(|flow| match flow {
+            Flow::Call(Target::Direct(target)) => pc_thunks.get(target),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let register = REGISTERS[reg as usize];
+
+        // add reg, imm: the thunk returns the address of this instruction.
+        let add_va = va + insn.length as u64;
+        let Ok(Some(add)) = read_insn_with_cache(&mut reader, &module.address_space, add_va, &decoder) else {
+            continue;
+        };
+        let operands = dis::get_operands(&add).collect::<Vec<_>>();
+        if add.mnemonic != zydis::Mnemonic::ADD
+            || operands.len() != 2
+            || operands[0].ty != zydis::OperandType::REGISTER
+            || operands[0].reg != register
+            || operands[1].ty != zydis::OperandType::IMMEDIATE
+        {
+            continue;
+        }
+        let base = (add_va as u32).wrapping_add(operands[1].imm.value as u32) as VA;
+        if base != got_base {
+            debug!("elf: GOT base: {add_va:#x}: unexpected base: {base:#x}");
+            continue;
+        }
+        debug!("elf: GOT base: {add_va:#x}: {}", get_pc_thunk_name(reg));
+
+        let mut seen: BTreeSet<VA> = Default::default();
+        let mut queue: Vec<VA> = vec![add_va];
+        while let Some(va) = queue.pop() {
+            if !seen.insert(va) {
+                continue;
+            }
+            let Some(desc) = cfg.insns.insns_by_address.get(&va) else {
+                continue;
+            };
+
+            if va != add_va {
+                let Ok(Some(insn)) = read_insn_with_cache(&mut reader, &module.address_space, va, &decoder) else {
+                    continue;
+                };
+
+                for op in dis::get_operands(&insn) {
+                    if op.ty == zydis::OperandType::MEMORY
+                        && op.mem.base == register
+                        && op.mem.index == zydis::Register::NONE
+                    {
+                        let slot = (base as u32).wrapping_add(op.mem.disp.displacement as u32) as VA;
+                        references.insert(va, slot);
+                    }
+                }
+
+                // most instructions that name a register as the first operand write to it.
+                let is_overwritten = dis::get_first_operand(&insn)
+                    .map(|op| op.ty == zydis::OperandType::REGISTER && op.reg == register)
+                    .unwrap_or(false)
+                    && !matches!(
+                        insn.mnemonic,
+                        zydis::Mnemonic::CMP | zydis::Mnemonic::TEST | zydis::Mnemonic::PUSH
+                    );
+                if is_overwritten {
+                    continue;
+                }
+
+                if insn.mnemonic == zydis::Mnemonic::CALL && register != zydis::Register::EBX {
+                    continue;
+                }
+            }
+
+            for flow in desc.successors.iter() {
+                match flow {
+                    Flow::Fallthrough(target)
+                    | Flow::ConditionalJump(target)
+                    | Flow::UnconditionalJump(Target::Direct(target)) => queue.push(*target),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    debug!("elf: found {} GOT references", references.len());
+
+    Ok(references)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+
+    /// the instruction that calls `puts` relative to the GOT base.
+    pub const PIE32_GOT_REFERENCE: u64 = 0x10C;
+
+    /// Build a minimal i386 PIE, linked at 0x0, with a single RWX segment
+    /// containing:
+    ///
+    /// ```text
+    ///   0x100: push ebx
+    ///   0x101: call __x86.get_pc_thunk.bx
+    ///   0x106: add ebx, 0x2FA           ; GOT base: 0x400
+    ///   0x10C: call [ebx+0xC]           ; puts
+    ///   0x112: pop ebx
+    ///   0x113: ret
+    ///   0x120: __x86.get_pc_thunk.bx: mov ebx, [esp]; ret
+    ///   0x200: .dynamic
+    ///   0x300: .rel.dyn: R_386_GLOB_DAT 0x40C puts
+    ///   0x400: .got, and DT_PLTGOT
+    ///   0x600: .dynstr
+    ///   0x680: .dynsym
+    /// ```
+    ///
+    /// with `puts` imported from `libc.so.6`.
+    pub fn build_pie32_with_got_references() -> Vec<u8> {
+        let mut buf = vec![0u8; 0x1000];
+
+        let mut hdr = vec![];
+        // Elf32_Ehdr
+        hdr.extend_from_slice(b"\x7fELF\x01\x01\x01\x00");
+        hdr.extend_from_slice(&[0u8; 8]);
+        hdr.write_u16::<LittleEndian>(goblin::elf::header::ET_DYN).unwrap();
+        hdr.write_u16::<LittleEndian>(goblin::elf::header::EM_386).unwrap();
+        hdr.write_u32::<LittleEndian>(1).unwrap(); // e_version
+        hdr.write_u32::<LittleEndian>(0x100).unwrap(); // e_entry
+        hdr.write_u32::<LittleEndian>(0x34).unwrap(); // e_phoff
+        hdr.write_u32::<LittleEndian>(0x800).unwrap(); // e_shoff
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // e_flags
+        hdr.write_u16::<LittleEndian>(0x34).unwrap(); // e_ehsize
+        hdr.write_u16::<LittleEndian>(0x20).unwrap(); // e_phentsize
+        hdr.write_u16::<LittleEndian>(2).unwrap(); // e_phnum
+        hdr.write_u16::<LittleEndian>(0x28).unwrap(); // e_shentsize
+        hdr.write_u16::<LittleEndian>(3).unwrap(); // e_shnum
+        hdr.write_u16::<LittleEndian>(2).unwrap(); // e_shstrndx
+
+        // Elf32_Phdr: (type, flags, offset/vaddr, size)
+        for (p_type, p_flags, address, size) in [
+            (goblin::elf::program_header::PT_LOAD, 0x7, 0x0u32, 0x1000u32),
+            (goblin::elf::program_header::PT_DYNAMIC, 0x6, 0x200, 0x50),
+        ] {
+            hdr.write_u32::<LittleEndian>(p_type).unwrap();
+            hdr.write_u32::<LittleEndian>(address).unwrap(); // p_offset
+            hdr.write_u32::<LittleEndian>(address).unwrap(); // p_vaddr
+            hdr.write_u32::<LittleEndian>(address).unwrap(); // p_paddr
+            hdr.write_u32::<LittleEndian>(size).unwrap(); // p_filesz
+            hdr.write_u32::<LittleEndian>(size).unwrap(); // p_memsz
+            hdr.write_u32::<LittleEndian>(p_flags).unwrap();
+            hdr.write_u32::<LittleEndian>(0x1000).unwrap(); // p_align
+        }
+        buf[..hdr.len()].copy_from_slice(&hdr);
+
+        let code: &[u8] = b"\x53\xE8\x1A\x00\x00\x00\x81\xC3\xFA\x02\x00\x00\xFF\x93\x0C\x00\x00\x00\x5B\xC3";
+        buf[0x100..0x100 + code.len()].copy_from_slice(code);
+        buf[0x120..0x124].copy_from_slice(b"\x8B\x1C\x24\xC3");
+
+        let dynstr = b"\x00libc.so.6\x00puts\x00";
+        buf[0x600..0x600 + dynstr.len()].copy_from_slice(dynstr);
+
+        // .dynsym: null, puts
+        let mut dynsym = vec![0u8; 0x10];
+        dynsym.write_u32::<LittleEndian>(11).unwrap(); // st_name
+        dynsym.write_u32::<LittleEndian>(0).unwrap(); // st_value
+        dynsym.write_u32::<LittleEndian>(0).unwrap(); // st_size
+        dynsym.write_u8(0x12).unwrap(); // st_info: STB_GLOBAL, STT_FUNC
+        dynsym.write_u8(0).unwrap(); // st_other
+        dynsym.write_u16::<LittleEndian>(0).unwrap(); // st_shndx: SHN_UNDEF
+        buf[0x680..0x680 + dynsym.len()].copy_from_slice(&dynsym);
+
+        let mut dynamic = vec![];
+        for (tag, value) in [
+            (goblin::elf::dynamic::DT_NEEDED, 1u64),
+            (goblin::elf::dynamic::DT_PLTGOT, 0x400),
+            (goblin::elf::dynamic::DT_REL, 0x300),
+            (goblin::elf::dynamic::DT_RELSZ, 0x8),
+            (goblin::elf::dynamic::DT_RELENT, 0x8),
+            (goblin::elf::dynamic::DT_SYMTAB, 0x680),
+            (goblin::elf::dynamic::DT_SYMENT, 0x10),
+            (goblin::elf::dynamic::DT_STRTAB, 0x600),
+            (goblin::elf::dynamic::DT_STRSZ, dynstr.len() as u64),
+            (goblin::elf::dynamic::DT_NULL, 0x0),
+        ] {
+            dynamic.write_u32::<LittleEndian>(tag as u32).unwrap();
+            dynamic.write_u32::<LittleEndian>(value as u32).unwrap();
+        }
+        buf[0x200..0x200 + dynamic.len()].copy_from_slice(&dynamic);
+
+        let mut rel = vec![];
+        rel.write_u32::<LittleEndian>(0x40C).unwrap(); // r_offset
+        rel.write_u32::<LittleEndian>((1 << 8) | goblin::elf::reloc::R_386_GLOB_DAT)
+            .unwrap(); // r_info
+        buf[0x300..0x300 + rel.len()].copy_from_slice(&rel);
+
+        let shstrtab = b"\x00.got\x00.shstrtab\x00";
+        buf[0x700..0x700 + shstrtab.len()].copy_from_slice(shstrtab);
+
+        // Elf32_Shdr: null, .got, .shstrtab
+        let mut shdrs = vec![0u8; 0x28];
+        for (name, sh_type, flags, address, offset, size, entsize) in [
+            (
+                1u32,
+                goblin::elf::section_header::SHT_PROGBITS,
+                goblin::elf::section_header::SHF_ALLOC | goblin::elf::section_header::SHF_WRITE,
+                0x400u32,
+                0x400u32,
+                0x10u32,
+                4u32,
+            ),
+            (
+                6,
+                goblin::elf::section_header::SHT_STRTAB,
+                0,
+                0,
+                0x700,
+                shstrtab.len() as u32,
+                0,
+            ),
+        ] {
+            shdrs.write_u32::<LittleEndian>(name).unwrap(); // sh_name
+            shdrs.write_u32::<LittleEndian>(sh_type).unwrap();
+            shdrs.write_u32::<LittleEndian>(flags).unwrap(); // sh_flags
+            shdrs.write_u32::<LittleEndian>(address).unwrap(); // sh_addr
+            shdrs.write_u32::<LittleEndian>(offset).unwrap(); // sh_offset
+            shdrs.write_u32::<LittleEndian>(size).unwrap(); // sh_size
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_link
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_info
+            shdrs.write_u32::<LittleEndian>(4).unwrap(); // sh_addralign
+            shdrs.write_u32::<LittleEndian>(entsize).unwrap(); // sh_entsize
+        }
+        buf[0x800..0x800 + shdrs.len()].copy_from_slice(&shdrs);
+
+        buf
+    }
+
+    #[test]
+    fn plt_stubs() {
+        // jmp [rip+0x2fe2]
+        assert_eq!(
+            Some(0x1000 + 6 + 0x2FE2),
+            decode_plt_stub(Arch::X64, 0x1000, b"\xFF\x25\xE2\x2F\x00\x00\x68\x00", None, 0)
+        );
+
+        // endbr64; bnd jmp [rip+0x2fe2]
+        assert_eq!(
+            Some(0x1000 + 11 + 0x2FE2),
+            decode_plt_stub(
+                Arch::X64,
+                0x1000,
+                b"\xF3\x0F\x1E\xFA\xF2\xFF\x25\xE2\x2F\x00\x00",
+                None,
+                0
+            )
+        );
+
+        // jmp [0x804a00c]
+        assert_eq!(
+            Some(0x804_A00C),
+            decode_plt_stub(Arch::X32, 0x8049030, b"\xFF\x25\x0C\xA0\x04\x08", None, 0)
+        );
+
+        // jmp [ebx+0xc]
+        assert_eq!(
+            Some(0x4000 + 0xC),
+            decode_plt_stub(Arch::X32, 0x1030, b"\xFF\xA3\x0C\x00\x00\x00", Some(0x4000), 0)
+        );
+        assert_eq!(
+            None,
+            decode_plt_stub(Arch::X32, 0x1030, b"\xFF\xA3\x0C\x00\x00\x00", None, 0)
+        );
+
+        // .plt.got: jmp [ebx-0x8]; xchg ax, ax
+        assert_eq!(
+            Some(0x4000 - 0x8),
+            decode_plt_stub(Arch::X32, 0x1060, b"\xFF\xA3\xF8\xFF\xFF\xFF\x66\x90", Some(0x4000), 0)
+        );
+
+        // push 0x0: the lazy binding half of a PLT entry.
+        assert_eq!(
+            None,
+            decode_plt_stub(Arch::X32, 0x1036, b"\x68\x00\x00\x00\x00\xE9", Some(0x4000), 0)
+        );
+    }
+
+    #[test]
+    fn pc_thunks() {
+        // mov ebx, [esp]; ret
+        assert_eq!(Some(3), decode_pc_thunk(b"\x8B\x1C\x24\xC3"));
+        assert_eq!("__x86.get_pc_thunk.bx", get_pc_thunk_name(3));
+
+        // mov eax, [esp]; ret
+        assert_eq!(Some(0), decode_pc_thunk(b"\x8B\x04\x24\xC3"));
+        assert_eq!("__x86.get_pc_thunk.ax", get_pc_thunk_name(0));
+
+        // mov ebx, [esp+4]; ret
+        assert_eq!(None, decode_pc_thunk(b"\x8B\x5C\x24\x04"));
+    }
+
+    #[test]
+    fn got_base() -> Result<()> {
+        let buf = build_pie32_with_got_references();
+        let elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+
+        assert_eq!(Some(0x400), get_elf_got_base(&elf)?);
+        assert_eq!(
+            vec![(0x120, 3)],
+            find_elf_pc_thunks(&elf.module, [0x100, 0x120].iter())
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+
+        let imports = crate::analysis::elf::get_imports(&elf)?;
+        assert_eq!("libc.so.6", imports[&0x40C].library);
+        assert_eq!("puts", imports[&0x40C].symbol.name);
+
+        Ok(())
+    }
+    #[test]
+    fn section_headers() -> Result<()> {
+        use crate::loader::elf::tests::{build_pie, PIE_SLOT};
+
+        // add a `.plt` at 0x500 with PLT0 and a stub at 0x510:
+        //   jmp [rip-0x116]  ; PIE_SLOT
+        let mut buf = build_pie();
+        buf[0x510..0x516].copy_from_slice(b"\xFF\x25\xEA\xFE\xFF\xFF");
+
+        let shstrtab = b"\x00.plt\x00.shstrtab\x00";
+        buf[0x900..0x900 + shstrtab.len()].copy_from_slice(shstrtab);
+
+        let mut shdrs = vec![0u8; 0x40];
+        // Elf64_Shdr: (name, type, flags, offset/addr, size, entsize)
+        for (name, sh_type, flags, offset, size, entsize) in [
+            (
+                1u32,
+                goblin::elf::section_header::SHT_PROGBITS,
+                0x6u64,
+                0x500u64,
+                0x20u64,
+                0x10u64,
+            ),
+            (
+                6,
+                goblin::elf::section_header::SHT_STRTAB,
+                0x0,
+                0x900,
+                shstrtab.len() as u64,
+                0,
+            ),
+        ] {
+            shdrs.write_u32::<LittleEndian>(name).unwrap(); // sh_name
+            shdrs.write_u32::<LittleEndian>(sh_type).unwrap(); // sh_type
+            shdrs.write_u64::<LittleEndian>(flags).unwrap(); // sh_flags
+            shdrs
+                .write_u64::<LittleEndian>(if flags != 0 { offset } else { 0 })
+                .unwrap(); // sh_addr
+            shdrs.write_u64::<LittleEndian>(offset).unwrap(); // sh_offset
+            shdrs.write_u64::<LittleEndian>(size).unwrap(); // sh_size
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_link
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_info
+            shdrs.write_u64::<LittleEndian>(0x10).unwrap(); // sh_addralign
+            shdrs.write_u64::<LittleEndian>(entsize).unwrap(); // sh_entsize
+        }
+        buf[0xA00..0xA00 + shdrs.len()].copy_from_slice(&shdrs);
+
+        buf[0x28..0x30].copy_from_slice(&0xA00u64.to_le_bytes()); // e_shoff
+        buf[0x3C..0x3E].copy_from_slice(&3u16.to_le_bytes()); // e_shnum
+        buf[0x3E..0x40].copy_from_slice(&2u16.to_le_bytes()); // e_shstrndx
+
+        let mut elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        assert_eq!(
+            vec![(0x510, PIE_SLOT)],
+            find_elf_plt_stubs(&elf)?.into_iter().collect::<Vec<_>>()
+        );
+
+        // the section headers are relocated along with the module.
+        elf.rebase(0x5555_5555_4000)?;
+        assert_eq!(
+            vec![(0x5555_5555_4000 + 0x510, 0x5555_5555_4000 + PIE_SLOT)],
+            find_elf_plt_stubs(&elf)?.into_iter().collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+}
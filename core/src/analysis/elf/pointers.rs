@@ -0,0 +1,25 @@
+//! Find pointers to code that were fixed up by dynamic relocations.
+//!
+//! Unlike a scan for pointer-sized values, each of these is known to be a
+//! pointer, so there's no need to guess. These are often vtable entries,
+//! function pointer tables, or `.init_array` entries.
+use anyhow::Result;
+use log::debug;
+
+use crate::{aspace::AddressSpace, loader::elf::ELF, module::Permissions, VA};
+
+pub fn find_elf_relocated_code_pointers(elf: &ELF) -> Result<Vec<VA>> {
+    let mut pointers: Vec<VA> = elf
+        .relocations
+        .iter()
+        .filter_map(|&address| elf.module.address_space.read_pointer(elf.module.arch, address).ok())
+        .filter(|&va| elf.module.probe_va(va, Permissions::X))
+        .collect();
+
+    pointers.sort_unstable();
+    pointers.dedup();
+
+    debug!("elf: found {} relocated pointers to code", pointers.len());
+
+    Ok(pointers)
+}
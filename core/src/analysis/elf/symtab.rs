@@ -9,12 +9,12 @@ use crate::{
 
 pub fn find_symtab_function_starts(elf: &ELF, goblin_elf: &elf::Elf) -> Result<Vec<VA>> {
     let mut function_starts = Vec::new();
-    let base_address = elf.module.address_space.base_address;
+    let load_bias = elf.load_bias();
 
     // parse .symtab
     for sym in &goblin_elf.syms {
         if is_function_symbol(&sym) && sym.st_value != 0 {
-            let address = sym.st_value + base_address;
+            let address = sym.st_value.wrapping_add(load_bias);
             function_starts.push(address);
             debug!("elf: found function symbol at {:#x} (size: {:#x})", address, sym.st_size);
         }
@@ -23,7 +23,7 @@ pub fn find_symtab_function_starts(elf: &ELF, goblin_elf: &elf::Elf) -> Result<V
     // parse .dynsym
     for sym in &goblin_elf.dynsyms {
         if is_function_symbol(&sym) && sym.st_value != 0 {
-            let address = sym.st_value + base_address;
+            let address = sym.st_value.wrapping_add(load_bias);
             function_starts.push(address);
             debug!("elf: found dynamic function symbol at {:#x} (size: {:#x})", address, sym.st_size);
         }
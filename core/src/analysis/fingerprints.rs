@@ -0,0 +1,416 @@
+//! Compute the hashes commonly used to cluster related samples:
+//!   - imphash, the MD5 of the PE import table, compatible with pefile,
+//!   - the ELF import hash, like telfhash, over the imported function names,
+//!   - the export hash, the MD5 of the exported symbol names, and
+//!   - the Rich hash, the MD5 of the decoded Rich header.
+//!
+//! Each hash is `None` when the module doesn't have the structure it covers.
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+use byteorder::{ByteOrder, LittleEndian};
+use goblin::elf::sym::{STB_GLOBAL, STB_WEAK, STT_FUNC, STT_OBJECT};
+use md5::{Digest, Md5};
+
+use crate::{
+    analysis::pe::{get_import_table, ordinals::get_builtin_ordinal_name, ImportedSymbol},
+    loader::{
+        elf::{
+            import::{read_import_symbols, ELFSymbolType, ElfSymbolBinding},
+            ELF,
+        },
+        pe::{rich::RichHeader, PE},
+    },
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fingerprints {
+    /// for PE files, the imphash; for ELF files, the telfhash-like import hash.
+    pub imphash:     Option<String>,
+    pub export_hash: Option<String>,
+    /// only present for PE files.
+    pub rich_hash:   Option<String>,
+}
+
+impl Fingerprints {
+    pub fn from_pe(pe: &PE) -> Result<Fingerprints> {
+        Ok(Fingerprints {
+            imphash:     imphash(pe)?,
+            export_hash: export_hash(pe)?,
+            rich_hash:   rich_hash(pe)?,
+        })
+    }
+
+    pub fn from_elf(elf: &ELF) -> Result<Fingerprints> {
+        Ok(Fingerprints {
+            imphash:     elf_imphash(elf)?,
+            export_hash: elf_export_hash(elf)?,
+            rich_hash:   None,
+        })
+    }
+
+    /// Compute the fingerprints of a PE or ELF file, detecting its format.
+    pub fn from_bytes(buf: &[u8]) -> Result<Fingerprints> {
+        if buf.starts_with(b"MZ") {
+            Fingerprints::from_pe(&PE::from_bytes(buf)?)
+        } else if buf.starts_with(b"\x7FELF") {
+            Fingerprints::from_elf(&ELF::from_bytes(buf)?)
+        } else {
+            Err(anyhow!("fingerprints: unsupported file format"))
+        }
+    }
+}
+
+fn md5_hex(buf: &[u8]) -> String {
+    Md5::digest(buf).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Compute the imphash of the given PE file, as pefile does:
+/// the MD5 of the comma-separated list of `dll.symbol` entries,
+/// in the order of the import directory, and in lowercase.
+///
+/// The DLL extension is dropped when it's `.dll`, `.ocx`, or `.sys`.
+/// Symbols imported by ordinal are named using the built-in table,
+/// or like `ord123` when they're not found there.
+pub fn imphash(pe: &PE) -> Result<Option<String>> {
+    let entries = get_import_table(pe)?
+        .iter()
+        .map(|import| {
+            let dll = import.dll.to_lowercase();
+            let library = match dll.rsplit_once('.') {
+                Some((library, "dll" | "ocx" | "sys")) => library,
+                _ => dll.as_str(),
+            };
+
+            let symbol = match &import.symbol {
+                ImportedSymbol::Name(name) => name.to_lowercase(),
+                ImportedSymbol::Ordinal(ordinal) => match get_builtin_ordinal_name(&dll, *ordinal) {
+                    Some(name) => name.to_lowercase(),
+                    None => format!("ord{ordinal}"),
+                },
+            };
+
+            format!("{library}.{symbol}")
+        })
+        .collect::<Vec<_>>();
+
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(md5_hex(entries.join(",").as_bytes())))
+}
+
+/// Compute the MD5 of the comma-separated, lowercase names
+/// found in the export name table, which is sorted.
+/// Exports without a name are not included.
+pub fn export_hash(pe: &PE) -> Result<Option<String>> {
+    let names = pe
+        .pe()?
+        .exports
+        .iter()
+        .filter_map(|export| export.name)
+        .map(|name| name.to_lowercase())
+        .collect::<Vec<_>>();
+
+    if names.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(md5_hex(names.join(",").as_bytes())))
+}
+
+/// Compute the MD5 of the decoded Rich header, from the `DanS` marker up to
+/// the `Rich` marker, as pefile and VirusTotal do.
+pub fn rich_hash(pe: &PE) -> Result<Option<String>> {
+    let Some(rich) = RichHeader::from_pe(pe)? else {
+        return Ok(None);
+    };
+
+    let Some(buf) = pe.buf.get(rich.offset..rich.offset + rich.size) else {
+        return Ok(None);
+    };
+
+    let decoded = buf
+        .chunks_exact(4)
+        .flat_map(|dword| (LittleEndian::read_u32(dword) ^ rich.key).to_le_bytes())
+        .collect::<Vec<u8>>();
+
+    Ok(Some(md5_hex(&decoded)))
+}
+
+/// Symbols that telfhash ignores because they're linked into most programs,
+/// regardless of what they do.
+const ELF_EXCLUDED_SYMBOLS: &[&str] = &[
+    "__libc_start_main",
+    "main",
+    "abort",
+    "cachectl",
+    "cacheflush",
+    "puts",
+    "atol",
+    "malloc_trim",
+];
+
+/// Select the symbols as telfhash does,
+/// ignoring runtime internals (`_` and `.` prefixes),
+/// the 64-bit variants of file APIs (`64` suffix),
+/// and the string and memory routines that compilers may inline.
+fn is_elf_symbol_hashed(name: &str) -> bool {
+    !(name.is_empty()
+        || name.starts_with('_')
+        || name.starts_with('.')
+        || name.ends_with("64")
+        || name.starts_with("str")
+        || name.starts_with("mem")
+        || ELF_EXCLUDED_SYMBOLS.contains(&name))
+}
+
+fn hash_elf_symbols<'a>(names: impl Iterator<Item = &'a str>) -> Option<String> {
+    let names = names
+        .filter(|&name| is_elf_symbol_hashed(name))
+        .map(|name| name.to_lowercase())
+        .collect::<BTreeSet<_>>();
+
+    if names.is_empty() {
+        return None;
+    }
+
+    Some(md5_hex(names.into_iter().collect::<Vec<_>>().join(",").as_bytes()))
+}
+
+/// Compute the ELF equivalent of the imphash,
+/// over the sorted names of the imported global functions,
+/// selected as telfhash does.
+///
+/// Unlike telfhash, this is an MD5 rather than a TLSH digest,
+/// so it only matches modules with the same set of imports.
+pub fn elf_imphash(elf: &ELF) -> Result<Option<String>> {
+    let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+
+    let symbols = read_import_symbols(&goblin_elf, &elf.symbol_versions);
+    Ok(hash_elf_symbols(
+        symbols
+            .iter()
+            .filter(|symbol| {
+                symbol.symbol_type == ELFSymbolType::Function && symbol.visibility == ElfSymbolBinding::Global
+            })
+            .map(|symbol| symbol.name.as_str()),
+    ))
+}
+
+/// Compute the MD5 over the sorted names of the global functions and objects
+/// defined in the dynamic symbol table.
+pub fn elf_export_hash(elf: &ELF) -> Result<Option<String>> {
+    let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+
+    let names = goblin_elf
+        .dynsyms
+        .iter()
+        .filter(|sym| sym.st_shndx != 0)
+        .filter(|sym| matches!(sym.st_bind(), STB_GLOBAL | STB_WEAK))
+        .filter(|sym| matches!(sym.st_type(), STT_FUNC | STT_OBJECT))
+        .filter_map(|sym| goblin_elf.dynstrtab.get_at(sym.st_name))
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_lowercase())
+        .collect::<BTreeSet<_>>();
+
+    if names.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(md5_hex(
+        names.into_iter().collect::<Vec<_>>().join(",").as_bytes(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::{
+        loader::{
+            elf::tests::build_pie,
+            pe::{
+                rich::tests::{add_rich_header, RICH_ENTRIES},
+                tests::{add_data_directory, build_mapped_pe, IAT_RVA, IMPORT_DESCRIPTOR_RVA},
+            },
+        },
+        rsrc::*,
+    };
+
+    /// Replace the imports of `build_mapped_pe` with:
+    ///
+    /// ```text
+    ///   KERNEL32.dll!ExitProcess
+    ///   WS2_32.dll!#115 (WSAStartup)
+    ///   OLEAUT32.dll!#9999 (unknown)
+    /// ```
+    fn build_pe_with_imports() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        // three descriptors and the terminator, with the names at 0x1200.
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_IMPORT,
+            IMPORT_DESCRIPTOR_RVA,
+            0x50,
+            0x400,
+        );
+
+        buf[0x1200..0x120C].copy_from_slice(b"WS2_32.dll\x00\x00");
+        buf[0x1210..0x121D].copy_from_slice(b"OLEAUT32.dll\x00");
+        buf[0x1222..0x122E].copy_from_slice(b"ExitProcess\x00");
+
+        for (i, (name, thunk)) in [
+            (0x11C0u32, 0x1220u64),
+            (0x1200, 0x8000_0000_0000_0000 | 115),
+            (0x1210, 0x8000_0000_0000_0000 | 9999),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let oft = 0x1150 + 0x10 * i as u32;
+            let ft = IAT_RVA as u32 + 0x10 * i as u32;
+
+            let mut descriptor = vec![];
+            descriptor.write_u32::<LittleEndian>(oft).unwrap(); // OriginalFirstThunk
+            descriptor.write_u32::<LittleEndian>(0).unwrap(); // TimeDateStamp
+            descriptor.write_u32::<LittleEndian>(0).unwrap(); // ForwarderChain
+            descriptor.write_u32::<LittleEndian>(name).unwrap(); // Name
+            descriptor.write_u32::<LittleEndian>(ft).unwrap(); // FirstThunk
+            let offset = IMPORT_DESCRIPTOR_RVA as usize + 20 * i;
+            buf[offset..offset + 20].copy_from_slice(&descriptor);
+
+            for table in [oft as usize, ft as usize] {
+                buf[table..table + 8].copy_from_slice(&thunk.to_le_bytes());
+                buf[table + 8..table + 16].copy_from_slice(&0u64.to_le_bytes());
+            }
+        }
+
+        buf
+    }
+
+    #[test]
+    fn pe() -> Result<()> {
+        let mut buf = build_pe_with_imports();
+        add_rich_header(&mut buf);
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+
+        let fingerprints = Fingerprints::from_pe(&pe)?;
+        assert_eq!(
+            Some(md5_hex(b"kernel32.exitprocess,ws2_32.wsastartup,oleaut32.ord9999")),
+            fingerprints.imphash
+        );
+        assert_eq!(None, fingerprints.export_hash);
+
+        let mut rich = b"DanS".to_vec();
+        rich.extend_from_slice(&[0u8; 12]);
+        for (product_id, build, count) in RICH_ENTRIES {
+            rich.write_u32::<LittleEndian>(((product_id as u32) << 16) | build as u32)
+                .unwrap();
+            rich.write_u32::<LittleEndian>(count).unwrap();
+        }
+        assert_eq!(Some(md5_hex(&rich)), fingerprints.rich_hash);
+
+        Ok(())
+    }
+
+    #[test]
+    fn imphash_ordinals() -> Result<()> {
+        let mut buf = build_pe_with_imports();
+        // import WS2_32.dll!#24 and OLEAUT32.dll!#411 instead,
+        // in both the import lookup table and the IAT.
+        for (i, ordinal) in [(1u32, 24u64), (2, 411)] {
+            for table in [0x1150 + 0x10 * i as usize, IAT_RVA as usize + 0x10 * i as usize] {
+                buf[table..table + 8].copy_from_slice(&(0x8000_0000_0000_0000 | ordinal).to_le_bytes());
+            }
+        }
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+
+        // as computed by pefile for
+        // `kernel32.exitprocess,ws2_32.getaddrinfow,oleaut32.safearraycreatevector`.
+        assert_eq!(Some("16bebc0fc326733d7262e6f941e4a67f".to_string()), imphash(&pe)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pe_without_imports() -> Result<()> {
+        let mut buf = build_mapped_pe();
+        // clear the import directory.
+        buf[0x110..0x118].copy_from_slice(&[0u8; 8]);
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+
+        assert_eq!(Fingerprints::default(), Fingerprints::from_pe(&pe)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn elf_symbols() {
+        assert_eq!(
+            Some(md5_hex(b"connect,fork,socket")),
+            hash_elf_symbols(
+                [
+                    "socket",
+                    "__libc_start_main",
+                    "_ITM_registerTMCloneTable",
+                    "fopen64",
+                    "strlen",
+                    "memcpy",
+                    "connect",
+                    "Fork",
+                    "puts",
+                    "connect",
+                ]
+                .into_iter()
+            )
+        );
+        assert_eq!(None, hash_elf_symbols(["main", "strcpy"].into_iter()));
+    }
+
+    #[test]
+    fn elf() -> Result<()> {
+        let elf = ELF::from_bytes(&build_pie())?;
+        assert_eq!(Fingerprints::default(), Fingerprints::from_elf(&elf)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn k32() -> Result<()> {
+        let buf = get_buf(Rsrc::K32);
+        let pe = PE::from_bytes(&buf)?;
+
+        assert_eq!(
+            Fingerprints {
+                imphash:     Some("100f313c3eeb0e6bb4bcd10918d650f0".to_string()),
+                export_hash: Some("45259597a0043963d8db986527ae5cc7".to_string()),
+                rich_hash:   Some("3863b1aa5e7189361625f8711c7d9ce8".to_string()),
+            },
+            Fingerprints::from_pe(&pe)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mimi() -> Result<()> {
+        // imports by ordinal from cabinet.dll, oleaut32.dll, and wldap32.dll.
+        let buf = get_buf(Rsrc::MIMI);
+
+        assert_eq!(
+            Fingerprints {
+                imphash:     Some("f0d0a258ef4645aabe53a8c67d59a6e0".to_string()),
+                export_hash: None,
+                rich_hash:   Some("5ed46cd820490d3367963808a74baee8".to_string()),
+            },
+            Fingerprints::from_bytes(&buf)?
+        );
+
+        Ok(())
+    }
+}
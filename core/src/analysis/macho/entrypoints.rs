@@ -0,0 +1,34 @@
+//! Find the entry point from LC_MAIN (or the older LC_UNIXTHREAD).
+//!
+//! Dylibs and bundles don't have an entry point.
+use anyhow::Result;
+use log::debug;
+
+use crate::{loader::macho::MachO, VA};
+
+pub fn find_macho_entrypoint(macho: &MachO) -> Result<Vec<VA>> {
+    let entry_point = macho.macho()?.entry;
+    if entry_point == 0 {
+        return Ok(vec![]);
+    }
+
+    debug!("entry point: {entry_point:#x}");
+    Ok(vec![entry_point])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::macho::tests::*;
+    use anyhow::Result;
+
+    #[test]
+    fn main() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        let fns = crate::analysis::macho::entrypoints::find_macho_entrypoint(&macho)?;
+        assert_eq!(vec![TEXT_VMADDR + MAIN_OFFSET], fns);
+
+        Ok(())
+    }
+}
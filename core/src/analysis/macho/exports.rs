@@ -0,0 +1,48 @@
+//! Parse the export trie (if present) to find exported code.
+//!
+//! Like PEs, Mach-O images may export data, which won't be in an executable
+//! segment.
+use anyhow::Result;
+use goblin::mach::exports::ExportInfo;
+use log::debug;
+
+use crate::{analysis::macho::get_text_address, loader::macho::MachO, module::Permissions, VA};
+
+pub fn find_macho_exports(macho: &MachO) -> Result<Vec<VA>> {
+    let m = macho.macho()?;
+
+    let base_address = match get_text_address(&m) {
+        Some(va) => va,
+        None => return Ok(vec![]),
+    };
+
+    let exports = match m.exports() {
+        Ok(exports) => exports,
+        Err(e) => {
+            debug!("exports: failed to parse export trie: {}", e);
+            return Ok(vec![]);
+        }
+    };
+
+    let exports: Vec<VA> = exports
+        .iter()
+        .filter_map(|exp| match exp.info {
+            // the address is untrusted, so skip it rather than wrap around.
+            ExportInfo::Regular { address, .. } => base_address.checked_add(address),
+            // re-exports point to another dylib, so they're not code in this image.
+            // stub-and-resolver exports are rare, and the resolver will be found via
+            // function starts.
+            _ => None,
+        })
+        .filter(|&va| macho.module.probe_va(va, Permissions::X))
+        .collect();
+
+    for export in exports.iter() {
+        debug!("export: {export:#x}");
+    }
+    if exports.is_empty() {
+        debug!("exports: none");
+    }
+
+    Ok(exports)
+}
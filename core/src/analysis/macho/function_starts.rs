@@ -0,0 +1,116 @@
+//! Parse LC_FUNCTION_STARTS, which the linker emits for (nearly) every
+//! function, even when the symbol table has been stripped.
+//!
+//! The data is a sequence of ULEB128-encoded deltas, the first of which is
+//! relative to the start of the __TEXT segment, terminated by a zero delta.
+use anyhow::Result;
+use goblin::mach::load_command::CommandVariant;
+use log::debug;
+
+use crate::{analysis::macho::get_text_address, loader::macho::MachO, VA};
+
+/// read a ULEB128 value from the start of the given buffer,
+/// returning the value and the number of bytes consumed.
+fn read_uleb128(buf: &[u8]) -> Option<(u64, usize)> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    for (i, &b) in buf.iter().enumerate() {
+        if shift >= 64 {
+            return None;
+        }
+        value |= ((b & 0x7F) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+        shift += 7;
+    }
+    None
+}
+
+pub fn find_macho_function_starts(macho: &MachO) -> Result<Vec<VA>> {
+    let m = macho.macho()?;
+
+    let command = m.load_commands.iter().find_map(|cmd| match cmd.command {
+        CommandVariant::FunctionStarts(command) => Some(command),
+        _ => None,
+    });
+    let command = match command {
+        Some(command) => command,
+        None => {
+            debug!("function starts: none");
+            return Ok(vec![]);
+        }
+    };
+
+    let start = command.dataoff as usize;
+    let end = start + command.datasize as usize;
+    if end > macho.buf.len() {
+        debug!("function starts: data out of bounds");
+        return Ok(vec![]);
+    }
+    let mut buf = &macho.buf[start..end];
+
+    let mut va = match get_text_address(&m) {
+        Some(va) => va,
+        None => return Ok(vec![]),
+    };
+
+    let mut function_starts = vec![];
+    while let Some((delta, size)) = read_uleb128(buf) {
+        if delta == 0 {
+            break;
+        }
+
+        // the deltas are untrusted, so stop rather than wrap around.
+        va = match va.checked_add(delta) {
+            Some(va) => va,
+            None => {
+                debug!("function starts: delta overflows: {:#x}", delta);
+                break;
+            }
+        };
+        function_starts.push(va);
+        buf = &buf[size..];
+    }
+
+    debug!("function starts: found {}", function_starts.len());
+
+    Ok(function_starts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::macho::tests::*;
+    use anyhow::Result;
+
+    #[test]
+    fn uleb128() {
+        assert_eq!(Some((0x400, 2)), super::read_uleb128(b"\x80\x08"));
+        assert_eq!(Some((0x10, 1)), super::read_uleb128(b"\x10\x80"));
+        assert_eq!(None, super::read_uleb128(b"\x80"));
+    }
+
+    #[test]
+    fn function_starts() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        let fns = crate::analysis::macho::function_starts::find_macho_function_starts(&macho)?;
+        assert_eq!(vec![TEXT_VMADDR + MAIN_OFFSET, TEXT_VMADDR + HELPER_OFFSET], fns);
+
+        Ok(())
+    }
+
+    #[test]
+    fn delta_overflow() -> Result<()> {
+        let mut buf = build_macho();
+        // the delta to `main`, then u64::MAX, which overflows.
+        buf[0x2000..0x200C].copy_from_slice(b"\x80\x08\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x01");
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        let fns = crate::analysis::macho::function_starts::find_macho_function_starts(&macho)?;
+        assert_eq!(vec![TEXT_VMADDR + MAIN_OFFSET], fns);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use goblin::mach::constants::{S_LAZY_SYMBOL_POINTERS, S_NON_LAZY_SYMBOL_POINTERS, S_SYMBOL_STUBS};
+use log::debug;
+
+use crate::{
+    analysis::pe::{Import, ImportedSymbol},
+    loader::macho::MachO,
+    module::Permissions,
+    VA,
+};
+
+pub mod entrypoints;
+pub mod exports;
+pub mod function_starts;
+pub mod symbols;
+
+#[cfg(feature = "disassembler")]
+pub mod noret_imports;
+
+/// the indirect symbol refers to a local symbol that has been stripped.
+const INDIRECT_SYMBOL_LOCAL: u32 = 0x8000_0000;
+/// the indirect symbol refers to an absolute symbol.
+const INDIRECT_SYMBOL_ABS: u32 = 0x4000_0000;
+
+/// The address of the image header, which is the start of the __TEXT segment.
+/// Offsets in LC_MAIN, LC_FUNCTION_STARTS and the export trie are relative to
+/// this.
+pub fn get_text_address(macho: &goblin::mach::MachO) -> Option<VA> {
+    macho
+        .segments
+        .iter()
+        .find(|segment| segment.fileoff == 0 && segment.filesize != 0)
+        .map(|segment| segment.vmaddr)
+}
+
+/// like `/usr/lib/libSystem.B.dylib` -> `libSystem.B.dylib`
+/// or `/System/Library/Frameworks/Foundation.framework/Versions/C/Foundation`
+/// -> `Foundation`.
+fn get_dylib_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+/// Resolve an entry in the indirect symbol table to an imported symbol.
+fn get_indirect_import(macho: &MachO, m: &goblin::mach::MachO, index: usize, address: VA) -> Option<Import> {
+    let symbol_index = *macho.indirect_symbols.get(index)?;
+    if symbol_index & (INDIRECT_SYMBOL_LOCAL | INDIRECT_SYMBOL_ABS) != 0 {
+        return None;
+    }
+
+    let (name, nlist) = m.symbols.as_ref()?.get(symbol_index as usize).ok()?;
+    if !nlist.is_undefined() {
+        return None;
+    }
+
+    // two-level namespace: the library ordinal is found in the high byte of n_desc.
+    // ordinal 0 is this image, and the dylibs are numbered from 1 in load command
+    // order, which matches goblin's `libs` (where index 0 is "self").
+    let ordinal = ((nlist.n_desc >> 8) & 0xFF) as usize;
+    let dylib = match m.libs.get(ordinal) {
+        Some(lib) => get_dylib_name(lib),
+        None => "unknown".to_string(),
+    };
+
+    Some(Import {
+        address,
+        dll: dylib,
+        symbol: ImportedSymbol::Name(name.to_string()),
+    })
+}
+
+/// Find the imported symbols, indexed by the address of their symbol pointer.
+/// That is, the slot in `__got` or `__la_symbol_ptr` that's referenced by code
+/// (usually via a stub).
+pub fn get_imports(macho: &MachO) -> Result<BTreeMap<VA, Import>> {
+    let m = macho.macho()?;
+    let mut imports: BTreeMap<VA, Import> = Default::default();
+
+    // the dyld bind opcodes (both regular and lazy) describe which slot is bound
+    // to which symbol.
+    match m.imports() {
+        Ok(bound) => {
+            for import in bound.iter() {
+                imports.insert(
+                    import.address,
+                    Import {
+                        address: import.address,
+                        dll:     get_dylib_name(import.dylib),
+                        symbol:  ImportedSymbol::Name(import.name.to_string()),
+                    },
+                );
+            }
+        }
+        Err(e) => debug!("macho: imports: failed to interpret bind opcodes: {}", e),
+    }
+
+    // newer binaries use chained fixups rather than bind opcodes,
+    // so also consult the indirect symbol table for the symbol pointer sections.
+    let psize = macho.module.arch.pointer_size() as u64;
+    for section in macho.section_headers.iter().filter(|section| {
+        matches!(
+            section.section_type(),
+            S_LAZY_SYMBOL_POINTERS | S_NON_LAZY_SYMBOL_POINTERS
+        )
+    }) {
+        for i in 0..(section.size / psize) {
+            let address = section.addr + i * psize;
+            if imports.contains_key(&address) {
+                continue;
+            }
+
+            if let Some(import) = get_indirect_import(macho, &m, section.reserved1 as usize + i as usize, address) {
+                imports.insert(address, import);
+            }
+        }
+    }
+
+    for import in imports.values() {
+        debug!("macho: import: {:#x}: {}", import.address, import);
+    }
+
+    Ok(imports)
+}
+
+/// Find the symbol stubs, like those in `__stubs`, indexed by the address of
+/// the stub. Code calls these stubs, which jump through the lazy symbol
+/// pointers.
+pub fn get_stubs(macho: &MachO) -> Result<BTreeMap<VA, Import>> {
+    let m = macho.macho()?;
+    let mut stubs: BTreeMap<VA, Import> = Default::default();
+
+    for section in macho
+        .section_headers
+        .iter()
+        .filter(|section| section.section_type() == S_SYMBOL_STUBS)
+    {
+        let stub_size = section.reserved2 as u64;
+        if stub_size == 0 {
+            debug!(
+                "macho: stubs: {},{}: invalid stub size",
+                section.segname, section.sectname
+            );
+            continue;
+        }
+
+        for i in 0..(section.size / stub_size) {
+            let address = section.addr + i * stub_size;
+            if let Some(import) = get_indirect_import(macho, &m, section.reserved1 as usize + i as usize, address) {
+                debug!("macho: stub: {:#x}: {}", address, import);
+                stubs.insert(address, import);
+            }
+        }
+    }
+
+    Ok(stubs)
+}
+
+pub fn find_function_starts(macho: &MachO) -> Result<Vec<VA>> {
+    let mut function_starts: Vec<VA> = Default::default();
+
+    function_starts.extend(entrypoints::find_macho_entrypoint(macho)?);
+    function_starts.extend(function_starts::find_macho_function_starts(macho)?);
+    function_starts.extend(exports::find_macho_exports(macho)?);
+    function_starts.extend(get_stubs(macho)?.into_keys());
+
+    function_starts.sort_unstable();
+    function_starts.dedup();
+    function_starts.retain(|&va| macho.module.probe_va(va, Permissions::X));
+
+    debug!("macho: found {} function starts", function_starts.len());
+
+    Ok(function_starts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::macho::tests::*;
+    use anyhow::Result;
+
+    #[test]
+    fn function_starts() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        let fns = crate::analysis::macho::find_function_starts(&macho)?;
+        assert_eq!(
+            vec![
+                TEXT_VMADDR + MAIN_OFFSET,
+                TEXT_VMADDR + HELPER_OFFSET,
+                TEXT_VMADDR + STUB_OFFSET
+            ],
+            fns
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn imports() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        let imports = crate::analysis::macho::get_imports(&macho)?;
+        assert_eq!(1, imports.len());
+        let import = &imports[&(TEXT_VMADDR + LAZY_POINTER_OFFSET)];
+        assert_eq!("libSystem.B.dylib!_exit", import.to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn stubs() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        let stubs = crate::analysis::macho::get_stubs(&macho)?;
+        assert_eq!(1, stubs.len());
+        let stub = &stubs[&(TEXT_VMADDR + STUB_OFFSET)];
+        assert_eq!("libSystem.B.dylib!_exit", stub.to_string());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use crate::{
+    analysis::{cfg::CFG, macho, pe::ImportedSymbol},
+    loader::macho::MachO,
+    VA,
+};
+
+pub fn cfg_prune_noret_imports(macho: &MachO, cfg: &mut CFG) -> Result<BTreeSet<VA>> {
+    // symbols are resolved through libSystem, which re-exports from
+    // libsystem_c and friends, so don't bother matching the dylib name.
+    let mut noret = macho::get_imports(macho)?
+        .values()
+        .filter(|imp| match &imp.symbol {
+            ImportedSymbol::Name(symbol) => matches!(
+                &**symbol,
+                "_exit" | "__exit" | "_abort" | "___stack_chk_fail" | "___assert_rtn" | "___cxa_throw"
+            ),
+            ImportedSymbol::Ordinal(_) => false,
+        })
+        .map(|imp| imp.address)
+        .collect::<BTreeSet<_>>();
+
+    for &noret_import in noret.clone().iter() {
+        log::debug!("noret import {:#x}", noret_import);
+        noret.extend(crate::analysis::cfg::noret::cfg_mark_noret(
+            &macho.module,
+            cfg,
+            noret_import,
+        )?);
+    }
+
+    Ok(noret)
+}
@@ -0,0 +1,41 @@
+//! Collect the names of symbols defined in this image from the symbol table.
+//!
+//! Stripped images will still contain the exported symbols.
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use goblin::mach::symbols::{N_SECT, N_STAB};
+use log::debug;
+
+use crate::{loader::macho::MachO, VA};
+
+pub fn find_macho_symbols(macho: &MachO) -> Result<BTreeMap<VA, String>> {
+    let mut symbols: BTreeMap<VA, String> = Default::default();
+
+    for symbol in macho.macho()?.symbols() {
+        let (name, nlist) = match symbol {
+            Ok(symbol) => symbol,
+            Err(e) => {
+                debug!("symbols: failed to parse symbol: {}", e);
+                continue;
+            }
+        };
+
+        if nlist.n_type & N_STAB != 0 {
+            // debugging entries
+            continue;
+        }
+
+        if nlist.get_type() != N_SECT || name.is_empty() {
+            continue;
+        }
+
+        // there may be multiple names at one address,
+        // like aliases, so prefer the first (global symbols come after local symbols).
+        symbols.entry(nlist.n_value).or_insert_with(|| name.to_string());
+    }
+
+    debug!("symbols: found {}", symbols.len());
+
+    Ok(symbols)
+}
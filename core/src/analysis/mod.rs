@@ -7,4 +7,9 @@ pub mod flirt;
 #[cfg(feature = "disassembler")]
 pub mod heuristics;
 pub mod elf;
+pub mod fingerprints;
+pub mod macho;
 pub mod pe;
+#[cfg(feature = "disassembler")]
+pub mod shellcode;
+pub mod toolchain;
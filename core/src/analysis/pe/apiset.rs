@@ -0,0 +1,362 @@
+//! Resolve API set contracts, like `api-ms-win-core-synch-l1-2-0.dll`, to the
+//! DLLs that host them, like `kernelbase.dll`.
+//!
+//! The Windows loader redirects imports from API set contracts using the
+//! schema embedded in the `.apiset` section of `apisetschema.dll`. The schema
+//! changes across Windows releases, so we prefer the one provided by the user
+//! and fall back to a built-in table of the contract families, which follows
+//! Windows 10.
+//!
+//! references:
+//!   - https://learn.microsoft.com/en-us/windows/win32/apiindex/windows-apisets
+//!   - https://lucasg.github.io/2017/10/15/Api-set-resolution/
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use log::debug;
+use thiserror::Error;
+
+use crate::loader::pe::PE;
+
+#[derive(Debug, Error)]
+pub enum ApiSetError {
+    #[error("apiset: section not found")]
+    SectionNotFound,
+    #[error("apiset: unsupported schema version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("apiset: invalid schema")]
+    InvalidSchema,
+}
+
+/// The hosts of the contract families, from most to least specific prefix.
+const BUILTIN_HOSTS: &[(&str, &str)] = &[
+    ("api-ms-win-core-com-", "combase.dll"),
+    ("api-ms-win-core-winrt-", "combase.dll"),
+    ("api-ms-win-core-rtlsupport-", "ntdll.dll"),
+    ("api-ms-win-core-kernel32-legacy-", "kernel32.dll"),
+    ("api-ms-win-core-", "kernelbase.dll"),
+    ("api-ms-win-crt-", "ucrtbase.dll"),
+    ("api-ms-win-eventing-provider-", "kernelbase.dll"),
+    ("api-ms-win-eventing-", "sechost.dll"),
+    ("api-ms-win-security-base-", "kernelbase.dll"),
+    ("api-ms-win-security-", "sechost.dll"),
+    ("api-ms-win-service-", "sechost.dll"),
+    ("api-ms-win-shcore-", "shcore.dll"),
+    ("ext-ms-win-advapi32-", "advapi32.dll"),
+    ("ext-ms-win-gdi-", "gdi32.dll"),
+    ("ext-ms-win-kernel32-", "kernel32.dll"),
+    ("ext-ms-win-ntuser-", "user32.dll"),
+    ("ext-ms-win-ole32-", "ole32.dll"),
+    ("ext-ms-win-rtcore-ntuser-", "user32.dll"),
+    ("ext-ms-win-shell32-", "shell32.dll"),
+];
+
+/// Find the host of the given contract, like `api-ms-win-core-synch-l1-2-0`,
+/// using the built-in table.
+fn get_builtin_host(contract: &str) -> Option<String> {
+    // like api-ms-win-downlevel-advapi32-l1-1-0, implemented by advapi32.dll
+    if let Some(rest) = contract.strip_prefix("api-ms-win-downlevel-") {
+        return rest.split_once('-').map(|(dll, _)| format!("{dll}.dll"));
+    }
+
+    BUILTIN_HOSTS
+        .iter()
+        .filter(|(prefix, _)| contract.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, host)| host.to_string())
+}
+
+/// Normalize the name of a contract so that it can be found in the schema,
+/// which, like the Windows loader, ignores the extension and the final
+/// version component: `api-ms-win-core-synch-l1-2-0.dll` becomes
+/// `api-ms-win-core-synch-l1-2`.
+///
+/// Returns `None` for the names of regular DLLs.
+fn get_contract_name(dll: &str) -> Option<String> {
+    let dll = dll.to_lowercase();
+    if !(dll.starts_with("api-") || dll.starts_with("ext-")) {
+        return None;
+    }
+
+    let dll = dll.strip_suffix(".dll").unwrap_or(&dll);
+    Some(dll.rsplit_once('-').map(|(name, _)| name).unwrap_or(dll).to_string())
+}
+
+fn read_utf16(buf: &[u8], offset: u32, length: u32) -> Result<String> {
+    let start = offset as usize;
+    let end = start + length as usize;
+    let Some(buf) = buf.get(start..end) else {
+        return Err(ApiSetError::InvalidSchema.into());
+    };
+
+    let chars = buf.chunks_exact(2).map(LittleEndian::read_u16).collect::<Vec<u16>>();
+    Ok(String::from_utf16_lossy(&chars).to_lowercase())
+}
+
+/// The hosts of API set contracts,
+/// from a schema provided by the user, or from the built-in table.
+#[derive(Debug, Clone, Default)]
+pub struct ApiSetSchema {
+    /// from contract name, without the final version component,
+    /// to the lowercase name of the default host, like `kernelbase.dll`.
+    /// the host is empty when the contract isn't implemented on the system.
+    hosts: BTreeMap<String, String>,
+}
+
+impl ApiSetSchema {
+    /// Read the schema from the `.apiset` section of `apisetschema.dll`.
+    pub fn from_pe(pe: &PE) -> Result<ApiSetSchema> {
+        let p = pe.pe()?;
+        let Some(section) = p.sections.iter().find(|section| section.name().ok() == Some(".apiset")) else {
+            return Err(ApiSetError::SectionNotFound.into());
+        };
+
+        let start = section.pointer_to_raw_data as usize;
+        let end = start + section.size_of_raw_data as usize;
+        let Some(buf) = pe.buf.get(start..end) else {
+            return Err(ApiSetError::InvalidSchema.into());
+        };
+
+        ApiSetSchema::from_namespace(buf)
+    }
+
+    /// Read the schema from the file at the given path, like
+    /// `C:\Windows\System32\apisetschema.dll`.
+    pub fn from_path(path: &Path) -> Result<ApiSetSchema> {
+        let buf = std::fs::read(path)?;
+        ApiSetSchema::from_pe(&PE::from_bytes(&buf)?)
+    }
+
+    /// Parse the schema from an `API_SET_NAMESPACE` structure.
+    /// Only version 6 of the schema, used since Windows 10, is supported.
+    pub fn from_namespace(buf: &[u8]) -> Result<ApiSetSchema> {
+        // API_SET_NAMESPACE:
+        //   u32 Version
+        //   u32 Size
+        //   u32 Flags
+        //   u32 Count
+        //   u32 EntryOffset
+        //   u32 HashOffset
+        //   u32 HashFactor
+        if buf.len() < 0x1C {
+            return Err(ApiSetError::InvalidSchema.into());
+        }
+
+        let version = LittleEndian::read_u32(&buf[0x0..]);
+        if version != 6 {
+            return Err(ApiSetError::UnsupportedVersion(version).into());
+        }
+        let count = LittleEndian::read_u32(&buf[0xC..]) as usize;
+        let entry_offset = LittleEndian::read_u32(&buf[0x10..]) as usize;
+
+        let mut hosts: BTreeMap<String, String> = Default::default();
+        for i in 0..count {
+            // API_SET_NAMESPACE_ENTRY:
+            //   u32 Flags
+            //   u32 NameOffset
+            //   u32 NameLength
+            //   u32 HashedLength
+            //   u32 ValueOffset
+            //   u32 ValueCount
+            let offset = entry_offset + i * 0x18;
+            let Some(entry) = buf.get(offset..offset + 0x18) else {
+                return Err(ApiSetError::InvalidSchema.into());
+            };
+            let name_offset = LittleEndian::read_u32(&entry[0x4..]);
+            let hashed_length = LittleEndian::read_u32(&entry[0xC..]);
+            let value_offset = LittleEndian::read_u32(&entry[0x10..]) as usize;
+            let value_count = LittleEndian::read_u32(&entry[0x14..]) as usize;
+
+            let name = read_utf16(buf, name_offset, hashed_length)?;
+
+            // API_SET_VALUE_ENTRY:
+            //   u32 Flags
+            //   u32 NameOffset
+            //   u32 NameLength
+            //   u32 ValueOffset
+            //   u32 ValueLength
+            //
+            // the default host is the value without a name,
+            // while the others apply to the named importing modules.
+            let mut host = String::new();
+            for j in 0..value_count {
+                let offset = value_offset + j * 0x14;
+                let Some(value) = buf.get(offset..offset + 0x14) else {
+                    return Err(ApiSetError::InvalidSchema.into());
+                };
+
+                if LittleEndian::read_u32(&value[0x8..]) != 0 {
+                    continue;
+                }
+
+                host = read_utf16(
+                    buf,
+                    LittleEndian::read_u32(&value[0xC..]),
+                    LittleEndian::read_u32(&value[0x10..]),
+                )?;
+                break;
+            }
+
+            hosts.insert(name, host);
+        }
+
+        debug!("apiset: found {} contracts", hosts.len());
+
+        Ok(ApiSetSchema { hosts })
+    }
+
+    /// Find the lowercase name of the DLL that hosts the given API set
+    /// contract, like `kernelbase.dll` for `api-ms-win-core-synch-l1-2-0.dll`.
+    ///
+    /// Returns `None` when the DLL isn't a contract, or its host is unknown.
+    pub fn resolve(&self, dll: &str) -> Option<String> {
+        let contract = get_contract_name(dll)?;
+
+        match self.hosts.get(&contract) {
+            Some(host) if host.is_empty() => None,
+            Some(host) => Some(host.clone()),
+            None => get_builtin_host(&contract),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+
+    /// Build an `API_SET_NAMESPACE` with the given contracts,
+    /// each with a list of (importing module, host) values.
+    fn build_namespace(contracts: &[(&str, &[(&str, &str)])]) -> Vec<u8> {
+        let entry_offset = 0x1C;
+        let mut strings_offset = entry_offset + 0x18 * contracts.len();
+        let values_offset = strings_offset;
+        let value_count = contracts.iter().map(|(_, values)| values.len()).sum::<usize>();
+        strings_offset += 0x14 * value_count;
+
+        let mut header = vec![];
+        let mut entries = vec![];
+        let mut values = vec![];
+        let mut strings = vec![];
+
+        let mut add_string = |s: &str| -> (u32, u32) {
+            let offset = strings_offset + strings.len();
+            for c in s.encode_utf16() {
+                strings.write_u16::<LittleEndian>(c).unwrap();
+            }
+            (offset as u32, (s.len() * 2) as u32)
+        };
+
+        let mut value_index = 0;
+        for (name, contract_values) in contracts.iter() {
+            let (name_offset, name_length) = add_string(name);
+            let hashed_length = name.rsplit_once('-').map(|(name, _)| name.len() * 2).unwrap() as u32;
+
+            entries.write_u32::<LittleEndian>(0).unwrap(); // Flags
+            entries.write_u32::<LittleEndian>(name_offset).unwrap();
+            entries.write_u32::<LittleEndian>(name_length).unwrap();
+            entries.write_u32::<LittleEndian>(hashed_length).unwrap();
+            entries
+                .write_u32::<LittleEndian>((values_offset + 0x14 * value_index) as u32)
+                .unwrap();
+            entries.write_u32::<LittleEndian>(contract_values.len() as u32).unwrap();
+
+            for (importer, host) in contract_values.iter() {
+                let (importer_offset, importer_length) = add_string(importer);
+                let (host_offset, host_length) = add_string(host);
+
+                values.write_u32::<LittleEndian>(0).unwrap(); // Flags
+                values.write_u32::<LittleEndian>(importer_offset).unwrap();
+                values.write_u32::<LittleEndian>(importer_length).unwrap();
+                values.write_u32::<LittleEndian>(host_offset).unwrap();
+                values.write_u32::<LittleEndian>(host_length).unwrap();
+                value_index += 1;
+            }
+        }
+
+        header.write_u32::<LittleEndian>(6).unwrap(); // Version
+        header.write_u32::<LittleEndian>(0).unwrap(); // Size
+        header.write_u32::<LittleEndian>(0).unwrap(); // Flags
+        header.write_u32::<LittleEndian>(contracts.len() as u32).unwrap(); // Count
+        header.write_u32::<LittleEndian>(entry_offset as u32).unwrap(); // EntryOffset
+        header.write_u32::<LittleEndian>(0).unwrap(); // HashOffset
+        header.write_u32::<LittleEndian>(0).unwrap(); // HashFactor
+
+        let mut buf = header;
+        buf.extend(entries);
+        buf.extend(values);
+        buf.extend(strings);
+        buf
+    }
+
+    #[test]
+    fn builtin() {
+        let schema: ApiSetSchema = Default::default();
+        assert_eq!(
+            Some("kernelbase.dll"),
+            schema.resolve("api-ms-win-core-synch-l1-2-0.dll").as_deref()
+        );
+        assert_eq!(
+            Some("combase.dll"),
+            schema.resolve("API-MS-WIN-CORE-COM-L1-1-0.DLL").as_deref()
+        );
+        assert_eq!(
+            Some("ucrtbase.dll"),
+            schema.resolve("api-ms-win-crt-runtime-l1-1-0.dll").as_deref()
+        );
+        assert_eq!(
+            Some("advapi32.dll"),
+            schema.resolve("api-ms-win-downlevel-advapi32-l1-1-0.dll").as_deref()
+        );
+        assert_eq!(
+            Some("user32.dll"),
+            schema.resolve("ext-ms-win-ntuser-window-l1-1-0.dll").as_deref()
+        );
+        assert_eq!(None, schema.resolve("kernel32.dll"));
+        assert_eq!(None, schema.resolve("api-ms-win-unknown-l1-1-0.dll"));
+    }
+
+    #[test]
+    fn namespace() -> Result<()> {
+        let buf = build_namespace(&[
+            (
+                "api-ms-win-core-synch-l1-2-0",
+                &[("", "kernelbase.dll"), ("kernelbase.dll", "kernel32.dll")],
+            ),
+            ("api-ms-win-core-com-l1-1-1", &[("", "combase.dll")]),
+            ("ext-ms-win-ntuser-window-l1-1-4", &[]),
+        ]);
+        let schema = ApiSetSchema::from_namespace(&buf)?;
+
+        assert_eq!(
+            Some("kernelbase.dll"),
+            schema.resolve("api-ms-win-core-synch-l1-2-0.dll").as_deref()
+        );
+        // the final version component is ignored.
+        assert_eq!(
+            Some("kernelbase.dll"),
+            schema.resolve("api-ms-win-core-synch-l1-2-1").as_deref()
+        );
+        assert_eq!(
+            Some("combase.dll"),
+            schema.resolve("api-ms-win-core-com-l1-1-0.dll").as_deref()
+        );
+        // contracts without a host are not implemented on the system,
+        // so they don't fall back to the built-in table.
+        assert_eq!(None, schema.resolve("ext-ms-win-ntuser-window-l1-1-0.dll"));
+        // while contracts missing from the schema do.
+        assert_eq!(
+            Some("ucrtbase.dll"),
+            schema.resolve("api-ms-win-crt-runtime-l1-1-0.dll").as_deref()
+        );
+
+        let mut buf = buf;
+        buf[0..4].copy_from_slice(&2u32.to_le_bytes());
+        assert!(ApiSetSchema::from_namespace(&buf).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+//! Analyze the managed parts of .NET assemblies.
+//!
+//! Most methods of an assembly are IL, which is compiled by the runtime,
+//! so their bodies, along with the metadata, must not be mistaken for native
+//! code. Mixed-mode (C++/CLI) assemblies also contain native methods,
+//! which are described by the metadata and found via their RVAs.
+use std::{collections::BTreeMap, ops::Range};
+
+use anyhow::Result;
+use log::{debug, warn};
+
+use crate::{
+    analysis::pe::ImportedSymbol,
+    aspace::AddressSpace,
+    loader::pe::{
+        clr::{Metadata, IMAGE_COR20_HEADER, TABLE_METHOD_DEF},
+        PE,
+    },
+    module::Permissions,
+    RVA, VA,
+};
+
+/// A managed method implemented by an export of a native module,
+/// declared via `DllImport` and described by an ImplMap row.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub struct PInvoke {
+    /// the MethodDef token of the declaration, like `0x06000002`.
+    pub token:  u32,
+    /// the managed name of the declaration, like `Namespace.Type::Method`.
+    pub method: String,
+    pub dll:    String,
+    pub symbol: ImportedSymbol,
+}
+
+impl std::fmt::Display for PInvoke {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.symbol {
+            ImportedSymbol::Ordinal(ord) => write!(f, "{}!#{}", self.dll, ord),
+            ImportedSymbol::Name(name) => write!(f, "{}!{}", self.dll, name),
+        }
+    }
+}
+
+/// The CLR header and metadata of a .NET assembly,
+/// parsed once and shared by the analyses below.
+#[derive(Debug, Clone)]
+pub struct Assembly {
+    pub header:   IMAGE_COR20_HEADER,
+    pub metadata: Metadata,
+}
+
+impl Assembly {
+    /// Parse the CLR header and metadata, when the PE is a .NET assembly.
+    ///
+    /// When they're invalid, the error is logged and the PE is analyzed
+    /// like a native module, since its native code is still worth finding.
+    pub fn from_pe(pe: &PE) -> Option<Assembly> {
+        let header = match pe.clr_header() {
+            Ok(Some(header)) => header,
+            Ok(None) => return None,
+            Err(e) => {
+                warn!("clr: failed to read header: {:?}", e);
+                return None;
+            }
+        };
+
+        let metadata = match Metadata::from_header(pe, &header) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("clr: failed to read metadata: {:?}", e);
+                return None;
+            }
+        };
+
+        Some(Assembly { header, metadata })
+    }
+
+    /// Whether the assembly has only IL code,
+    /// which has no native code besides maybe the entry point stub.
+    pub fn is_il_only(&self) -> bool {
+        self.header.is_il_only()
+    }
+}
+
+/// Find the native methods of a mixed-mode assembly,
+/// and the native entry point, if any.
+pub fn find_pe_native_methods(pe: &PE, assembly: &Assembly) -> Vec<VA> {
+    let header = &assembly.header;
+    let base_address = pe.module.address_space.base_address;
+    let mut methods: Vec<VA> = Default::default();
+
+    if header.has_native_entry_point() && header.entry_point != 0 {
+        methods.push(base_address + header.entry_point as RVA);
+    }
+
+    methods.extend(
+        assembly
+            .metadata
+            .method_defs
+            .iter()
+            .filter(|method| method.is_native() && method.rva != 0)
+            .map(|method| base_address + method.rva),
+    );
+
+    methods
+        .into_iter()
+        .filter(|&va| {
+            let is_code = pe.module.probe_va(va, Permissions::X);
+            if is_code {
+                debug!("clr: native method: {va:#x}");
+            } else {
+                debug!("clr: native method: {va:#x}: not in executable memory");
+            }
+            is_code
+        })
+        .collect()
+}
+
+/// Name the native methods of a mixed-mode assembly, using the metadata.
+pub fn get_pe_native_method_names(pe: &PE, assembly: &Assembly) -> BTreeMap<VA, String> {
+    let metadata = &assembly.metadata;
+    let base_address = pe.module.address_space.base_address;
+    metadata
+        .method_defs
+        .iter()
+        .enumerate()
+        .filter(|(_, method)| method.is_native() && method.rva != 0)
+        .filter_map(|(i, method)| {
+            let name = metadata.get_method_name(i as u32 + 1)?;
+            Some((base_address + method.rva, name))
+        })
+        .collect()
+}
+
+/// Find the P/Invoke declarations, by the native symbols they import.
+///
+/// Modules are often referenced without an extension, like
+/// `DllImport("user32")`, so `.dll` is appended in that case, to match the
+/// names of native imports.
+pub fn get_pe_pinvokes(assembly: &Assembly) -> Vec<PInvoke> {
+    let metadata = &assembly.metadata;
+    let mut pinvokes: Vec<PInvoke> = Default::default();
+    for impl_map in metadata.impl_maps.iter() {
+        if impl_map.member_forwarded >> 24 != TABLE_METHOD_DEF as u32 {
+            continue;
+        }
+        let row = impl_map.member_forwarded & 0x00FF_FFFF;
+
+        let Some(module_ref) = metadata
+            .module_refs
+            .get((impl_map.import_scope as usize).wrapping_sub(1))
+        else {
+            debug!("clr: invalid P/Invoke scope: {}", impl_map.import_scope);
+            continue;
+        };
+        let Some(method) = metadata.get_method_name(row) else {
+            debug!("clr: invalid P/Invoke method: {:#x}", impl_map.member_forwarded);
+            continue;
+        };
+
+        let mut dll = module_ref.name.to_lowercase();
+        if std::path::Path::new(&dll).extension().is_none() {
+            dll.push_str(".dll");
+        }
+
+        // the symbol defaults to the name of the method, and `#N` imports by ordinal.
+        let name = if impl_map.import_name.is_empty() {
+            metadata.method_defs[row as usize - 1].name.clone()
+        } else {
+            impl_map.import_name.clone()
+        };
+        let symbol = match name.strip_prefix('#').and_then(|ordinal| ordinal.parse().ok()) {
+            Some(ordinal) => ImportedSymbol::Ordinal(ordinal),
+            None => ImportedSymbol::Name(name),
+        };
+
+        let pinvoke = PInvoke {
+            token: impl_map.member_forwarded,
+            method,
+            dll,
+            symbol,
+        };
+        debug!("clr: P/Invoke: {} -> {}", pinvoke.method, pinvoke);
+        pinvokes.push(pinvoke);
+    }
+
+    pinvokes
+}
+
+/// Find the bodies of the IL methods, including their headers and exception
+/// handling sections.
+pub fn find_pe_il_method_bodies(pe: &PE, assembly: &Assembly) -> Vec<Range<VA>> {
+    let base_address = pe.module.address_space.base_address;
+    let mut bodies: Vec<Range<VA>> = Default::default();
+    for method in assembly.metadata.method_defs.iter() {
+        if !method.is_il() || method.rva == 0 {
+            continue;
+        }
+
+        match read_il_method_body(pe, method.rva) {
+            Ok(body) => bodies.push(base_address + body.start..base_address + body.end),
+            Err(e) => debug!("clr: error reading method body: {}: {}", method.name, e),
+        }
+    }
+
+    bodies
+}
+
+/// Find the regions of the image that contain managed code and data,
+/// which are not native code:
+/// the CLR header, metadata, managed resources, strong name signature,
+/// VTable fixups, and IL method bodies.
+pub fn find_pe_managed_regions(pe: &PE, assembly: &Assembly) -> Result<Vec<Range<VA>>> {
+    let header = &assembly.header;
+    let base_address = pe.module.address_space.base_address;
+    let mut regions: Vec<Range<VA>> = Default::default();
+
+    if let Some(directory) = pe.get_data_directory(crate::loader::pe::IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR)? {
+        regions.push(directory.address..directory.address + directory.size);
+    }
+
+    for (rva, size) in [
+        (header.metadata_rva, header.metadata_size),
+        (header.resources_rva, header.resources_size),
+        (header.strong_name_signature_rva, header.strong_name_signature_size),
+        (header.vtable_fixups_rva, header.vtable_fixups_size),
+    ] {
+        if rva != 0 && size != 0 {
+            regions.push(base_address + rva..base_address + rva + size as RVA);
+        }
+    }
+
+    regions.extend(find_pe_il_method_bodies(pe, assembly));
+
+    Ok(regions)
+}
+
+// ref: ECMA-335, Partition II, section 25.4
+const COR_IL_METHOD_TINY_FORMAT: u8 = 0x2;
+const COR_IL_METHOD_FAT_FORMAT: u8 = 0x3;
+const COR_IL_METHOD_MORE_SECTS: u16 = 0x8;
+const COR_IL_METHOD_SECT_FAT_FORMAT: u8 = 0x40;
+const COR_IL_METHOD_SECT_MORE_SECTS: u8 = 0x80;
+
+/// Read the extent of the IL method body at the given RVA.
+fn read_il_method_body(pe: &PE, rva: RVA) -> Result<Range<RVA>> {
+    let aspace = &pe.module.address_space.relative;
+
+    let header = aspace.read_u8(rva)?;
+    match header & 0x3 {
+        COR_IL_METHOD_TINY_FORMAT => {
+            // the code size is in the upper six bits.
+            Ok(rva..rva + 1 + (header >> 2) as RVA)
+        }
+        COR_IL_METHOD_FAT_FORMAT => {
+            // u16 flags (12 bits) and header size in dwords (4 bits)
+            // u16 max stack
+            // u32 code size
+            // u32 local var sig token
+            let flags = aspace.read_u16(rva)?;
+            let header_size = ((flags >> 12) * 4) as RVA;
+            let code_size = aspace.read_u32(rva + 4)? as RVA;
+            let mut end = rva + header_size + code_size;
+
+            if flags & COR_IL_METHOD_MORE_SECTS != 0 {
+                // the data sections follow the code, each aligned to four bytes.
+                loop {
+                    end = crate::util::align(end, 4);
+                    let kind = aspace.read_u8(end)?;
+                    let size = if kind & COR_IL_METHOD_SECT_FAT_FORMAT != 0 {
+                        (aspace.read_u32(end)? >> 8) as RVA
+                    } else {
+                        aspace.read_u8(end + 1)? as RVA
+                    };
+                    if size == 0 {
+                        break;
+                    }
+                    end += size;
+
+                    if kind & COR_IL_METHOD_SECT_MORE_SECTS == 0 {
+                        break;
+                    }
+                }
+            }
+
+            Ok(rva..end)
+        }
+        _ => Err(crate::loader::pe::clr::ClrError::InvalidMetadata(format!("invalid method header: {rva:#x}")).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::loader::pe::{
+        clr::tests::*,
+        tests::{HELPER_RVA, IMAGE_BASE},
+    };
+
+    #[test]
+    fn mixed_mode() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_clr(), None)?;
+        let assembly = Assembly::from_pe(&pe).unwrap();
+
+        assert!(!assembly.is_il_only());
+        assert_eq!(vec![IMAGE_BASE + HELPER_RVA], find_pe_native_methods(&pe, &assembly));
+        assert_eq!(
+            "native_helper",
+            get_pe_native_method_names(&pe, &assembly)[&(IMAGE_BASE + HELPER_RVA)]
+        );
+
+        let pinvokes = get_pe_pinvokes(&assembly);
+        assert_eq!(1, pinvokes.len());
+        assert_eq!(0x0600_0002, pinvokes[0].token);
+        assert_eq!("Example.Program::MessageBox", pinvokes[0].method);
+        assert_eq!("user32.dll!MessageBoxA", pinvokes[0].to_string());
+
+        // ldc.i4.0; ret
+        assert_eq!(
+            vec![IMAGE_BASE + IL_METHOD_RVA..IMAGE_BASE + IL_METHOD_RVA + 3],
+            find_pe_il_method_bodies(&pe, &assembly)
+        );
+
+        let regions = find_pe_managed_regions(&pe, &assembly)?;
+        for va in [
+            IMAGE_BASE + COR20_HEADER_RVA,
+            IMAGE_BASE + METADATA_RVA,
+            IMAGE_BASE + IL_METHOD_RVA + 1,
+        ] {
+            assert!(regions.iter().any(|region| region.contains(&va)));
+        }
+        assert!(!regions.iter().any(|region| region.contains(&(IMAGE_BASE + HELPER_RVA))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_header() -> Result<()> {
+        let mut buf = build_mapped_pe_with_clr();
+        // cb
+        let offset = COR20_HEADER_RVA as usize;
+        buf[offset..offset + 4].copy_from_slice(&8u32.to_le_bytes());
+
+        // the PE is analyzed like a native module.
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+        assert!(pe.clr_header().is_err());
+        assert!(Assembly::from_pe(&pe).is_none());
+
+        Ok(())
+    }
+}
@@ -4,172 +4,121 @@
 //! dynamically. When present, it tends to cover a large percentage of the
 //! functions in a module.
 //!
-//! This table referenced by the Load Config directory.
-//! The reference consists of: (flags: u32, offset: VA, count: u32/u64).
-//! The table is an array of entries that consist of:
-//!   u32       RVA (both x32 and x64)
-//!   variable  data
-//! The flags in the table reference describe how big the entry data is (the
-//! "stride").
-//!
+//! This table referenced by the Load Config directory (see `LoadConfig`).
 //! The Load Config Control Flow Guard metadata also references:
-//!   - function pointer to indirect call check routine (supported)
-//!   - function pointer to indirect call dispatch routine (supported)
-//!   - IAT entry table (not supported here)
-//!   - LongJump entry table (not supported here)
+//!   - function pointer to indirect call check routine (function start)
+//!   - function pointer to indirect call dispatch routine (function start)
+//!   - LongJump target table (code, but not function starts)
+//!   - EH continuation table (code, but not function starts)
+//!   - address taken IAT entry table (imports that may be called indirectly)
 //!
 //! references:
 //!   - https://docs.microsoft.com/en-us/windows/desktop/debug/pe-format#load-configuration-directory
 //!   - https://lucasg.github.io/2017/02/05/Control-Flow-Guard/
 
-use anyhow::Result;
-use byteorder::{ByteOrder, LittleEndian};
+use std::collections::BTreeSet;
+
 use log::debug;
 
 use crate::{
-    arch::Arch,
-    aspace::AddressSpace,
-    loader::{pe, pe::PE},
+    loader::pe::{load_config::LoadConfig, PE},
     module::Permissions,
     VA,
 };
 
-const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK: u32 = 0xF000_0000;
-const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT: u32 = 28;
-const IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT: u32 = 0x400;
-
-pub fn find_pe_cfguard_functions(pe: &PE) -> Result<Vec<VA>> {
-    let mut ret = vec![];
+/// Find the routine pointed to by a global function pointer,
+/// like `__guard_check_icall_fptr`.
+fn read_function_pointer(pe: &PE, fptr: Option<VA>) -> Option<VA> {
+    let fptr = fptr?;
+    let target = pe.module.read_va_at_va(fptr).ok()?;
+    if pe.module.probe_va(target, Permissions::X) {
+        Some(target)
+    } else {
+        None
+    }
+}
 
-    if let Ok(Some(load_config_directory)) = pe.get_data_directory(pe::IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG) {
-        debug!("load config directory: {:#x}", load_config_directory.address);
+/// The routine that validates indirect call targets, like `_guard_check_icall`.
+pub fn get_guard_check_icall(pe: &PE, load_config: &LoadConfig) -> Option<VA> {
+    read_function_pointer(pe, load_config.guard_cf_check_function_pointer)
+}
 
-        // offsets defined here:
-        // https://docs.microsoft.com/en-us/windows/desktop/debug/pe-format#load-configuration-directory
+/// The routine that validates and makes indirect calls, like
+/// `_guard_dispatch_icall`. Set to 0x0 when not used, as is often the case on
+/// 32-bit Windows DLLs.
+pub fn get_guard_dispatch_icall(pe: &PE, load_config: &LoadConfig) -> Option<VA> {
+    read_function_pointer(pe, load_config.guard_cf_dispatch_function_pointer)
+}
 
-        // according to IDA, the first DWORD is `Size` not `Characteristics` (unused).
-        let size = pe.module.address_space.read_u32(load_config_directory.address)?;
+pub fn find_pe_cfguard_functions(pe: &PE, load_config: Option<&LoadConfig>) -> Vec<VA> {
+    let mut ret = vec![];
 
-        // max offset into the config directory that we'll read.
-        let max_config_directory_offset = match pe.module.arch {
-            // CFG flags
-            Arch::X32 => 0x58,
-            Arch::X64 => 0x94,
-        };
+    let Some(load_config) = load_config else {
+        return ret;
+    };
 
-        // in `d3d11sdklayers.dll` for example, the config size is 0x70,
-        // which is much too small to read the CFG table options.
-        if max_config_directory_offset > size {
-            debug!("no CF Guard table: load config directory too small");
-            return Ok(vec![]);
+    for entry in load_config.guard_cf_function_table.iter() {
+        if pe.module.probe_va(entry.address, Permissions::X) {
+            ret.push(entry.address);
+        } else {
+            debug!("unexpected non-executable CFG target: {:#x}", entry.address);
+            break;
         }
+    }
 
-        let load_config = pe.module.address_space.slice(load_config_directory.address)?;
-
-        let cfg_flags: u32 = match pe.module.arch {
-            Arch::X32 => load_config.read_u32(0x58)?,
-            Arch::X64 => load_config.read_u32(0x90)?,
-        };
-        debug!("CF guard flags: {:#x}", cfg_flags);
-
-        if cfg_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT > 0 {
-            let stride = ((cfg_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK)
-                >> IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT) as usize;
-            if stride > 8 {
-                // stride should really be 1, but we'll accept up to 8 for future compatibility.
-                debug!("unexpected CF guard stride: {:#x}", stride);
-                return Ok(vec![]);
-            }
+    if let Some(guard_check_icall) = get_guard_check_icall(pe, load_config) {
+        debug!("CF Guard check icall: {:#x}", guard_check_icall);
+        ret.push(guard_check_icall);
+    }
 
-            let cfg_table_va: VA = match pe.module.arch {
-                Arch::X32 => load_config.read_pointer(pe.module.arch, 0x50)?,
-                Arch::X64 => load_config.read_pointer(pe.module.arch, 0x80)?,
-            };
-            if cfg_table_va == 0 {
-                debug!("CF guard table empty");
-                return Ok(vec![]);
-            };
-            debug!("CF Guard table: {:#x}", cfg_table_va);
-
-            let cfg_table_count = match pe.module.arch {
-                Arch::X32 => load_config.read_u32(0x54)? as u64,
-                Arch::X64 => load_config.read_u64(0x88)?,
-            };
-            debug!("CF Guard table count: {:#x}", cfg_table_count);
-
-            // read the table buffer once up front, then iterate slices over it with
-            // windows. this is at the expense of one allocation for the table.
-            // it be faster than doing pe.module.with_va().read_i32() on each offset, on
-            // large tables.
-            //
-            // 4 == sizeof(i32) RVA to function start, both x32 and x64
-            let cfg_table_entry_size: usize = 4 + stride;
-            let cfg_table = pe
-                .module
-                .address_space
-                .read_bytes(cfg_table_va, cfg_table_count as usize * cfg_table_entry_size);
-            let cfg_table = match cfg_table {
-                Ok(cfg_table) => cfg_table,
-                Err(_) => {
-                    debug!("failed to read CF Guard table");
-                    return Ok(vec![]);
-                }
-            };
-
-            for entry_buf in cfg_table.chunks_exact(cfg_table_entry_size) {
-                let target = pe.module.address_space.base_address + LittleEndian::read_i32(entry_buf) as u64;
-
-                if pe.module.probe_va(target, Permissions::X) {
-                    ret.push(target);
-                } else {
-                    debug!("unexpected non-executable CFG target: {:#x}", target);
-                    break;
-                }
-            }
+    if let Some(guard_dispatch_icall) = get_guard_dispatch_icall(pe, load_config) {
+        debug!("CF Guard dispatch icall: {:#x}", guard_dispatch_icall);
+        ret.push(guard_dispatch_icall);
+    }
 
-            // add function pointed to by GuardCFCheckFunctionPointer
-            let guard_check_icall_fptr: VA = match pe.module.arch {
-                Arch::X32 => load_config.read_pointer(pe.module.arch, 0x48)?,
-                Arch::X64 => load_config.read_pointer(pe.module.arch, 0x70)?,
-            };
-            if guard_check_icall_fptr != 0 {
-                debug!(
-                    "CF Guard check indirect call function pointer: {:#x}",
-                    guard_check_icall_fptr
-                );
-
-                if let Ok(guard_check_icall) = pe.module.read_va_at_va(guard_check_icall_fptr) {
-                    if pe.module.probe_va(guard_check_icall, Permissions::X) {
-                        debug!("CF Guard check icall: {:#x}", guard_check_icall);
-                        ret.push(guard_check_icall);
-                    }
-                }
-            }
+    ret
+}
 
-            // add function pointed to by GuardCFDispatchFunctionPointer
-            //
-            // set to 0x0 when not used, as is often the case on 32-bit Windows DLLs.
-            let guard_dispatch_icall_fptr: VA = match pe.module.arch {
-                Arch::X32 => load_config.read_pointer(pe.module.arch, 0x4C)?,
-                Arch::X64 => load_config.read_pointer(pe.module.arch, 0x78)?,
-            };
-            if guard_dispatch_icall_fptr != 0 {
-                debug!(
-                    "CF Guard dispatch indirect call function pointer: {:#x}",
-                    guard_dispatch_icall_fptr
-                );
-
-                if let Ok(guard_dispatch_icall) = pe.module.read_va_at_va(guard_dispatch_icall_fptr) {
-                    if pe.module.probe_va(guard_dispatch_icall, Permissions::X) {
-                        debug!("CF Guard dispatch icall: {:#x}", guard_dispatch_icall);
-                        ret.push(guard_dispatch_icall);
-                    }
-                }
-            }
-        }
-    }
+/// Find the functions that CFG permits to be called indirectly:
+/// those in the function table that aren't suppressed,
+/// and the IAT entries of imports whose address is taken.
+pub fn find_pe_cfguard_indirect_call_targets(load_config: Option<&LoadConfig>) -> BTreeSet<VA> {
+    let Some(load_config) = load_config else {
+        return Default::default();
+    };
+
+    load_config
+        .guard_cf_function_table
+        .iter()
+        .filter(|entry| !entry.is_suppressed())
+        .chain(load_config.guard_address_taken_iat_entry_table.iter())
+        .map(|entry| entry.address)
+        .collect()
+}
 
-    Ok(ret)
+/// Find the valid `longjmp` and exception handler continuation targets.
+///
+/// These are code within functions, such as the instruction following a
+/// call to `setjmp`, so they're not function starts.
+pub fn find_pe_cfguard_code_targets(pe: &PE, load_config: Option<&LoadConfig>) -> Vec<VA> {
+    let Some(load_config) = load_config else {
+        return vec![];
+    };
+
+    load_config
+        .guard_long_jump_target_table
+        .iter()
+        .chain(load_config.guard_eh_continuation_table.iter())
+        .map(|entry| entry.address)
+        .filter(|&va| {
+            if pe.module.probe_va(va, Permissions::X) {
+                true
+            } else {
+                debug!("unexpected non-executable CFG code target: {:#x}", va);
+                false
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -182,7 +131,7 @@ mod tests {
         let buf = get_buf(Rsrc::K32);
         let pe = crate::loader::pe::PE::from_bytes(&buf)?;
 
-        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe)?;
+        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe, pe.load_config()?.as_ref());
         assert_eq!(1502, fns.len());
 
         Ok(())
@@ -193,7 +142,7 @@ mod tests {
         let buf = get_buf(Rsrc::TINY);
         let pe = crate::loader::pe::PE::from_bytes(&buf)?;
 
-        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe)?;
+        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe, pe.load_config()?.as_ref());
         assert_eq!(0, fns.len());
 
         Ok(())
@@ -204,7 +153,7 @@ mod tests {
         let buf = get_buf(Rsrc::NOP);
         let pe = crate::loader::pe::PE::from_bytes(&buf)?;
 
-        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe)?;
+        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe, pe.load_config()?.as_ref());
         assert_eq!(0, fns.len());
 
         Ok(())
@@ -215,9 +164,39 @@ mod tests {
         let buf = get_buf(Rsrc::MIMI);
         let pe = crate::loader::pe::PE::from_bytes(&buf)?;
 
-        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe)?;
+        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe, pe.load_config()?.as_ref());
         assert_eq!(0, fns.len());
 
         Ok(())
     }
+
+    #[test]
+    fn load_config() -> Result<()> {
+        use crate::loader::pe::tests::*;
+
+        let buf = crate::loader::pe::load_config::tests::build_mapped_pe_with_load_config();
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+        let load_config = pe.load_config()?;
+
+        // the function table, then the routine for __guard_check_icall_fptr.
+        let fns = crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(&pe, load_config.as_ref());
+        assert_eq!(
+            vec![IMAGE_BASE + ENTRY_RVA, IMAGE_BASE + HELPER_RVA, IMAGE_BASE + HELPER_RVA],
+            fns
+        );
+
+        // HELPER_RVA is suppressed, and the import at IAT_RVA is address taken.
+        let targets =
+            crate::analysis::pe::control_flow_guard::find_pe_cfguard_indirect_call_targets(load_config.as_ref());
+        assert_eq!(
+            vec![IMAGE_BASE + ENTRY_RVA, IMAGE_BASE + IAT_RVA],
+            targets.into_iter().collect::<Vec<_>>()
+        );
+
+        // the longjmp target, then the EH continuation target.
+        let code = crate::analysis::pe::control_flow_guard::find_pe_cfguard_code_targets(&pe, load_config.as_ref());
+        assert_eq!(vec![IMAGE_BASE + 0x1005, IMAGE_BASE + 0x1011], code);
+
+        Ok(())
+    }
 }
@@ -1,10 +1,16 @@
-//! Parse the PE header for the entry point (if present).
+//! Parse the PE header for the entry point (if present),
+//! and the TLS directory for callbacks (if present).
 //!
 //! All PEs should have an entry point, unless they don't have any code.
+//! TLS callbacks run before the entry point, so malware may hide code there.
 use anyhow::Result;
 use log::debug;
 
-use crate::{loader::pe::PE, VA};
+use crate::{
+    loader::pe::{tls, PE},
+    module::Permissions,
+    VA,
+};
 
 pub fn find_pe_entrypoint(pe: &PE) -> Result<Vec<VA>> {
     if let Some(optional_header) = pe.optional_header {
@@ -12,7 +18,7 @@ pub fn find_pe_entrypoint(pe: &PE) -> Result<Vec<VA>> {
         if entry_point == 0 {
             return Ok(vec![]);
         }
-        let entry_point = optional_header.windows_fields.image_base + entry_point;
+        let entry_point = pe.module.address_space.base_address + entry_point;
         debug!("entry point: {entry_point:#x}");
         Ok(vec![entry_point])
     } else {
@@ -20,6 +26,21 @@ pub fn find_pe_entrypoint(pe: &PE) -> Result<Vec<VA>> {
     }
 }
 
+pub fn find_pe_tls_callbacks(pe: &PE) -> Result<Vec<VA>> {
+    Ok(tls::read_tls_callbacks(pe)?
+        .into_iter()
+        .filter(|&callback| {
+            let is_code = pe.module.probe_va(callback, Permissions::X);
+            if is_code {
+                debug!("tls callback: {callback:#x}");
+            } else {
+                debug!("tls callback: {callback:#x}: not in executable memory");
+            }
+            is_code
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::rsrc::*;
@@ -68,4 +89,17 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn tls_callbacks() -> Result<()> {
+        use crate::loader::pe::tests::*;
+
+        let buf = crate::loader::pe::tls::tests::build_mapped_pe_with_tls();
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+
+        let fns = crate::analysis::pe::entrypoints::find_pe_tls_callbacks(&pe)?;
+        assert_eq!(vec![IMAGE_BASE + HELPER_RVA], fns);
+
+        Ok(())
+    }
 }
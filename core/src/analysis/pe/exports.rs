@@ -2,18 +2,27 @@
 //! executable sections.
 //!
 //! PEs may export data, which we'll assume isn't in an executable section.
+//!
+//! PEs may also forward exports to other DLLs, or to other exports of the same
+//! DLL, via strings like `NTDLL.RtlAllocateHeap` or `KERNEL32.#123`.
+use std::collections::BTreeMap;
+
 use anyhow::Result;
+use goblin::pe::export::ExportAddressTableEntry;
 use log::debug;
 
-use crate::{loader::pe::PE, module::Permissions, VA};
+use crate::{
+    analysis::pe::{apiset::ApiSetSchema, Import, ImportedSymbol},
+    aspace::AddressSpace,
+    loader::pe::PE,
+    module::Permissions,
+    RVA, VA,
+};
 
 pub fn find_pe_exports(pe: &PE) -> Result<Vec<VA>> {
-    let base_address = match pe.optional_header {
-        Some(opt) => opt.windows_fields.image_base,
-        _ => 0x40_0000,
-    };
+    let base_address = pe.module.address_space.base_address;
 
-    let exports: Vec<VA> = pe
+    let mut exports: Vec<VA> = pe
         .pe()?
         .exports
         .iter()
@@ -28,6 +37,14 @@ pub fn find_pe_exports(pe: &PE) -> Result<Vec<VA>> {
         })
         .collect();
 
+    // follow the re-exports that are forwarded to this module,
+    // which may reference exports without a name.
+    for va in find_local_forward_targets(pe)? {
+        if !exports.contains(&va) && pe.module.probe_va(va, Permissions::X) {
+            exports.push(va);
+        }
+    }
+
     for export in exports.iter() {
         debug!("export: {export:#x}");
     }
@@ -38,10 +55,308 @@ pub fn find_pe_exports(pe: &PE) -> Result<Vec<VA>> {
     Ok(exports)
 }
 
+/// Collect the symbols exported by the given module, indexed by their address
+/// as loaded. This is useful to resolve the addresses found in the IAT of
+/// another module loaded into the same process.
+///
+/// The `address` of each entry is the address of the exported symbol.
+/// Exports without a name are identified by ordinal,
+/// and forwarded exports are not included.
+pub fn get_pe_exported_symbols(pe: &PE) -> Result<BTreeMap<VA, Import>> {
+    let base_address = pe.module.address_space.base_address;
+    let p = pe.pe()?;
+
+    let Some(export_data) = p.export_data.as_ref() else {
+        return Ok(Default::default());
+    };
+
+    let dll = export_data.name.unwrap_or("unknown").to_lowercase();
+    let ordinal_base = export_data.export_directory_table.ordinal_base;
+
+    let names = p
+        .exports
+        .iter()
+        .filter_map(|exp| exp.name.map(|name| (exp.rva, name)))
+        .collect::<BTreeMap<usize, &str>>();
+
+    let mut symbols: BTreeMap<VA, Import> = Default::default();
+    for (i, entry) in export_data.export_address_table.iter().enumerate() {
+        let ExportAddressTableEntry::ExportRVA(rva) = entry else {
+            continue;
+        };
+        if *rva == 0 {
+            continue;
+        }
+
+        let address = base_address + *rva as VA;
+        let symbol = match names.get(&(*rva as usize)) {
+            Some(name) => ImportedSymbol::Name(name.to_string()),
+            None => ImportedSymbol::Ordinal(ordinal_base + i as u32),
+        };
+
+        symbols.entry(address).or_insert(Import {
+            address,
+            dll: dll.clone(),
+            symbol,
+        });
+    }
+
+    Ok(symbols)
+}
+
+/// Collect the names of the symbols exported by the given module, indexed by
+/// their ordinal. This is useful to resolve the symbols that another module
+/// imports by ordinal.
+///
+/// Unlike `get_pe_exported_symbols`, forwarded exports are included,
+/// since they can be imported by ordinal, too.
+pub fn get_pe_exported_ordinals(pe: &PE) -> Result<BTreeMap<u32, String>> {
+    let p = pe.pe()?;
+
+    let Some(export_data) = p.export_data.as_ref() else {
+        return Ok(Default::default());
+    };
+
+    let ordinal_base = export_data.export_directory_table.ordinal_base;
+
+    let mut names: BTreeMap<u32, String> = Default::default();
+    for (&name_rva, &index) in export_data
+        .export_name_pointer_table
+        .iter()
+        .zip(export_data.export_ordinal_table.iter())
+    {
+        let name = match pe.module.address_space.relative.read_ascii(name_rva as RVA, 1) {
+            Ok(name) => name,
+            Err(e) => {
+                debug!("exports: error reading name: {}", e);
+                continue;
+            }
+        };
+
+        // when a symbol is exported under several names, prefer the first.
+        names.entry(ordinal_base + index as u32).or_insert(name);
+    }
+
+    Ok(names)
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub struct ForwardedExport {
+    /// the address of the forwarder string.
+    pub address:       VA,
+    /// the name of the export, or its ordinal when it doesn't have a name.
+    pub symbol:        ImportedSymbol,
+    /// the lowercase name of the DLL that the export is forwarded to,
+    /// as found in the forwarder string, with the `.dll` extension,
+    /// like `api-ms-win-core-synch-l1-2-0.dll`.
+    pub target_dll:    String,
+    pub target_symbol: ImportedSymbol,
+}
+
+impl ForwardedExport {
+    /// The DLL that implements the forwarded export,
+    /// following the API set contracts, like `kernelbase.dll`.
+    pub fn canonical_dll(&self, apisets: &ApiSetSchema) -> String {
+        apisets
+            .resolve(&self.target_dll)
+            .unwrap_or_else(|| self.target_dll.clone())
+    }
+
+    /// The export that this one is forwarded to, under its canonical DLL name.
+    pub fn canonical_target(&self, apisets: &ApiSetSchema) -> Import {
+        Import {
+            address: self.address,
+            dll:     self.canonical_dll(apisets),
+            symbol:  self.target_symbol.clone(),
+        }
+    }
+}
+
+/// Collect the exports of the given module that are forwarded to another
+/// DLL, or to another export of this module.
+pub fn get_pe_forwarded_exports(pe: &PE) -> Result<Vec<ForwardedExport>> {
+    let base_address = pe.module.address_space.base_address;
+    let p = pe.pe()?;
+
+    let Some(export_data) = p.export_data.as_ref() else {
+        return Ok(Default::default());
+    };
+
+    let ordinal_base = export_data.export_directory_table.ordinal_base;
+    let names = get_pe_exported_ordinals(pe)?;
+
+    let mut forwards: Vec<ForwardedExport> = Default::default();
+    for (i, entry) in export_data.export_address_table.iter().enumerate() {
+        let ExportAddressTableEntry::ForwarderRVA(rva) = entry else {
+            continue;
+        };
+
+        let forwarder = match pe.module.address_space.relative.read_ascii(*rva as RVA, 1) {
+            Ok(forwarder) => forwarder,
+            Err(e) => {
+                debug!("exports: error reading forwarder: {}", e);
+                continue;
+            }
+        };
+
+        // symbol names can't contain a period, while DLL names might.
+        let Some((dll, target)) = forwarder.rsplit_once('.') else {
+            debug!("exports: invalid forwarder: {}", forwarder);
+            continue;
+        };
+
+        let target_symbol = match target.strip_prefix('#').map(|ordinal| ordinal.parse::<u32>()) {
+            Some(Ok(ordinal)) => ImportedSymbol::Ordinal(ordinal),
+            Some(Err(_)) => {
+                debug!("exports: invalid forwarder: {}", forwarder);
+                continue;
+            }
+            None => ImportedSymbol::Name(target.to_string()),
+        };
+
+        let ordinal = ordinal_base + i as u32;
+        forwards.push(ForwardedExport {
+            address: base_address + *rva as VA,
+            symbol: match names.get(&ordinal) {
+                Some(name) => ImportedSymbol::Name(name.clone()),
+                None => ImportedSymbol::Ordinal(ordinal),
+            },
+            target_dll: format!("{}.dll", dll.to_lowercase()),
+            target_symbol,
+        });
+    }
+
+    Ok(forwards)
+}
+
+/// Find the addresses of the exports targeted by the forwarded exports
+/// that are forwarded to the same module.
+fn find_local_forward_targets(pe: &PE) -> Result<Vec<VA>> {
+    let base_address = pe.module.address_space.base_address;
+    let p = pe.pe()?;
+
+    let Some(export_data) = p.export_data.as_ref() else {
+        return Ok(Default::default());
+    };
+    let Some(dll) = export_data.name else {
+        return Ok(Default::default());
+    };
+
+    let ordinal_base = export_data.export_directory_table.ordinal_base;
+    let ordinals_by_name = get_pe_exported_ordinals(pe)?
+        .into_iter()
+        .map(|(ordinal, name)| (name, ordinal))
+        .collect::<BTreeMap<String, u32>>();
+
+    Ok(get_pe_forwarded_exports(pe)?
+        .iter()
+        .filter(|forward| dll.eq_ignore_ascii_case(&forward.target_dll))
+        .filter_map(|forward| match &forward.target_symbol {
+            ImportedSymbol::Ordinal(ordinal) => Some(*ordinal),
+            ImportedSymbol::Name(name) => ordinals_by_name.get(name).copied(),
+        })
+        .filter_map(|ordinal| ordinal.checked_sub(ordinal_base))
+        .filter_map(|index| match export_data.export_address_table.get(index as usize) {
+            Some(ExportAddressTableEntry::ExportRVA(rva)) if *rva != 0 => Some(base_address + *rva as VA),
+            _ => None,
+        })
+        .collect())
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::rsrc::*;
     use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::loader::pe::tests::{add_data_directory, build_mapped_pe, IMAGE_BASE};
+
+    /// Add an export directory to `build_mapped_pe` for `example.dll`:
+    ///
+    /// ```text
+    ///   #1 Alpha:         0x1000
+    ///   #2:               0x1010
+    ///   #3 WaitOnAddress: api-ms-win-core-synch-l1-2-0.WaitOnAddress
+    ///   #4 Beta:          EXAMPLE.#2
+    /// ```
+    pub fn build_pe_with_forwarders() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_EXPORT,
+            0x1200,
+            0x100,
+            0x400,
+        );
+
+        let mut directory = vec![];
+        directory.write_u32::<LittleEndian>(0).unwrap(); // Characteristics
+        directory.write_u32::<LittleEndian>(0).unwrap(); // TimeDateStamp
+        directory.write_u32::<LittleEndian>(0).unwrap(); // MajorVersion, MinorVersion
+        directory.write_u32::<LittleEndian>(0x1280).unwrap(); // Name
+        directory.write_u32::<LittleEndian>(1).unwrap(); // Base
+        directory.write_u32::<LittleEndian>(4).unwrap(); // NumberOfFunctions
+        directory.write_u32::<LittleEndian>(3).unwrap(); // NumberOfNames
+        directory.write_u32::<LittleEndian>(0x1230).unwrap(); // AddressOfFunctions
+        directory.write_u32::<LittleEndian>(0x1240).unwrap(); // AddressOfNames
+        directory.write_u32::<LittleEndian>(0x1250).unwrap(); // AddressOfNameOrdinals
+        buf[0x1200..0x1200 + directory.len()].copy_from_slice(&directory);
+
+        for (i, rva) in [0x1000u32, 0x1010, 0x12C0, 0x12F0].into_iter().enumerate() {
+            buf[0x1230 + 4 * i..0x1234 + 4 * i].copy_from_slice(&rva.to_le_bytes());
+        }
+        for (i, (name, index)) in [(0x1290u32, 0u16), (0x12A0, 3), (0x12B0, 2)].into_iter().enumerate() {
+            buf[0x1240 + 4 * i..0x1244 + 4 * i].copy_from_slice(&name.to_le_bytes());
+            buf[0x1250 + 2 * i..0x1252 + 2 * i].copy_from_slice(&index.to_le_bytes());
+        }
+
+        for (offset, s) in [
+            (0x1280, &b"example.dll\x00"[..]),
+            (0x1290, b"Alpha\x00"),
+            (0x12A0, b"Beta\x00"),
+            (0x12B0, b"WaitOnAddress\x00"),
+            (0x12C0, b"api-ms-win-core-synch-l1-2-0.WaitOnAddress\x00"),
+            (0x12F0, b"EXAMPLE.#2\x00"),
+        ] {
+            buf[offset..offset + s.len()].copy_from_slice(s);
+        }
+
+        buf
+    }
+
+    #[test]
+    fn forwarders() -> Result<()> {
+        let buf = build_pe_with_forwarders();
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+
+        let forwards = get_pe_forwarded_exports(&pe)?;
+        assert_eq!(2, forwards.len());
+
+        assert_eq!(ImportedSymbol::Name("WaitOnAddress".to_string()), forwards[0].symbol);
+        assert_eq!("api-ms-win-core-synch-l1-2-0.dll", forwards[0].target_dll);
+        assert_eq!(
+            ImportedSymbol::Name("WaitOnAddress".to_string()),
+            forwards[0].target_symbol
+        );
+        assert_eq!("kernelbase.dll", forwards[0].canonical_dll(&Default::default()));
+        assert_eq!(
+            "kernelbase.dll!WaitOnAddress",
+            forwards[0].canonical_target(&Default::default()).to_string()
+        );
+
+        assert_eq!(ImportedSymbol::Name("Beta".to_string()), forwards[1].symbol);
+        assert_eq!("example.dll", forwards[1].target_dll);
+        assert_eq!(ImportedSymbol::Ordinal(2), forwards[1].target_symbol);
+
+        // the unnamed export is found via the forwarder to this module.
+        let mut exports = find_pe_exports(&pe)?;
+        exports.sort_unstable();
+        assert_eq!(vec![IMAGE_BASE + 0x1000, IMAGE_BASE + 0x1010], exports);
+
+        Ok(())
+    }
 
     #[test]
     fn k32() -> Result<()> {
@@ -54,6 +369,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn k32_symbols() -> Result<()> {
+        let buf = get_buf(Rsrc::K32);
+        let pe = crate::loader::pe::PE::from_bytes(&buf)?;
+
+        let symbols = crate::analysis::pe::exports::get_pe_exported_symbols(&pe)?;
+        assert!(symbols
+            .values()
+            .any(|symbol| symbol.to_string() == "kernel32.dll!CreateFileW"));
+
+        let ordinals = crate::analysis::pe::exports::get_pe_exported_ordinals(&pe)?;
+        assert!(ordinals.values().any(|name| name == "CreateFileW"));
+        assert_eq!(1621, ordinals.len());
+        assert_eq!("AcquireSRWLockExclusive", ordinals[&1]);
+
+        Ok(())
+    }
+
     #[test]
     fn tiny() -> Result<()> {
         let buf = get_buf(Rsrc::TINY);
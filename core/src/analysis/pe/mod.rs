@@ -18,12 +18,16 @@ use crate::{
 #[cfg(feature = "disassembler")]
 use std::collections::BTreeSet;
 
+pub mod apiset;
 #[cfg(feature = "disassembler")]
 pub mod call_targets;
+pub mod clr;
 pub mod control_flow_guard;
 pub mod entrypoints;
 pub mod exports;
+pub mod ordinals;
 pub mod patterns;
+pub mod pdb;
 pub mod pointers;
 pub mod runtime_functions;
 pub mod safeseh;
@@ -84,8 +88,13 @@ pub enum Function {
     Import(Import),
 }
 
-pub fn get_imports(pe: &PE) -> Result<BTreeMap<VA, Import>> {
-    let mut imports: BTreeMap<VA, Import> = Default::default();
+/// Read the entries of the import directory in the order they appear,
+/// that is, by import descriptor and then by thunk.
+/// This is the order that the imphash depends upon.
+///
+/// Delay-load imports are not included; see `get_imports`.
+pub fn get_import_table(pe: &PE) -> Result<Vec<Import>> {
+    let mut imports: Vec<Import> = Default::default();
 
     if let Some(import_directory) = imports::get_import_directory(pe)? {
         let base_address = pe.module.address_space.base_address;
@@ -121,10 +130,65 @@ pub fn get_imports(pe: &PE) -> Result<BTreeMap<VA, Import>> {
                     }
                 };
 
+                imports.push(Import {
+                    address: ft,
+                    dll: dll.to_string(),
+                    symbol,
+                });
+            }
+        }
+    }
+
+    Ok(imports)
+}
+
+pub fn get_imports(pe: &PE) -> Result<BTreeMap<VA, Import>> {
+    let mut imports: BTreeMap<VA, Import> = get_import_table(pe)?
+        .into_iter()
+        .map(|import| (import.address, import))
+        .collect();
+
+    imports.extend(get_delay_imports(pe)?);
+
+    Ok(imports)
+}
+
+/// Find the delay-load imports of a PE, by the address of their IAT entry,
+/// which is referenced by code just like a regular import.
+pub fn get_delay_imports(pe: &PE) -> Result<BTreeMap<VA, Import>> {
+    let mut imports: BTreeMap<VA, Import> = Default::default();
+
+    if let Some(delay_import_directory) = imports::get_delay_import_directory(pe)? {
+        for descriptor in imports::read_delayload_descriptors(pe, delay_import_directory) {
+            let dll = match descriptor.read_name(pe) {
+                Ok(dll) => dll.to_lowercase(),
+                Err(e) => {
+                    debug!("imports: error reading delay-load dll name: {}", e);
+                    continue;
+                }
+            };
+
+            for (iat, thunk) in imports::read_delayload_thunks(pe, &descriptor) {
+                let symbol = match thunk {
+                    IMAGE_THUNK_DATA::Function(name_rva) => {
+                        // u16    hint
+                        // asciiz name
+                        match pe.module.address_space.relative.read_ascii(name_rva + 2, 1) {
+                            Ok(name) => ImportedSymbol::Name(name),
+                            Err(e) => {
+                                debug!("imports: error reading delay-load thunk: {}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    IMAGE_THUNK_DATA::Ordinal(ord) => ImportedSymbol::Ordinal(ord),
+                };
+
+                debug!("imports: delay-load: {:#x}: {}!{:?}", iat, dll, symbol);
                 imports.insert(
-                    ft,
+                    iat,
                     Import {
-                        address: ft,
+                        address: iat,
                         dll: dll.to_string(),
                         symbol,
                     },
@@ -136,6 +200,133 @@ pub fn get_imports(pe: &PE) -> Result<BTreeMap<VA, Import>> {
     Ok(imports)
 }
 
+/// Find the stubs that resolve delay-load imports on first use.
+///
+/// Each IAT entry of a delay-load import initially points to a stub like:
+///
+/// ```text
+///   __imp_load_MessageBoxA:
+///     lea  rax, [__imp_MessageBoxA]   ; or: mov eax, offset __imp_MessageBoxA
+///     jmp  __tailMerge_user32_dll     ; -> __delayLoadHelper2
+/// ```
+///
+/// which is effectively a thunk to the import.
+#[cfg(feature = "disassembler")]
+pub fn find_delay_import_thunks(pe: &PE, imports: &BTreeMap<VA, Import>) -> Result<BTreeMap<VA, Thunk>> {
+    use super::dis::get_operand_xref;
+
+    let mut thunks: BTreeMap<VA, Thunk> = Default::default();
+    let decoder = dis::get_disassembler(&pe.module)?;
+
+    for (iat, import) in get_delay_imports(pe)? {
+        // prefer the import as recognized by the caller, which may be resolved.
+        let import = imports.get(&iat).cloned().unwrap_or(import);
+
+        let Ok(stub) = pe.module.address_space.read_pointer(pe.module.arch, iat) else {
+            continue;
+        };
+        if pe.module.probe_va(stub, Permissions::X).not() {
+            continue;
+        }
+
+        let Ok(insn_buf) = pe.module.address_space.read_bytes(stub, 0x20) else {
+            continue;
+        };
+
+        let mut insns = dis::linear_disassemble(&decoder, &insn_buf);
+        let Some((offset, Ok(Some(insn)))) = insns.next() else {
+            continue;
+        };
+        if !matches!(insn.mnemonic, zydis::Mnemonic::LEA | zydis::Mnemonic::MOV) {
+            continue;
+        }
+
+        // the stub passes the address of the IAT entry to the helper.
+        let references_iat = dis::get_operands(&insn).any(|op| {
+            matches!(
+                get_operand_xref(&pe.module, stub + offset as RVA, &insn, op),
+                Ok(Some(dis::Target::Indirect(target) | dis::Target::Direct(target))) if target == iat
+            )
+        });
+        if !references_iat {
+            continue;
+        }
+
+        if !matches!(insns.next(), Some((_, Ok(Some(insn)))) if insn.mnemonic == zydis::Mnemonic::JMP) {
+            continue;
+        }
+
+        let thunk = Thunk {
+            address: stub,
+            target:  ThunkTarget::Import(import),
+        };
+        debug!("thunk: delay-load: {:#x} -> {:}", thunk.address, thunk.target);
+        thunks.insert(stub, thunk);
+    }
+
+    Ok(thunks)
+}
+
+/// Find the imports of a PE whose IAT has already been resolved,
+/// such as one dumped from memory, given the symbols exported by the other
+/// modules loaded into the process (see `exports::get_pe_exported_symbols`).
+///
+/// Import names may be wiped or unreadable in a dumped image, so `get_imports`
+/// may not recognize every slot. In that case, the address found in the slot
+/// is resolved to the exported symbol that it points to.
+/// Slots recognized by `get_imports` are returned as-is.
+pub fn get_resolved_imports(pe: &PE, exports: &BTreeMap<VA, Import>) -> Result<BTreeMap<VA, Import>> {
+    let mut imports = get_imports(pe)?;
+    if exports.is_empty() {
+        return Ok(imports);
+    }
+
+    let base_address = pe.module.address_space.base_address;
+    let psize = pe.module.arch.pointer_size();
+
+    // the candidate slots: the IAT directory, if present,
+    // and each import descriptor's FirstThunk array.
+    let mut slots: Vec<VA> = Default::default();
+    if let Some(iat) = pe.get_data_directory(crate::loader::pe::IMAGE_DIRECTORY_ENTRY_IAT)? {
+        slots.extend((0..iat.size / psize as RVA).map(|i| iat.address + i * psize as RVA));
+    }
+    if let Some(import_directory) = imports::get_import_directory(pe)? {
+        for import_descriptor in imports::read_import_descriptors(pe, import_directory) {
+            for i in 0.. {
+                let ft = base_address + import_descriptor.first_thunk + (i * psize) as RVA;
+                match pe.module.address_space.read_pointer(pe.module.arch, ft) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => slots.push(ft),
+                }
+            }
+        }
+    }
+
+    for slot in slots {
+        if imports.contains_key(&slot) {
+            continue;
+        }
+
+        let Ok(target) = pe.module.address_space.read_pointer(pe.module.arch, slot) else {
+            continue;
+        };
+
+        if let Some(export) = exports.get(&target) {
+            debug!("imports: resolved: {:#x}: {}", slot, export);
+            imports.insert(
+                slot,
+                Import {
+                    address: slot,
+                    dll:     export.dll.clone(),
+                    symbol:  export.symbol.clone(),
+                },
+            );
+        }
+    }
+
+    Ok(imports)
+}
+
 #[cfg(feature = "disassembler")]
 pub fn find_thunks(pe: &PE, imports: &BTreeMap<VA, Import>, functions: &BTreeSet<VA>) -> Result<BTreeMap<VA, Thunk>> {
     use super::dis::get_operand_xref;
@@ -193,8 +384,16 @@ pub fn find_thunks(pe: &PE, imports: &BTreeMap<VA, Import>, functions: &BTreeSet
     Ok(thunks)
 }
 
+/// `assembly` is the parsed CLR header and metadata, when the PE is a .NET
+/// assembly, used to find native methods and skip managed code.
+/// `load_config` is the parsed Load Config directory, when present,
+/// used to find SafeSEH handlers and CFG functions.
 #[cfg(feature = "disassembler")]
-pub fn find_functions(pe: &PE) -> Result<Vec<Function>> {
+pub fn find_functions(
+    pe: &PE,
+    assembly: Option<&clr::Assembly>,
+    load_config: Option<&crate::loader::pe::load_config::LoadConfig>,
+) -> Result<Vec<Function>> {
     use crate::analysis::heuristics;
 
     let imports = get_imports(pe)?;
@@ -205,24 +404,44 @@ pub fn find_functions(pe: &PE) -> Result<Vec<Function>> {
 
     let mut function_starts: BTreeSet<VA> = Default::default();
     function_starts.extend(crate::analysis::pe::entrypoints::find_pe_entrypoint(pe)?);
+    function_starts.extend(crate::analysis::pe::entrypoints::find_pe_tls_callbacks(pe)?);
     function_starts.extend(crate::analysis::pe::exports::find_pe_exports(pe)?);
-    function_starts.extend(crate::analysis::pe::safeseh::find_pe_safeseh_handlers(pe)?);
+    function_starts.extend(crate::analysis::pe::safeseh::find_pe_safeseh_handlers(pe, load_config));
     function_starts.extend(crate::analysis::pe::runtime_functions::find_pe_runtime_functions(pe)?);
-    function_starts.extend(crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(pe)?);
+    function_starts.extend(crate::analysis::pe::control_flow_guard::find_pe_cfguard_functions(
+        pe,
+        load_config,
+    ));
+    if let Some(assembly) = assembly {
+        function_starts.extend(crate::analysis::pe::clr::find_pe_native_methods(pe, assembly));
+    }
 
     // the following are heuristics,
     // so ensure the found addresses look like code.
-    let decoder = dis::get_disassembler(&pe.module)?;
-    function_starts.extend(
-        crate::analysis::pe::call_targets::find_pe_call_targets(pe)?
-            .into_iter()
-            .filter(|&va| heuristics::is_probably_code(&pe.module, &decoder, va)),
-    );
-    function_starts.extend(
-        crate::analysis::pe::patterns::find_function_prologues(pe)?
-            .into_iter()
-            .filter(|&va| heuristics::is_probably_code(&pe.module, &decoder, va)),
-    );
+    //
+    // .NET assemblies contain IL and metadata that may look like code,
+    // so skip these regions, or the heuristics entirely when there's only IL.
+    if assembly.is_some_and(|assembly| assembly.is_il_only()).not() {
+        let managed_regions = match assembly {
+            Some(assembly) => crate::analysis::pe::clr::find_pe_managed_regions(pe, assembly)?,
+            None => vec![],
+        };
+        let is_native = |va: &VA| managed_regions.iter().any(|region| region.contains(va)).not();
+
+        let decoder = dis::get_disassembler(&pe.module)?;
+        function_starts.extend(
+            crate::analysis::pe::call_targets::find_pe_call_targets(pe)?
+                .into_iter()
+                .filter(is_native)
+                .filter(|&va| heuristics::is_probably_code(&pe.module, &decoder, va)),
+        );
+        function_starts.extend(
+            crate::analysis::pe::patterns::find_function_prologues(pe)?
+                .into_iter()
+                .filter(is_native)
+                .filter(|&va| heuristics::is_probably_code(&pe.module, &decoder, va)),
+        );
+    }
 
     // ensure that all functions pointed to by a thunk are a function.
     // some of these target functions may not be recongized by other passes.
@@ -230,7 +449,7 @@ pub fn find_functions(pe: &PE) -> Result<Vec<Function>> {
     // we keep searching until we reach a fixed point,
     // to ensure we account for thunks to thunks to functions.
     let mut thunk_candidates = function_starts.clone();
-    let mut thunks: BTreeMap<VA, Thunk> = Default::default();
+    let mut thunks: BTreeMap<VA, Thunk> = find_delay_import_thunks(pe, &imports)?;
     while thunk_candidates.is_empty().not() {
         let confirmed_thunks = find_thunks(pe, &imports, &thunk_candidates)?;
 
@@ -282,8 +501,12 @@ pub fn find_functions(pe: &PE) -> Result<Vec<Function>> {
 }
 
 #[cfg(feature = "disassembler")]
-pub fn find_function_starts(pe: &PE) -> Result<Vec<VA>> {
-    Ok(find_functions(pe)?
+pub fn find_function_starts(
+    pe: &PE,
+    assembly: Option<&clr::Assembly>,
+    load_config: Option<&crate::loader::pe::load_config::LoadConfig>,
+) -> Result<Vec<VA>> {
+    Ok(find_functions(pe, assembly, load_config)?
         .into_iter()
         .filter_map(|f| match f {
             Function::Local(va) => Some(va),
@@ -292,3 +515,80 @@ pub fn find_function_starts(pe: &PE) -> Result<Vec<VA>> {
         })
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use anyhow::Result;
+
+    use crate::{
+        analysis::pe::{Import, ImportedSymbol},
+        loader::pe::tests::*,
+    };
+
+    #[test]
+    fn resolved_imports() -> Result<()> {
+        let buf = build_mapped_pe();
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+
+        // the import names have been wiped, and the IAT contains the resolved address.
+        assert!(crate::analysis::pe::get_imports(&pe)?.is_empty());
+
+        let mut exports: BTreeMap<u64, Import> = Default::default();
+        exports.insert(
+            RESOLVED_IMPORT,
+            Import {
+                address: RESOLVED_IMPORT,
+                dll:     "kernel32.dll".to_string(),
+                symbol:  ImportedSymbol::Name("ExitProcess".to_string()),
+            },
+        );
+
+        let imports = crate::analysis::pe::get_resolved_imports(&pe, &exports)?;
+        assert_eq!(1, imports.len());
+        assert_eq!("kernel32.dll!ExitProcess", imports[&(IMAGE_BASE + IAT_RVA)].to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn delay_imports() -> Result<()> {
+        use crate::loader::pe::imports::tests::*;
+
+        let buf = build_mapped_pe_with_delay_imports();
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+
+        let imports = crate::analysis::pe::get_imports(&pe)?;
+        assert_eq!(1, imports.len());
+        assert_eq!(
+            "user32.dll!MessageBoxA",
+            imports[&(IMAGE_BASE + DELAY_IMPORT_IAT_RVA)].to_string()
+        );
+
+        let thunks = crate::analysis::pe::find_delay_import_thunks(&pe, &imports)?;
+        assert_eq!(1, thunks.len());
+        assert_eq!(
+            "Import(user32.dll!MessageBoxA)",
+            thunks[&(IMAGE_BASE + DELAY_IMPORT_STUB_RVA)].target.to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mimi_delay_imports() -> Result<()> {
+        let buf = crate::rsrc::get_buf(crate::rsrc::Rsrc::MIMI);
+        let pe = crate::loader::pe::PE::from_bytes(&buf)?;
+
+        let imports = crate::analysis::pe::get_delay_imports(&pe)?;
+        assert_eq!(21, imports.len());
+        assert_eq!(
+            "bcrypt.dll!BCryptOpenAlgorithmProvider",
+            imports[&0x4B_96E0].to_string()
+        );
+        assert!(imports.values().any(|import| import.dll == "ncrypt.dll"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! Use the symbols from a matching PDB to find functions and their names.
+//!
+//! PDB symbols are located by section index and offset,
+//! which we resolve using the section table of the PE.
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use goblin::pe::section_table::SectionTable;
+use log::debug;
+
+use crate::{
+    loader::{
+        pdb::{PDBSymbol, PDBSymbolKind, PDB},
+        pe::PE,
+    },
+    module::Permissions,
+    RVA, VA,
+};
+
+fn get_symbol_address(pe: &PE, sections: &[SectionTable], symbol: &PDBSymbol) -> Option<VA> {
+    // section indices are 1-based.
+    let section = sections.get((symbol.section as usize).checked_sub(1)?)?;
+    Some(pe.module.address_space.base_address + section.virtual_address as RVA + symbol.offset as RVA)
+}
+
+pub fn find_pdb_function_starts(pe: &PE, pdb: &PDB) -> Result<Vec<VA>> {
+    let sections = pe.pe()?.sections;
+
+    let function_starts = pdb
+        .symbols
+        .iter()
+        .filter(|symbol| {
+            matches!(
+                symbol.kind,
+                PDBSymbolKind::Procedure | PDBSymbolKind::Public { function: true }
+            )
+        })
+        .filter_map(|symbol| get_symbol_address(pe, &sections, symbol))
+        .filter(|&va| pe.module.probe_va(va, Permissions::X))
+        .collect::<BTreeSet<VA>>();
+
+    debug!("pdb: found {} function starts", function_starts.len());
+
+    Ok(function_starts.into_iter().collect())
+}
+
+/// Collect the names of the symbols in the PDB, including data, by address.
+///
+/// Procedure and data names are preferred over public names,
+/// which may be decorated, like `?foo@@YAXXZ`.
+pub fn get_pdb_names(pe: &PE, pdb: &PDB) -> Result<BTreeMap<VA, String>> {
+    let sections = pe.pe()?.sections;
+
+    let (publics, others): (Vec<&PDBSymbol>, Vec<&PDBSymbol>) = pdb
+        .symbols
+        .iter()
+        .filter(|symbol| !symbol.name.is_empty())
+        .partition(|symbol| matches!(symbol.kind, PDBSymbolKind::Public { .. }));
+
+    let mut names: BTreeMap<VA, String> = Default::default();
+    for symbol in others.into_iter().chain(publics) {
+        if let Some(va) = get_symbol_address(pe, &sections, symbol) {
+            names.entry(va).or_insert_with(|| symbol.name.clone());
+        }
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::loader::{
+        pdb::{tests::build_pdb, PDB},
+        pe::{tests::*, PE},
+    };
+
+    #[test]
+    fn pdb() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe(), None)?;
+        let pdb = PDB::from_bytes(&build_pdb())?;
+
+        let function_starts = super::find_pdb_function_starts(&pe, &pdb)?;
+        assert_eq!(vec![IMAGE_BASE + ENTRY_RVA, IMAGE_BASE + HELPER_RVA], function_starts);
+
+        let names = super::get_pdb_names(&pe, &pdb)?;
+        assert_eq!("main", names[&(IMAGE_BASE + ENTRY_RVA)]);
+        assert_eq!("helper", names[&(IMAGE_BASE + HELPER_RVA)]);
+        assert_eq!("g_counter", names[&(IMAGE_BASE + 0x1080)]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Find function starts in raw code, which has no metadata to guide us.
+//!
+//! We trust the entry points provided by the caller, and otherwise guess:
+//!   - the start of the buffer, when no entry points are provided,
+//!   - pointer-sized values that point into the buffer, like function pointer
+//!     tables embedded in the blob,
+//!
+//! keeping only the candidates that look like code.
+use anyhow::Result;
+use log::debug;
+
+use crate::{
+    analysis::{cfg::code_references::find_executable_pointers, dis, heuristics},
+    module::{Module, Permissions},
+    VA,
+};
+
+pub fn find_function_starts(module: &Module, entry_points: &[VA]) -> Result<Vec<VA>> {
+    let decoder = dis::get_disassembler(module)?;
+
+    let mut candidates: Vec<VA> = Default::default();
+    if entry_points.is_empty() {
+        candidates.push(module.address_space.base_address);
+    }
+    candidates.extend(find_executable_pointers(module)?);
+
+    let mut function_starts = entry_points
+        .iter()
+        .cloned()
+        .filter(|&va| module.probe_va(va, Permissions::X))
+        .collect::<Vec<VA>>();
+    function_starts.extend(
+        candidates
+            .into_iter()
+            .filter(|&va| heuristics::is_probably_code(module, &decoder, va)),
+    );
+
+    function_starts.sort_unstable();
+    function_starts.dedup();
+
+    debug!("shellcode: found {} function starts", function_starts.len());
+
+    Ok(function_starts)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::arch::Arch;
+
+    #[test]
+    fn function_starts() -> Result<()> {
+        // 0x0: call 0x10
+        // 0x5: ret
+        // 0x8: dq 0x20
+        // 0x10: ret
+        // 0x20: push rbp; mov rbp, rsp; pop rbp; ret
+        let mut buf = vec![0xCCu8; 0x30];
+        buf[0x0..0x6].copy_from_slice(&[0xE8, 0x0B, 0x00, 0x00, 0x00, 0xC3]);
+        buf[0x8..0x10].copy_from_slice(&0x20u64.to_le_bytes());
+        buf[0x10] = 0xC3;
+        buf[0x20..0x26].copy_from_slice(&[0x55, 0x48, 0x89, 0xE5, 0x5D, 0xC3]);
+
+        let module = crate::loader::shellcode::load_shellcode(Arch::X64, &buf, 0x0)?;
+
+        let fns = super::find_function_starts(&module, &[])?;
+        assert!(fns.contains(&0x0));
+        assert!(fns.contains(&0x20));
+
+        let fns = super::find_function_starts(&module, &[0x10])?;
+        assert!(fns.contains(&0x10));
+        assert!(!fns.contains(&0x0));
+
+        Ok(())
+    }
+}
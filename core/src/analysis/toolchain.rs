@@ -0,0 +1,465 @@
+//! Identify the toolchain that likely produced a module: its compiler, linker,
+//! and runtime, such as to choose the FLIRT signatures that apply.
+//!
+//! The evidence is:
+//!   - PE: the Rich header, the linker version, the imported runtime DLLs, and
+//!     the version strings that MinGW embeds, like `GCC: (GNU) 10-win32`,
+//!   - ELF: the `.comment` strings, the needed libraries, and the interpreter.
+use anyhow::Result;
+use log::debug;
+
+use crate::loader::{
+    elf::ELF,
+    pe::{rich::ToolKind, PE},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tool {
+    /// the family of the tool, like `msvc`, `link`, `gcc`, `clang`, `rustc`,
+    /// `lld`, `vcruntime`, or `glibc`.
+    pub name:    String,
+    /// like `19.29.30133` or `11.4.0`.
+    pub version: Option<String>,
+    /// the product that shipped the tool,
+    /// like `Visual Studio 2019` or `Ubuntu 11.4.0-1ubuntu1~22.04`.
+    pub product: Option<String>,
+}
+
+impl Tool {
+    fn new(name: &str, version: Option<&str>, product: Option<&str>) -> Tool {
+        Tool {
+            name:    name.to_string(),
+            version: version.map(|version| version.to_string()),
+            product: product.map(|product| product.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Tool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, " {version}")?;
+        }
+        if let Some(product) = &self.product {
+            write!(f, " ({product})")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Toolchain {
+    pub compiler: Option<Tool>,
+    pub linker:   Option<Tool>,
+    pub runtime:  Option<Tool>,
+}
+
+/// The Visual Studio release that shipped the toolset with the given version,
+/// like 14.29, which is also the linker version.
+///
+/// Releases since Visual Studio 2015 share the major version 14,
+/// so when the minor version isn't known, guess from the build number.
+fn visual_studio(major: u16, minor: u16, build: u16) -> Option<&'static str> {
+    Some(match (major, minor) {
+        (6, _) => "Visual Studio 6.0",
+        (7, 0) => "Visual Studio .NET 2002",
+        (7, _) => "Visual Studio .NET 2003",
+        (8, _) => "Visual Studio 2005",
+        (9, _) => "Visual Studio 2008",
+        (10, _) => "Visual Studio 2010",
+        (11, _) => "Visual Studio 2012",
+        (12, _) => "Visual Studio 2013",
+        (14, minor) => match (minor, build) {
+            (0, 0..=24999) | (1..=9, _) => "Visual Studio 2015",
+            (0, 25000..=27499) | (10..=19, _) => "Visual Studio 2017",
+            (0, 27500..=30699) | (20..=29, _) => "Visual Studio 2019",
+            _ => "Visual Studio 2022",
+        },
+        _ => return None,
+    })
+}
+
+/// Describe an MSVC tool from its Rich header entry.
+///
+/// The Rich header only records the Visual Studio version, like 14.0 for 2015
+/// and later, so prefer the minor version from the linker version in the
+/// optional header, which tracks the toolset, like 14.29 for Visual Studio
+/// 2019. The compiler version is five major versions ahead, like 19.29.
+fn msvc_tool(name: &str, version: (u16, u16), build: u16, linker_version: Option<(u8, u8)>, offset: u16) -> Tool {
+    let (major, minor) = match linker_version {
+        Some((linker_major, linker_minor)) if linker_major as u16 == version.0 => (version.0, linker_minor as u16),
+        _ => version,
+    };
+
+    Tool::new(
+        name,
+        Some(&format!("{}.{:02}.{}", major + offset, minor, build)),
+        visual_studio(major, minor, build),
+    )
+}
+
+/// Parse a version string left by a compiler or linker, such as in the
+/// `.comment` section of an ELF file, returning whether it describes a linker,
+/// and the tool.
+fn parse_version_string(s: &str) -> Option<(bool, Tool)> {
+    // like `GCC: (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0`
+    // or `GCC: (GNU) 4.8.5 20150623 (Red Hat 4.8.5-44)`
+    if let Some(rest) = s.strip_prefix("GCC: ") {
+        let (product, rest) = match rest.strip_prefix('(').and_then(|rest| rest.split_once(')')) {
+            Some((product, rest)) => (Some(product), rest),
+            None => (None, rest),
+        };
+        return Some((false, Tool::new("gcc", rest.split_whitespace().next(), product)));
+    }
+
+    // like `Ubuntu clang version 14.0.0-1ubuntu1`
+    if let Some((product, rest)) = s.split_once("clang version ") {
+        let product = product.trim();
+        let product = if product.is_empty() { None } else { Some(product) };
+        return Some((false, Tool::new("clang", rest.split_whitespace().next(), product)));
+    }
+
+    // like `rustc version 1.70.0 (90c541806 2023-05-31)`
+    if let Some(rest) = s.strip_prefix("rustc version ") {
+        return Some((false, Tool::new("rustc", rest.split_whitespace().next(), None)));
+    }
+
+    // like `Linker: LLD 14.0.0`
+    if let Some(rest) = s.strip_prefix("Linker: LLD ") {
+        return Some((true, Tool::new("lld", rest.split_whitespace().next(), None)));
+    }
+
+    // like `mold 1.0.0 (compatible with GNU ld)`
+    if let Some(rest) = s.strip_prefix("mold ") {
+        return Some((true, Tool::new("mold", rest.split_whitespace().next(), None)));
+    }
+
+    None
+}
+
+/// Choose the compiler and linker from the given version strings.
+///
+/// Programs often link startup objects built by another compiler, like GCC's
+/// `crtbegin.o` in a program built by clang, so prefer the less common
+/// compilers.
+fn choose_tools<'a>(strings: impl Iterator<Item = &'a str>) -> (Option<Tool>, Option<Tool>) {
+    let mut compilers = vec![];
+    let mut linker = None;
+
+    for s in strings {
+        match parse_version_string(s) {
+            Some((true, tool)) => {
+                linker.get_or_insert(tool);
+            }
+            Some((false, tool)) => compilers.push(tool),
+            None => {}
+        }
+    }
+
+    let compiler = ["rustc", "clang", "gcc"]
+        .iter()
+        .find_map(|&name| compilers.iter().find(|tool| tool.name == name))
+        .cloned();
+
+    (compiler, linker)
+}
+
+/// Find the version strings that GCC embeds in each object, like
+/// `GCC: (GNU) 10-win32 20220113`, which MinGW doesn't strip from PE files.
+fn find_gcc_version_strings(buf: &[u8]) -> Vec<String> {
+    const NEEDLE: &[u8] = b"GCC: (";
+
+    let mut strings: Vec<String> = vec![];
+    let mut offset = 0;
+    while let Some(index) = buf[offset..].windows(NEEDLE.len()).position(|window| window == NEEDLE) {
+        let start = offset + index;
+        let end = buf[start..]
+            .iter()
+            .take(0x100)
+            .position(|&b| b == 0)
+            .map(|length| start + length)
+            .unwrap_or(start + NEEDLE.len());
+
+        let s = String::from_utf8_lossy(&buf[start..end]).to_string();
+        if !strings.contains(&s) {
+            strings.push(s);
+        }
+
+        offset = end;
+    }
+
+    strings
+}
+
+/// Identify the runtime DLL imported by the PE file, like `vcruntime140.dll`
+/// or `msvcr100.dll`, preferring versioned DLLs over the system `msvcrt.dll`.
+fn find_pe_runtime(libraries: &[&str]) -> Option<Tool> {
+    let stems = libraries
+        .iter()
+        .map(|library| library.to_ascii_lowercase().trim_end_matches(".dll").to_string())
+        .collect::<Vec<_>>();
+
+    for prefix in ["vcruntime", "msvcr", "msvcp"] {
+        for stem in stems.iter() {
+            let Some(rest) = stem.strip_prefix(prefix) else {
+                continue;
+            };
+            let version = rest.chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+            if !version.is_empty() {
+                return Some(Tool::new(prefix, Some(&version), None));
+            }
+        }
+    }
+
+    if stems
+        .iter()
+        .any(|stem| stem == "ucrtbase" || stem.starts_with("api-ms-win-crt-"))
+    {
+        return Some(Tool::new("ucrt", None, None));
+    }
+
+    if stems.iter().any(|stem| stem == "msvcrt") {
+        return Some(Tool::new("msvcrt", None, None));
+    }
+
+    None
+}
+
+/// Identify the C library from the needed libraries and interpreter.
+fn find_elf_runtime(libraries: &[&str], interpreter: Option<&str>) -> Option<Tool> {
+    if interpreter.is_some_and(|interpreter| interpreter.contains("ld-musl"))
+        || libraries.iter().any(|library| library.starts_with("libc.musl"))
+    {
+        Some(Tool::new("musl", None, None))
+    } else if interpreter.is_some_and(|interpreter| interpreter.starts_with("/system/bin/linker")) {
+        Some(Tool::new("bionic", None, None))
+    } else if libraries.contains(&"libc.so.6") {
+        Some(Tool::new("glibc", None, None))
+    } else {
+        None
+    }
+}
+
+impl Toolchain {
+    pub fn from_pe(pe: &PE) -> Result<Toolchain> {
+        let goblin_pe = pe.pe()?;
+        let linker_version = goblin_pe.header.optional_header.map(|opt| {
+            (
+                opt.standard_fields.major_linker_version,
+                opt.standard_fields.minor_linker_version,
+            )
+        });
+
+        let mut compiler = None;
+        let mut linker = None;
+
+        if let Some(rich) = pe.rich_header()? {
+            // the newest tool is the one that built the program,
+            // rather than the static libraries that it links, like the CRT.
+            let newest = |kinds: &[ToolKind]| {
+                rich.entries
+                    .iter()
+                    .filter_map(|entry| entry.product().map(|product| (product, entry)))
+                    .filter(|(product, _)| kinds.contains(&product.kind))
+                    .max_by_key(|(product, entry)| (product.version, entry.build))
+            };
+
+            compiler = newest(&[ToolKind::CCompiler, ToolKind::CPPCompiler])
+                .map(|(product, entry)| msvc_tool("msvc", product.version, entry.build, linker_version, 5));
+            linker = newest(&[ToolKind::Linker])
+                .map(|(product, entry)| msvc_tool("link", product.version, entry.build, linker_version, 0));
+        }
+
+        if compiler.is_none() {
+            let strings = find_gcc_version_strings(&pe.buf);
+            compiler = choose_tools(strings.iter().map(|s| s.as_str())).0;
+        }
+
+        if linker.is_none() {
+            linker = match (linker_version, &compiler) {
+                // MinGW's GNU ld records the version of binutils, like 2.38.
+                (Some((2, minor)), Some(compiler)) if compiler.name == "gcc" => {
+                    Some(Tool::new("ld", Some(&format!("2.{minor}")), Some("GNU Binutils")))
+                }
+                (Some((major, minor)), _) if major >= 6 => Some(Tool::new(
+                    "link",
+                    Some(&format!("{major}.{minor:02}")),
+                    visual_studio(major as u16, minor as u16, 0),
+                )),
+                _ => None,
+            };
+        }
+
+        let toolchain = Toolchain {
+            compiler,
+            linker,
+            runtime: find_pe_runtime(&goblin_pe.libraries),
+        };
+
+        debug!("toolchain: {:?}", toolchain);
+
+        Ok(toolchain)
+    }
+
+    pub fn from_elf(elf: &ELF) -> Result<Toolchain> {
+        let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+        let build_info = elf.build_info()?;
+
+        let (compiler, linker) = choose_tools(build_info.comments.iter().map(|s| s.as_str()));
+
+        let toolchain = Toolchain {
+            compiler,
+            linker,
+            runtime: find_elf_runtime(&goblin_elf.libraries, goblin_elf.interpreter),
+        };
+
+        debug!("toolchain: {:?}", toolchain);
+
+        Ok(toolchain)
+    }
+
+    /// The directories, relative to a directory of FLIRT signatures, whose
+    /// signatures apply to modules built by this toolchain, from general to
+    /// specific, like `msvc` and `msvc/vs2019`, or `gcc` and `gcc/11`.
+    pub fn sig_directories(&self) -> Vec<String> {
+        let Some(compiler) = &self.compiler else {
+            return vec![];
+        };
+
+        let release = if compiler.name == "msvc" {
+            // like `Visual Studio .NET 2003` -> `vs2003`
+            compiler
+                .product
+                .as_deref()
+                .and_then(|product| product.strip_prefix("Visual Studio "))
+                .map(|release| release.trim_start_matches(".NET ").trim_end_matches(".0"))
+                .map(|release| format!("vs{release}"))
+        } else {
+            // the major version, like `11.4.0` -> `11`
+            compiler
+                .version
+                .as_deref()
+                .and_then(|version| version.split(['.', '-']).next())
+                .map(|major| major.to_string())
+        };
+
+        let mut directories = vec![compiler.name.clone()];
+        if let Some(release) = release {
+            directories.push(format!("{}/{}", compiler.name, release));
+        }
+        directories
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::loader::{
+        elf::build_info::tests::build_pie_with_build_info,
+        pe::{rich::tests::add_rich_header, tests::build_mapped_pe},
+    };
+
+    #[test]
+    fn msvc() -> Result<()> {
+        let mut buf = build_mapped_pe();
+        add_rich_header(&mut buf);
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+
+        let toolchain = Toolchain::from_pe(&pe)?;
+        assert_eq!(
+            "msvc 19.29.30133 (Visual Studio 2019)",
+            toolchain.compiler.as_ref().unwrap().to_string()
+        );
+        assert_eq!(
+            "link 14.29.30133 (Visual Studio 2019)",
+            toolchain.linker.as_ref().unwrap().to_string()
+        );
+        // only KERNEL32.dll is imported.
+        assert_eq!(None, toolchain.runtime);
+        assert_eq!(vec!["msvc", "msvc/vs2019"], toolchain.sig_directories());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mingw() -> Result<()> {
+        let mut buf = build_mapped_pe();
+        buf[0x1040..0x1040 + 30].copy_from_slice(b"GCC: (GNU) 10-win32 20220113\x00\x00");
+        // MajorLinkerVersion, MinorLinkerVersion
+        buf[0x80 + 4 + 20 + 2] = 2;
+        buf[0x80 + 4 + 20 + 3] = 38;
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+
+        let toolchain = Toolchain::from_pe(&pe)?;
+        assert_eq!("gcc 10-win32 (GNU)", toolchain.compiler.as_ref().unwrap().to_string());
+        assert_eq!("ld 2.38 (GNU Binutils)", toolchain.linker.as_ref().unwrap().to_string());
+        assert_eq!(vec!["gcc", "gcc/10"], toolchain.sig_directories());
+
+        Ok(())
+    }
+
+    #[test]
+    fn elf() -> Result<()> {
+        let buf = build_pie_with_build_info(&[
+            "GCC: (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0",
+            "Ubuntu clang version 14.0.0-1ubuntu1",
+            "Linker: LLD 14.0.0",
+        ]);
+        let elf = ELF::from_bytes(&buf)?;
+
+        let toolchain = Toolchain::from_elf(&elf)?;
+        assert_eq!(
+            "clang 14.0.0-1ubuntu1 (Ubuntu)",
+            toolchain.compiler.as_ref().unwrap().to_string()
+        );
+        assert_eq!("lld 14.0.0", toolchain.linker.as_ref().unwrap().to_string());
+        assert_eq!(vec!["clang", "clang/14"], toolchain.sig_directories());
+
+        Ok(())
+    }
+
+    #[test]
+    fn version_strings() {
+        let tool = |s| parse_version_string(s).map(|(_, tool)| tool.to_string());
+
+        assert_eq!(
+            Some("gcc 4.8.5 (GNU)".to_string()),
+            tool("GCC: (GNU) 4.8.5 20150623 (Red Hat 4.8.5-44)")
+        );
+        assert_eq!(
+            Some("gcc 11.4.0 (Ubuntu 11.4.0-1ubuntu1~22.04)".to_string()),
+            tool("GCC: (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0")
+        );
+        assert_eq!(Some("clang 14.0.0".to_string()), tool("clang version 14.0.0"));
+        assert_eq!(
+            Some("rustc 1.70.0".to_string()),
+            tool("rustc version 1.70.0 (90c541806 2023-05-31)")
+        );
+        assert_eq!(None, tool("Android (8490178, based on r450784d)"));
+    }
+
+    #[test]
+    fn runtimes() {
+        let runtime = |libraries: &[&str]| find_pe_runtime(libraries).map(|tool| tool.to_string());
+
+        assert_eq!(
+            Some("vcruntime 140".to_string()),
+            runtime(&["KERNEL32.dll", "api-ms-win-crt-runtime-l1-1-0.dll", "VCRUNTIME140.dll"])
+        );
+        assert_eq!(Some("msvcr 100".to_string()), runtime(&["MSVCR100.dll"]));
+        assert_eq!(Some("msvcrt".to_string()), runtime(&["msvcrt.dll", "KERNEL32.dll"]));
+        assert_eq!(None, runtime(&["KERNEL32.dll"]));
+
+        assert_eq!(
+            Some("musl".to_string()),
+            find_elf_runtime(&["libc.musl-x86_64.so.1"], Some("/lib/ld-musl-x86_64.so.1")).map(|tool| tool.to_string())
+        );
+        assert_eq!(
+            Some("glibc".to_string()),
+            find_elf_runtime(&["libc.so.6"], Some("/lib64/ld-linux-x86-64.so.2")).map(|tool| tool.to_string())
+        );
+    }
+}
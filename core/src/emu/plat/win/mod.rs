@@ -50,7 +50,7 @@ pub fn link_imports(emu: &mut Emulator, pe: &PE) -> Result<BTreeMap<VA, String>>
                 let original_thunk_addr = original_thunk_array + (i * psize) as RVA;
 
                 let name = match read_image_thunk_data(pe, original_thunk_addr)? {
-                    IMAGE_THUNK_DATA::Ordinal(n) => format!("{dll}!#{n}"),
+                    IMAGE_THUNK_DATA::Ordinal(n) => format!("#{n}"),
                     IMAGE_THUNK_DATA::Function(rva) => {
                         read_image_import_by_name(pe, pe.module.address_space.base_address + rva)?.name
                     }
@@ -70,6 +70,37 @@ pub fn link_imports(emu: &mut Emulator, pe: &PE) -> Result<BTreeMap<VA, String>>
         }
     }
 
+    // delay-load IAT entries initially point to stubs that call
+    // __delayLoadHelper2, which we don't emulate.
+    // so, link them like regular imports, using the import name table (INT)
+    // entry addresses.
+    if let Some(delay_import_directory) = get_delay_import_directory(pe)? {
+        for descriptor in read_delayload_descriptors(pe, delay_import_directory) {
+            let dll = descriptor.read_name(pe)?.to_lowercase();
+            let name_table = base_address + descriptor.import_name_table;
+
+            for (i, (iat_addr, thunk)) in read_delayload_thunks(pe, &descriptor).enumerate() {
+                let name_addr = name_table + (i * psize) as RVA;
+
+                let name = match thunk {
+                    IMAGE_THUNK_DATA::Ordinal(n) => format!("#{n}"),
+                    IMAGE_THUNK_DATA::Function(rva) => read_image_import_by_name(pe, base_address + rva)?.name,
+                };
+                debug!("emu: plat: win: link delay-load import {name_addr:#x} -> {dll}!{name} ");
+                imports.insert(name_addr, format!("{dll}!{name}"));
+
+                match pe.module.arch {
+                    Arch::X32 => {
+                        emu.mem.poke_u32(iat_addr, name_addr as u32)?;
+                    }
+                    Arch::X64 => {
+                        emu.mem.poke_u64(iat_addr, name_addr)?;
+                    }
+                }
+            }
+        }
+    }
+
     Ok(imports)
 }
 
@@ -179,8 +179,9 @@ mod tests {
         let mut emu: Win32Emulator = Default::default();
         emu.load_pe(&pe)?;
 
+        // relative to where the image was loaded, not its preferred base address.
         let opt = pe.optional_header.unwrap();
-        let ep = opt.windows_fields.image_base + opt.standard_fields.address_of_entry_point;
+        let ep = pe.module.address_space.base_address + opt.standard_fields.address_of_entry_point;
         emu.set_pc(ep);
 
         emu.mem().mmap(0x5000, 0x2000, Permissions::RW)?;
@@ -0,0 +1,243 @@
+//! Read the members of archives: static libraries (`.a` from GCC/Clang,
+//! `.lib` from MSVC) and import libraries (`.lib`).
+//!
+//! All of these use the System V `ar` container. MSVC adds two linker members
+//! and a long names member (`//`) whose entries are NUL-terminated rather than
+//! `/\n`-terminated; the `object` crate handles these variants for us.
+//!
+//! Each member is classified by its contents, so that callers can pick a
+//! loader: COFF objects, ELF relocatable objects, and short-format import
+//! members, which describe a single symbol exported by a DLL and contain no
+//! code.
+use anyhow::Result;
+use log::{debug, warn};
+use thiserror::Error;
+
+use crate::analysis::pe::ImportedSymbol;
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("format not supported: {0}")]
+    FormatNotSupported(String),
+
+    #[error("malformed archive: {0}")]
+    MalformedArchive(String),
+}
+
+pub const ARCHIVE_MAGIC: &[u8] = b"!<arch>\n";
+
+pub fn is_archive(buf: &[u8]) -> bool {
+    buf.starts_with(ARCHIVE_MAGIC)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemberKind {
+    /// A COFF object, which can be loaded via `loader::coff`.
+    Coff,
+    /// An ELF relocatable object, which can be loaded via
+    /// `loader::elf::relocatable`.
+    Elf,
+    /// A short-format import member from an import library,
+    /// which names a symbol exported by a DLL.
+    Import { dll: String, symbol: ImportedSymbol },
+    /// Anything else, like LTCG (`/GL`) objects containing compiler IR,
+    /// or COFF objects with an unknown machine type.
+    Other,
+}
+
+pub struct ArchiveMember<'a> {
+    /// the member name, resolved via the long names member if necessary.
+    /// MSVC archives often contain the full path at which the object was built,
+    /// like `d:\a01\_work\...\x64\Release\foo.obj`.
+    pub name: String,
+    pub kind: MemberKind,
+    pub buf:  &'a [u8],
+}
+
+fn get_member_kind(buf: &[u8]) -> MemberKind {
+    match object::FileKind::parse(buf) {
+        Ok(object::FileKind::Coff) | Ok(object::FileKind::CoffBig) => MemberKind::Coff,
+        Ok(object::FileKind::Elf32) | Ok(object::FileKind::Elf64) => {
+            if crate::loader::elf::relocatable::is_elf_object(buf) {
+                MemberKind::Elf
+            } else {
+                MemberKind::Other
+            }
+        }
+        Ok(object::FileKind::CoffImport) => match object::read::coff::ImportFile::parse(buf) {
+            Ok(import) => MemberKind::Import {
+                dll:    String::from_utf8_lossy(import.dll()).into_owned(),
+                symbol: match import.import() {
+                    object::read::coff::ImportName::Ordinal(ordinal) => ImportedSymbol::Ordinal(ordinal as u32),
+                    object::read::coff::ImportName::Name(name) => {
+                        ImportedSymbol::Name(String::from_utf8_lossy(name).into_owned())
+                    }
+                },
+            },
+            Err(e) => {
+                debug!("ar: failed to parse import member: {}", e);
+                MemberKind::Other
+            }
+        },
+        _ => MemberKind::Other,
+    }
+}
+
+/// Read the members of the given archive, in order.
+/// The symbol table and long names members are not included.
+///
+/// Malformed members are logged and skipped,
+/// and a malformed member header ends the archive.
+pub fn read_archive(buf: &[u8]) -> Result<Vec<ArchiveMember<'_>>> {
+    if !is_archive(buf) {
+        return Err(ArchiveError::FormatNotSupported("missing archive magic".to_string()).into());
+    }
+
+    let archive =
+        object::read::archive::ArchiveFile::parse(buf).map_err(|e| ArchiveError::MalformedArchive(e.to_string()))?;
+    debug!("ar: kind: {:?}", archive.kind());
+
+    let mut members = Vec::new();
+    for member in archive.members() {
+        let member = match member {
+            Ok(member) => member,
+            Err(e) => {
+                warn!("ar: failed to read member: {}", e);
+                continue;
+            }
+        };
+        let name = String::from_utf8_lossy(member.name()).into_owned();
+        let data = match member.data(buf) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("ar: failed to read member: {}: {}", name, e);
+                continue;
+            }
+        };
+        let kind = get_member_kind(data);
+
+        debug!("ar: member: {}: {:?}", name, kind);
+        members.push(ArchiveMember { name, kind, buf: data });
+    }
+
+    Ok(members)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+
+    use super::{read_archive, MemberKind};
+    use crate::analysis::pe::ImportedSymbol;
+
+    /// Wrap the given members into an archive.
+    /// Names longer than 15 bytes are placed in an MSVC-style long names
+    /// member.
+    pub fn build_archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+        fn push_member(buf: &mut Vec<u8>, name: &str, data: &[u8]) {
+            let header = format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, 0, 0, 0, 644, data.len());
+            buf.extend_from_slice(header.as_bytes());
+            buf.extend_from_slice(data);
+            if buf.len() % 2 == 1 {
+                buf.push(b'\n');
+            }
+        }
+
+        let mut names = vec![];
+        let mut headers = vec![];
+        for (name, _) in members.iter() {
+            if name.len() < 16 {
+                headers.push(format!("{name}/"));
+            } else {
+                headers.push(format!("/{}", names.len()));
+                names.extend_from_slice(name.as_bytes());
+                names.push(0);
+            }
+        }
+
+        let mut buf = b"!<arch>\n".to_vec();
+        if !names.is_empty() {
+            push_member(&mut buf, "//", &names);
+        }
+        for (header, (_, data)) in headers.iter().zip(members.iter()) {
+            push_member(&mut buf, header, data);
+        }
+        buf
+    }
+
+    /// Build a short-format import member, as found in import libraries,
+    /// for the given symbol imported by name.
+    pub fn build_import_member(dll: &str, symbol: &str) -> Vec<u8> {
+        let mut buf = vec![];
+        buf.extend_from_slice(&0x0000u16.to_le_bytes()); // Sig1: IMAGE_FILE_MACHINE_UNKNOWN
+        buf.extend_from_slice(&0xFFFFu16.to_le_bytes()); // Sig2
+        buf.extend_from_slice(&0x0000u16.to_le_bytes()); // Version
+        buf.extend_from_slice(&0x8664u16.to_le_bytes()); // Machine: AMD64
+        buf.extend_from_slice(&0u32.to_le_bytes()); // TimeDateStamp
+        buf.extend_from_slice(&((symbol.len() + 1 + dll.len() + 1) as u32).to_le_bytes()); // SizeOfData
+        buf.extend_from_slice(&0u16.to_le_bytes()); // Hint
+        buf.extend_from_slice(&(1u16 << 2).to_le_bytes()); // Type: IMPORT_OBJECT_CODE, NameType: IMPORT_OBJECT_NAME
+        buf.extend_from_slice(symbol.as_bytes());
+        buf.push(0);
+        buf.extend_from_slice(dll.as_bytes());
+        buf.push(0);
+        buf
+    }
+
+    #[test]
+    fn members() -> Result<()> {
+        let obj = crate::loader::elf::relocatable::tests::build_elf_object();
+        let import = build_import_member("KERNEL32.dll", "CreateFileW");
+        let buf = build_archive(&[
+            ("short.o", &obj),
+            ("d:\\build\\x64\\Release\\long_name.obj", &obj),
+            ("KERNEL32.dll", &import),
+            ("README", b"not an object"),
+        ]);
+
+        let members = read_archive(&buf)?;
+        let names = members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "short.o",
+                "d:\\build\\x64\\Release\\long_name.obj",
+                "KERNEL32.dll",
+                "README"
+            ],
+            names
+        );
+
+        assert_eq!(MemberKind::Elf, members[0].kind);
+        assert_eq!(obj.as_slice(), members[0].buf);
+        assert_eq!(MemberKind::Elf, members[1].kind);
+        assert_eq!(
+            MemberKind::Import {
+                dll:    "KERNEL32.dll".to_string(),
+                symbol: ImportedSymbol::Name("CreateFileW".to_string()),
+            },
+            members[2].kind
+        );
+        assert_eq!(MemberKind::Other, members[3].kind);
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_member() -> Result<()> {
+        let obj = crate::loader::elf::relocatable::tests::build_elf_object();
+        let mut buf = build_archive(&[("first.o", &obj), ("second.o", &obj)]);
+        buf.truncate(buf.len() - 8);
+
+        // the second member extends beyond the archive, so only the first is read.
+        let members = read_archive(&buf)?;
+        assert_eq!(1, members.len());
+        assert_eq!("first.o", members[0].name);
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_an_archive() {
+        assert!(read_archive(b"MZ\x90\x00").is_err());
+    }
+}
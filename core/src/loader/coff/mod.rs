@@ -238,12 +238,14 @@ fn load_coff_module(
         Module {
             arch,
             sections,
+            section_headers: Default::default(),
             address_space: address_space.into_absolute(base_address)?,
         }
     } else {
         Module {
             arch,
             sections: vec![],
+            section_headers: vec![],
             address_space: RelativeAddressSpace::with_capacity(0x0).into_absolute(base_address)?,
         }
     };
@@ -0,0 +1,203 @@
+//! Read the traces that toolchains leave in ELF files:
+//!   - `.comment`, the version strings of the compilers and linkers, like `GCC:
+//!     (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0`, and
+//!   - `.note.gnu.build-id`, the unique ID of the build.
+use anyhow::Result;
+use goblin::elf::note::NT_GNU_BUILD_ID;
+use log::debug;
+
+use crate::loader::elf::ELF;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// the distinct strings from the `.comment` section, in order.
+    pub comments: Vec<String>,
+    /// the description of the `NT_GNU_BUILD_ID` note, typically a SHA-1 hash.
+    pub build_id: Option<Vec<u8>>,
+}
+
+impl BuildInfo {
+    pub fn from_elf(elf: &ELF) -> Result<BuildInfo> {
+        let goblin_elf = super::get_elf(&elf.buf)?;
+
+        let mut comments: Vec<String> = vec![];
+        for section in goblin_elf.section_headers.iter() {
+            if goblin_elf.shdr_strtab.get_at(section.sh_name) != Some(".comment") {
+                continue;
+            }
+
+            let start = section.sh_offset as usize;
+            let end = start.saturating_add(section.sh_size as usize);
+            let Some(buf) = elf.buf.get(start..end) else {
+                debug!("elf: .comment: out of bounds");
+                continue;
+            };
+
+            for comment in buf.split(|&b| b == 0) {
+                let comment = String::from_utf8_lossy(comment).trim().to_string();
+                if !comment.is_empty() && !comments.contains(&comment) {
+                    comments.push(comment);
+                }
+            }
+        }
+
+        // prefer the section, but fall back to the PT_NOTE segments
+        // when the section headers have been stripped.
+        let build_id = goblin_elf
+            .iter_note_sections(&elf.buf, Some(".note.gnu.build-id"))
+            .into_iter()
+            .chain(goblin_elf.iter_note_headers(&elf.buf))
+            .flatten()
+            .filter_map(|note| note.ok())
+            .find(|note| note.n_type == NT_GNU_BUILD_ID && note.name.trim_end_matches('\0') == "GNU")
+            .map(|note| note.desc.to_vec());
+
+        debug!(
+            "elf: found {} comments, build id: {}",
+            comments.len(),
+            build_id.is_some()
+        );
+
+        Ok(BuildInfo { comments, build_id })
+    }
+
+    /// The build ID formatted as hex, like `file` and `readelf` show it.
+    pub fn build_id_hex(&self) -> Option<String> {
+        self.build_id
+            .as_ref()
+            .map(|build_id| build_id.iter().map(|b| format!("{b:02x}")).collect())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::loader::elf::tests::build_pie;
+
+    pub const BUILD_ID: [u8; 20] = [
+        0x8b, 0x9a, 0x6c, 0x47, 0x1f, 0x2e, 0x3d, 0x4c, 0x5b, 0x6a, 0x79, 0x88, 0x97, 0xa6, 0xb5, 0xc4, 0xd3, 0xe2,
+        0xf1, 0x00,
+    ];
+
+    /// Extend `build_pie` with section headers for `.comment`, containing the
+    /// given strings, and `.note.gnu.build-id`, containing `BUILD_ID`:
+    ///
+    /// ```text
+    ///   0x800: .note.gnu.build-id
+    ///   0x840: .comment
+    ///   0x900: .shstrtab
+    ///   0xA00: section headers
+    /// ```
+    pub fn build_pie_with_build_info(comments: &[&str]) -> Vec<u8> {
+        let mut buf = build_pie();
+
+        let mut note = vec![];
+        note.write_u32::<LittleEndian>(4).unwrap(); // n_namesz
+        note.write_u32::<LittleEndian>(BUILD_ID.len() as u32).unwrap(); // n_descsz
+        note.write_u32::<LittleEndian>(NT_GNU_BUILD_ID).unwrap(); // n_type
+        note.extend_from_slice(b"GNU\x00");
+        note.extend_from_slice(&BUILD_ID);
+        buf[0x800..0x800 + note.len()].copy_from_slice(&note);
+
+        let comment = comments
+            .iter()
+            .flat_map(|comment| comment.bytes().chain(std::iter::once(0)))
+            .collect::<Vec<u8>>();
+        buf[0x840..0x840 + comment.len()].copy_from_slice(&comment);
+
+        let shstrtab = b"\x00.note.gnu.build-id\x00.comment\x00.shstrtab\x00";
+        buf[0x900..0x900 + shstrtab.len()].copy_from_slice(shstrtab);
+
+        let mut shdrs = vec![0u8; 0x40];
+        // Elf64_Shdr: (name, type, flags, offset/addr, size, align)
+        for (name, sh_type, flags, offset, size, align) in [
+            (
+                1u32,
+                goblin::elf::section_header::SHT_NOTE,
+                0x2u64,
+                0x800u64,
+                note.len() as u64,
+                4u64,
+            ),
+            (
+                20,
+                goblin::elf::section_header::SHT_PROGBITS,
+                0x30,
+                0x840,
+                comment.len() as u64,
+                1,
+            ),
+            (
+                29,
+                goblin::elf::section_header::SHT_STRTAB,
+                0x0,
+                0x900,
+                shstrtab.len() as u64,
+                1,
+            ),
+        ] {
+            shdrs.write_u32::<LittleEndian>(name).unwrap(); // sh_name
+            shdrs.write_u32::<LittleEndian>(sh_type).unwrap(); // sh_type
+            shdrs.write_u64::<LittleEndian>(flags).unwrap(); // sh_flags
+            shdrs
+                .write_u64::<LittleEndian>(if flags & 0x2 != 0 { offset } else { 0 })
+                .unwrap(); // sh_addr
+            shdrs.write_u64::<LittleEndian>(offset).unwrap(); // sh_offset
+            shdrs.write_u64::<LittleEndian>(size).unwrap(); // sh_size
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_link
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_info
+            shdrs.write_u64::<LittleEndian>(align).unwrap(); // sh_addralign
+            shdrs
+                .write_u64::<LittleEndian>(if sh_type == goblin::elf::section_header::SHT_NOTE {
+                    0
+                } else {
+                    1
+                })
+                .unwrap(); // sh_entsize
+        }
+        buf[0xA00..0xA00 + shdrs.len()].copy_from_slice(&shdrs);
+
+        buf[0x28..0x30].copy_from_slice(&0xA00u64.to_le_bytes()); // e_shoff
+        buf[0x3C..0x3E].copy_from_slice(&4u16.to_le_bytes()); // e_shnum
+        buf[0x3E..0x40].copy_from_slice(&3u16.to_le_bytes()); // e_shstrndx
+
+        buf
+    }
+
+    #[test]
+    fn build_info() -> Result<()> {
+        let buf = build_pie_with_build_info(&[
+            "GCC: (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0",
+            "clang version 14.0.0",
+            "GCC: (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0",
+        ]);
+        let elf = ELF::from_bytes(&buf)?;
+
+        let build_info = BuildInfo::from_elf(&elf)?;
+        assert_eq!(
+            vec![
+                "GCC: (Ubuntu 11.4.0-1ubuntu1~22.04) 11.4.0".to_string(),
+                "clang version 14.0.0".to_string()
+            ],
+            build_info.comments
+        );
+        assert_eq!(Some(BUILD_ID.to_vec()), build_info.build_id);
+        assert_eq!(
+            Some("8b9a6c471f2e3d4c5b6a798897a6b5c4d3e2f100"),
+            build_info.build_id_hex().as_deref()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_build_info() -> Result<()> {
+        let elf = ELF::from_bytes(&build_pie())?;
+        assert_eq!(BuildInfo::default(), BuildInfo::from_elf(&elf)?);
+
+        Ok(())
+    }
+}
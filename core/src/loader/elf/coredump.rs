@@ -0,0 +1,633 @@
+//! Load Linux ELF core dumps (`ET_CORE`).
+//!
+//! A core dump has a PT_LOAD segment for each memory mapping of the process,
+//! and a PT_NOTE segment that describes the process:
+//!   - NT_PRSTATUS: one per thread, with its registers,
+//!   - NT_FILE: the file backing each mapping,
+//!   - NT_AUXV: the auxiliary vector, including the program entry point.
+//!
+//! Like minidumps, the address space of a process is far too sparse to map
+//! into a single `Module`, so callers pick a window: either an arbitrary range
+//! via `load_range`, or the mappings of a single file via `load_file`.
+//!
+//! Note that by default, the kernel dumps only the first page of file-backed
+//! private mappings (see `coredump_filter(5)`), so code from the executable
+//! and libraries is often missing. Missing memory reads as zero.
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use goblin::elf::{
+    header::{EM_386, EM_X86_64, ET_CORE},
+    note::{NT_FILE, NT_PRSTATUS},
+    program_header::PT_LOAD,
+};
+use log::{debug, warn};
+
+use crate::{
+    arch::Arch,
+    aspace::RelativeAddressSpace,
+    loader::elf::ELFError,
+    module::{Module, Permissions, Section},
+    util, VA,
+};
+
+const PAGE_SIZE: u64 = 0x1000;
+
+/// note: the auxiliary vector, `Elf64_auxv_t[]`.
+const NT_AUXV: u32 = 6;
+
+/// auxv: the entry point of the program.
+pub const AT_ENTRY: u64 = 9;
+/// auxv: the base address of the program interpreter (`ld.so`).
+pub const AT_BASE: u64 = 7;
+/// auxv: the address of the program headers of the program.
+pub const AT_PHDR: u64 = 3;
+
+/// the names of the registers in `struct user_regs_struct`, in order,
+/// as found in `elf_prstatus.pr_reg`.
+const X64_REGISTERS: [&str; 27] = [
+    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax", "rcx", "rdx", "rsi", "rdi", "orig_rax",
+    "rip", "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds", "es", "fs", "gs",
+];
+const X32_REGISTERS: [&str; 17] = [
+    "ebx", "ecx", "edx", "esi", "edi", "ebp", "eax", "ds", "es", "fs", "gs", "orig_eax", "eip", "cs", "eflags", "esp",
+    "ss",
+];
+
+/// A memory mapping of the process, from a PT_LOAD segment.
+#[derive(Debug, Clone)]
+pub struct Mapping {
+    pub start:       VA,
+    pub end:         VA,
+    pub permissions: Permissions,
+    /// the range of the core file that contains the dumped data,
+    /// which may be shorter than the mapping, or empty.
+    pub data:        std::ops::Range<u64>,
+    /// the path of the backing file, from NT_FILE, like `/usr/lib/libc.so.6`.
+    /// `None` for anonymous mappings, like the heap and stacks.
+    pub file:        Option<String>,
+    /// the offset into the backing file at which the mapping starts.
+    pub file_offset: u64,
+}
+
+/// The state of a thread at the time of the dump, from NT_PRSTATUS.
+#[derive(Debug, Clone)]
+pub struct ThreadState {
+    pub pid:       u32,
+    /// the signal that caused the dump (`pr_cursig`),
+    /// which is set for the thread that crashed.
+    pub signal:    u16,
+    /// the general purpose registers, by name, like `rip` or `eip`.
+    pub registers: BTreeMap<&'static str, u64>,
+}
+
+impl ThreadState {
+    pub fn pc(&self) -> Option<VA> {
+        self.registers.get("rip").or_else(|| self.registers.get("eip")).cloned()
+    }
+
+    pub fn sp(&self) -> Option<VA> {
+        self.registers.get("rsp").or_else(|| self.registers.get("esp")).cloned()
+    }
+}
+
+/// A parsed ELF core dump.
+/// The `buf` field contains the raw data, from which memory is read on demand.
+pub struct ELFCore {
+    pub buf:      Vec<u8>,
+    pub arch:     Arch,
+    /// sorted by address.
+    pub mappings: Vec<Mapping>,
+    /// in the order found in the dump. the kernel writes the thread that
+    /// received the signal first.
+    pub threads:  Vec<ThreadState>,
+    pub auxv:     BTreeMap<u64, u64>,
+}
+
+impl ELFCore {
+    pub fn from_bytes(buf: &[u8]) -> Result<ELFCore> {
+        load_elf_core(buf)
+    }
+
+    /// The thread that received the signal that caused the dump,
+    /// or the first thread if none did.
+    pub fn crashing_thread(&self) -> Option<&ThreadState> {
+        self.threads
+            .iter()
+            .find(|thread| thread.signal != 0)
+            .or_else(|| self.threads.first())
+    }
+
+    /// The entry point of the program, from the auxiliary vector.
+    pub fn entry_point(&self) -> Option<VA> {
+        self.auxv.get(&AT_ENTRY).cloned()
+    }
+
+    /// The path of the program, which is the file backing the mapping
+    /// that contains the entry point.
+    pub fn executable(&self) -> Option<&str> {
+        self.mapping_by_address(self.entry_point()?)
+            .and_then(|mapping| mapping.file.as_deref())
+    }
+
+    /// Find the mapping that contains the given address.
+    pub fn mapping_by_address(&self, va: VA) -> Option<&Mapping> {
+        self.mappings
+            .iter()
+            .find(|mapping| mapping.start <= va && va < mapping.end)
+    }
+
+    /// Read the given range of process memory.
+    /// Bytes that weren't dumped, or that lie beyond the end of the address
+    /// space, are zero.
+    pub fn read_memory(&self, address: VA, size: u64) -> Vec<u8> {
+        let mut buf = vec![0u8; size as usize];
+        let end = address.saturating_add(size);
+
+        for mapping in self.mappings.iter() {
+            let data_end = mapping.start + (mapping.data.end - mapping.data.start);
+            if data_end <= address || mapping.start >= end {
+                continue;
+            }
+
+            let start = std::cmp::max(address, mapping.start);
+            let stop = std::cmp::min(end, data_end);
+
+            let src_start = (mapping.data.start + (start - mapping.start)) as usize;
+            let src_end = std::cmp::min(src_start + (stop - start) as usize, self.buf.len());
+            if src_start >= src_end {
+                continue;
+            }
+
+            let dst_start = (start - address) as usize;
+            buf[dst_start..dst_start + (src_end - src_start)].copy_from_slice(&self.buf[src_start..src_end]);
+        }
+
+        buf
+    }
+
+    /// Map the mappings found within the given range into a module,
+    /// at their real virtual addresses, with sections named after the backing
+    /// files.
+    pub fn load_range(&self, start: VA, end: VA) -> Result<Module> {
+        let base_address = start - start % PAGE_SIZE;
+        let Some(end) = end.checked_next_multiple_of(PAGE_SIZE) else {
+            return Err(ELFError::FormatNotSupported("range beyond the address space".to_string()).into());
+        };
+        if end <= base_address {
+            return Err(ELFError::FormatNotSupported("empty range".to_string()).into());
+        }
+
+        let mut address_space = RelativeAddressSpace::with_capacity(end - base_address);
+        let mut sections = vec![];
+
+        for mapping in self.mappings.iter() {
+            if mapping.end <= base_address || mapping.start >= end {
+                continue;
+            }
+
+            let vstart = std::cmp::max(base_address, mapping.start);
+            let vend = std::cmp::min(end, mapping.end);
+
+            // mappings are page aligned, so this maps whole pages.
+            let page_start = util::align(vstart + 1, PAGE_SIZE) - PAGE_SIZE;
+            let page_end = util::align(vend, PAGE_SIZE);
+            let buf = self.read_memory(page_start, page_end - page_start);
+            address_space.map.write(page_start - base_address, &buf)?;
+
+            let data_start = std::cmp::min(mapping.data.start + (vstart - mapping.start), mapping.data.end);
+            let data_end = std::cmp::min(mapping.data.start + (vend - mapping.start), mapping.data.end);
+
+            sections.push(Section {
+                physical_range: data_start..data_end,
+                virtual_range:  vstart..vend,
+                permissions:    mapping.permissions,
+                name:           match &mapping.file {
+                    Some(file) => file.clone(),
+                    None => format!("mapping_{vstart:x}"),
+                },
+            });
+        }
+
+        Ok(Module {
+            arch: self.arch,
+            sections,
+            section_headers: Default::default(),
+            address_space: address_space.into_absolute(base_address)?,
+        })
+    }
+
+    /// Map each mapping backed by the given file, like `/usr/bin/bash`,
+    /// into a module spanning them.
+    pub fn load_file(&self, file: &str) -> Result<Module> {
+        let mappings = self
+            .mappings
+            .iter()
+            .filter(|mapping| mapping.file.as_deref() == Some(file));
+
+        let start = mappings.clone().map(|mapping| mapping.start).min();
+        let end = mappings.map(|mapping| mapping.end).max();
+
+        match (start, end) {
+            (Some(start), Some(end)) => self.load_range(start, end),
+            _ => Err(ELFError::FormatNotSupported(format!("no mappings for file: {file}")).into()),
+        }
+    }
+}
+
+/// Is the given buffer an ELF core dump?
+pub fn is_elf_core(buf: &[u8]) -> bool {
+    match goblin::elf::Elf::parse_header(buf) {
+        Ok(header) => header.e_type == ET_CORE,
+        Err(_) => false,
+    }
+}
+
+fn read_word(arch: Arch, buf: &[u8], offset: usize) -> Option<u64> {
+    match arch {
+        Arch::X32 => buf.get(offset..offset + 4).map(|b| LittleEndian::read_u32(b) as u64),
+        Arch::X64 => buf.get(offset..offset + 8).map(LittleEndian::read_u64),
+    }
+}
+
+fn parse_prstatus(arch: Arch, desc: &[u8]) -> Option<ThreadState> {
+    // offsets within `struct elf_prstatus`.
+    let (pid_offset, registers_offset, registers) = match arch {
+        Arch::X32 => (24, 72, &X32_REGISTERS[..]),
+        Arch::X64 => (32, 112, &X64_REGISTERS[..]),
+    };
+
+    let psize = arch.pointer_size();
+    let mut state = ThreadState {
+        signal:    LittleEndian::read_u16(desc.get(12..14)?),
+        pid:       LittleEndian::read_u32(desc.get(pid_offset..pid_offset + 4)?),
+        registers: Default::default(),
+    };
+
+    for (i, &name) in registers.iter().enumerate() {
+        state
+            .registers
+            .insert(name, read_word(arch, desc, registers_offset + i * psize)?);
+    }
+
+    Some(state)
+}
+
+/// NT_FILE:
+///
+/// ```text
+///   word count
+///   word page_size
+///   { word start; word end; word file_ofs; } [count]
+///   char filenames[count][]  // NUL-terminated
+/// ```
+fn parse_file_note(arch: Arch, desc: &[u8]) -> Option<Vec<(VA, VA, u64, String)>> {
+    let psize = arch.pointer_size();
+    let count = read_word(arch, desc, 0)? as usize;
+    let page_size = read_word(arch, desc, psize)?;
+
+    let names_offset = count.checked_mul(3 * psize)?.checked_add(2 * psize)?;
+    let mut names = desc
+        .get(names_offset..)?
+        .split(|&b| b == 0x0)
+        .map(|name| String::from_utf8_lossy(name).into_owned());
+
+    let mut files = vec![];
+    for i in 0..count {
+        let entry = 2 * psize + i * 3 * psize;
+        let start = read_word(arch, desc, entry)?;
+        let end = read_word(arch, desc, entry + psize)?;
+        let file_offset = read_word(arch, desc, entry + 2 * psize)?.checked_mul(page_size)?;
+        files.push((start, end, file_offset, names.next()?));
+    }
+
+    Some(files)
+}
+
+fn parse_auxv(arch: Arch, desc: &[u8]) -> BTreeMap<u64, u64> {
+    let psize = arch.pointer_size();
+    let mut auxv: BTreeMap<u64, u64> = Default::default();
+
+    for entry in (0..desc.len()).step_by(2 * psize) {
+        let (Some(key), Some(value)) = (read_word(arch, desc, entry), read_word(arch, desc, entry + psize)) else {
+            break;
+        };
+        // AT_NULL
+        if key == 0 {
+            break;
+        }
+        auxv.insert(key, value);
+    }
+
+    auxv
+}
+
+fn load_elf_core(buf: &[u8]) -> Result<ELFCore> {
+    let elf = goblin::elf::Elf::parse(buf).map_err(|e| ELFError::MalformedElfFile(e.to_string()))?;
+    if elf.header.e_type != ET_CORE {
+        return Err(ELFError::FormatNotSupported("not a core dump".to_string()).into());
+    }
+
+    let arch = match elf.header.e_machine {
+        EM_X86_64 => Arch::X64,
+        EM_386 => Arch::X32,
+        _ => {
+            return Err(
+                ELFError::FormatNotSupported(format!("Unsupported architecture: {}", elf.header.e_machine)).into(),
+            )
+        }
+    };
+    debug!("elf: core: arch: {:?}", arch);
+
+    let mut threads = vec![];
+    let mut files = vec![];
+    let mut auxv = Default::default();
+
+    if let Some(notes) = elf.iter_note_headers(buf) {
+        for note in notes {
+            let note = note.map_err(|e| ELFError::MalformedElfFile(e.to_string()))?;
+            if note.name != "CORE" {
+                continue;
+            }
+
+            match note.n_type {
+                NT_PRSTATUS => match parse_prstatus(arch, note.desc) {
+                    Some(thread) => {
+                        debug!("elf: core: thread: {}: pc: {:#x?}", thread.pid, thread.pc());
+                        threads.push(thread);
+                    }
+                    None => debug!("elf: core: invalid NT_PRSTATUS"),
+                },
+                NT_FILE => match parse_file_note(arch, note.desc) {
+                    Some(f) => files = f,
+                    None => debug!("elf: core: invalid NT_FILE"),
+                },
+                NT_AUXV => auxv = parse_auxv(arch, note.desc),
+                _ => continue,
+            }
+        }
+    }
+
+    let mut mappings = elf
+        .program_headers
+        .iter()
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .filter_map(|ph| {
+            let (Some(end), Some(data_end)) = (
+                ph.p_vaddr.checked_add(ph.p_memsz),
+                ph.p_offset.checked_add(std::cmp::min(ph.p_filesz, ph.p_memsz)),
+            ) else {
+                warn!("elf: core: mapping beyond the address space: {:#x}", ph.p_vaddr);
+                return None;
+            };
+
+            let mut permissions = Permissions::empty();
+            if ph.is_read() {
+                permissions |= Permissions::R;
+            }
+            if ph.is_write() {
+                permissions |= Permissions::W;
+            }
+            if ph.is_executable() {
+                permissions |= Permissions::X;
+            }
+
+            let (file, file_offset) = match files.iter().find(|(start, _, _, _)| *start == ph.p_vaddr) {
+                Some((_, _, file_offset, name)) => (Some(name.clone()), *file_offset),
+                None => (None, 0),
+            };
+
+            Some(Mapping {
+                start: ph.p_vaddr,
+                end,
+                permissions,
+                data: ph.p_offset..data_end,
+                file,
+                file_offset,
+            })
+        })
+        .collect::<Vec<_>>();
+    mappings.sort_by_key(|mapping| mapping.start);
+
+    for mapping in mappings.iter() {
+        debug!(
+            "elf: core: mapping: {:#x} - {:#x} {:?} {}",
+            mapping.start,
+            mapping.end,
+            mapping.permissions,
+            mapping.file.as_deref().unwrap_or("")
+        );
+    }
+
+    debug!("elf: core: loaded");
+    Ok(ELFCore {
+        buf: buf.to_vec(),
+        arch,
+        mappings,
+        threads,
+        auxv,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use crate::{aspace::AddressSpace, module::Permissions};
+
+    pub const TEXT_ADDRESS: u64 = 0x5555_5555_4000;
+    pub const STACK_ADDRESS: u64 = 0x7FFF_FFFD_E000;
+    pub const CRASH_OFFSET: u64 = 0x10;
+    pub const EXECUTABLE: &str = "/usr/bin/crash";
+
+    fn write_note(buf: &mut Vec<u8>, type_: u32, desc: &[u8]) {
+        buf.write_u32::<LittleEndian>(5).unwrap(); // n_namesz
+        buf.write_u32::<LittleEndian>(desc.len() as u32).unwrap(); // n_descsz
+        buf.write_u32::<LittleEndian>(type_).unwrap(); // n_type
+        buf.extend_from_slice(b"CORE\x00\x00\x00\x00");
+        buf.extend_from_slice(desc);
+        buf.resize(crate::util::align(buf.len() as u64, 4) as usize, 0);
+    }
+
+    fn build_prstatus(pid: u32, signal: u16, rip: u64, rsp: u64) -> Vec<u8> {
+        let mut desc = vec![0u8; 336];
+        desc[12..14].copy_from_slice(&signal.to_le_bytes());
+        desc[32..36].copy_from_slice(&pid.to_le_bytes());
+        // pr_reg.rip, pr_reg.rsp
+        desc[112 + 16 * 8..112 + 17 * 8].copy_from_slice(&rip.to_le_bytes());
+        desc[112 + 19 * 8..112 + 20 * 8].copy_from_slice(&rsp.to_le_bytes());
+        desc
+    }
+
+    /// Synthesize an x86-64 core dump of a process with two threads,
+    /// the second of which crashed at `TEXT_ADDRESS + CRASH_OFFSET`, with
+    /// mappings:
+    ///
+    /// ```text
+    ///   TEXT_ADDRESS:  r-x /usr/bin/crash, one page dumped
+    ///   STACK_ADDRESS: rw- anonymous, two pages, only the first dumped
+    /// ```
+    pub fn build_elf_core() -> Vec<u8> {
+        let mut notes = vec![];
+        write_note(
+            &mut notes,
+            1,
+            &build_prstatus(100, 0, 0x7FFF_F7E0_0000, STACK_ADDRESS + 0x800),
+        );
+        write_note(
+            &mut notes,
+            1,
+            &build_prstatus(101, 11, TEXT_ADDRESS + CRASH_OFFSET, STACK_ADDRESS + 0x100),
+        );
+
+        let mut auxv = vec![];
+        for (key, value) in [(3u64, TEXT_ADDRESS + 0x40), (9, TEXT_ADDRESS), (0, 0)] {
+            auxv.write_u64::<LittleEndian>(key).unwrap();
+            auxv.write_u64::<LittleEndian>(value).unwrap();
+        }
+        write_note(&mut notes, 6, &auxv);
+
+        let mut file = vec![];
+        file.write_u64::<LittleEndian>(1).unwrap(); // count
+        file.write_u64::<LittleEndian>(0x1000).unwrap(); // page_size
+        file.write_u64::<LittleEndian>(TEXT_ADDRESS).unwrap(); // start
+        file.write_u64::<LittleEndian>(TEXT_ADDRESS + 0x1000).unwrap(); // end
+        file.write_u64::<LittleEndian>(1).unwrap(); // file_ofs, in pages
+        file.extend_from_slice(EXECUTABLE.as_bytes());
+        file.push(0);
+        write_note(&mut notes, 0x4649_4C45, &file);
+
+        let phdrs_offset = 0x40u64;
+        let notes_offset = 0x200u64;
+        let text_offset = 0x1000u64;
+        let stack_offset = 0x2000u64;
+
+        let mut buf = vec![];
+        // Elf64_Ehdr
+        buf.extend_from_slice(b"\x7FELF\x02\x01\x01\x00");
+        buf.extend_from_slice(&[0u8; 8]);
+        buf.write_u16::<LittleEndian>(4).unwrap(); // e_type: ET_CORE
+        buf.write_u16::<LittleEndian>(62).unwrap(); // e_machine: EM_X86_64
+        buf.write_u32::<LittleEndian>(1).unwrap(); // e_version
+        buf.write_u64::<LittleEndian>(0).unwrap(); // e_entry
+        buf.write_u64::<LittleEndian>(phdrs_offset).unwrap(); // e_phoff
+        buf.write_u64::<LittleEndian>(0).unwrap(); // e_shoff
+        buf.write_u32::<LittleEndian>(0).unwrap(); // e_flags
+        buf.write_u16::<LittleEndian>(0x40).unwrap(); // e_ehsize
+        buf.write_u16::<LittleEndian>(0x38).unwrap(); // e_phentsize
+        buf.write_u16::<LittleEndian>(3).unwrap(); // e_phnum
+        buf.write_u16::<LittleEndian>(0x40).unwrap(); // e_shentsize
+        buf.write_u16::<LittleEndian>(0).unwrap(); // e_shnum
+        buf.write_u16::<LittleEndian>(0).unwrap(); // e_shstrndx
+
+        // Elf64_Phdr
+        for (type_, flags, offset, vaddr, filesz, memsz, align) in [
+            (4u32, 0u32, notes_offset, 0u64, notes.len() as u64, 0u64, 4u64), // PT_NOTE
+            (1, 5, text_offset, TEXT_ADDRESS, 0x1000, 0x1000, 0x1000),        // PT_LOAD r-x
+            (1, 6, stack_offset, STACK_ADDRESS, 0x1000, 0x2000, 0x1000),      // PT_LOAD rw-
+        ] {
+            buf.write_u32::<LittleEndian>(type_).unwrap();
+            buf.write_u32::<LittleEndian>(flags).unwrap();
+            buf.write_u64::<LittleEndian>(offset).unwrap();
+            buf.write_u64::<LittleEndian>(vaddr).unwrap();
+            buf.write_u64::<LittleEndian>(0).unwrap(); // p_paddr
+            buf.write_u64::<LittleEndian>(filesz).unwrap();
+            buf.write_u64::<LittleEndian>(memsz).unwrap();
+            buf.write_u64::<LittleEndian>(align).unwrap();
+        }
+
+        buf.resize(notes_offset as usize, 0);
+        buf.extend_from_slice(&notes);
+
+        buf.resize(text_offset as usize, 0);
+        let mut text = vec![0xCCu8; 0x1000];
+        // crash site: ud2
+        text[CRASH_OFFSET as usize..CRASH_OFFSET as usize + 2].copy_from_slice(&[0x0F, 0x0B]);
+        buf.extend_from_slice(&text);
+
+        buf.extend_from_slice(&[0x41u8; 0x1000]);
+
+        buf
+    }
+
+    #[test]
+    fn notes() -> Result<()> {
+        let buf = build_elf_core();
+        assert!(super::is_elf_core(&buf));
+        let core = super::ELFCore::from_bytes(&buf)?;
+
+        assert!(matches!(core.arch, crate::arch::Arch::X64));
+
+        assert_eq!(2, core.threads.len());
+        let thread = core.crashing_thread().unwrap();
+        assert_eq!(101, thread.pid);
+        assert_eq!(11, thread.signal);
+        assert_eq!(Some(TEXT_ADDRESS + CRASH_OFFSET), thread.pc());
+        assert_eq!(Some(STACK_ADDRESS + 0x100), thread.sp());
+
+        assert_eq!(Some(TEXT_ADDRESS), core.entry_point());
+        assert_eq!(Some(EXECUTABLE), core.executable());
+
+        assert_eq!(2, core.mappings.len());
+        assert_eq!(Some(EXECUTABLE.to_string()), core.mappings[0].file);
+        assert_eq!(0x1000, core.mappings[0].file_offset);
+        assert_eq!(None, core.mappings[1].file);
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_file() -> Result<()> {
+        let buf = build_elf_core();
+        let core = super::ELFCore::from_bytes(&buf)?;
+
+        let module = core.load_file(EXECUTABLE)?;
+        assert_eq!(TEXT_ADDRESS, module.address_space.base_address);
+        assert_eq!(1, module.sections.len());
+        assert_eq!(EXECUTABLE, module.sections[0].name);
+        assert_eq!(Permissions::RX, module.sections[0].permissions);
+        assert_eq!(0x0F, module.address_space.read_u8(TEXT_ADDRESS + CRASH_OFFSET)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_range() -> Result<()> {
+        let buf = build_elf_core();
+        let core = super::ELFCore::from_bytes(&buf)?;
+
+        let module = core.load_range(STACK_ADDRESS, STACK_ADDRESS + 0x2000)?;
+        assert_eq!(0x41, module.address_space.read_u8(STACK_ADDRESS)?);
+        // not dumped, so zero.
+        assert_eq!(0x00, module.address_space.read_u8(STACK_ADDRESS + 0x1000)?);
+        assert_eq!("mapping_7ffffffde000", module.sections[0].name);
+
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_bounds() -> Result<()> {
+        let mut buf = build_elf_core();
+        // the second PT_LOAD: p_memsz
+        let phdr = 0x40 + 2 * 0x38;
+        buf[phdr + 0x28..phdr + 0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        // the mapping that wraps around is skipped.
+        let core = super::ELFCore::from_bytes(&buf)?;
+        assert_eq!(1, core.mappings.len());
+        assert_eq!(TEXT_ADDRESS, core.mappings[0].start);
+
+        assert!(core.load_range(u64::MAX - 0x10, u64::MAX).is_err());
+        assert_eq!(vec![0u8; 0x20], core.read_memory(u64::MAX - 0x10, 0x20));
+
+        // NT_FILE with so many entries that their size overflows.
+        let mut file = vec![];
+        file.write_u64::<LittleEndian>(u64::MAX / 8).unwrap(); // count
+        file.write_u64::<LittleEndian>(0x1000).unwrap(); // page_size
+        assert!(super::parse_file_note(crate::arch::Arch::X64, &file).is_none());
+
+        Ok(())
+    }
+}
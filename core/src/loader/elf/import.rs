@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use crate::{
     arch::Arch,
     aspace::{self, RelativeAddressSpace},
+    loader::elf::version::ELFSymbolVersion,
     module::{Module, Permissions, Section},
     util, RVA, VA,
 };
@@ -21,6 +22,19 @@ pub struct ELFImportSymbol {
     pub visibility: ElfSymbolBinding,
     pub plt_address: Option<u64>,
     pub got_address: Option<u64>,
+    /// the version needed, like `GLIBC_2.14`, from `.gnu.version_r`.
+    pub version: Option<ELFSymbolVersion>,
+}
+
+impl ELFImportSymbol {
+    /// The name with its version, like `memcpy@GLIBC_2.14`,
+    /// or just the name, when the symbol isn't versioned.
+    pub fn versioned_name(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.name, version.name),
+            None => self.name.clone(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -38,11 +52,12 @@ pub enum ElfSymbolBinding {
     Unknown(u8),
 }
 
-pub fn read_import_libraries(elf: &goblin::elf::Elf) -> Vec<ELFDynamicImport> {
+/// `versions` are the versions of the dynamic symbols, from `read_symbol_versions`.
+pub fn read_import_libraries(elf: &goblin::elf::Elf, versions: &[Option<ELFSymbolVersion>]) -> Vec<ELFDynamicImport> {
     let mut imports = Vec::new();
     
     // get all import symbols with PLT/GOT addresses resolved
-    let import_symbols = read_import_symbols(elf);
+    let import_symbols = read_import_symbols(elf, versions);
     
     // group symbols by library
     let mut lib_symbols: HashMap<String, Vec<ELFImportSymbol>> = HashMap::new();
@@ -55,8 +70,11 @@ pub fn read_import_libraries(elf: &goblin::elf::Elf) -> Vec<ELFDynamicImport> {
     // add undefined symbols
     for symbol in import_symbols {
         if !symbol.name.is_empty() {
-            // cant easily determine library for each symbol so just assigned to first library for now
-            let lib_name = determine_symbol_library(&symbol.name, &elf.libraries)
+            // versioned symbols name the library that provides the version,
+            // otherwise, guess the first library.
+            let lib_name = symbol.version.as_ref()
+                .and_then(|version| version.library.clone())
+                .or_else(|| determine_symbol_library(&symbol.name, &elf.libraries))
                 .unwrap_or_else(|| "unknown".to_string());
             
             lib_symbols.entry(lib_name).or_insert_with(Vec::new).push(symbol);
@@ -74,7 +92,7 @@ pub fn read_import_libraries(elf: &goblin::elf::Elf) -> Vec<ELFDynamicImport> {
 }
 
 fn determine_symbol_library(symbol_name: &str, libraries: &[&str]) -> Option<String> {
-    // for now just return the first library or None needs to be improved
+    // without a version, there's no record of the library, so use the first one
     if !libraries.is_empty() {
         Some(libraries[0].to_string())
     } else {
@@ -82,10 +100,10 @@ fn determine_symbol_library(symbol_name: &str, libraries: &[&str]) -> Option<Str
     }
 }
 
-pub fn read_symbol_table(elf: &goblin::elf::Elf) -> Vec<ELFImportSymbol> {
+pub fn read_symbol_table(elf: &goblin::elf::Elf, versions: &[Option<ELFSymbolVersion>]) -> Vec<ELFImportSymbol> {
     let mut symbols = Vec::new();
     
-    for sym in elf.dynsyms.iter() {
+    for (idx, sym) in elf.dynsyms.iter().enumerate() {
         let name = elf.dynstrtab.get_at(sym.st_name)
             .unwrap_or("")
             .to_string();
@@ -110,14 +128,15 @@ pub fn read_symbol_table(elf: &goblin::elf::Elf) -> Vec<ELFImportSymbol> {
             visibility,
             plt_address: None,
             got_address: None,
+            version: versions.get(idx).cloned().flatten(),
         });
     }
     
     symbols
 }
 
-pub fn read_import_symbols(elf: &goblin::elf::Elf) -> Vec<ELFImportSymbol> {
-    let mut symbols = read_symbol_table(elf);
+pub fn read_import_symbols(elf: &goblin::elf::Elf, versions: &[Option<ELFSymbolVersion>]) -> Vec<ELFImportSymbol> {
+    let mut symbols = read_symbol_table(elf, versions);
     
     // find PLT and GOT sections
     let plt_section = find_section_by_name(elf, ".plt");
@@ -125,10 +144,9 @@ pub fn read_import_symbols(elf: &goblin::elf::Elf) -> Vec<ELFImportSymbol> {
     let got_plt_section = find_section_by_name(elf, ".got.plt");
     let got_section = find_section_by_name(elf, ".got");
     
-    // use .got.plt if available otherwise fall back to .got
-    let got_section = got_plt_section.or(got_section);
-    
-    if plt_section.is_none() && plt_got_section.is_none() && got_section.is_none() {
+    // slots are found in .got.plt (JUMP_SLOT) and .got (GLOB_DAT),
+    // like those referenced by the stubs in .plt.got.
+    if plt_section.is_none() && plt_got_section.is_none() && got_plt_section.is_none() && got_section.is_none() {
         return symbols.into_iter()
             .filter(|sym| is_import_symbol(elf, sym))
             .collect();
@@ -137,8 +155,9 @@ pub fn read_import_symbols(elf: &goblin::elf::Elf) -> Vec<ELFImportSymbol> {
     // build symbol index from relocations
     let mut symbol_addresses: HashMap<usize, (Option<u64>, Option<u64>)> = HashMap::new();
     
-    // process PLT relocations
-    for rel in elf.dynrels.iter() {
+    // process the dynamic relocations, and the PLT relocations (JUMP_SLOT),
+    // which are found in `.rel.plt` on i386 and `.rela.plt` on x86-64.
+    for rel in elf.dynrels.iter().chain(elf.dynrelas.iter()).chain(elf.pltrelocs.iter()) {
         let sym_idx = rel.r_sym;
         
         // check if this relocation is in any PLT range
@@ -148,39 +167,10 @@ pub fn read_import_symbols(elf: &goblin::elf::Elf) -> Vec<ELFImportSymbol> {
             .any(|plt| rel.r_offset >= plt.sh_addr && rel.r_offset < plt.sh_addr + plt.sh_size);
         
         // check if this relocation is in GOT range  
-        let is_got_reloc = if let Some(got) = got_section {
-            rel.r_offset >= got.sh_addr && rel.r_offset < got.sh_addr + got.sh_size
-        } else {
-            false
-        };
-        
-        if is_plt_reloc || is_got_reloc {
-            let entry = symbol_addresses.entry(sym_idx).or_insert((None, None));
-            
-            if is_plt_reloc {
-                entry.0 = Some(rel.r_offset);
-            }
-            if is_got_reloc {
-                entry.1 = Some(rel.r_offset);
-            }
-        }
-    }
-
-    for rel in elf.dynrelas.iter() {
-        let sym_idx = rel.r_sym;
-        
-        // check if this relocation is in any PLT range
-        let is_plt_reloc = [plt_section, plt_got_section]
+        let is_got_reloc = [got_plt_section, got_section]
             .iter()
             .flatten()
-            .any(|plt| rel.r_offset >= plt.sh_addr && rel.r_offset < plt.sh_addr + plt.sh_size);
-        
-        // check if this relocation is in GOT range  
-        let is_got_reloc = if let Some(got) = got_section {
-            rel.r_offset >= got.sh_addr && rel.r_offset < got.sh_addr + got.sh_size
-        } else {
-            false
-        };
+            .any(|got| rel.r_offset >= got.sh_addr && rel.r_offset < got.sh_addr + got.sh_size);
         
         if is_plt_reloc || is_got_reloc {
             let entry = symbol_addresses.entry(sym_idx).or_insert((None, None));
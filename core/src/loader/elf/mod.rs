@@ -1,18 +1,24 @@
 #![allow(clippy::nonstandard_macro_braces)]
 
+use std::collections::BTreeSet;
+
 use anyhow::Result;
 use goblin::elf::header::{EM_386, EM_X86_64};
-use goblin::elf::program_header::PT_LOAD;
 use log::debug;
 use prost::bytes::buf;
 use thiserror::Error;
 
+pub mod build_info;
+pub mod coredump;
 pub mod import;
+pub mod reloc;
+pub mod relocatable;
+pub mod version;
 
 use crate::{
     arch::Arch,
     aspace::{self, RelativeAddressSpace},
-    module::{Module, Permissions, Section},
+    module::{Module, Permissions, Section, SectionHeader},
     util, RVA, VA,
 };
 
@@ -31,6 +37,14 @@ pub enum ELFError {
 pub struct ELF {
     pub buf: Vec<u8>,
     pub module: Module,
+    /// the address of the lowest loadable segment, as linked.
+    /// for most PIEs and shared objects, this is zero.
+    pub link_base_address: VA,
+    /// addresses of the pointer slots that were fixed up by dynamic relocations.
+    /// analysis can trust the values found here to be pointers.
+    pub relocations: BTreeSet<VA>,
+    /// the versions of the dynamic symbols, indexed like `.dynsym`.
+    pub symbol_versions: Vec<Option<version::ELFSymbolVersion>>,
 }
 
 pub struct DynamicEntry {
@@ -49,7 +63,40 @@ pub struct Symbol {
 
 impl ELF {
     pub fn from_bytes(buf: &[u8]) -> Result<ELF>{
-        load_elf(buf)
+        load_elf(buf, None)
+    }
+
+    /// Load the ELF with its lowest loadable segment at `base_address`,
+    /// such as where the dynamic loader placed a PIE or shared object at runtime.
+    pub fn from_bytes_at(buf: &[u8], base_address: VA) -> Result<ELF>{
+        load_elf(buf, Some(base_address))
+    }
+
+    /// The difference between the address at which the module is loaded
+    /// and the address at which it was linked.
+    ///
+    /// Add this to link-time addresses, like symbol values and `e_entry`,
+    /// to find them in the module.
+    pub fn load_bias(&self) -> VA {
+        self.module.address_space.base_address.wrapping_sub(self.link_base_address)
+    }
+
+    /// Move the module so that its lowest loadable segment is at `base_address`,
+    /// re-applying the dynamic relocations for the new location.
+    ///
+    /// Position-dependent executables (`ET_EXEC`) can only be loaded at their link address.
+    pub fn rebase(&mut self, base_address: VA) -> Result<()> {
+        if base_address == self.module.address_space.base_address {
+            return Ok(());
+        }
+
+        *self = load_elf(&self.buf, Some(base_address))?;
+        Ok(())
+    }
+
+    /// Read the `.comment` strings and build ID left by the toolchain.
+    pub fn build_info(&self) -> Result<build_info::BuildInfo> {
+        build_info::BuildInfo::from_elf(self)
     }
 }
 
@@ -64,9 +111,14 @@ fn get_elf(buf: &[u8]) -> Result<goblin::elf::Elf> {
     Ok(elf)
 }
 
-fn load_elf(buf: &[u8]) -> Result<ELF>{
+fn load_elf(buf: &[u8], requested_base_address: Option<VA>) -> Result<ELF>{
     let elf = get_elf(buf)?;
 
+    // core dumps span the whole process address space, which doesn't fit in a single module
+    if elf.header.e_type == goblin::elf::header::ET_CORE {
+        return Err(ELFError::FormatNotSupported("core dump, use `loader::elf::coredump`".to_string()).into());
+    }
+
     // check that the elf matched x86_64
     let arch = match elf.header.e_machine {
         EM_X86_64 => Arch::X64,
@@ -76,16 +128,29 @@ fn load_elf(buf: &[u8]) -> Result<ELF>{
     debug!("elf: arch: {:?}", arch);
 
     // determine base address from the lowest virtual address of loadable segments
-    let base_address = elf.program_headers
-        .iter()
-        .filter(|ph| ph.p_type == PT_LOAD)
-        .map(|ph| ph.p_vaddr)
-        .min()
-        .unwrap();
-        debug!("elf: base address: {:#x}", base_address);
+    let link_base_address = reloc::get_link_base_address(&elf)
+        .ok_or_else(|| ELFError::FormatNotSupported("no loadable segments (relocatable object?)".to_string()))?;
+
+    let base_address = match requested_base_address {
+        None => link_base_address,
+        Some(base_address) if base_address == link_base_address => base_address,
+        Some(_) if elf.header.e_type == goblin::elf::header::ET_EXEC => {
+            return Err(ELFError::FormatNotSupported("position-dependent executable cannot be rebased".to_string()).into());
+        }
+        Some(base_address) if aspace::page_offset(base_address.wrapping_sub(link_base_address)) != 0 => {
+            return Err(ELFError::FormatNotSupported(format!("base address not page aligned: {:#x}", base_address)).into());
+        }
+        Some(base_address) => base_address,
+    };
+    let load_bias = base_address.wrapping_sub(link_base_address);
+    debug!("elf: base address: {:#x} (link base address: {:#x})", base_address, link_base_address);
 
     // load sections from program headers (segments) - these are what actually get loaded
     let mut sections = load_elf_segments(buf, &elf);
+    for section in sections.iter_mut() {
+        section.virtual_range.start = section.virtual_range.start.wrapping_add(load_bias);
+        section.virtual_range.end = section.virtual_range.end.wrapping_add(load_bias);
+    }
 
     // calculate total virtual memory size needed
     let max_address = sections.iter().map(|sec| sec.virtual_range.end).max().unwrap_or(base_address);
@@ -145,17 +210,28 @@ fn load_elf(buf: &[u8]) -> Result<ELF>{
         .filter(|s| s.virtual_range.start >= base_address)
         .collect();
 
+    // the named sections, like `.text` and `.plt`, within the segments.
+    let section_headers = load_elf_section_headers(&elf, buf.len() as u64, load_bias);
+
     let module = Module {
         arch,
         sections: mapped_sections,
+        section_headers,
         address_space: address_space.into_absolute(base_address)?,
     };
 
-    debug!("elf: loaded");
-    Ok(ELF {
+    let mut elf = ELF {
         buf: buf.to_vec(),
         module,
-    })
+        link_base_address,
+        relocations: Default::default(),
+        symbol_versions: version::read_symbol_versions(&elf),
+    };
+
+    reloc::apply_relocations(&mut elf)?;
+
+    debug!("elf: loaded");
+    Ok(elf)
 }
 
 pub fn load_elf_segments(buf: &[u8], elf: &goblin::elf::Elf) -> Vec<Section>{
@@ -204,14 +280,227 @@ pub fn load_elf_segments(buf: &[u8], elf: &goblin::elf::Elf) -> Vec<Section>{
     }).collect()
 }
 
+/// Collect the allocated sections, like `.text`, `.plt`, and `.bss`, from the section headers,
+/// relocated by `load_bias`.
+///
+/// The loader doesn't need these, so they may be missing, such as from stripped or packed files.
+/// Likewise, they're untrusted, so headers that overflow or extend beyond the file are skipped.
+pub fn load_elf_section_headers(elf: &goblin::elf::Elf, file_size: u64, load_bias: VA) -> Vec<SectionHeader> {
+    elf.section_headers.iter().filter(|sh| sh.is_alloc() && sh.sh_addr != 0).filter_map(|sh| {
+        let name = elf.shdr_strtab.get_at(sh.sh_name).unwrap_or_default().to_string();
+
+        let mut permissions = Permissions::R;
+        if sh.is_writable() {
+            permissions |= Permissions::W;
+        }
+        if sh.is_executable() {
+            permissions |= Permissions::X;
+        }
+
+        // sections like .bss occupy memory but not the file.
+        let physical_size = if sh.sh_type == goblin::elf::section_header::SHT_NOBITS { 0 } else { sh.sh_size };
+        let physical_end = match sh.sh_offset.checked_add(physical_size) {
+            Some(end) if end <= file_size => end,
+            _ => {
+                debug!("elf: section: {}: invalid file range: {:#x} size: {:#x}", name, sh.sh_offset, physical_size);
+                return None;
+            }
+        };
+
+        let virtual_address = sh.sh_addr.wrapping_add(load_bias);
+        let Some(virtual_end) = virtual_address.checked_add(sh.sh_size) else {
+            debug!("elf: section: {}: invalid size: {:#x}", name, sh.sh_size);
+            return None;
+        };
+
+        debug!("elf: section: {} at {:#x}", name, virtual_address);
+
+        Some(SectionHeader {
+            physical_range: std::ops::Range {
+                start: sh.sh_offset,
+                end: physical_end,
+            },
+            virtual_range: std::ops::Range {
+                start: virtual_address,
+                end: virtual_end,
+            },
+            permissions,
+            name,
+            flags: sh.sh_flags,
+            entry_size: sh.sh_entsize,
+        })
+    }).collect()
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     #![allow(clippy::identity_op)]
 
     use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
 
     use crate::{aspace::AddressSpace, rsrc::*};
 
+    pub const PIE_ENTRY: u64 = 0x100;
+    /// a pointer slot fixed up by a RELATIVE relocation to point to `PIE_ENTRY`.
+    pub const PIE_SLOT: u64 = 0x400;
+
+    /// Build a minimal x64 PIE, linked at 0x0, with a single RWX segment containing:
+    ///
+    /// ```text
+    ///   0x100: ret
+    ///   0x200: .dynamic
+    ///   0x300: .rela.dyn: R_X86_64_RELATIVE PIE_SLOT + PIE_ENTRY
+    ///   0x400: pointer slot
+    /// ```
+    pub fn build_pie() -> Vec<u8> {
+        let mut buf = vec![0u8; 0x1000];
+
+        let mut hdr = vec![];
+        // Elf64_Ehdr
+        hdr.extend_from_slice(b"\x7fELF\x02\x01\x01\x00");
+        hdr.extend_from_slice(&[0u8; 8]);
+        hdr.write_u16::<LittleEndian>(goblin::elf::header::ET_DYN).unwrap();
+        hdr.write_u16::<LittleEndian>(goblin::elf::header::EM_X86_64).unwrap();
+        hdr.write_u32::<LittleEndian>(1).unwrap(); // e_version
+        hdr.write_u64::<LittleEndian>(PIE_ENTRY).unwrap(); // e_entry
+        hdr.write_u64::<LittleEndian>(0x40).unwrap(); // e_phoff
+        hdr.write_u64::<LittleEndian>(0).unwrap(); // e_shoff
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // e_flags
+        hdr.write_u16::<LittleEndian>(0x40).unwrap(); // e_ehsize
+        hdr.write_u16::<LittleEndian>(0x38).unwrap(); // e_phentsize
+        hdr.write_u16::<LittleEndian>(2).unwrap(); // e_phnum
+        hdr.write_u16::<LittleEndian>(0x40).unwrap(); // e_shentsize
+        hdr.write_u16::<LittleEndian>(0).unwrap(); // e_shnum
+        hdr.write_u16::<LittleEndian>(0).unwrap(); // e_shstrndx
+
+        // Elf64_Phdr: (type, flags, offset/vaddr, size)
+        for (p_type, p_flags, address, size) in [
+            (goblin::elf::program_header::PT_LOAD, 0x7, 0x0u64, 0x1000u64),
+            (goblin::elf::program_header::PT_DYNAMIC, 0x6, 0x200, 0x40),
+        ] {
+            hdr.write_u32::<LittleEndian>(p_type).unwrap();
+            hdr.write_u32::<LittleEndian>(p_flags).unwrap();
+            hdr.write_u64::<LittleEndian>(address).unwrap(); // p_offset
+            hdr.write_u64::<LittleEndian>(address).unwrap(); // p_vaddr
+            hdr.write_u64::<LittleEndian>(address).unwrap(); // p_paddr
+            hdr.write_u64::<LittleEndian>(size).unwrap(); // p_filesz
+            hdr.write_u64::<LittleEndian>(size).unwrap(); // p_memsz
+            hdr.write_u64::<LittleEndian>(0x1000).unwrap(); // p_align
+        }
+        buf[..hdr.len()].copy_from_slice(&hdr);
+
+        buf[PIE_ENTRY as usize] = 0xC3;
+
+        let mut dynamic = vec![];
+        for (tag, value) in [
+            (goblin::elf::dynamic::DT_RELA, 0x300u64),
+            (goblin::elf::dynamic::DT_RELASZ, 0x18),
+            (goblin::elf::dynamic::DT_RELAENT, 0x18),
+            (goblin::elf::dynamic::DT_NULL, 0x0),
+        ] {
+            dynamic.write_u64::<LittleEndian>(tag).unwrap();
+            dynamic.write_u64::<LittleEndian>(value).unwrap();
+        }
+        buf[0x200..0x200 + dynamic.len()].copy_from_slice(&dynamic);
+
+        let mut rela = vec![];
+        rela.write_u64::<LittleEndian>(PIE_SLOT).unwrap(); // r_offset
+        rela.write_u64::<LittleEndian>(goblin::elf::reloc::R_X86_64_RELATIVE as u64).unwrap(); // r_info
+        rela.write_i64::<LittleEndian>(PIE_ENTRY as i64).unwrap(); // r_addend
+        buf[0x300..0x300 + rela.len()].copy_from_slice(&rela);
+
+        buf
+    }
+
+    #[test]
+    fn rebase_pie() -> Result<()> {
+        let buf = build_pie();
+
+        let mut elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        assert_eq!(0x0, elf.module.address_space.base_address);
+        assert_eq!(0x0, elf.load_bias());
+        assert_eq!(PIE_ENTRY, elf.module.address_space.read_u64(PIE_SLOT)?);
+
+        elf.rebase(0x5555_5555_4000)?;
+        assert_eq!(0x5555_5555_4000, elf.module.address_space.base_address);
+        assert_eq!(0x5555_5555_4000, elf.load_bias());
+        assert_eq!(0x5555_5555_4000, elf.module.sections[0].virtual_range.start);
+        assert_eq!(0xC3, elf.module.address_space.read_u8(0x5555_5555_4000 + PIE_ENTRY)?);
+        assert_eq!(
+            0x5555_5555_4000 + PIE_ENTRY,
+            elf.module.address_space.read_u64(0x5555_5555_4000 + PIE_SLOT)?
+        );
+        assert!(elf.relocations.contains(&(0x5555_5555_4000 + PIE_SLOT)));
+
+        // misaligned base addresses are rejected.
+        assert!(elf.rebase(0x5555_5555_4010).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn section_headers() -> Result<()> {
+        use crate::loader::elf::build_info::tests::build_pie_with_build_info;
+
+        let buf = build_pie_with_build_info(&["GCC: (GNU) 13.2.0"]);
+
+        // the loaded segment remains the mapped section.
+        let mut elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        assert_eq!(1, elf.module.sections.len());
+
+        // only allocated sections are kept: not .comment or .shstrtab.
+        assert_eq!(1, elf.module.section_headers.len());
+        let note = elf.module.get_section_header(".note.gnu.build-id").unwrap();
+        assert_eq!(0x800, note.virtual_range.start);
+        assert_eq!(0x800, note.physical_range.start);
+        assert_eq!(crate::module::Permissions::R, note.permissions);
+        assert_eq!(goblin::elf::section_header::SHF_ALLOC as u64, note.flags);
+
+        assert_eq!(Some(".note.gnu.build-id"), elf.module.get_section_name(0x810));
+        assert_eq!(Some("segment_text_0"), elf.module.get_section_name(PIE_ENTRY));
+
+        elf.rebase(0x5555_5555_4000)?;
+        let note = elf.module.get_section_header(".note.gnu.build-id").unwrap();
+        assert_eq!(0x5555_5555_4000 + 0x800, note.virtual_range.start);
+
+        Ok(())
+    }
+
+    #[test]
+    fn section_header_size() -> Result<()> {
+        use crate::loader::elf::build_info::tests::build_pie_with_build_info;
+
+        // the sh_size of .note.gnu.build-id.
+        let size = 0xA00 + 0x40 + 0x20;
+
+        // a header that overflows the address space is skipped.
+        let mut buf = build_pie_with_build_info(&["GCC: (GNU) 13.2.0"]);
+        buf[size..size + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        assert!(elf.module.section_headers.is_empty());
+
+        // as is a header that extends beyond the file.
+        let mut buf = build_pie_with_build_info(&["GCC: (GNU) 13.2.0"]);
+        buf[size..size + 8].copy_from_slice(&0x1000u64.to_le_bytes());
+        let elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        assert!(elf.module.section_headers.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rebase_exec() -> Result<()> {
+        let mut buf = build_pie();
+        buf[0x10..0x12].copy_from_slice(&goblin::elf::header::ET_EXEC.to_le_bytes());
+
+        let mut elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        assert!(elf.rebase(0x0).is_ok());
+        assert!(elf.rebase(0x40_0000).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn elf_header_libcso6() -> Result<()> {
         let buf = get_buf(Rsrc::LIBCSO6);
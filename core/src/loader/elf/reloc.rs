@@ -0,0 +1,233 @@
+//! Apply the dynamic relocations found in `.rela.dyn`/`.rel.dyn`.
+//!
+//! Position independent executables and shared objects store most of their
+//! pointers (vtables, function pointer tables, `.init_array`, GOT entries) as
+//! relocations rather than literal values, so the slots read as zero (or as an
+//! offset) until the dynamic loader fixes them up. We do the same, so that
+//! pointer scanning sees the values the program would see at runtime.
+//!
+//! Relocations against the PLT (`.rela.plt`) are not applied: those GOT
+//! slots already point back into the PLT and are handled by import analysis.
+//! The exception is IRELATIVE relocations, found in `.rela.plt` or, for static
+//! binaries, `.rela.iplt`: the dynamic loader calls the IFUNC resolver and
+//! stores the implementation it returns. We can't run the resolver, so the
+//! slot is filled with the address of the resolver itself, which at least
+//! leads indirect calls to the code that selects the implementation.
+#![allow(non_upper_case_globals)]
+
+use anyhow::Result;
+use goblin::elf::{
+    program_header::PT_LOAD,
+    reloc::{
+        R_386_32, R_386_GLOB_DAT, R_386_IRELATIVE, R_386_RELATIVE, R_X86_64_64, R_X86_64_GLOB_DAT, R_X86_64_IRELATIVE,
+        R_X86_64_RELATIVE,
+    },
+    section_header::SHN_UNDEF,
+};
+use log::debug;
+
+use crate::{
+    arch::Arch,
+    aspace::{AddressSpace, WritableAddressSpace},
+    loader::elf::ELF,
+    VA,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelocationType {
+    /// B + A: the load bias plus the addend.
+    /// R_X86_64_RELATIVE, R_386_RELATIVE.
+    Relative,
+    /// S + A: the symbol value plus the addend.
+    /// R_X86_64_64, R_386_32.
+    Absolute,
+    /// S: the symbol value, typically a GOT entry.
+    /// R_X86_64_GLOB_DAT, R_386_GLOB_DAT.
+    GlobDat,
+    /// B + A: the address of an IFUNC resolver,
+    /// which is called to find the value of the slot.
+    /// R_X86_64_IRELATIVE, R_386_IRELATIVE.
+    IRelative,
+    /// any other relocation type, which we don't apply,
+    /// such as TLS or copy relocations.
+    Other(u32),
+}
+
+impl RelocationType {
+    fn from_elf(arch: Arch, r_type: u32) -> RelocationType {
+        match (arch, r_type) {
+            (Arch::X64, R_X86_64_RELATIVE) => RelocationType::Relative,
+            (Arch::X64, R_X86_64_64) => RelocationType::Absolute,
+            (Arch::X64, R_X86_64_GLOB_DAT) => RelocationType::GlobDat,
+            (Arch::X64, R_X86_64_IRELATIVE) => RelocationType::IRelative,
+            (Arch::X32, R_386_RELATIVE) => RelocationType::Relative,
+            (Arch::X32, R_386_32) => RelocationType::Absolute,
+            (Arch::X32, R_386_GLOB_DAT) => RelocationType::GlobDat,
+            (Arch::X32, R_386_IRELATIVE) => RelocationType::IRelative,
+            (_, r_type) => RelocationType::Other(r_type),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Relocation {
+    pub type_:   RelocationType,
+    /// the address of the slot to fix up, at the link-time address.
+    pub address: VA,
+    /// the explicit addend from a RELA entry,
+    /// or `None` for a REL entry, whose addend is found in the slot.
+    pub addend:  Option<i64>,
+    /// the link-time value of the referenced symbol,
+    /// or `None` if there's no symbol or its undefined (imported).
+    pub symbol:  Option<VA>,
+}
+
+/// The address at which the ELF was linked to be loaded,
+/// which is zero for most PIEs and shared objects.
+pub fn get_link_base_address(elf: &goblin::elf::Elf) -> Option<VA> {
+    elf.program_headers
+        .iter()
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .map(|ph| ph.p_vaddr)
+        .min()
+}
+
+pub fn read_relocations(elf: &goblin::elf::Elf, arch: Arch) -> Vec<Relocation> {
+    // IRELATIVE relocations may also be found in `.rela.plt`,
+    // or in `.rela.iplt` of a static binary, which has no dynamic section.
+    let irelatives = elf
+        .pltrelocs
+        .iter()
+        .chain(
+            elf.shdr_relocs
+                .iter()
+                .filter(|(idx, _)| {
+                    elf.section_headers
+                        .get(*idx)
+                        .and_then(|section| elf.shdr_strtab.get_at(section.sh_name))
+                        .map(|name| name == ".rela.iplt" || name == ".rel.iplt")
+                        .unwrap_or(false)
+                })
+                .flat_map(|(_, relocs)| relocs.iter()),
+        )
+        .filter(|reloc| RelocationType::from_elf(arch, reloc.r_type) == RelocationType::IRelative);
+
+    elf.dynrelas
+        .iter()
+        .chain(elf.dynrels.iter())
+        .chain(irelatives)
+        .map(|reloc| {
+            let symbol = if reloc.r_sym == 0 {
+                None
+            } else {
+                elf.dynsyms
+                    .get(reloc.r_sym)
+                    .filter(|sym| sym.st_shndx != SHN_UNDEF as usize)
+                    .map(|sym| sym.st_value)
+            };
+
+            Relocation {
+                type_: RelocationType::from_elf(arch, reloc.r_type),
+                address: reloc.r_offset,
+                addend: reloc.r_addend,
+                symbol,
+            }
+        })
+        .collect()
+}
+
+/// Apply the dynamic relocations to the loaded module,
+/// recording each address that was fixed up in `elf.relocations`.
+pub fn apply_relocations(elf: &mut ELF) -> Result<()> {
+    let relocations = {
+        let goblin_elf = goblin::elf::Elf::parse(&elf.buf)?;
+        read_relocations(&goblin_elf, elf.module.arch)
+    };
+
+    if relocations.is_empty() {
+        debug!("elf: reloc: no relocations found");
+        return Ok(());
+    }
+
+    // zero when the module is loaded at its link-time address,
+    // otherwise the module has been rebased.
+    let bias = elf.load_bias() as i64;
+
+    debug!("elf: reloc: applying {} relocations", relocations.len());
+
+    for relocation in relocations.iter() {
+        let address = (relocation.address as i64).wrapping_add(bias) as VA;
+
+        if elf
+            .module
+            .address_space
+            .read_bytes(address, elf.module.arch.pointer_size())
+            .is_err()
+        {
+            debug!("elf: reloc: invalid address: {:#x}", address);
+            continue;
+        }
+
+        // REL entries (i386) store the addend in the slot itself.
+        let addend = match relocation.addend {
+            Some(addend) => addend,
+            None => match elf.module.arch {
+                Arch::X32 => elf.module.address_space.read_u32(address)? as i32 as i64,
+                Arch::X64 => elf.module.address_space.read_u64(address)? as i64,
+            },
+        };
+
+        let value = match (relocation.type_, relocation.symbol) {
+            (RelocationType::Relative, _) | (RelocationType::IRelative, _) => bias.wrapping_add(addend),
+            (RelocationType::Absolute, Some(symbol)) => bias.wrapping_add(symbol as i64).wrapping_add(addend),
+            (RelocationType::GlobDat, Some(symbol)) => bias.wrapping_add(symbol as i64),
+            // imported symbols have no address in this module.
+            (RelocationType::Absolute, None) | (RelocationType::GlobDat, None) => continue,
+            (RelocationType::Other(_), _) => continue,
+        };
+
+        match elf.module.arch {
+            Arch::X32 => elf.module.address_space.write_u32(address, value as u32)?,
+            Arch::X64 => elf.module.address_space.write_u64(address, value as u64)?,
+        };
+
+        elf.relocations.insert(address);
+    }
+
+    debug!("elf: reloc: applied {} relocations", elf.relocations.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{arch::Arch, aspace::AddressSpace, rsrc::*};
+    use anyhow::Result;
+
+    use super::{read_relocations, RelocationType};
+
+    #[test]
+    fn apply_relocations() -> Result<()> {
+        let buf = get_buf(Rsrc::LIBCSO6);
+        let elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+
+        let goblin_elf = goblin::elf::Elf::parse(&buf)?;
+        let relocations = read_relocations(&goblin_elf, Arch::X64);
+
+        // this libc stores its RELATIVE relocations in `.relr.dyn`,
+        // so check the GOT entries of the symbols that it defines.
+        let glob_dats = relocations
+            .iter()
+            .filter(|reloc| reloc.type_ == RelocationType::GlobDat && reloc.symbol.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(50, glob_dats.len());
+
+        // libc is linked at 0x0, so GLOB_DAT slots contain exactly the symbol value.
+        for reloc in glob_dats.iter() {
+            assert!(elf.relocations.contains(&reloc.address));
+            assert_eq!(reloc.symbol.unwrap(), elf.module.address_space.read_u64(reloc.address)?);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,929 @@
+//! Load relocatable ELF objects (`ET_REL`, `.o` files) and the members of
+//! static libraries (`.a` files).
+//!
+//! Objects don't have program headers, so we lay out each allocated section on
+//! its own page, like the COFF loader does, and then apply the relocations.
+//! References to symbols that aren't defined in the object are resolved against
+//! a synthetic section named `UNDEF`, in which each extern gets a
+//! pointer-sized slot that contains its own address.
+//!
+//! Position-independent code references symbols through the GOT,
+//! which the linker would create, like `mov rax, [rip+data@GOTPCREL]`.
+//! Extern slots already contain the address of the extern, so they serve as
+//! its GOT entry, and the symbols defined in the object get a slot in a
+//! synthetic section named `.got`.
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use log::{debug, warn};
+use object::{Object, ObjectSection, ObjectSymbol, ObjectSymbolTable};
+
+use crate::{
+    arch::Arch,
+    aspace::{AddressSpace, RelativeAddressSpace, WritableAddressSpace},
+    loader::{
+        coff::{Symbol, SymbolKind, Symbols},
+        elf::ELFError,
+    },
+    module::{Module, Permissions, Section},
+    util, VA,
+};
+
+const PAGE_SIZE: u64 = 0x1000;
+
+/// A parsed and loaded relocatable ELF object.
+/// The `buf` field contains the raw data.
+/// The `module` field contains an address space with the sections laid out and
+/// relocations applied.
+pub struct ELFObject {
+    pub buf:     Vec<u8>,
+    pub module:  Module,
+    pub symbols: Symbols,
+    pub externs: BTreeMap<String, VA>,
+}
+
+impl ELFObject {
+    pub fn from_bytes(buf: &[u8]) -> Result<ELFObject> {
+        load_elf_object(buf)
+    }
+}
+
+/// Is the given buffer a relocatable ELF object?
+pub fn is_elf_object(buf: &[u8]) -> bool {
+    match object::File::parse(buf) {
+        Ok(obj) => obj.format() == object::BinaryFormat::Elf && obj.kind() == object::ObjectKind::Relocatable,
+        Err(_) => false,
+    }
+}
+
+/// Load each ELF object found in the given static library (`.a` file),
+/// along with the name of the archive member.
+///
+/// Members that aren't ELF objects are skipped, as are objects that fail to
+/// load.
+pub fn load_elf_archive(buf: &[u8]) -> Result<Vec<(String, ELFObject)>> {
+    let mut objects = Vec::new();
+    for member in crate::loader::ar::read_archive(buf)?.into_iter() {
+        if member.kind != crate::loader::ar::MemberKind::Elf {
+            debug!("elf: archive: {}: not an ELF object", member.name);
+            continue;
+        }
+
+        match ELFObject::from_bytes(member.buf) {
+            Ok(obj) => objects.push((member.name, obj)),
+            Err(e) => warn!("elf: archive: {}: failed to load: {}", member.name, e),
+        }
+    }
+
+    Ok(objects)
+}
+
+fn get_section_permissions(section: &object::read::Section) -> Option<Permissions> {
+    let object::SectionFlags::Elf { sh_flags } = section.flags() else {
+        return None;
+    };
+
+    if sh_flags & object::elf::SHF_ALLOC as u64 == 0 {
+        // sections like .symtab, .rela.text, or .debug_info
+        return None;
+    }
+
+    let mut perms = Permissions::R;
+    if sh_flags & object::elf::SHF_WRITE as u64 > 0 {
+        perms.insert(Permissions::W);
+    }
+    if sh_flags & object::elf::SHF_EXECINSTR as u64 > 0 {
+        perms.insert(Permissions::X);
+    }
+
+    Some(perms)
+}
+
+/// lay out each allocated section on its own page, starting at `base_address`,
+/// and return the address assigned to each ELF section index.
+fn load_elf_object_sections(obj: &object::File, base_address: VA) -> (Vec<Section>, BTreeMap<usize, VA>) {
+    let mut vstart = base_address;
+    let mut sections = Vec::new();
+    let mut addresses = BTreeMap::new();
+
+    for section in obj.sections() {
+        let Some(permissions) = get_section_permissions(&section) else {
+            continue;
+        };
+
+        let name = section.name().unwrap_or_default().to_string();
+
+        // .bss and friends have no data in the file.
+        let physical_range = match section.file_range() {
+            Some((start, size)) if section.kind() != object::SectionKind::UninitializedData => std::ops::Range {
+                start,
+                end: start + size,
+            },
+            _ => std::ops::Range { start: 0, end: 0 },
+        };
+
+        let virtual_range = std::ops::Range {
+            start: vstart,
+            end:   vstart + section.size(),
+        };
+
+        debug!(
+            "elf: object: section: {} {:#x} - {:#x} {:?}",
+            name, virtual_range.start, virtual_range.end, permissions
+        );
+
+        addresses.insert(section.index().0, vstart);
+        vstart = util::align(std::cmp::max(virtual_range.end, vstart + 1), PAGE_SIZE);
+
+        sections.push(Section {
+            physical_range,
+            virtual_range,
+            permissions,
+            name,
+        });
+    }
+
+    (sections, addresses)
+}
+
+fn load_elf_object_module(buf: &[u8], arch: Arch, base_address: VA, sections: Vec<Section>) -> Result<Module> {
+    let max_address = sections
+        .iter()
+        .map(|section| util::align(section.virtual_range.end, PAGE_SIZE))
+        .max()
+        .unwrap_or(base_address);
+
+    let mut address_space = RelativeAddressSpace::with_capacity(max_address - base_address);
+
+    for section in sections.iter() {
+        let vsize = section.virtual_range.end - section.virtual_range.start;
+        if vsize == 0 {
+            continue;
+        }
+
+        let pstart = section.physical_range.start as usize;
+        let pend = std::cmp::min(section.physical_range.end as usize, buf.len());
+        let psize = std::cmp::min(pend.saturating_sub(pstart), vsize as usize);
+
+        let mut vbuf = vec![0u8; vsize as usize];
+        if psize > 0 {
+            vbuf[..psize].copy_from_slice(&buf[pstart..pstart + psize]);
+        }
+
+        address_space
+            .map
+            .writezx(section.virtual_range.start - base_address, &vbuf)?;
+    }
+
+    Ok(Module {
+        arch,
+        sections,
+        section_headers: Default::default(),
+        address_space: address_space.into_absolute(base_address)?,
+    })
+}
+
+fn get_symbol_kind(symbol: &object::read::Symbol) -> SymbolKind {
+    match symbol.kind() {
+        object::SymbolKind::Text => SymbolKind::Text,
+        object::SymbolKind::Data => SymbolKind::Data,
+        object::SymbolKind::Section => SymbolKind::Section,
+        object::SymbolKind::File => SymbolKind::File,
+        object::SymbolKind::Label => SymbolKind::Label,
+        object::SymbolKind::Tls => SymbolKind::Tls,
+        _ => SymbolKind::Unknown,
+    }
+}
+
+fn get_elf_object_symbols(obj: &object::File, addresses: &BTreeMap<usize, VA>) -> Symbols {
+    let mut symbols: Symbols = Default::default();
+
+    for symbol in obj.symbols() {
+        let Ok(name) = symbol.name() else {
+            continue;
+        };
+
+        if name.is_empty() {
+            continue;
+        }
+
+        let object::SymbolSection::Section(secindex) = symbol.section() else {
+            continue;
+        };
+
+        let Some(&section_address) = addresses.get(&secindex.0) else {
+            continue;
+        };
+
+        // in an object, the symbol value is the offset into its section.
+        let s = Symbol {
+            name:    name.to_string(),
+            address: section_address + symbol.address(),
+            kind:    get_symbol_kind(&symbol),
+        };
+
+        symbols.by_address.entry(s.address).or_default().push(s.clone());
+        symbols.by_name.insert(s.name.clone(), s);
+    }
+
+    symbols
+}
+
+/// the names of symbols referenced by relocations but not defined within this
+/// object.
+fn get_elf_object_extern_names(obj: &object::File) -> BTreeSet<String> {
+    let mut externs: BTreeSet<String> = Default::default();
+
+    let Some(symtab) = obj.symbol_table() else {
+        return externs;
+    };
+
+    for section in obj.sections() {
+        for (_, reloc) in section.relocations() {
+            let object::RelocationTarget::Symbol(symindex) = reloc.target() else {
+                continue;
+            };
+
+            let Ok(symbol) = symtab.symbol_by_index(symindex) else {
+                continue;
+            };
+
+            if !matches!(
+                symbol.section(),
+                object::SymbolSection::Undefined | object::SymbolSection::Common
+            ) {
+                continue;
+            }
+
+            match symbol.name() {
+                Ok(name) if !name.is_empty() => {
+                    externs.insert(name.to_string());
+                }
+                _ => warn!("elf: object: reloc: extern with no name: {:?}", symbol),
+            }
+        }
+    }
+
+    externs
+}
+
+/// append the synthetic `UNDEF` section that holds a pointer-sized slot for
+/// each extern, and return the address assigned to each extern.
+fn load_elf_object_extern_section(
+    sections: &mut Vec<Section>,
+    arch: Arch,
+    base_address: VA,
+    extern_names: BTreeSet<String>,
+) -> BTreeMap<String, VA> {
+    let psize = arch.pointer_size() as u64;
+    let extern_section_va = sections
+        .iter()
+        .map(|section| util::align(section.virtual_range.end, PAGE_SIZE))
+        .max()
+        .unwrap_or(base_address);
+    let extern_section_size = std::cmp::max(PAGE_SIZE, util::align(extern_names.len() as u64 * psize, PAGE_SIZE));
+
+    sections.push(Section {
+        physical_range: std::ops::Range { start: 0, end: 0 },
+        virtual_range:  std::ops::Range {
+            start: extern_section_va,
+            end:   extern_section_va + extern_section_size,
+        },
+        permissions:    Permissions::R,
+        name:           "UNDEF".to_string(),
+    });
+
+    extern_names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name, extern_section_va + i as u64 * psize))
+        .collect::<BTreeMap<String, VA>>()
+}
+
+/// Is the relocation relative to the GOT entry of its symbol: G + GOT + A - P?
+///
+/// The relaxable R_X86_64_GOTPCRELX and R_X86_64_REX_GOTPCRELX,
+/// which the linker may rewrite to reference the symbol directly,
+/// are also computed against the GOT entry, which is always correct.
+fn is_got_relative(reloc: &object::Relocation) -> bool {
+    match reloc.flags() {
+        object::RelocationFlags::Elf { r_type } => matches!(
+            r_type,
+            object::elf::R_X86_64_GOTPCREL | object::elf::R_X86_64_GOTPCRELX | object::elf::R_X86_64_REX_GOTPCRELX
+        ),
+        _ => false,
+    }
+}
+
+/// the indices of the symbols defined within this object that are referenced
+/// via the GOT, which need a synthetic GOT entry.
+fn get_elf_object_got_symbols(obj: &object::File) -> BTreeSet<usize> {
+    let mut symbols: BTreeSet<usize> = Default::default();
+
+    let Some(symtab) = obj.symbol_table() else {
+        return symbols;
+    };
+
+    for section in obj.sections() {
+        for (_, reloc) in section.relocations() {
+            if !is_got_relative(&reloc) {
+                continue;
+            }
+
+            let object::RelocationTarget::Symbol(symindex) = reloc.target() else {
+                continue;
+            };
+
+            let Ok(symbol) = symtab.symbol_by_index(symindex) else {
+                continue;
+            };
+
+            // externs are their own GOT entries.
+            if matches!(
+                symbol.section(),
+                object::SymbolSection::Undefined | object::SymbolSection::Common
+            ) {
+                continue;
+            }
+
+            symbols.insert(symindex.0);
+        }
+    }
+
+    symbols
+}
+
+/// append the synthetic `.got` section that holds a pointer-sized slot for
+/// each symbol referenced via the GOT, when there are any,
+/// and return the address assigned to each symbol index.
+fn load_elf_object_got_section(
+    sections: &mut Vec<Section>,
+    arch: Arch,
+    base_address: VA,
+    got_symbols: BTreeSet<usize>,
+) -> BTreeMap<usize, VA> {
+    if got_symbols.is_empty() {
+        return Default::default();
+    }
+
+    let psize = arch.pointer_size() as u64;
+    let got_section_va = sections
+        .iter()
+        .map(|section| util::align(section.virtual_range.end, PAGE_SIZE))
+        .max()
+        .unwrap_or(base_address);
+    let got_section_size = util::align(got_symbols.len() as u64 * psize, PAGE_SIZE);
+
+    sections.push(Section {
+        physical_range: std::ops::Range { start: 0, end: 0 },
+        virtual_range:  std::ops::Range {
+            start: got_section_va,
+            end:   got_section_va + got_section_size,
+        },
+        permissions:    Permissions::R,
+        name:           ".got".to_string(),
+    });
+
+    got_symbols
+        .into_iter()
+        .enumerate()
+        .map(|(i, symindex)| (symindex, got_section_va + i as u64 * psize))
+        .collect::<BTreeMap<usize, VA>>()
+}
+
+/// each GOT slot contains the address of its symbol.
+fn write_elf_object_got(
+    module: &mut Module,
+    obj: &object::File,
+    addresses: &BTreeMap<usize, VA>,
+    got: &BTreeMap<usize, VA>,
+) -> Result<()> {
+    let Some(symtab) = obj.symbol_table() else {
+        return Ok(());
+    };
+
+    for (&symindex, &va) in got.iter() {
+        let symbol = symtab.symbol_by_index(object::SymbolIndex(symindex))?;
+        let target = match symbol.section() {
+            object::SymbolSection::Section(secindex) => match addresses.get(&secindex.0) {
+                Some(&section_address) => section_address + symbol.address(),
+                None => {
+                    debug!(
+                        "elf: object: got: {:#x}: symbol in unmapped section: {:?}",
+                        va, secindex
+                    );
+                    continue;
+                }
+            },
+            object::SymbolSection::Absolute => symbol.address(),
+            _ => {
+                debug!("elf: object: got: {:#x}: unsupported symbol: {:?}", va, symbol);
+                continue;
+            }
+        };
+
+        match module.arch {
+            Arch::X32 => module.address_space.write_u32(va, target as u32)?,
+            Arch::X64 => module.address_space.write_u64(va, target)?,
+        };
+    }
+
+    Ok(())
+}
+
+/// each extern slot contains its own address,
+/// so that references through the slot resolve to the extern, too.
+fn write_elf_object_externs(module: &mut Module, externs: &BTreeMap<String, VA>) -> Result<()> {
+    for &va in externs.values() {
+        match module.arch {
+            Arch::X32 => module.address_space.write_u32(va, va as u32)?,
+            Arch::X64 => module.address_space.write_u64(va, va)?,
+        };
+    }
+
+    Ok(())
+}
+
+enum FixupSize {
+    _32,
+    _64,
+}
+
+struct Fixup {
+    // the location to change
+    address: VA,
+    // the size to change, either 4 or 8 bytes
+    size:    FixupSize,
+    // the value to write at the location
+    value:   i64,
+}
+
+fn get_elf_object_fixups(
+    module: &Module,
+    obj: &object::File,
+    addresses: &BTreeMap<usize, VA>,
+    externs: &BTreeMap<String, VA>,
+    got: &BTreeMap<usize, VA>,
+) -> Result<Vec<Fixup>> {
+    let mut fixups: Vec<Fixup> = Default::default();
+
+    let Some(symtab) = obj.symbol_table() else {
+        // we're only able to apply relocations if we can resolve symbols.
+        return Ok(fixups);
+    };
+
+    for section in obj.sections() {
+        let Some(&section_address) = addresses.get(&section.index().0) else {
+            continue;
+        };
+
+        for (offset, reloc) in section.relocations() {
+            // P: the place that needs to be updated.
+            let reloc_va: VA = section_address + offset;
+
+            let object::RelocationTarget::Symbol(symindex) = reloc.target() else {
+                debug!(
+                    "elf: object: reloc: {:#x}: unsupported target: {:?}",
+                    reloc_va,
+                    reloc.target()
+                );
+                continue;
+            };
+
+            let Ok(symbol) = symtab.symbol_by_index(symindex) else {
+                warn!(
+                    "elf: object: reloc: {:#x}: failed to find symbol: {:?}",
+                    reloc_va, symindex
+                );
+                continue;
+            };
+
+            // S: the address of the symbol.
+            let target: VA = match symbol.section() {
+                object::SymbolSection::Section(secindex) => {
+                    let Some(&target_section_address) = addresses.get(&secindex.0) else {
+                        // such as a reference into a debug section.
+                        debug!(
+                            "elf: object: reloc: {:#x}: symbol in unmapped section: {:?}",
+                            reloc_va, secindex
+                        );
+                        continue;
+                    };
+                    target_section_address + symbol.address()
+                }
+                object::SymbolSection::Undefined | object::SymbolSection::Common => {
+                    let Some(&extern_) = symbol.name().ok().and_then(|name| externs.get(name)) else {
+                        warn!(
+                            "elf: object: reloc: {:#x}: failed to find extern: {:?}",
+                            reloc_va, symbol
+                        );
+                        continue;
+                    };
+                    extern_
+                }
+                object::SymbolSection::Absolute => symbol.address(),
+                _ => {
+                    debug!("elf: object: reloc: {:#x}: unsupported symbol: {:?}", reloc_va, symbol);
+                    continue;
+                }
+            };
+
+            // A: the addend, which i386 REL entries store at the place itself.
+            let addend = if reloc.has_implicit_addend() {
+                match reloc.size() {
+                    32 => module.address_space.read_u32(reloc_va)? as i32 as i64,
+                    64 => module.address_space.read_u64(reloc_va)? as i64,
+                    _ => 0,
+                }
+            } else {
+                reloc.addend()
+            };
+
+            let value = if is_got_relative(&reloc) {
+                // G + GOT + A - P
+                //
+                // R_X86_64_GOTPCREL, R_X86_64_GOTPCRELX, R_X86_64_REX_GOTPCRELX:
+                // the address of the slot that contains the symbol.
+                let slot = match got.get(&symindex.0) {
+                    Some(&slot) => slot,
+                    // externs are their own slots.
+                    None => target,
+                };
+                (slot as i64).wrapping_add(addend).wrapping_sub(reloc_va as i64)
+            } else {
+                match reloc.kind() {
+                    // S + A
+                    //
+                    // R_X86_64_64, R_X86_64_32, R_X86_64_32S, R_386_32
+                    object::RelocationKind::Absolute => (target as i64).wrapping_add(addend),
+                    // S + A - P
+                    //
+                    // R_X86_64_PC32, R_386_PC32.
+                    //
+                    // R_X86_64_PLT32, R_386_PLT32: L + A - P,
+                    // but there's no PLT, so we branch directly to the symbol (or extern).
+                    object::RelocationKind::Relative | object::RelocationKind::PltRelative => {
+                        (target as i64).wrapping_add(addend).wrapping_sub(reloc_va as i64)
+                    }
+                    _ => {
+                        debug!(
+                            "elf: object: reloc: {:#x}: unsupported kind: {:?}",
+                            reloc_va,
+                            reloc.flags()
+                        );
+                        continue;
+                    }
+                }
+            };
+
+            debug!(
+                "elf: object: reloc: {}+{:#x}: {:?} -> {:#x}",
+                section.name().unwrap_or_default(),
+                offset,
+                reloc.kind(),
+                target
+            );
+
+            // the relaxable GOT relocations are unknown to `object`, so have no size.
+            let size = if is_got_relative(&reloc) { 32 } else { reloc.size() };
+            let size = match size {
+                32 => FixupSize::_32,
+                64 => FixupSize::_64,
+                size => {
+                    debug!("elf: object: reloc: {:#x}: unsupported size: {}", reloc_va, size);
+                    continue;
+                }
+            };
+
+            fixups.push(Fixup {
+                address: reloc_va,
+                size,
+                value,
+            });
+        }
+    }
+
+    Ok(fixups)
+}
+
+fn apply_elf_object_fixups(module: &mut Module, fixups: Vec<Fixup>) -> Result<()> {
+    for fixup in fixups.into_iter() {
+        match fixup.size {
+            FixupSize::_32 => {
+                module.address_space.write_i32(fixup.address, fixup.value as i32)?;
+            }
+            FixupSize::_64 => {
+                module.address_space.write_i64(fixup.address, fixup.value)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn load_elf_object(buf: &[u8]) -> Result<ELFObject> {
+    let obj = object::File::parse(buf).map_err(|e| ELFError::MalformedElfFile(e.to_string()))?;
+
+    if obj.format() != object::BinaryFormat::Elf || obj.kind() != object::ObjectKind::Relocatable {
+        return Err(ELFError::FormatNotSupported("not a relocatable ELF object".to_string()).into());
+    }
+
+    let arch = match obj.architecture() {
+        object::Architecture::X86_64 => Arch::X64,
+        object::Architecture::I386 => Arch::X32,
+        _ => {
+            return Err(ELFError::FormatNotSupported(format!("{:?}", obj.architecture())).into());
+        }
+    };
+    debug!("elf: object: arch: {:?}", arch);
+
+    // objects aren't linked to any address, so pick something non-zero.
+    // R_X86_64_32S and R_386_32 relocations require that this fits in 32 bits.
+    let base_address = 0x2000_0000u64;
+    debug!("elf: object: base address: {:#x}", base_address);
+
+    let (mut sections, addresses) = load_elf_object_sections(&obj, base_address);
+
+    let extern_names = get_elf_object_extern_names(&obj);
+
+    let externs = load_elf_object_extern_section(&mut sections, arch, base_address, extern_names);
+
+    let got_symbols = get_elf_object_got_symbols(&obj);
+
+    let got = load_elf_object_got_section(&mut sections, arch, base_address, got_symbols);
+
+    let mut module = load_elf_object_module(buf, arch, base_address, sections)?;
+
+    write_elf_object_externs(&mut module, &externs)?;
+
+    write_elf_object_got(&mut module, &obj, &addresses, &got)?;
+
+    let symbols = get_elf_object_symbols(&obj, &addresses);
+
+    let fixups = get_elf_object_fixups(&module, &obj, &addresses, &externs, &got)?;
+    apply_elf_object_fixups(&mut module, fixups)?;
+
+    debug!("elf: object: loaded");
+    Ok(ELFObject {
+        buf: buf.to_vec(),
+        module,
+        symbols,
+        externs,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use crate::aspace::AddressSpace;
+
+    pub const TEXT_ADDRESS: u64 = 0x2000_0000;
+    pub const DATA_ADDRESS: u64 = 0x2000_1000;
+    pub const EXTERN_ADDRESS: u64 = 0x2000_2000;
+    pub const GOT_ADDRESS: u64 = 0x2000_3000;
+    pub const HELPER_OFFSET: u64 = 0x20;
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_shdr(
+        buf: &mut Vec<u8>,
+        name: u32,
+        type_: u32,
+        flags: u64,
+        offset: u64,
+        size: u64,
+        link: u32,
+        info: u32,
+        align: u64,
+        entsize: u64,
+    ) {
+        buf.write_u32::<LittleEndian>(name).unwrap();
+        buf.write_u32::<LittleEndian>(type_).unwrap();
+        buf.write_u64::<LittleEndian>(flags).unwrap();
+        buf.write_u64::<LittleEndian>(0).unwrap(); // sh_addr
+        buf.write_u64::<LittleEndian>(offset).unwrap();
+        buf.write_u64::<LittleEndian>(size).unwrap();
+        buf.write_u32::<LittleEndian>(link).unwrap();
+        buf.write_u32::<LittleEndian>(info).unwrap();
+        buf.write_u64::<LittleEndian>(align).unwrap();
+        buf.write_u64::<LittleEndian>(entsize).unwrap();
+    }
+
+    fn write_sym(buf: &mut Vec<u8>, name: u32, info: u8, shndx: u16, value: u64) {
+        buf.write_u32::<LittleEndian>(name).unwrap();
+        buf.write_u8(info).unwrap();
+        buf.write_u8(0).unwrap(); // st_other
+        buf.write_u16::<LittleEndian>(shndx).unwrap();
+        buf.write_u64::<LittleEndian>(value).unwrap();
+        buf.write_u64::<LittleEndian>(0).unwrap(); // st_size
+    }
+
+    fn write_rela(buf: &mut Vec<u8>, offset: u64, sym: u32, type_: u32, addend: i64) {
+        buf.write_u64::<LittleEndian>(offset).unwrap();
+        buf.write_u64::<LittleEndian>(((sym as u64) << 32) | type_ as u64)
+            .unwrap();
+        buf.write_i64::<LittleEndian>(addend).unwrap();
+    }
+
+    /// Synthesize an x86-64 relocatable object, like what GCC produces for:
+    ///
+    /// ```c
+    /// int data;
+    /// static void helper(void) {}
+    /// void main(void) { data; helper(); puts(); &data; &puts; }
+    /// ```
+    ///
+    /// where the addresses are taken via the GOT, as when compiled with
+    /// `-fPIC`.
+    ///
+    /// with sections:
+    ///   1. .text
+    ///   2. .data
+    ///   3. .rela.text
+    ///   4. .symtab
+    ///   5. .strtab
+    ///   6. .shstrtab
+    pub fn build_elf_object() -> Vec<u8> {
+        use object::elf::*;
+
+        let mut text = vec![];
+        // main:
+        //   mov eax, [data]     ; R_X86_64_32S .data+0
+        text.extend_from_slice(&[0x8B, 0x04, 0x25, 0x00, 0x00, 0x00, 0x00]);
+        //   call helper         ; R_X86_64_PLT32 helper-4
+        text.extend_from_slice(&[0xE8, 0x00, 0x00, 0x00, 0x00]);
+        //   call puts           ; R_X86_64_PC32 puts-4
+        text.extend_from_slice(&[0xE8, 0x00, 0x00, 0x00, 0x00]);
+        //   mov rax, [rip+data] ; R_X86_64_REX_GOTPCRELX data-4
+        text.extend_from_slice(&[0x48, 0x8B, 0x05, 0x00, 0x00, 0x00, 0x00]);
+        //   mov rax, [rip+puts] ; R_X86_64_GOTPCREL puts-4
+        text.extend_from_slice(&[0x48, 0x8B, 0x05, 0x00, 0x00, 0x00, 0x00]);
+        //   ret
+        text.push(0xC3);
+        text.resize(HELPER_OFFSET as usize, 0xCC);
+        // helper:
+        //   ret
+        text.push(0xC3);
+
+        let data = vec![0x41u8; 8];
+
+        let mut rela = vec![];
+        write_rela(&mut rela, 0x3, 2, R_X86_64_32S, 0);
+        write_rela(&mut rela, 0x8, 4, R_X86_64_PLT32, -4);
+        write_rela(&mut rela, 0xD, 5, R_X86_64_PC32, -4);
+        write_rela(&mut rela, 0x14, 6, R_X86_64_REX_GOTPCRELX, -4);
+        write_rela(&mut rela, 0x1B, 5, R_X86_64_GOTPCREL, -4);
+
+        let strtab = b"\x00main\x00helper\x00puts\x00data\x00".to_vec();
+        let shstrtab = b"\x00.text\x00.data\x00.rela.text\x00.symtab\x00.strtab\x00.shstrtab\x00".to_vec();
+
+        let mut symtab = vec![];
+        write_sym(&mut symtab, 0, 0, 0, 0);
+        write_sym(&mut symtab, 0, STT_SECTION, 1, 0);
+        write_sym(&mut symtab, 0, STT_SECTION, 2, 0);
+        write_sym(&mut symtab, 1, (STB_GLOBAL << 4) | STT_FUNC, 1, 0);
+        write_sym(&mut symtab, 6, (STB_GLOBAL << 4) | STT_FUNC, 1, HELPER_OFFSET);
+        write_sym(&mut symtab, 13, STB_GLOBAL << 4, SHN_UNDEF, 0);
+        write_sym(&mut symtab, 18, (STB_GLOBAL << 4) | STT_OBJECT, 2, 0);
+
+        let mut buf = vec![];
+        // e_ident
+        buf.extend_from_slice(b"\x7FELF");
+        buf.extend_from_slice(&[ELFCLASS64, ELFDATA2LSB, EV_CURRENT, 0]);
+        buf.resize(0x10, 0);
+        buf.write_u16::<LittleEndian>(ET_REL).unwrap();
+        buf.write_u16::<LittleEndian>(EM_X86_64).unwrap();
+        buf.write_u32::<LittleEndian>(EV_CURRENT as u32).unwrap();
+        buf.write_u64::<LittleEndian>(0).unwrap(); // e_entry
+        buf.write_u64::<LittleEndian>(0).unwrap(); // e_phoff
+        let shoff_offset = buf.len();
+        buf.write_u64::<LittleEndian>(0).unwrap(); // e_shoff, fixed up below
+        buf.write_u32::<LittleEndian>(0).unwrap(); // e_flags
+        buf.write_u16::<LittleEndian>(0x40).unwrap(); // e_ehsize
+        buf.write_u16::<LittleEndian>(0).unwrap(); // e_phentsize
+        buf.write_u16::<LittleEndian>(0).unwrap(); // e_phnum
+        buf.write_u16::<LittleEndian>(0x40).unwrap(); // e_shentsize
+        buf.write_u16::<LittleEndian>(7).unwrap(); // e_shnum
+        buf.write_u16::<LittleEndian>(6).unwrap(); // e_shstrndx
+
+        let mut offsets = vec![];
+        for content in [&text, &data, &rela, &symtab, &strtab, &shstrtab] {
+            buf.resize(crate::util::align(buf.len() as u64, 0x10) as usize, 0);
+            offsets.push(buf.len() as u64);
+            buf.extend_from_slice(content);
+        }
+
+        buf.resize(crate::util::align(buf.len() as u64, 0x10) as usize, 0);
+        let shoff = buf.len() as u64;
+        buf[shoff_offset..shoff_offset + 8].copy_from_slice(&shoff.to_le_bytes());
+
+        write_shdr(&mut buf, 0, SHT_NULL, 0, 0, 0, 0, 0, 0, 0);
+        #[rustfmt::skip]
+        {
+            write_shdr(&mut buf, 1, SHT_PROGBITS, (SHF_ALLOC | SHF_EXECINSTR) as u64, offsets[0], text.len() as u64, 0, 0, 16, 0);
+            write_shdr(&mut buf, 7, SHT_PROGBITS, (SHF_ALLOC | SHF_WRITE) as u64, offsets[1], data.len() as u64, 0, 0, 8, 0);
+            write_shdr(&mut buf, 13, SHT_RELA, SHF_INFO_LINK as u64, offsets[2], rela.len() as u64, 4, 1, 8, 24);
+            write_shdr(&mut buf, 24, SHT_SYMTAB, 0, offsets[3], symtab.len() as u64, 5, 3, 8, 24);
+            write_shdr(&mut buf, 32, SHT_STRTAB, 0, offsets[4], strtab.len() as u64, 0, 0, 1, 0);
+            write_shdr(&mut buf, 40, SHT_STRTAB, 0, offsets[5], shstrtab.len() as u64, 0, 0, 1, 0);
+        };
+
+        buf
+    }
+
+    #[test]
+    fn layout() -> Result<()> {
+        let buf = build_elf_object();
+        let obj = crate::loader::elf::relocatable::ELFObject::from_bytes(&buf)?;
+
+        let names = obj.module.sections.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec![".text", ".data", "UNDEF", ".got"], names);
+        assert_eq!(TEXT_ADDRESS, obj.module.sections[0].virtual_range.start);
+        assert_eq!(DATA_ADDRESS, obj.module.sections[1].virtual_range.start);
+        assert_eq!(EXTERN_ADDRESS, obj.module.sections[2].virtual_range.start);
+        assert_eq!(GOT_ADDRESS, obj.module.sections[3].virtual_range.start);
+
+        // mov eax, [data]
+        assert_eq!(0x8B, obj.module.address_space.read_u8(TEXT_ADDRESS)?);
+        assert_eq!(0x41, obj.module.address_space.read_u8(DATA_ADDRESS)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn symbols() -> Result<()> {
+        let buf = build_elf_object();
+        let obj = crate::loader::elf::relocatable::ELFObject::from_bytes(&buf)?;
+
+        assert_eq!(TEXT_ADDRESS, obj.symbols.by_name["main"].address);
+        assert_eq!(TEXT_ADDRESS + HELPER_OFFSET, obj.symbols.by_name["helper"].address);
+        assert!(!obj.symbols.by_name.contains_key("puts"));
+
+        assert_eq!(1, obj.externs.len());
+        assert_eq!(EXTERN_ADDRESS, obj.externs["puts"]);
+        // the extern slot points to itself.
+        assert_eq!(EXTERN_ADDRESS, obj.module.address_space.read_u64(EXTERN_ADDRESS)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn relocations() -> Result<()> {
+        let buf = build_elf_object();
+        let obj = crate::loader::elf::relocatable::ELFObject::from_bytes(&buf)?;
+
+        // R_X86_64_32S: mov eax, [0x20001000]
+        assert_eq!(
+            DATA_ADDRESS as u32,
+            obj.module.address_space.read_u32(TEXT_ADDRESS + 0x3)?
+        );
+
+        // R_X86_64_PLT32: call helper
+        let next = TEXT_ADDRESS + 0xC;
+        let rel = obj.module.address_space.read_u32(TEXT_ADDRESS + 0x8)? as i32 as i64;
+        assert_eq!(TEXT_ADDRESS + HELPER_OFFSET, (next as i64 + rel) as u64);
+
+        // R_X86_64_PC32: call puts
+        let next = TEXT_ADDRESS + 0x11;
+        let rel = obj.module.address_space.read_u32(TEXT_ADDRESS + 0xD)? as i32 as i64;
+        assert_eq!(EXTERN_ADDRESS, (next as i64 + rel) as u64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn got() -> Result<()> {
+        let buf = build_elf_object();
+        let obj = crate::loader::elf::relocatable::ELFObject::from_bytes(&buf)?;
+
+        // R_X86_64_REX_GOTPCRELX: mov rax, [rip+data@GOTPCREL]
+        // via the synthetic GOT slot that contains the address of data.
+        let next = TEXT_ADDRESS + 0x18;
+        let rel = obj.module.address_space.read_u32(TEXT_ADDRESS + 0x14)? as i32 as i64;
+        assert_eq!(GOT_ADDRESS, (next as i64 + rel) as u64);
+        assert_eq!(DATA_ADDRESS, obj.module.address_space.read_u64(GOT_ADDRESS)?);
+
+        // R_X86_64_GOTPCREL: mov rax, [rip+puts@GOTPCREL]
+        // via the extern slot, which contains the address of the extern.
+        let next = TEXT_ADDRESS + 0x1F;
+        let rel = obj.module.address_space.read_u32(TEXT_ADDRESS + 0x1B)? as i32 as i64;
+        assert_eq!(EXTERN_ADDRESS, (next as i64 + rel) as u64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive() -> Result<()> {
+        let obj = build_elf_object();
+        let buf =
+            crate::loader::ar::tests::build_archive(&[("a.o", &obj), ("README", b"not an object"), ("b.o", &obj)]);
+
+        let objects = crate::loader::elf::relocatable::load_elf_archive(&buf)?;
+        let names = objects.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec!["a.o", "b.o"], names);
+        assert_eq!(TEXT_ADDRESS, objects[1].1.symbols.by_name["main"].address);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+//! Read the versions of dynamic symbols, from `.gnu.version`,
+//! `.gnu.version_r` (needed from other modules), and `.gnu.version_d`
+//! (defined by this module).
+//!
+//! A module may import, or export, multiple versions of a symbol,
+//! like `memcpy@GLIBC_2.2.5` and `memcpy@GLIBC_2.14`,
+//! and the versions needed from each library name the library,
+//! which is the only record of where an import is expected to come from.
+use std::collections::HashMap;
+
+use goblin::elf::symver::VER_FLG_BASE;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ELFSymbolVersion {
+    /// like `GLIBC_2.14`.
+    pub name:    String,
+    /// the library expected to provide the version, like `libc.so.6`,
+    /// for versions needed from other modules.
+    pub library: Option<String>,
+    /// set when this isn't the default version of a defined symbol,
+    /// which is then only used by modules linked against the old version,
+    /// like `memcpy@GLIBC_2.2.5` versus the default `memcpy@@GLIBC_2.14`.
+    pub hidden:  bool,
+}
+
+/// Read the versions of the dynamic symbols, indexed like `.dynsym`.
+///
+/// Symbols that are local, unversioned, or have the base version
+/// (which names the module itself) have no version.
+pub fn read_symbol_versions(elf: &goblin::elf::Elf) -> Vec<Option<ELFSymbolVersion>> {
+    let Some(versym) = elf.versym.as_ref() else {
+        return vec![];
+    };
+
+    // version index -> (version name, library)
+    let mut versions: HashMap<u16, (String, Option<String>)> = HashMap::new();
+
+    if let Some(verneed) = elf.verneed.as_ref() {
+        for need in verneed.iter() {
+            let library = elf.dynstrtab.get_at(need.vn_file).map(|name| name.to_string());
+            for aux in need.iter() {
+                if let Some(name) = elf.dynstrtab.get_at(aux.vna_name) {
+                    versions.insert(aux.vna_other, (name.to_string(), library.clone()));
+                }
+            }
+        }
+    }
+
+    if let Some(verdef) = elf.verdef.as_ref() {
+        for def in verdef.iter() {
+            if def.vd_flags & VER_FLG_BASE != 0 {
+                continue;
+            }
+
+            // the first name is the version, any others are the versions it inherits.
+            if let Some(name) = def.iter().next().and_then(|aux| elf.dynstrtab.get_at(aux.vda_name)) {
+                versions.insert(def.vd_ndx, (name.to_string(), None));
+            }
+        }
+    }
+
+    versym
+        .iter()
+        .map(|sym| {
+            let (name, library) = versions.get(&sym.version())?;
+            Some(ELFSymbolVersion {
+                name:    name.clone(),
+                library: library.clone(),
+                hidden:  sym.is_hidden(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::loader::elf::tests::{build_pie, PIE_ENTRY, PIE_SLOT};
+
+    /// Extend `build_pie` with two imports, versioned by `.gnu.version_r`:
+    ///
+    /// ```text
+    ///   memcpy@GLIBC_2.14 from libc.so.6
+    ///   cos@GLIBC_2.29    from libm.so.6
+    /// ```
+    ///
+    /// each referenced by a GLOB_DAT relocation,
+    /// and listed in the order that the libraries are needed: libm, then libc.
+    pub fn build_pie_with_versions() -> Vec<u8> {
+        let mut buf = build_pie();
+
+        let mut dynstr = vec![0u8];
+        let mut add_str = |s: &str| -> u64 {
+            let offset = dynstr.len() as u64;
+            dynstr.extend_from_slice(s.as_bytes());
+            dynstr.push(0);
+            offset
+        };
+        let libc = add_str("libc.so.6");
+        let libm = add_str("libm.so.6");
+        let memcpy = add_str("memcpy");
+        let cos = add_str("cos");
+        let glibc_2_14 = add_str("GLIBC_2.14");
+        let glibc_2_29 = add_str("GLIBC_2.29");
+        buf[0x600..0x600 + dynstr.len()].copy_from_slice(&dynstr);
+
+        // .dynsym: null, memcpy, cos
+        let mut dynsym = vec![0u8; 0x18];
+        for name in [memcpy, cos] {
+            dynsym.write_u32::<LittleEndian>(name as u32).unwrap(); // st_name
+            dynsym.write_u8(0x12).unwrap(); // st_info: STB_GLOBAL, STT_FUNC
+            dynsym.write_u8(0).unwrap(); // st_other
+            dynsym.write_u16::<LittleEndian>(0).unwrap(); // st_shndx: SHN_UNDEF
+            dynsym.write_u64::<LittleEndian>(0).unwrap(); // st_value
+            dynsym.write_u64::<LittleEndian>(0).unwrap(); // st_size
+        }
+        buf[0x680..0x680 + dynsym.len()].copy_from_slice(&dynsym);
+
+        // .gnu.version: local, 2, 3
+        let mut versym = vec![];
+        for version in [0u16, 2, 3] {
+            versym.write_u16::<LittleEndian>(version).unwrap();
+        }
+        buf[0x700..0x700 + versym.len()].copy_from_slice(&versym);
+
+        // .gnu.version_r: Elf64_Verneed, each with one Elf64_Vernaux
+        let mut verneed = vec![];
+        for (i, (file, version, index)) in [(libc, glibc_2_14, 2u16), (libm, glibc_2_29, 3)]
+            .into_iter()
+            .enumerate()
+        {
+            verneed.write_u16::<LittleEndian>(1).unwrap(); // vn_version
+            verneed.write_u16::<LittleEndian>(1).unwrap(); // vn_cnt
+            verneed.write_u32::<LittleEndian>(file as u32).unwrap(); // vn_file
+            verneed.write_u32::<LittleEndian>(0x10).unwrap(); // vn_aux
+            verneed
+                .write_u32::<LittleEndian>(if i == 0 { 0x20 } else { 0 })
+                .unwrap(); // vn_next
+
+            verneed.write_u32::<LittleEndian>(0).unwrap(); // vna_hash
+            verneed.write_u16::<LittleEndian>(0).unwrap(); // vna_flags
+            verneed.write_u16::<LittleEndian>(index).unwrap(); // vna_other
+            verneed.write_u32::<LittleEndian>(version as u32).unwrap(); // vna_name
+            verneed.write_u32::<LittleEndian>(0).unwrap(); // vna_next
+        }
+        buf[0x720..0x720 + verneed.len()].copy_from_slice(&verneed);
+
+        // PT_DYNAMIC p_filesz and p_memsz
+        buf[0x98..0xA0].copy_from_slice(&0xA0u64.to_le_bytes());
+        buf[0xA0..0xA8].copy_from_slice(&0xA0u64.to_le_bytes());
+
+        let mut dynamic = vec![];
+        for (tag, value) in [
+            (goblin::elf::dynamic::DT_NEEDED, libm),
+            (goblin::elf::dynamic::DT_NEEDED, libc),
+            (goblin::elf::dynamic::DT_RELA, 0x300),
+            (goblin::elf::dynamic::DT_RELASZ, 0x48),
+            (goblin::elf::dynamic::DT_RELAENT, 0x18),
+            (goblin::elf::dynamic::DT_SYMTAB, 0x680),
+            (goblin::elf::dynamic::DT_SYMENT, 0x18),
+            (goblin::elf::dynamic::DT_STRTAB, 0x600),
+            (goblin::elf::dynamic::DT_STRSZ, dynstr.len() as u64),
+            (goblin::elf::dynamic::DT_NULL, 0x0),
+        ] {
+            dynamic.write_u64::<LittleEndian>(tag).unwrap();
+            dynamic.write_u64::<LittleEndian>(value).unwrap();
+        }
+        buf[0x200..0x200 + dynamic.len()].copy_from_slice(&dynamic);
+
+        let mut rela = vec![];
+        for (slot, info, addend) in [
+            (PIE_SLOT, goblin::elf::reloc::R_X86_64_RELATIVE as u64, PIE_ENTRY),
+            (0x410, (1 << 32) | goblin::elf::reloc::R_X86_64_GLOB_DAT as u64, 0),
+            (0x418, (2 << 32) | goblin::elf::reloc::R_X86_64_GLOB_DAT as u64, 0),
+        ] {
+            rela.write_u64::<LittleEndian>(slot).unwrap(); // r_offset
+            rela.write_u64::<LittleEndian>(info).unwrap(); // r_info
+            rela.write_i64::<LittleEndian>(addend as i64).unwrap(); // r_addend
+        }
+        buf[0x300..0x300 + rela.len()].copy_from_slice(&rela);
+
+        // Elf64_Shdr: null, .gnu.version, .gnu.version_r
+        let mut shdrs = vec![0u8; 0x40];
+        for (sh_type, offset, size, info, entsize) in [
+            (
+                goblin::elf::section_header::SHT_GNU_VERSYM,
+                0x700u64,
+                versym.len() as u64,
+                0u32,
+                2u64,
+            ),
+            (
+                goblin::elf::section_header::SHT_GNU_VERNEED,
+                0x720,
+                verneed.len() as u64,
+                2,
+                0,
+            ),
+        ] {
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_name
+            shdrs.write_u32::<LittleEndian>(sh_type).unwrap();
+            shdrs
+                .write_u64::<LittleEndian>(goblin::elf::section_header::SHF_ALLOC as u64)
+                .unwrap(); // sh_flags
+            shdrs.write_u64::<LittleEndian>(offset).unwrap(); // sh_addr
+            shdrs.write_u64::<LittleEndian>(offset).unwrap(); // sh_offset
+            shdrs.write_u64::<LittleEndian>(size).unwrap(); // sh_size
+            shdrs.write_u32::<LittleEndian>(0).unwrap(); // sh_link
+            shdrs.write_u32::<LittleEndian>(info).unwrap(); // sh_info
+            shdrs.write_u64::<LittleEndian>(2).unwrap(); // sh_addralign
+            shdrs.write_u64::<LittleEndian>(entsize).unwrap(); // sh_entsize
+        }
+        buf[0x800..0x800 + shdrs.len()].copy_from_slice(&shdrs);
+
+        buf[0x28..0x30].copy_from_slice(&0x800u64.to_le_bytes()); // e_shoff
+        buf[0x3C..0x3E].copy_from_slice(&3u16.to_le_bytes()); // e_shnum
+
+        buf
+    }
+
+    #[test]
+    fn versions() -> Result<()> {
+        let buf = build_pie_with_versions();
+        let goblin_elf = goblin::elf::Elf::parse(&buf)?;
+
+        let versions = read_symbol_versions(&goblin_elf);
+        assert_eq!(3, versions.len());
+        assert_eq!(None, versions[0]);
+        assert_eq!(
+            Some(ELFSymbolVersion {
+                name:    "GLIBC_2.14".to_string(),
+                library: Some("libc.so.6".to_string()),
+                hidden:  false,
+            }),
+            versions[1]
+        );
+        assert_eq!(
+            Some(ELFSymbolVersion {
+                name:    "GLIBC_2.29".to_string(),
+                library: Some("libm.so.6".to_string()),
+                hidden:  false,
+            }),
+            versions[2]
+        );
+
+        // the loader reads them once, for the import and symbol analysis.
+        assert_eq!(versions, crate::loader::elf::ELF::from_bytes(&buf)?.symbol_versions);
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_libraries() -> Result<()> {
+        let buf = build_pie_with_versions();
+        let goblin_elf = goblin::elf::Elf::parse(&buf)?;
+
+        // libm.so.6 is needed first, but only provides cos.
+        let versions = read_symbol_versions(&goblin_elf);
+        let mut imports = crate::loader::elf::import::read_import_libraries(&goblin_elf, &versions)
+            .into_iter()
+            .map(|lib| {
+                (
+                    lib.lib_name,
+                    lib.symbols
+                        .iter()
+                        .map(|symbol| symbol.versioned_name())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        imports.sort();
+
+        assert_eq!(
+            vec![
+                ("libc.so.6".to_string(), vec!["memcpy@GLIBC_2.14".to_string()]),
+                ("libm.so.6".to_string(), vec!["cos@GLIBC_2.29".to_string()]),
+            ],
+            imports
+        );
+
+        Ok(())
+    }
+}
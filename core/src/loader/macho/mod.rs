@@ -0,0 +1,767 @@
+//! Load x86 and x86-64 Mach-O images.
+//!
+//! Universal ("fat") binaries are supported by selecting a single slice:
+//! x86-64 is preferred, falling back to i386.
+//! The selected slice is then loaded like any other thin image,
+//! by mapping each of its `LC_SEGMENT`/`LC_SEGMENT_64` commands.
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use goblin::mach::{
+    constants::{
+        cputype::{CPU_TYPE_X86, CPU_TYPE_X86_64},
+        SECTION_TYPE, VM_PROT_EXECUTE, VM_PROT_READ, VM_PROT_WRITE,
+    },
+    load_command::{
+        CommandVariant, SIZEOF_SECTION_32, SIZEOF_SECTION_64, SIZEOF_SEGMENT_COMMAND_32, SIZEOF_SEGMENT_COMMAND_64,
+    },
+    Mach,
+};
+use log::{debug, warn};
+use thiserror::Error;
+
+use crate::{
+    arch::Arch,
+    aspace::{self, RelativeAddressSpace},
+    module::{Module, Permissions, Section},
+    util, VA,
+};
+
+#[derive(Error, Debug)]
+pub enum MachOError {
+    #[error("format not supported: {0}")]
+    FormatNotSupported(String),
+
+    #[error("malformed Mach-O file: {0}")]
+    MalformedMachOFile(String),
+}
+
+const PAGE_SIZE: u64 = 0x1000;
+
+/// The most zero-fill (like `__bss`) a segment may have beyond its file data.
+/// The segment size is untrusted, and sizes the allocation of the segment.
+const MAX_ZERO_FILL: u64 = 0x400_0000;
+
+/// The largest span of address space that the segments may cover.
+const MAX_ADDRESS_SPACE: u64 = 0x1_0000_0000;
+
+/// The most slices a universal binary may contain.
+/// Java class files share the `0xCAFEBABE` magic, but have their version here,
+/// which is at least 45, so this is the same limit that file(1) uses.
+const MAX_FAT_ARCH: u32 = 20;
+
+/// Is the given buffer a Mach-O image or a universal binary?
+pub fn is_macho(buf: &[u8]) -> bool {
+    if buf.len() < 8 {
+        return false;
+    }
+
+    match &buf[0..4] {
+        // MH_MAGIC_64, MH_MAGIC
+        b"\xCF\xFA\xED\xFE" | b"\xCE\xFA\xED\xFE" => true,
+        // FAT_MAGIC, followed by nfat_arch
+        b"\xCA\xFE\xBA\xBE" => u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) < MAX_FAT_ARCH,
+        _ => false,
+    }
+}
+
+/// A Mach-O section header, as found following a segment load command.
+///
+/// goblin doesn't expose the `reserved1` and `reserved2` fields,
+/// which are needed to resolve symbol stubs and symbol pointers
+/// via the indirect symbol table.
+#[derive(Debug, Clone)]
+pub struct MachOSection {
+    pub segname:   String,
+    pub sectname:  String,
+    pub addr:      VA,
+    pub size:      u64,
+    pub flags:     u32,
+    /// for stubs and symbol pointers: the index of the first entry in the
+    /// indirect symbol table.
+    pub reserved1: u32,
+    /// for stubs: the size of each stub.
+    pub reserved2: u32,
+}
+
+impl MachOSection {
+    /// like `S_SYMBOL_STUBS` or `S_LAZY_SYMBOL_POINTERS`.
+    pub fn section_type(&self) -> u32 {
+        self.flags & SECTION_TYPE
+    }
+}
+
+/// A parsed and loaded Mach-O file.
+/// The `buf` field contains the raw data of the thin image;
+/// for a universal binary, this is only the selected slice.
+/// The `module` field contains an address space as the image would be loaded.
+pub struct MachO {
+    pub buf:              Vec<u8>,
+    pub module:           Module,
+    pub section_headers:  Vec<MachOSection>,
+    /// the `LC_DYSYMTAB` indirect symbol table:
+    /// indices into the symbol table, referenced by stubs and symbol pointers.
+    pub indirect_symbols: Vec<u32>,
+}
+
+impl MachO {
+    pub fn from_bytes(buf: &[u8]) -> Result<MachO> {
+        load_macho(buf)
+    }
+
+    /// Access other Mach-O structures via goblin.
+    pub fn macho(&self) -> Result<goblin::mach::MachO<'_>> {
+        get_macho(&self.buf)
+    }
+}
+
+fn get_macho(buf: &[u8]) -> Result<goblin::mach::MachO<'_>> {
+    match goblin::mach::MachO::parse(buf, 0) {
+        Ok(macho) => Ok(macho),
+        Err(e) => Err(MachOError::MalformedMachOFile(e.to_string()).into()),
+    }
+}
+
+/// Select the thin image to load from the given buffer.
+/// For a universal binary, this is the x86-64 slice, or the i386 slice.
+fn select_image(buf: &[u8]) -> Result<&[u8]> {
+    if !is_macho(buf) {
+        return Err(MachOError::FormatNotSupported("not a Mach-O file".to_string()).into());
+    }
+
+    let multi = match Mach::parse(buf) {
+        Ok(Mach::Binary(_)) => return Ok(buf),
+        Ok(Mach::Fat(multi)) => multi,
+        Err(e) => return Err(MachOError::MalformedMachOFile(e.to_string()).into()),
+    };
+
+    for cputype in [CPU_TYPE_X86_64, CPU_TYPE_X86] {
+        match multi.find_cputype(cputype) {
+            Ok(Some(arch)) => {
+                debug!("macho: fat: selected slice at {:#x} size {:#x}", arch.offset, arch.size);
+                let slice = arch.slice(buf);
+                if slice.is_empty() {
+                    return Err(MachOError::MalformedMachOFile("invalid fat slice".to_string()).into());
+                }
+                return Ok(slice);
+            }
+            Ok(None) => continue,
+            Err(e) => return Err(MachOError::MalformedMachOFile(e.to_string()).into()),
+        }
+    }
+
+    Err(MachOError::FormatNotSupported("no x86 slice in universal binary".to_string()).into())
+}
+
+/// Load the given segment, whose zero-fill is bounded by the size of the file.
+fn load_macho_segment(segment: &goblin::mach::segment::Segment, file_size: u64) -> Result<Option<Section>> {
+    let name = segment
+        .name()
+        .map_err(|e| MachOError::MalformedMachOFile(e.to_string()))?
+        .to_string();
+
+    if segment.vmsize == 0 {
+        return Ok(None);
+    }
+
+    if segment.initprot == 0 && segment.filesize == 0 {
+        // like __PAGEZERO, which reserves the low 4GB of address space
+        // in 64-bit executables, but doesn't contain anything.
+        debug!("macho: segment: {} skipped", name);
+        return Ok(None);
+    }
+
+    let mut perms = Permissions::empty();
+    if segment.initprot & VM_PROT_READ > 0 {
+        perms.insert(Permissions::R);
+    }
+    if segment.initprot & VM_PROT_WRITE > 0 {
+        perms.insert(Permissions::W);
+    }
+    if segment.initprot & VM_PROT_EXECUTE > 0 {
+        perms.insert(Permissions::X);
+    }
+
+    debug!("macho: segment: {} at {:#x}", name, segment.vmaddr);
+
+    let max_vmsize = util::align(std::cmp::min(segment.filesize, file_size), PAGE_SIZE) + MAX_ZERO_FILL;
+    let vmsize = if segment.vmsize > max_vmsize {
+        warn!(
+            "macho: segment: {} size {:#x} truncated to {:#x}",
+            name, segment.vmsize, max_vmsize
+        );
+        max_vmsize
+    } else {
+        segment.vmsize
+    };
+
+    let (Some(vmend), Some(fileend)) = (
+        segment.vmaddr.checked_add(vmsize),
+        segment.fileoff.checked_add(segment.filesize),
+    ) else {
+        return Err(MachOError::MalformedMachOFile(format!("segment {name} beyond the address space")).into());
+    };
+
+    Ok(Some(Section {
+        physical_range: std::ops::Range {
+            start: segment.fileoff,
+            end:   fileend,
+        },
+        virtual_range: std::ops::Range {
+            start: segment.vmaddr,
+            end:   vmend,
+        },
+        permissions: perms,
+        name,
+    }))
+}
+
+/// read a fixed-size, NULL-padded name, like `sectname` or `segname`.
+fn read_name(buf: &[u8]) -> String {
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).into_owned()
+}
+
+/// Parse the section headers that follow each segment load command.
+fn load_macho_section_headers(buf: &[u8], macho: &goblin::mach::MachO) -> Result<Vec<MachOSection>> {
+    let mut sections = vec![];
+
+    for cmd in macho.load_commands.iter() {
+        let (nsects, header_size, section_size) = match cmd.command {
+            CommandVariant::Segment32(seg) => (seg.nsects, SIZEOF_SEGMENT_COMMAND_32, SIZEOF_SECTION_32),
+            CommandVariant::Segment64(seg) => (seg.nsects, SIZEOF_SEGMENT_COMMAND_64, SIZEOF_SECTION_64),
+            _ => continue,
+        };
+
+        for i in 0..nsects as usize {
+            let start = cmd.offset + header_size + i * section_size;
+            let end = start + section_size;
+            if end > buf.len() {
+                return Err(MachOError::MalformedMachOFile("section header out of bounds".to_string()).into());
+            }
+            let sbuf = &buf[start..end];
+
+            //  section:    sectname[16] segname[16] addr:u32 size:u32 offset:u32 align:u32
+            //              reloff:u32 nreloff:u32 flags:u32 reserved1:u32 reserved2:u32
+            //  section_64: sectname[16] segname[16] addr:u64 size:u64 offset:u32 align:u32
+            //              reloff:u32 nreloff:u32 flags:u32 reserved1:u32 reserved2:u32
+            // reserved3:u32
+            let (addr, size, rest) = if section_size == SIZEOF_SECTION_64 {
+                (
+                    LittleEndian::read_u64(&sbuf[0x20..]),
+                    LittleEndian::read_u64(&sbuf[0x28..]),
+                    &sbuf[0x30..],
+                )
+            } else {
+                (
+                    LittleEndian::read_u32(&sbuf[0x20..]) as u64,
+                    LittleEndian::read_u32(&sbuf[0x24..]) as u64,
+                    &sbuf[0x28..],
+                )
+            };
+
+            let section = MachOSection {
+                sectname: read_name(&sbuf[0x0..0x10]),
+                segname: read_name(&sbuf[0x10..0x20]),
+                addr,
+                size,
+                flags: LittleEndian::read_u32(&rest[0x10..]),
+                reserved1: LittleEndian::read_u32(&rest[0x14..]),
+                reserved2: LittleEndian::read_u32(&rest[0x18..]),
+            };
+
+            debug!(
+                "macho: section: {},{} at {:#x} type: {:#x}",
+                section.segname,
+                section.sectname,
+                section.addr,
+                section.section_type()
+            );
+            sections.push(section);
+        }
+    }
+
+    Ok(sections)
+}
+
+fn load_macho_indirect_symbols(buf: &[u8], macho: &goblin::mach::MachO) -> Vec<u32> {
+    for cmd in macho.load_commands.iter() {
+        if let CommandVariant::Dysymtab(dysymtab) = cmd.command {
+            let start = dysymtab.indirectsymoff as usize;
+            let end = start + (dysymtab.nindirectsyms as usize * 4);
+            if end > buf.len() {
+                debug!("macho: indirect symbol table out of bounds");
+                return vec![];
+            }
+
+            return buf[start..end].chunks_exact(4).map(LittleEndian::read_u32).collect();
+        }
+    }
+
+    vec![]
+}
+
+fn load_macho(buf: &[u8]) -> Result<MachO> {
+    let buf = select_image(buf)?;
+    let macho = get_macho(buf)?;
+
+    if !macho.little_endian {
+        return Err(MachOError::FormatNotSupported("big endian".to_string()).into());
+    }
+
+    let arch = match macho.header.cputype {
+        CPU_TYPE_X86_64 => Arch::X64,
+        CPU_TYPE_X86 => Arch::X32,
+        cputype => return Err(MachOError::FormatNotSupported(format!("cpu type: {cputype:#x}")).into()),
+    };
+    debug!("macho: arch: {:?}", arch);
+
+    let mut sections = vec![];
+    for segment in macho.segments.iter() {
+        if let Some(section) = load_macho_segment(segment, buf.len() as u64)? {
+            sections.push(section);
+        }
+    }
+
+    let base_address = match sections.iter().map(|sec| sec.virtual_range.start).min() {
+        Some(base_address) => base_address,
+        None => return Err(MachOError::MalformedMachOFile("no segments".to_string()).into()),
+    };
+    debug!("macho: base address: {:#x}", base_address);
+
+    if aspace::page_offset(base_address) != 0 {
+        return Err(MachOError::FormatNotSupported("non-page aligned segment".to_string()).into());
+    }
+
+    let max_address = sections.iter().map(|sec| sec.virtual_range.end).max().unwrap();
+    if max_address - base_address > MAX_ADDRESS_SPACE {
+        return Err(MachOError::FormatNotSupported(format!(
+            "sparse address space: {:#x} - {:#x}",
+            base_address, max_address
+        ))
+        .into());
+    }
+
+    let max_page_address = util::align(max_address - base_address, PAGE_SIZE);
+    debug!("macho: address space: capacity: {:#x}", max_page_address);
+
+    let mut address_space = RelativeAddressSpace::with_capacity(max_page_address);
+
+    for section in sections.iter() {
+        let pstart = section.physical_range.start as usize;
+        let pend = section.physical_range.end as usize;
+
+        let (psize, pbuf) = if pstart >= buf.len() {
+            (0, &[] as &[u8])
+        } else if pend > buf.len() {
+            (buf.len() - pstart, &buf[pstart..])
+        } else {
+            (pend - pstart, &buf[pstart..pend])
+        };
+
+        // the section range contains VAs,
+        // while we're writing to the RelativeAddressSpace.
+        // so shift down by `base_address`.
+        let vstart = section.virtual_range.start;
+        let rstart = vstart - base_address;
+        let vsize = section.virtual_range.end - section.virtual_range.start;
+        let mut vbuf = vec![0u8; vsize as usize];
+
+        // segments may be zero-filled beyond their file data (like __DATA with __bss),
+        // or the file data may be truncated.
+        let psize = std::cmp::min(psize, vsize as usize);
+        vbuf[0..psize].copy_from_slice(&pbuf[0..psize]);
+
+        if aspace::page_offset(rstart) != 0 {
+            return Err(MachOError::FormatNotSupported("non-page aligned segment".to_string()).into());
+        }
+
+        address_space.map.writezx(rstart, &vbuf)?;
+
+        debug!(
+            "macho: address space: mapped {:#x} - {:#x} {:?}",
+            vstart, section.virtual_range.end, section.permissions
+        );
+    }
+
+    let section_headers = load_macho_section_headers(buf, &macho)?;
+    let indirect_symbols = load_macho_indirect_symbols(buf, &macho);
+
+    let module = Module {
+        arch,
+        sections,
+        section_headers: Default::default(),
+        address_space: address_space.into_absolute(base_address)?,
+    };
+
+    debug!("macho: loaded");
+    Ok(MachO {
+        buf: buf.to_vec(),
+        module,
+        section_headers,
+        indirect_symbols,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use crate::{aspace::AddressSpace, module::Permissions};
+
+    pub const TEXT_VMADDR: u64 = 0x1_0000_0000;
+
+    /// the offset of `main` within the __TEXT segment.
+    pub const MAIN_OFFSET: u64 = 0x400;
+    /// the offset of a second, internal function within the __TEXT segment.
+    pub const HELPER_OFFSET: u64 = 0x410;
+    /// the offset of the symbol stub for `_exit` within the __TEXT segment.
+    pub const STUB_OFFSET: u64 = 0x420;
+    /// the offset of the lazy symbol pointer for `_exit` within the __TEXT
+    /// segment (it's found in __DATA, which immediately follows).
+    pub const LAZY_POINTER_OFFSET: u64 = 0x1000;
+
+    fn push_name(buf: &mut Vec<u8>, name: &str) {
+        let mut raw = [0u8; 16];
+        raw[..name.len()].copy_from_slice(name.as_bytes());
+        buf.extend_from_slice(&raw);
+    }
+
+    fn push_u32(buf: &mut Vec<u8>, v: u32) {
+        let mut raw = [0u8; 4];
+        LittleEndian::write_u32(&mut raw, v);
+        buf.extend_from_slice(&raw);
+    }
+
+    fn push_u64(buf: &mut Vec<u8>, v: u64) {
+        let mut raw = [0u8; 8];
+        LittleEndian::write_u64(&mut raw, v);
+        buf.extend_from_slice(&raw);
+    }
+
+    struct TestSection {
+        name:      &'static str,
+        offset:    u64,
+        size:      u64,
+        flags:     u32,
+        reserved1: u32,
+        reserved2: u32,
+    }
+
+    fn push_segment(
+        buf: &mut Vec<u8>,
+        name: &str,
+        range: (u64, u64),
+        file_range: (u64, u64),
+        prot: u32,
+        sections: &[TestSection],
+    ) {
+        push_u32(buf, 0x19); // LC_SEGMENT_64
+        push_u32(buf, 72 + 80 * sections.len() as u32);
+        push_name(buf, name);
+        push_u64(buf, range.0); // vmaddr
+        push_u64(buf, range.1); // vmsize
+        push_u64(buf, file_range.0); // fileoff
+        push_u64(buf, file_range.1); // filesize
+        push_u32(buf, prot); // maxprot
+        push_u32(buf, prot); // initprot
+        push_u32(buf, sections.len() as u32);
+        push_u32(buf, 0); // flags
+
+        for section in sections.iter() {
+            push_name(buf, section.name);
+            push_name(buf, name);
+            push_u64(buf, TEXT_VMADDR + section.offset); // addr
+            push_u64(buf, section.size);
+            push_u32(buf, section.offset as u32); // offset
+            push_u32(buf, 0); // align
+            push_u32(buf, 0); // reloff
+            push_u32(buf, 0); // nreloc
+            push_u32(buf, section.flags);
+            push_u32(buf, section.reserved1);
+            push_u32(buf, section.reserved2);
+            push_u32(buf, 0); // reserved3
+        }
+    }
+
+    /// build a minimal x86-64 executable with:
+    ///   - __PAGEZERO
+    ///   - __TEXT at 0x1_0000_0000 containing `main`, a helper, and a stub for
+    ///     `_exit`
+    ///   - __DATA containing the lazy symbol pointer for `_exit`
+    ///   - __LINKEDIT containing function starts, symbols, and indirect symbols
+    ///   - LC_MAIN pointing to `main`
+    pub fn build_macho() -> Vec<u8> {
+        const LINKEDIT: u64 = 0x2000;
+        const FUNCTION_STARTS: u64 = LINKEDIT;
+        const SYMTAB: u64 = LINKEDIT + 0x10;
+        const STRTAB: u64 = LINKEDIT + 0x20;
+        const INDIRECT_SYMBOLS: u64 = LINKEDIT + 0x30;
+
+        let mut cmds = vec![];
+        push_segment(&mut cmds, "__PAGEZERO", (0, TEXT_VMADDR), (0, 0), 0, &[]);
+        push_segment(
+            &mut cmds,
+            "__TEXT",
+            (TEXT_VMADDR, 0x1000),
+            (0, 0x1000),
+            0x5,
+            &[
+                TestSection {
+                    name:      "__text",
+                    offset:    MAIN_OFFSET,
+                    size:      STUB_OFFSET - MAIN_OFFSET,
+                    flags:     0x8000_0400, // S_REGULAR | S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS
+                    reserved1: 0,
+                    reserved2: 0,
+                },
+                TestSection {
+                    name:      "__stubs",
+                    offset:    STUB_OFFSET,
+                    size:      6,
+                    flags:     0x8000_0408, // S_SYMBOL_STUBS | ...
+                    reserved1: 0,
+                    reserved2: 6,
+                },
+            ],
+        );
+        push_segment(
+            &mut cmds,
+            "__DATA",
+            (TEXT_VMADDR + 0x1000, 0x1000),
+            (0x1000, 0x1000),
+            0x3,
+            &[TestSection {
+                name:      "__la_symbol_ptr",
+                offset:    LAZY_POINTER_OFFSET,
+                size:      8,
+                flags:     0x7, // S_LAZY_SYMBOL_POINTERS
+                reserved1: 1,
+                reserved2: 0,
+            }],
+        );
+        push_segment(
+            &mut cmds,
+            "__LINKEDIT",
+            (TEXT_VMADDR + LINKEDIT, 0x1000),
+            (LINKEDIT, 0x40),
+            0x1,
+            &[],
+        );
+
+        push_u32(&mut cmds, 0xC); // LC_LOAD_DYLIB
+        push_u32(&mut cmds, 56);
+        push_u32(&mut cmds, 24); // name offset
+        push_u32(&mut cmds, 0); // timestamp
+        push_u32(&mut cmds, 0); // current version
+        push_u32(&mut cmds, 0); // compatibility version
+        let mut name = b"/usr/lib/libSystem.B.dylib".to_vec();
+        name.resize(32, 0);
+        cmds.extend_from_slice(&name);
+
+        push_u32(&mut cmds, 0x2); // LC_SYMTAB
+        push_u32(&mut cmds, 24);
+        push_u32(&mut cmds, SYMTAB as u32); // symoff
+        push_u32(&mut cmds, 1); // nsyms
+        push_u32(&mut cmds, STRTAB as u32); // stroff
+        push_u32(&mut cmds, 8); // strsize
+
+        push_u32(&mut cmds, 0xB); // LC_DYSYMTAB
+        push_u32(&mut cmds, 80);
+        for v in [
+            0,
+            0,
+            0,
+            0,
+            0,
+            1,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            INDIRECT_SYMBOLS as u32,
+            2,
+            0,
+            0,
+            0,
+            0,
+        ] {
+            push_u32(&mut cmds, v);
+        }
+
+        push_u32(&mut cmds, 0x8000_0028); // LC_MAIN
+        push_u32(&mut cmds, 24);
+        push_u64(&mut cmds, MAIN_OFFSET); // entryoff
+        push_u64(&mut cmds, 0); // stacksize
+
+        push_u32(&mut cmds, 0x26); // LC_FUNCTION_STARTS
+        push_u32(&mut cmds, 16);
+        push_u32(&mut cmds, FUNCTION_STARTS as u32); // dataoff
+        push_u32(&mut cmds, 0x10); // datasize
+
+        let mut buf = vec![];
+        push_u32(&mut buf, 0xFEED_FACF); // MH_MAGIC_64
+        push_u32(&mut buf, 0x0100_0007); // CPU_TYPE_X86_64
+        push_u32(&mut buf, 0x3); // CPU_SUBTYPE_X86_64_ALL
+        push_u32(&mut buf, 0x2); // MH_EXECUTE
+        push_u32(&mut buf, 9); // ncmds
+        push_u32(&mut buf, cmds.len() as u32);
+        push_u32(&mut buf, 0x0020_0085); // flags: MH_NOUNDEFS | MH_DYLDLINK | MH_TWOLEVEL | MH_PIE
+        push_u32(&mut buf, 0); // reserved
+        buf.extend_from_slice(&cmds);
+        assert!(buf.len() < MAIN_OFFSET as usize);
+        buf.resize(LINKEDIT as usize + 0x40, 0);
+
+        // main:
+        //   call helper
+        //   xor eax, eax
+        //   ret
+        let main = MAIN_OFFSET as usize;
+        buf[main..main + 8].copy_from_slice(b"\xE8\x0B\x00\x00\x00\x31\xC0\xC3");
+        // helper:
+        //   call _exit
+        //   ret
+        let helper = HELPER_OFFSET as usize;
+        buf[helper..helper + 6].copy_from_slice(b"\xE8\x0B\x00\x00\x00\xC3");
+        // _exit stub:
+        //   jmp [rip+0xBDA]  ; __la_symbol_ptr
+        let stub = STUB_OFFSET as usize;
+        buf[stub..stub + 6].copy_from_slice(b"\xFF\x25\xDA\x0B\x00\x00");
+
+        // function starts: ULEB128 deltas from the start of __TEXT, terminated by zero.
+        let function_starts = FUNCTION_STARTS as usize;
+        buf[function_starts..function_starts + 3].copy_from_slice(b"\x80\x08\x10");
+
+        // nlist_64 for `_exit`: N_UNDF | N_EXT, library ordinal 1.
+        let mut symtab = vec![];
+        push_u32(&mut symtab, 1); // n_strx
+        symtab.extend_from_slice(&[0x01, 0x00]); // n_type, n_sect
+        symtab.extend_from_slice(&0x0100u16.to_le_bytes()); // n_desc
+        push_u64(&mut symtab, 0); // n_value
+        buf[SYMTAB as usize..SYMTAB as usize + 0x10].copy_from_slice(&symtab);
+        buf[STRTAB as usize..STRTAB as usize + 8].copy_from_slice(b"\x00_exit\x00\x00");
+
+        // indirect symbols: the stub, then the lazy pointer, both `_exit`.
+        let indirect_symbols = INDIRECT_SYMBOLS as usize;
+        buf[indirect_symbols..indirect_symbols + 8].copy_from_slice(&[0u8; 8]);
+
+        buf
+    }
+
+    /// wrap the given thin image in a universal binary with a single slice.
+    pub fn build_fat(thin: &[u8]) -> Vec<u8> {
+        let mut buf = vec![];
+        buf.extend_from_slice(b"\xCA\xFE\xBA\xBE"); // FAT_MAGIC
+        buf.extend_from_slice(&1u32.to_be_bytes()); // nfat_arch
+        buf.extend_from_slice(&0x0100_0007u32.to_be_bytes()); // cputype
+        buf.extend_from_slice(&0x3u32.to_be_bytes()); // cpusubtype
+        buf.extend_from_slice(&0x1000u32.to_be_bytes()); // offset
+        buf.extend_from_slice(&(thin.len() as u32).to_be_bytes()); // size
+        buf.extend_from_slice(&12u32.to_be_bytes()); // align
+        buf.resize(0x1000, 0);
+        buf.extend_from_slice(thin);
+        buf
+    }
+
+    #[test]
+    fn base_address() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        assert_eq!(TEXT_VMADDR, macho.module.address_space.base_address);
+        // __PAGEZERO is not mapped.
+        assert_eq!(3, macho.module.sections.len());
+        assert_eq!("__TEXT", macho.module.sections[0].name);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mach_header() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        assert_eq!(0xFEED_FACF, macho.module.address_space.read_u32(TEXT_VMADDR)?);
+        assert_eq!(0xE8, macho.module.address_space.read_u8(TEXT_VMADDR + HELPER_OFFSET)?);
+
+        assert!(macho.module.probe_va(TEXT_VMADDR + MAIN_OFFSET, Permissions::X));
+        assert!(!macho.module.probe_va(TEXT_VMADDR + LAZY_POINTER_OFFSET, Permissions::X));
+        assert!(macho.module.probe_va(TEXT_VMADDR + LAZY_POINTER_OFFSET, Permissions::W));
+
+        Ok(())
+    }
+
+    #[test]
+    fn section_headers() -> Result<()> {
+        let buf = build_macho();
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        assert_eq!(3, macho.section_headers.len());
+        assert_eq!("__stubs", macho.section_headers[1].sectname);
+        assert_eq!(
+            goblin::mach::constants::S_SYMBOL_STUBS,
+            macho.section_headers[1].section_type()
+        );
+        assert_eq!(6, macho.section_headers[1].reserved2);
+        assert_eq!(vec![0, 0], macho.indirect_symbols);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fat() -> Result<()> {
+        let buf = build_fat(&build_macho());
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+
+        assert_eq!(TEXT_VMADDR, macho.module.address_space.base_address);
+        assert_eq!(0xFEED_FACF, macho.module.address_space.read_u32(TEXT_VMADDR)?);
+        assert_eq!(build_macho(), macho.buf);
+
+        Ok(())
+    }
+
+    #[test]
+    fn java_class() -> Result<()> {
+        // like a class file, with version 52.0 where nfat_arch would be.
+        let mut buf = b"\xCA\xFE\xBA\xBE\x00\x00\x00\x34".to_vec();
+        buf.resize(0x1000, 0);
+
+        assert!(!crate::loader::macho::is_macho(&buf));
+        assert!(crate::loader::macho::is_macho(&build_fat(&build_macho())));
+        assert!(crate::loader::macho::MachO::from_bytes(&buf).is_err());
+
+        Ok(())
+    }
+
+    /// Find the `vmsize` field of the given segment's load command.
+    fn find_segment_vmsize(buf: &[u8], segname: &str) -> usize {
+        let mut name = [0u8; 16];
+        name[..segname.len()].copy_from_slice(segname.as_bytes());
+        let offset = buf.windows(16).position(|window| window == name).unwrap();
+        // segment_command_64: cmd cmdsize segname[16] vmaddr vmsize
+        offset + 0x18
+    }
+
+    #[test]
+    fn segment_size() -> Result<()> {
+        // the zero-fill of __DATA is truncated,
+        // rather than sizing an allocation by the untrusted vmsize.
+        let mut buf = build_macho();
+        let offset = find_segment_vmsize(&buf, "__DATA");
+        buf[offset..offset + 8].copy_from_slice(&0x10_0000_0000u64.to_le_bytes());
+        let macho = crate::loader::macho::MachO::from_bytes(&buf)?;
+        let data = macho.module.sections.iter().find(|sec| sec.name == "__DATA").unwrap();
+        assert!(data.virtual_range.end - data.virtual_range.start <= 0x1000 + super::MAX_ZERO_FILL);
+
+        // and a segment can't wrap around the address space.
+        let mut buf = build_macho();
+        let offset = find_segment_vmsize(&buf, "__DATA");
+        buf[offset - 8..offset].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(crate::loader::macho::MachO::from_bytes(&buf).is_err());
+
+        Ok(())
+    }
+}
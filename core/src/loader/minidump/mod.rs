@@ -0,0 +1,651 @@
+//! Load Windows minidumps (`MDMP`), such as crash dumps and process memory
+//! dumps.
+//!
+//! A minidump is a directory of streams. We use:
+//!   - SystemInfoStream: the processor architecture,
+//!   - ModuleListStream: the images loaded into the process,
+//!   - ThreadListStream: each thread's register context,
+//!   - MemoryListStream/Memory64ListStream: the captured memory ranges.
+//!
+//! A process address space is far too sparse to map into a single `Module`,
+//! so callers pick a window: either an arbitrary range via `load_range`,
+//! or a module's image via `load_pe`, which reconstructs the in-memory image
+//! as a PE at its real base address.
+//!
+//! ref: https://learn.microsoft.com/en-us/windows/win32/api/minidumpapiset/
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use log::{debug, warn};
+use thiserror::Error;
+
+use crate::{
+    arch::Arch,
+    aspace::RelativeAddressSpace,
+    loader::pe::PE,
+    module::{Module, Permissions, Section},
+    util, VA,
+};
+
+#[derive(Error, Debug)]
+pub enum MinidumpError {
+    #[error("format not supported: {0}")]
+    FormatNotSupported(String),
+
+    #[error("malformed minidump: {0}")]
+    MalformedMinidump(String),
+}
+
+const PAGE_SIZE: u64 = 0x1000;
+
+pub const MINIDUMP_SIGNATURE: &[u8] = b"MDMP";
+
+const THREAD_LIST_STREAM: u32 = 3;
+const MODULE_LIST_STREAM: u32 = 4;
+const MEMORY_LIST_STREAM: u32 = 5;
+const SYSTEM_INFO_STREAM: u32 = 7;
+const MEMORY64_LIST_STREAM: u32 = 9;
+
+const PROCESSOR_ARCHITECTURE_INTEL: u16 = 0;
+const PROCESSOR_ARCHITECTURE_AMD64: u16 = 9;
+
+/// sizeof(MINIDUMP_THREAD)
+const THREAD_SIZE: usize = 48;
+/// sizeof(MINIDUMP_MODULE)
+const MODULE_SIZE: usize = 108;
+/// sizeof(MINIDUMP_MEMORY_DESCRIPTOR)
+const MEMORY_DESCRIPTOR_SIZE: usize = 16;
+/// sizeof(MINIDUMP_MEMORY_DESCRIPTOR64)
+const MEMORY_DESCRIPTOR64_SIZE: usize = 16;
+
+/// A range of process memory captured in the dump.
+#[derive(Debug, Clone)]
+pub struct MemoryRegion {
+    pub address: VA,
+    pub size:    u64,
+    /// offset of the data within the dump file.
+    pub offset:  u64,
+}
+
+/// An image loaded into the process.
+#[derive(Debug, Clone)]
+pub struct MinidumpModule {
+    /// full path, like `C:\Windows\System32\kernel32.dll`.
+    pub name:         String,
+    pub base_address: VA,
+    pub size:         u64,
+    pub checksum:     u32,
+    pub timestamp:    u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MinidumpThread {
+    pub id:  u32,
+    /// RIP or EIP, if the thread context was captured.
+    pub pc:  Option<VA>,
+    /// RSP or ESP, if the thread context was captured.
+    pub sp:  Option<VA>,
+    pub teb: VA,
+}
+
+pub struct Minidump {
+    pub buf:     Vec<u8>,
+    pub arch:    Arch,
+    /// ordered as in the dump, so the first entry is typically the main
+    /// executable.
+    pub modules: Vec<MinidumpModule>,
+    pub threads: Vec<MinidumpThread>,
+    /// sorted by address.
+    pub memory:  Vec<MemoryRegion>,
+}
+
+impl Minidump {
+    pub fn from_bytes(buf: &[u8]) -> Result<Minidump> {
+        load_minidump(buf)
+    }
+
+    /// Read the given range of process memory.
+    /// Bytes that weren't captured in the dump, or that lie beyond the end of
+    /// the address space, are zero.
+    pub fn read_memory(&self, address: VA, size: u64) -> Vec<u8> {
+        let mut buf = vec![0u8; size as usize];
+        let end = address.saturating_add(size);
+
+        for region in self.memory.iter() {
+            let region_end = region.address + region.size;
+            if region_end <= address || region.address >= end {
+                continue;
+            }
+
+            let start = std::cmp::max(address, region.address);
+            let stop = std::cmp::min(end, region_end);
+
+            let src_start = (region.offset + (start - region.address)) as usize;
+            let src_end = std::cmp::min(src_start + (stop - start) as usize, self.buf.len());
+            if src_start >= src_end {
+                continue;
+            }
+
+            let dst_start = (start - address) as usize;
+            buf[dst_start..dst_start + (src_end - src_start)].copy_from_slice(&self.buf[src_start..src_end]);
+        }
+
+        buf
+    }
+
+    /// Map the captured memory found within the given range into a module,
+    /// at its real virtual addresses.
+    ///
+    /// The memory streams don't describe page protections,
+    /// so each captured region is mapped RWX.
+    pub fn load_range(&self, start: VA, end: VA) -> Result<Module> {
+        let base_address = start - start % PAGE_SIZE;
+        let Some(end) = end.checked_next_multiple_of(PAGE_SIZE) else {
+            return Err(MinidumpError::FormatNotSupported("range beyond the address space".to_string()).into());
+        };
+        if end <= base_address {
+            return Err(MinidumpError::FormatNotSupported("empty range".to_string()).into());
+        }
+
+        let mut address_space = RelativeAddressSpace::with_capacity(end - base_address);
+        let mut sections = vec![];
+
+        for region in self.memory.iter() {
+            let region_end = region.address + region.size;
+            if region_end <= base_address || region.address >= end {
+                continue;
+            }
+
+            let vstart = std::cmp::max(base_address, region.address);
+            let vend = std::cmp::min(end, region_end);
+
+            // pages may be shared with neighboring regions,
+            // so read the whole page range rather than just this region.
+            // this is page aligned, so don't use `writezx`, which would map an extra page.
+            let page_start = util::align(vstart + 1, PAGE_SIZE) - PAGE_SIZE;
+            let page_end = util::align(vend, PAGE_SIZE);
+            let buf = self.read_memory(page_start, page_end - page_start);
+            address_space.map.write(page_start - base_address, &buf)?;
+
+            sections.push(Section {
+                physical_range: std::ops::Range {
+                    start: region.offset + (vstart - region.address),
+                    end:   region.offset + (vend - region.address),
+                },
+                virtual_range:  std::ops::Range {
+                    start: vstart,
+                    end:   vend,
+                },
+                permissions:    Permissions::RWX,
+                name:           format!("memory_{vstart:x}"),
+            });
+        }
+
+        Ok(Module {
+            arch: self.arch,
+            sections,
+            section_headers: Default::default(),
+            address_space: address_space.into_absolute(base_address)?,
+        })
+    }
+
+    /// Reconstruct the image of the given module as a PE loaded at its real
+    /// base address.
+    pub fn load_pe(&self, module: &MinidumpModule) -> Result<PE> {
+        let image = self.read_memory(module.base_address, module.size);
+        PE::from_mapped_bytes(&image, Some(module.base_address))
+    }
+
+    /// Find the module whose image contains the given address.
+    pub fn module_by_address(&self, va: VA) -> Option<&MinidumpModule> {
+        self.modules
+            .iter()
+            .find(|module| module.base_address <= va && va - module.base_address < module.size)
+    }
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Result<u16> {
+    match buf.get(offset..offset + 2) {
+        Some(b) => Ok(LittleEndian::read_u16(b)),
+        None => Err(MinidumpError::MalformedMinidump(format!("read out of bounds: {offset:#x}")).into()),
+    }
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Result<u32> {
+    match buf.get(offset..offset + 4) {
+        Some(b) => Ok(LittleEndian::read_u32(b)),
+        None => Err(MinidumpError::MalformedMinidump(format!("read out of bounds: {offset:#x}")).into()),
+    }
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> Result<u64> {
+    match buf.get(offset..offset + 8) {
+        Some(b) => Ok(LittleEndian::read_u64(b)),
+        None => Err(MinidumpError::MalformedMinidump(format!("read out of bounds: {offset:#x}")).into()),
+    }
+}
+
+/// read a MINIDUMP_STRING, which is a length-prefixed UTF-16 string.
+fn read_string(buf: &[u8], offset: usize) -> Result<String> {
+    let length = read_u32(buf, offset)? as usize;
+    let Some(data) = buf.get(offset + 4..offset + 4 + length) else {
+        return Err(MinidumpError::MalformedMinidump(format!("string out of bounds: {offset:#x}")).into());
+    };
+
+    let chars = data.chunks_exact(2).map(LittleEndian::read_u16).collect::<Vec<u16>>();
+    Ok(String::from_utf16_lossy(&chars))
+}
+
+struct Stream {
+    type_:  u32,
+    offset: usize,
+    size:   usize,
+}
+
+fn get_streams(buf: &[u8]) -> Result<Vec<Stream>> {
+    let count = read_u32(buf, 0x8)? as usize;
+    let directory = read_u32(buf, 0xC)? as usize;
+
+    let mut streams = vec![];
+    for i in 0..count {
+        let entry = directory + i * 12;
+        streams.push(Stream {
+            type_:  read_u32(buf, entry)?,
+            size:   read_u32(buf, entry + 4)? as usize,
+            offset: read_u32(buf, entry + 8)? as usize,
+        });
+    }
+
+    Ok(streams)
+}
+
+fn get_arch(buf: &[u8], streams: &[Stream]) -> Result<Arch> {
+    let Some(stream) = streams.iter().find(|s| s.type_ == SYSTEM_INFO_STREAM) else {
+        return Err(MinidumpError::FormatNotSupported("no SystemInfoStream".to_string()).into());
+    };
+
+    match read_u16(buf, stream.offset)? {
+        PROCESSOR_ARCHITECTURE_INTEL => Ok(Arch::X32),
+        PROCESSOR_ARCHITECTURE_AMD64 => Ok(Arch::X64),
+        arch => Err(MinidumpError::FormatNotSupported(format!("processor architecture: {arch}")).into()),
+    }
+}
+
+fn get_modules(buf: &[u8], streams: &[Stream]) -> Result<Vec<MinidumpModule>> {
+    let mut modules = vec![];
+
+    for stream in streams.iter().filter(|s| s.type_ == MODULE_LIST_STREAM) {
+        let count = read_u32(buf, stream.offset)? as usize;
+        for i in 0..count {
+            let entry = stream.offset + 4 + i * MODULE_SIZE;
+            let module = MinidumpModule {
+                base_address: read_u64(buf, entry)?,
+                size:         read_u32(buf, entry + 8)? as u64,
+                checksum:     read_u32(buf, entry + 12)?,
+                timestamp:    read_u32(buf, entry + 16)?,
+                name:         read_string(buf, read_u32(buf, entry + 20)? as usize)?,
+            };
+
+            let Some(end) = module.base_address.checked_add(module.size) else {
+                warn!(
+                    "minidump: module beyond the address space: {:#x} {}",
+                    module.base_address, module.name
+                );
+                continue;
+            };
+
+            debug!(
+                "minidump: module: {:#x} - {:#x} {}",
+                module.base_address, end, module.name
+            );
+            modules.push(module);
+        }
+    }
+
+    Ok(modules)
+}
+
+fn get_threads(buf: &[u8], streams: &[Stream], arch: Arch) -> Result<Vec<MinidumpThread>> {
+    // offsets of the program counter and stack pointer within CONTEXT.
+    let (pc_offset, sp_offset) = match arch {
+        Arch::X32 => (0xB8, 0xC4),
+        Arch::X64 => (0xF8, 0x98),
+    };
+
+    let mut threads = vec![];
+
+    for stream in streams.iter().filter(|s| s.type_ == THREAD_LIST_STREAM) {
+        let count = read_u32(buf, stream.offset)? as usize;
+        for i in 0..count {
+            let entry = stream.offset + 4 + i * THREAD_SIZE;
+            let context_size = read_u32(buf, entry + 40)? as usize;
+            let context = read_u32(buf, entry + 44)? as usize;
+
+            let read_register = |offset: usize| -> Option<VA> {
+                if offset + arch.pointer_size() > context_size {
+                    return None;
+                }
+                match arch {
+                    Arch::X32 => read_u32(buf, context + offset).ok().map(|v| v as VA),
+                    Arch::X64 => read_u64(buf, context + offset).ok(),
+                }
+            };
+
+            let thread = MinidumpThread {
+                id:  read_u32(buf, entry)?,
+                teb: read_u64(buf, entry + 16)?,
+                pc:  read_register(pc_offset),
+                sp:  read_register(sp_offset),
+            };
+
+            debug!("minidump: thread: {}: pc: {:#x?}", thread.id, thread.pc);
+            threads.push(thread);
+        }
+    }
+
+    Ok(threads)
+}
+
+fn get_memory(buf: &[u8], streams: &[Stream]) -> Result<Vec<MemoryRegion>> {
+    let mut memory = vec![];
+
+    for stream in streams.iter() {
+        match stream.type_ {
+            MEMORY_LIST_STREAM => {
+                let count = read_u32(buf, stream.offset)? as usize;
+                for i in 0..count {
+                    let entry = stream.offset + 4 + i * MEMORY_DESCRIPTOR_SIZE;
+                    let region = MemoryRegion {
+                        address: read_u64(buf, entry)?,
+                        size:    read_u32(buf, entry + 8)? as u64,
+                        offset:  read_u32(buf, entry + 12)? as u64,
+                    };
+
+                    if region.address.checked_add(region.size).is_none() {
+                        warn!(
+                            "minidump: memory region beyond the address space: {:#x}",
+                            region.address
+                        );
+                        continue;
+                    }
+
+                    memory.push(region);
+                }
+            }
+            MEMORY64_LIST_STREAM => {
+                // full memory dumps store the data contiguously, starting at BaseRva.
+                let count = read_u64(buf, stream.offset)? as usize;
+                let mut offset = read_u64(buf, stream.offset + 8)?;
+                for i in 0..count {
+                    let entry = stream.offset + 16 + i * MEMORY_DESCRIPTOR64_SIZE;
+                    let region = MemoryRegion {
+                        address: read_u64(buf, entry)?,
+                        size: read_u64(buf, entry + 8)?,
+                        offset,
+                    };
+
+                    // the data of the following regions is found after this one,
+                    // so they can't be found, either.
+                    let Some(next_offset) = offset.checked_add(region.size) else {
+                        warn!(
+                            "minidump: memory region beyond the end of the dump: {:#x}",
+                            region.address
+                        );
+                        break;
+                    };
+                    offset = next_offset;
+
+                    if region.address.checked_add(region.size).is_none() {
+                        warn!(
+                            "minidump: memory region beyond the address space: {:#x}",
+                            region.address
+                        );
+                        continue;
+                    }
+
+                    memory.push(region);
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    memory.sort_by_key(|region| region.address);
+    debug!("minidump: found {} memory regions", memory.len());
+
+    Ok(memory)
+}
+
+fn load_minidump(buf: &[u8]) -> Result<Minidump> {
+    if !buf.starts_with(MINIDUMP_SIGNATURE) {
+        return Err(MinidumpError::FormatNotSupported("missing MDMP signature".to_string()).into());
+    }
+
+    let streams = get_streams(buf)?;
+    for stream in streams.iter() {
+        debug!(
+            "minidump: stream: type: {} offset: {:#x} size: {:#x}",
+            stream.type_, stream.offset, stream.size
+        );
+    }
+
+    let arch = get_arch(buf, &streams)?;
+    debug!("minidump: arch: {:?}", arch);
+
+    let modules = get_modules(buf, &streams)?;
+    let threads = get_threads(buf, &streams, arch)?;
+    let memory = get_memory(buf, &streams)?;
+
+    debug!("minidump: loaded");
+    Ok(Minidump {
+        buf: buf.to_vec(),
+        arch,
+        modules,
+        threads,
+        memory,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use crate::aspace::AddressSpace;
+
+    pub use crate::loader::pe::tests::{build_mapped_pe, ENTRY_RVA, HELPER_RVA, IMAGE_SIZE};
+
+    pub const MODULE_BASE: u64 = 0x7FF6_1234_0000;
+    pub const THREAD_PC_RVA: u64 = HELPER_RVA;
+    pub const HEAP_ADDRESS: u64 = 0x1_0000_0000;
+
+    /// Build an x64 minidump of a process with a single module, `test.exe`,
+    /// and a single thread, which is executing at `THREAD_PC_RVA` in the
+    /// module. Memory is captured via a Memory64ListStream for the image, and
+    /// a MemoryListStream for a page of heap.
+    pub fn build_minidump() -> Vec<u8> {
+        let image = build_mapped_pe();
+        let heap = vec![0x41u8; 0x1000];
+        let name = "C:\\test.exe".encode_utf16().collect::<Vec<u16>>();
+
+        let mut buf = vec![];
+        // MINIDUMP_HEADER
+        buf.extend_from_slice(b"MDMP");
+        buf.write_u32::<LittleEndian>(0xA793).unwrap(); // Version
+        buf.write_u32::<LittleEndian>(5).unwrap(); // NumberOfStreams
+        buf.write_u32::<LittleEndian>(0x20).unwrap(); // StreamDirectoryRva
+        buf.write_u32::<LittleEndian>(0).unwrap(); // CheckSum
+        buf.write_u32::<LittleEndian>(0).unwrap(); // TimeDateStamp
+        buf.write_u64::<LittleEndian>(0).unwrap(); // Flags
+
+        // layout, after the stream directory at 0x20:
+        let system_info = 0x100usize;
+        let module_list = 0x200usize;
+        let module_name = 0x300usize;
+        let thread_list = 0x400usize;
+        let context = 0x500usize;
+        let memory_list = 0x1000usize;
+        let memory64_list = 0x1100usize;
+        let heap_data = 0x2000usize;
+        let image_data = 0x3000usize;
+
+        // MINIDUMP_DIRECTORY
+        for (type_, size, offset) in [
+            (super::SYSTEM_INFO_STREAM, 56, system_info),
+            (super::MODULE_LIST_STREAM, 4 + super::MODULE_SIZE, module_list),
+            (super::THREAD_LIST_STREAM, 4 + super::THREAD_SIZE, thread_list),
+            (
+                super::MEMORY_LIST_STREAM,
+                4 + super::MEMORY_DESCRIPTOR_SIZE,
+                memory_list,
+            ),
+            (
+                super::MEMORY64_LIST_STREAM,
+                16 + super::MEMORY_DESCRIPTOR64_SIZE,
+                memory64_list,
+            ),
+        ] {
+            buf.write_u32::<LittleEndian>(type_).unwrap();
+            buf.write_u32::<LittleEndian>(size as u32).unwrap();
+            buf.write_u32::<LittleEndian>(offset as u32).unwrap();
+        }
+
+        buf.resize(image_data + image.len(), 0);
+
+        // MINIDUMP_SYSTEM_INFO
+        buf[system_info..system_info + 2].copy_from_slice(&super::PROCESSOR_ARCHITECTURE_AMD64.to_le_bytes());
+
+        // MINIDUMP_MODULE_LIST
+        let mut m = vec![];
+        m.write_u32::<LittleEndian>(1).unwrap(); // NumberOfModules
+        m.write_u64::<LittleEndian>(MODULE_BASE).unwrap(); // BaseOfImage
+        m.write_u32::<LittleEndian>(IMAGE_SIZE as u32).unwrap(); // SizeOfImage
+        m.write_u32::<LittleEndian>(0).unwrap(); // CheckSum
+        m.write_u32::<LittleEndian>(0).unwrap(); // TimeDateStamp
+        m.write_u32::<LittleEndian>(module_name as u32).unwrap(); // ModuleNameRva
+        m.resize(4 + super::MODULE_SIZE, 0);
+        buf[module_list..module_list + m.len()].copy_from_slice(&m);
+
+        // MINIDUMP_STRING
+        let mut s = vec![];
+        s.write_u32::<LittleEndian>((name.len() * 2) as u32).unwrap();
+        for c in name.iter() {
+            s.write_u16::<LittleEndian>(*c).unwrap();
+        }
+        buf[module_name..module_name + s.len()].copy_from_slice(&s);
+
+        // MINIDUMP_THREAD_LIST
+        let mut t = vec![];
+        t.write_u32::<LittleEndian>(1).unwrap(); // NumberOfThreads
+        t.write_u32::<LittleEndian>(0x1234).unwrap(); // ThreadId
+        t.write_u32::<LittleEndian>(0).unwrap(); // SuspendCount
+        t.write_u32::<LittleEndian>(0).unwrap(); // PriorityClass
+        t.write_u32::<LittleEndian>(0).unwrap(); // Priority
+        t.write_u64::<LittleEndian>(0x7FF_FFFD_E000).unwrap(); // Teb
+        t.extend_from_slice(&[0u8; super::MEMORY_DESCRIPTOR_SIZE]); // Stack
+        t.write_u32::<LittleEndian>(0x4D0).unwrap(); // ThreadContext.DataSize
+        t.write_u32::<LittleEndian>(context as u32).unwrap(); // ThreadContext.Rva
+        buf[thread_list..thread_list + t.len()].copy_from_slice(&t);
+
+        // CONTEXT: Rsp, Rip
+        buf[context + 0x98..context + 0xA0].copy_from_slice(&0x7FF_FFFF_F000u64.to_le_bytes());
+        buf[context + 0xF8..context + 0x100].copy_from_slice(&(MODULE_BASE + THREAD_PC_RVA).to_le_bytes());
+
+        // MINIDUMP_MEMORY_LIST
+        let mut l = vec![];
+        l.write_u32::<LittleEndian>(1).unwrap(); // NumberOfMemoryRanges
+        l.write_u64::<LittleEndian>(HEAP_ADDRESS).unwrap(); // StartOfMemoryRange
+        l.write_u32::<LittleEndian>(heap.len() as u32).unwrap(); // DataSize
+        l.write_u32::<LittleEndian>(heap_data as u32).unwrap(); // Rva
+        buf[memory_list..memory_list + l.len()].copy_from_slice(&l);
+        buf[heap_data..heap_data + heap.len()].copy_from_slice(&heap);
+
+        // MINIDUMP_MEMORY64_LIST
+        let mut l = vec![];
+        l.write_u64::<LittleEndian>(1).unwrap(); // NumberOfMemoryRanges
+        l.write_u64::<LittleEndian>(image_data as u64).unwrap(); // BaseRva
+        l.write_u64::<LittleEndian>(MODULE_BASE).unwrap(); // StartOfMemoryRange
+        l.write_u64::<LittleEndian>(image.len() as u64).unwrap(); // DataSize
+        buf[memory64_list..memory64_list + l.len()].copy_from_slice(&l);
+        buf[image_data..image_data + image.len()].copy_from_slice(&image);
+
+        buf
+    }
+
+    #[test]
+    fn streams() -> Result<()> {
+        let buf = build_minidump();
+        let dump = crate::loader::minidump::Minidump::from_bytes(&buf)?;
+
+        assert!(matches!(dump.arch, crate::arch::Arch::X64));
+
+        assert_eq!(1, dump.modules.len());
+        assert_eq!("C:\\test.exe", dump.modules[0].name);
+        assert_eq!(MODULE_BASE, dump.modules[0].base_address);
+        assert_eq!(IMAGE_SIZE, dump.modules[0].size);
+
+        assert_eq!(1, dump.threads.len());
+        assert_eq!(0x1234, dump.threads[0].id);
+        assert_eq!(Some(MODULE_BASE + THREAD_PC_RVA), dump.threads[0].pc);
+        assert_eq!(Some(0x7FF_FFFF_F000), dump.threads[0].sp);
+
+        assert_eq!(2, dump.memory.len());
+        assert_eq!(HEAP_ADDRESS, dump.memory[0].address);
+        assert_eq!(MODULE_BASE, dump.memory[1].address);
+
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_bounds() -> Result<()> {
+        let mut buf = build_minidump();
+        // MINIDUMP_MODULE.BaseOfImage
+        buf[0x204..0x20C].copy_from_slice(&(u64::MAX - 0x10).to_le_bytes());
+        // MINIDUMP_MEMORY_DESCRIPTOR.StartOfMemoryRange
+        buf[0x1004..0x100C].copy_from_slice(&(u64::MAX - 0x10).to_le_bytes());
+        // MINIDUMP_MEMORY_DESCRIPTOR64.DataSize
+        buf[0x1118..0x1120].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        // entries that wrap around are skipped.
+        let dump = crate::loader::minidump::Minidump::from_bytes(&buf)?;
+        assert!(dump.modules.is_empty());
+        assert!(dump.memory.is_empty());
+
+        assert!(dump.load_range(u64::MAX - 0x10, u64::MAX).is_err());
+        assert_eq!(vec![0u8; 0x20], dump.read_memory(u64::MAX - 0x10, 0x20));
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_range() -> Result<()> {
+        let buf = build_minidump();
+        let dump = crate::loader::minidump::Minidump::from_bytes(&buf)?;
+
+        let module = dump.load_range(HEAP_ADDRESS, HEAP_ADDRESS + 0x2000)?;
+        assert_eq!(HEAP_ADDRESS, module.address_space.base_address);
+        assert_eq!(1, module.sections.len());
+        assert_eq!(0x41, module.address_space.read_u8(HEAP_ADDRESS + 0x10)?);
+        // not captured, so not mapped.
+        assert!(module.address_space.read_u8(HEAP_ADDRESS + 0x1000).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_pe() -> Result<()> {
+        let buf = build_minidump();
+        let dump = crate::loader::minidump::Minidump::from_bytes(&buf)?;
+
+        let module = dump.module_by_address(MODULE_BASE + THREAD_PC_RVA).unwrap();
+        let pe = dump.load_pe(module)?;
+
+        assert_eq!(MODULE_BASE, pe.module.address_space.base_address);
+        assert_eq!(0xE8, pe.module.address_space.read_u8(MODULE_BASE + ENTRY_RVA)?);
+        assert_eq!(0x90, pe.module.address_space.read_u8(MODULE_BASE + THREAD_PC_RVA)?);
+
+        let text = pe.module.sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(MODULE_BASE + 0x1000, text.virtual_range.start);
+
+        Ok(())
+    }
+}
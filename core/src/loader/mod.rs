@@ -6,6 +6,11 @@
 // somewhere we should introduce a generic trait over the common functionality
 // of loading/workspacing.
 
+pub mod ar;
 pub mod coff;
 pub mod pe;
 pub mod elf;
+pub mod macho;
+pub mod minidump;
+pub mod pdb;
+pub mod shellcode;
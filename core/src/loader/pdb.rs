@@ -0,0 +1,448 @@
+//! Parse the symbols from a Program Database (PDB),
+//! the debug information produced by the Microsoft toolchain.
+//!
+//! We read just enough to recover names and function starts:
+//!   - the MSF container, which splits the file into streams of blocks,
+//!   - the PDB information stream, with the GUID that identifies the build,
+//!   - the DBI stream, with the age and the indices of the other streams,
+//!   - the symbol record stream, with public and global data symbols, and
+//!   - the module streams, with procedure symbols.
+//!
+//! ref: https://llvm.org/docs/PDB/index.html
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use log::debug;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PDBError {
+    #[error("format not supported: {0}")]
+    FormatNotSupported(String),
+
+    #[error("malformed PDB file: {0}")]
+    MalformedPDBFile(String),
+}
+
+pub const MSF_MAGIC: &[u8] = b"Microsoft C/C++ MSF 7.00\r\n\x1aDS\x00\x00\x00";
+
+const PDB_STREAM: usize = 1;
+const DBI_STREAM: usize = 3;
+const DBI_HEADER_SIZE: usize = 0x40;
+const MODULE_INFO_SIZE: usize = 0x40;
+const NIL_STREAM_SIZE: u32 = 0xFFFF_FFFF;
+const NIL_STREAM_INDEX: u16 = 0xFFFF;
+
+const S_LDATA32: u16 = 0x110C;
+const S_GDATA32: u16 = 0x110D;
+const S_PUB32: u16 = 0x110E;
+const S_LPROC32: u16 = 0x110F;
+const S_GPROC32: u16 = 0x1110;
+const S_LPROC32_ID: u16 = 0x1146;
+const S_GPROC32_ID: u16 = 0x1147;
+
+/// CV_PUBSYMFLAGS.fFunction
+const CVPSF_FUNCTION: u32 = 0x2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PDBSymbolKind {
+    /// S_PUB32: a linker symbol, such as `?foo@@YAXXZ`,
+    /// which may be flagged as a function.
+    Public { function: bool },
+    /// S_GDATA32, S_LDATA32: a global or static variable.
+    Data,
+    /// S_GPROC32, S_LPROC32: a function.
+    Procedure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PDBSymbol {
+    pub kind:    PDBSymbolKind,
+    pub name:    String,
+    /// the 1-based index of the PE section that contains the symbol.
+    pub section: u16,
+    /// the offset of the symbol from the start of its section.
+    pub offset:  u32,
+}
+
+pub struct PDB {
+    /// identifies the build, as found in the CodeView record of the PE.
+    pub guid:    [u8; 16],
+    /// incremented each time the PDB is written,
+    /// as found in the CodeView record of the PE.
+    pub age:     u32,
+    pub symbols: Vec<PDBSymbol>,
+}
+
+impl PDB {
+    pub fn from_bytes(buf: &[u8]) -> Result<PDB> {
+        load_pdb(buf)
+    }
+
+    /// Does this PDB describe the build with the given CodeView GUID and age?
+    pub fn matches(&self, guid: &[u8; 16], age: u32) -> bool {
+        self.guid == *guid && self.age == age
+    }
+}
+
+pub fn is_pdb(buf: &[u8]) -> bool {
+    buf.starts_with(MSF_MAGIC)
+}
+
+fn malformed(message: &str) -> anyhow::Error {
+    PDBError::MalformedPDBFile(message.to_string()).into()
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> Result<u16> {
+    buf.get(offset..offset + 2)
+        .map(LittleEndian::read_u16)
+        .ok_or_else(|| malformed("buffer too small"))
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Result<u32> {
+    buf.get(offset..offset + 4)
+        .map(LittleEndian::read_u32)
+        .ok_or_else(|| malformed("buffer too small"))
+}
+
+/// Read the NULL-terminated string at the given offset, without the NULL.
+fn read_cstr(buf: &[u8], offset: usize) -> Result<&[u8]> {
+    let buf = buf.get(offset..).ok_or_else(|| malformed("buffer too small"))?;
+    let end = buf
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| malformed("unterminated string"))?;
+    Ok(&buf[..end])
+}
+
+/// Reassemble each stream of the MSF container from its blocks.
+/// Unused stream indices are `None`.
+fn read_streams(buf: &[u8]) -> Result<Vec<Option<Vec<u8>>>> {
+    if !is_pdb(buf) {
+        return Err(PDBError::FormatNotSupported("not an MSF 7.00 file".to_string()).into());
+    }
+
+    let block_size = read_u32(buf, 0x20)? as usize;
+    let directory_size = read_u32(buf, 0x2C)? as usize;
+    let block_map_address = read_u32(buf, 0x34)? as usize;
+    if !matches!(block_size, 0x200 | 0x400 | 0x800 | 0x1000) {
+        return Err(malformed("invalid block size"));
+    }
+    debug!(
+        "pdb: block size: {:#x} directory size: {:#x}",
+        block_size, directory_size
+    );
+
+    let read_blocks = |blocks: &[u32], size: usize| -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(size);
+        for &block in blocks.iter() {
+            let start = block as usize * block_size;
+            let end = std::cmp::min(start + block_size, buf.len());
+            let chunk = buf.get(start..end).ok_or_else(|| malformed("block out of range"))?;
+            data.extend_from_slice(chunk);
+        }
+        if data.len() < size {
+            return Err(malformed("stream truncated"));
+        }
+        data.truncate(size);
+        Ok(data)
+    };
+
+    let directory_blocks = (0..directory_size.div_ceil(block_size))
+        .map(|i| read_u32(buf, block_map_address * block_size + i * 4))
+        .collect::<Result<Vec<_>>>()?;
+    let directory = read_blocks(&directory_blocks, directory_size)?;
+
+    let stream_count = read_u32(&directory, 0)? as usize;
+    let sizes = (0..stream_count)
+        .map(|i| read_u32(&directory, 4 + i * 4))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut offset = 4 + stream_count * 4;
+    let mut streams = Vec::with_capacity(stream_count);
+    for size in sizes.into_iter() {
+        if size == NIL_STREAM_SIZE {
+            streams.push(None);
+            continue;
+        }
+
+        let size = size as usize;
+        let block_count = size.div_ceil(block_size);
+        let blocks = (0..block_count)
+            .map(|i| read_u32(&directory, offset + i * 4))
+            .collect::<Result<Vec<_>>>()?;
+        offset += block_count * 4;
+
+        streams.push(Some(read_blocks(&blocks, size)?));
+    }
+
+    Ok(streams)
+}
+
+/// Collect the symbols we recognize from a sequence of CodeView symbol records.
+fn read_symbols(buf: &[u8], symbols: &mut Vec<PDBSymbol>) -> Result<()> {
+    let mut offset = 0x0;
+    while offset + 4 <= buf.len() {
+        // the length includes the kind, but not the length field itself.
+        let length = read_u16(buf, offset)? as usize;
+        let kind = read_u16(buf, offset + 2)?;
+        if length < 2 {
+            return Err(malformed("invalid symbol record length"));
+        }
+        let record = buf
+            .get(offset + 4..offset + 2 + length)
+            .ok_or_else(|| malformed("symbol record out of range"))?;
+
+        let symbol = match kind {
+            // PUBSYM32 and DATASYM32 share a layout:
+            // flags or type index, offset, section, name.
+            S_PUB32 | S_GDATA32 | S_LDATA32 => Some(PDBSymbol {
+                kind:    match kind {
+                    S_PUB32 => PDBSymbolKind::Public {
+                        function: read_u32(record, 0x0)? & CVPSF_FUNCTION != 0,
+                    },
+                    _ => PDBSymbolKind::Data,
+                },
+                offset:  read_u32(record, 0x4)?,
+                section: read_u16(record, 0x8)?,
+                name:    String::from_utf8_lossy(read_cstr(record, 0xA)?).to_string(),
+            }),
+            S_GPROC32 | S_LPROC32 | S_GPROC32_ID | S_LPROC32_ID => Some(PDBSymbol {
+                kind:    PDBSymbolKind::Procedure,
+                offset:  read_u32(record, 0x1C)?,
+                section: read_u16(record, 0x20)?,
+                name:    String::from_utf8_lossy(read_cstr(record, 0x23)?).to_string(),
+            }),
+            _ => None,
+        };
+
+        if let Some(symbol) = symbol {
+            symbols.push(symbol);
+        }
+
+        offset += 2 + length;
+    }
+
+    Ok(())
+}
+
+fn load_pdb(buf: &[u8]) -> Result<PDB> {
+    let streams = read_streams(buf)?;
+    let get_stream = |index: usize| streams.get(index).and_then(|stream| stream.as_deref());
+
+    let info = get_stream(PDB_STREAM).ok_or_else(|| malformed("missing PDB information stream"))?;
+    let mut guid = [0u8; 16];
+    guid.copy_from_slice(info.get(0xC..0x1C).ok_or_else(|| malformed("buffer too small"))?);
+    let mut age = read_u32(info, 0x8)?;
+
+    let mut symbols = vec![];
+    if let Some(dbi) = get_stream(DBI_STREAM) {
+        // the PE references the age found in the DBI stream,
+        // which may lag the age of the PDB information stream.
+        age = read_u32(dbi, 0x8)?;
+
+        let symbol_record_stream = read_u16(dbi, 0x14)?;
+        if symbol_record_stream != NIL_STREAM_INDEX {
+            if let Some(stream) = get_stream(symbol_record_stream as usize) {
+                read_symbols(stream, &mut symbols)?;
+            }
+        }
+
+        let module_info_end = DBI_HEADER_SIZE + read_u32(dbi, 0x18)? as usize;
+        let mut offset = DBI_HEADER_SIZE;
+        while offset + MODULE_INFO_SIZE <= module_info_end {
+            let module_stream = read_u16(dbi, offset + 0x22)?;
+            let symbols_size = read_u32(dbi, offset + 0x24)? as usize;
+
+            if module_stream != NIL_STREAM_INDEX {
+                if let Some(stream) = get_stream(module_stream as usize) {
+                    // skip the CV_SIGNATURE_C13 that precedes the symbol records.
+                    let stream = stream
+                        .get(4..symbols_size)
+                        .ok_or_else(|| malformed("module symbols out of range"))?;
+                    read_symbols(stream, &mut symbols)?;
+                }
+            }
+
+            // the module name and object file name follow,
+            // and the next entry is aligned to four bytes.
+            let module_name = read_cstr(dbi, offset + MODULE_INFO_SIZE)?;
+            let object_name = read_cstr(dbi, offset + MODULE_INFO_SIZE + module_name.len() + 1)?;
+            let end = offset + MODULE_INFO_SIZE + module_name.len() + 1 + object_name.len() + 1;
+            offset = (end + 3) & !3;
+        }
+    }
+
+    debug!("pdb: age: {} symbols: {}", age, symbols.len());
+
+    Ok(PDB { guid, age, symbols })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+
+    pub const PDB_GUID: [u8; 16] = [
+        0x78, 0x56, 0x34, 0x12, 0x34, 0x12, 0x78, 0x56, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+    ];
+    pub const PDB_AGE: u32 = 2;
+
+    const BLOCK_SIZE: usize = 0x200;
+
+    /// Build an MSF 7.00 container with the given streams,
+    /// laid out after the superblock and free block maps.
+    fn build_msf(streams: &[Vec<u8>]) -> Vec<u8> {
+        let mut blocks: Vec<Vec<u8>> = vec![vec![0u8; BLOCK_SIZE]; 3];
+
+        let mut directory = vec![];
+        directory.write_u32::<LittleEndian>(streams.len() as u32).unwrap();
+        for stream in streams.iter() {
+            directory.write_u32::<LittleEndian>(stream.len() as u32).unwrap();
+        }
+        for stream in streams.iter() {
+            for chunk in stream.chunks(BLOCK_SIZE) {
+                directory.write_u32::<LittleEndian>(blocks.len() as u32).unwrap();
+                let mut block = chunk.to_vec();
+                block.resize(BLOCK_SIZE, 0);
+                blocks.push(block);
+            }
+        }
+
+        let mut block_map = vec![];
+        for chunk in directory.chunks(BLOCK_SIZE) {
+            block_map.write_u32::<LittleEndian>(blocks.len() as u32).unwrap();
+            let mut block = chunk.to_vec();
+            block.resize(BLOCK_SIZE, 0);
+            blocks.push(block);
+        }
+        let block_map_address = blocks.len() as u32;
+        block_map.resize(BLOCK_SIZE, 0);
+        blocks.push(block_map);
+
+        let superblock = &mut blocks[0];
+        superblock[..MSF_MAGIC.len()].copy_from_slice(MSF_MAGIC);
+        let mut fields = vec![];
+        fields.write_u32::<LittleEndian>(BLOCK_SIZE as u32).unwrap(); // BlockSize
+        fields.write_u32::<LittleEndian>(1).unwrap(); // FreeBlockMapBlock
+        fields.write_u32::<LittleEndian>(block_map_address + 1).unwrap(); // NumBlocks
+        fields.write_u32::<LittleEndian>(directory.len() as u32).unwrap(); // NumDirectoryBytes
+        fields.write_u32::<LittleEndian>(0).unwrap(); // Unknown
+        fields.write_u32::<LittleEndian>(block_map_address).unwrap(); // BlockMapAddr
+        superblock[0x20..0x20 + fields.len()].copy_from_slice(&fields);
+
+        blocks.concat()
+    }
+
+    fn build_record(kind: u16, fields: &[u8], name: &str) -> Vec<u8> {
+        let mut data = fields.to_vec();
+        data.extend_from_slice(name.as_bytes());
+        data.push(0);
+        // pad the record, including its length and kind, to four bytes.
+        data.resize(crate::util::align(data.len() as u64 + 4, 4) as usize - 4, 0);
+
+        let mut record = vec![];
+        record.write_u16::<LittleEndian>(data.len() as u16 + 2).unwrap();
+        record.write_u16::<LittleEndian>(kind).unwrap();
+        record.extend_from_slice(&data);
+        record
+    }
+
+    fn build_public(flags: u32, section: u16, offset: u32, name: &str) -> Vec<u8> {
+        let mut fields = vec![];
+        fields.write_u32::<LittleEndian>(flags).unwrap();
+        fields.write_u32::<LittleEndian>(offset).unwrap();
+        fields.write_u16::<LittleEndian>(section).unwrap();
+        build_record(S_PUB32, &fields, name)
+    }
+
+    fn build_procedure(section: u16, offset: u32, name: &str) -> Vec<u8> {
+        let mut fields = vec![0u8; 0x1C]; // parent, end, next, length, debug start/end, type
+        fields.write_u32::<LittleEndian>(offset).unwrap();
+        fields.write_u16::<LittleEndian>(section).unwrap();
+        fields.push(0); // flags
+        build_record(S_GPROC32, &fields, name)
+    }
+
+    /// Build a PDB with the GUID `PDB_GUID` and age `PDB_AGE` that describes:
+    ///
+    /// ```text
+    ///   section 1 + 0x00: public function `main`
+    ///   section 1 + 0x10: procedure `helper`, with public symbol `?helper@@YAXXZ`
+    ///   section 1 + 0x80: public data `g_counter`
+    /// ```
+    pub fn build_pdb() -> Vec<u8> {
+        let mut info = vec![];
+        info.write_u32::<LittleEndian>(20000404).unwrap(); // Version: VC70
+        info.write_u32::<LittleEndian>(0).unwrap(); // Signature
+        info.write_u32::<LittleEndian>(PDB_AGE + 1).unwrap(); // Age
+        info.extend_from_slice(&PDB_GUID);
+
+        let mut records = vec![];
+        records.extend(build_public(CVPSF_FUNCTION, 1, 0x0, "main"));
+        records.extend(build_public(CVPSF_FUNCTION, 1, 0x10, "?helper@@YAXXZ"));
+        records.extend(build_public(0, 1, 0x80, "g_counter"));
+
+        let mut module = vec![];
+        module.write_u32::<LittleEndian>(4).unwrap(); // CV_SIGNATURE_C13
+        module.extend(build_procedure(1, 0x10, "helper"));
+
+        let mut module_info = vec![0u8; MODULE_INFO_SIZE];
+        module_info[0x22..0x24].copy_from_slice(&5u16.to_le_bytes()); // ModuleSymStream
+        module_info[0x24..0x28].copy_from_slice(&(module.len() as u32).to_le_bytes()); // SymByteSize
+        module_info.extend_from_slice(b"test.obj\x00test.obj\x00");
+        module_info.resize(crate::util::align(module_info.len() as u64, 4) as usize, 0);
+
+        let mut dbi = vec![];
+        dbi.write_i32::<LittleEndian>(-1).unwrap(); // VersionSignature
+        dbi.write_u32::<LittleEndian>(19990903).unwrap(); // VersionHeader: V70
+        dbi.write_u32::<LittleEndian>(PDB_AGE).unwrap(); // Age
+        dbi.write_u16::<LittleEndian>(NIL_STREAM_INDEX).unwrap(); // GlobalStreamIndex
+        dbi.write_u16::<LittleEndian>(0).unwrap(); // BuildNumber
+        dbi.write_u16::<LittleEndian>(NIL_STREAM_INDEX).unwrap(); // PublicStreamIndex
+        dbi.write_u16::<LittleEndian>(0).unwrap(); // PdbDllVersion
+        dbi.write_u16::<LittleEndian>(4).unwrap(); // SymRecordStream
+        dbi.write_u16::<LittleEndian>(0).unwrap(); // PdbDllRbld
+        dbi.write_u32::<LittleEndian>(module_info.len() as u32).unwrap(); // ModInfoSize
+        dbi.resize(DBI_HEADER_SIZE, 0);
+        dbi.extend_from_slice(&module_info);
+
+        build_msf(&[vec![], info, vec![], dbi, records, module])
+    }
+
+    #[test]
+    fn symbols() -> Result<()> {
+        let pdb = PDB::from_bytes(&build_pdb())?;
+
+        assert!(pdb.matches(&PDB_GUID, PDB_AGE));
+        assert!(!pdb.matches(&PDB_GUID, PDB_AGE + 1));
+
+        assert_eq!(4, pdb.symbols.len());
+        assert!(pdb.symbols.contains(&PDBSymbol {
+            kind:    PDBSymbolKind::Public { function: true },
+            name:    "main".to_string(),
+            section: 1,
+            offset:  0x0,
+        }));
+        assert!(pdb.symbols.contains(&PDBSymbol {
+            kind:    PDBSymbolKind::Public { function: false },
+            name:    "g_counter".to_string(),
+            section: 1,
+            offset:  0x80,
+        }));
+        assert!(pdb.symbols.contains(&PDBSymbol {
+            kind:    PDBSymbolKind::Procedure,
+            name:    "helper".to_string(),
+            section: 1,
+            offset:  0x10,
+        }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_pdb() {
+        assert!(PDB::from_bytes(b"MZ\x90\x00").is_err());
+    }
+}
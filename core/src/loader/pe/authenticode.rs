@@ -0,0 +1,1007 @@
+//! Parse the Authenticode signatures from the certificate table,
+//! which is referenced by the security data directory.
+//!
+//! Each `WIN_CERTIFICATE` of type `WIN_CERT_TYPE_PKCS_SIGNED_DATA` contains a
+//! DER-encoded PKCS#7 SignedData structure, whose content is the Authenticode
+//! hash of the file. We decode just enough to describe the signers and to
+//! recompute the hash. The cryptographic signatures and certificate chains
+//! are not validated, and nothing is fetched from the network.
+//!
+//! ref: https://learn.microsoft.com/en-us/windows-hardware/drivers/install/authenticode
+#![allow(non_camel_case_types)]
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use log::debug;
+use sha2::Digest;
+
+use crate::loader::pe::{PEError, PE};
+
+pub const WIN_CERT_REVISION_1_0: u16 = 0x0100;
+pub const WIN_CERT_REVISION_2_0: u16 = 0x0200;
+
+pub const WIN_CERT_TYPE_X509: u16 = 0x0001;
+pub const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 0x0002;
+pub const WIN_CERT_TYPE_RESERVED_1: u16 = 0x0003;
+pub const WIN_CERT_TYPE_TS_STACK_SIGNED: u16 = 0x0004;
+
+const OID_SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const OID_SPC_INDIRECT_DATA: &str = "1.3.6.1.4.1.311.2.1.4";
+const OID_SIGNING_TIME: &str = "1.2.840.113549.1.9.5";
+const OID_COUNTERSIGNATURE: &str = "1.2.840.113549.1.9.6";
+const OID_TST_INFO: &str = "1.2.840.113549.1.9.16.1.4";
+const OID_RFC3161_TIMESTAMP: &str = "1.3.6.1.4.1.311.3.3.1";
+const OID_NESTED_SIGNATURE: &str = "1.3.6.1.4.1.311.2.4.1";
+
+/// The most levels of nested signatures and countersignatures to parse,
+/// since each level recurses.
+const MAX_NESTING_DEPTH: usize = 4;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTF8_STRING: u8 = 0x0C;
+const TAG_PRINTABLE_STRING: u8 = 0x13;
+const TAG_T61_STRING: u8 = 0x14;
+const TAG_IA5_STRING: u8 = 0x16;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_UNIVERSAL_STRING: u8 = 0x1C;
+const TAG_BMP_STRING: u8 = 0x1E;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_CONTEXT_0: u8 = 0xA0;
+const TAG_CONTEXT_1: u8 = 0xA1;
+
+/// An entry from the certificate table, with the header fields
+/// `dwLength`, `wRevision`, and `wCertificateType`.
+#[derive(Debug, Clone)]
+pub struct WIN_CERTIFICATE {
+    pub revision:         u16,
+    pub certificate_type: u16,
+    /// `bCertificate`, which may be followed by padding.
+    pub data:             Vec<u8>,
+}
+
+/// fetch the entries of the certificate table, if any.
+///
+/// unlike the other data directories, the security directory contains a file
+/// offset, since the table isn't mapped into memory.
+pub fn read_certificates(pe: &PE) -> Result<Vec<WIN_CERTIFICATE>> {
+    let certificates = pe
+        .pe()?
+        .certificates
+        .iter()
+        .map(|certificate| WIN_CERTIFICATE {
+            revision:         certificate.revision as u16,
+            certificate_type: certificate.certificate_type as u16,
+            data:             certificate.certificate.to_vec(),
+        })
+        .collect::<Vec<_>>();
+
+    debug!("authenticode: found {} certificates", certificates.len());
+
+    Ok(certificates)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    MD5,
+    SHA1,
+    SHA256,
+    SHA384,
+    SHA512,
+    /// an unrecognized algorithm, by OID.
+    Other(String),
+}
+
+impl DigestAlgorithm {
+    fn from_oid(oid: &str) -> DigestAlgorithm {
+        match oid {
+            "1.2.840.113549.2.5" => DigestAlgorithm::MD5,
+            "1.3.14.3.2.26" => DigestAlgorithm::SHA1,
+            "2.16.840.1.101.3.4.2.1" => DigestAlgorithm::SHA256,
+            "2.16.840.1.101.3.4.2.2" => DigestAlgorithm::SHA384,
+            "2.16.840.1.101.3.4.2.3" => DigestAlgorithm::SHA512,
+            _ => DigestAlgorithm::Other(oid.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DigestAlgorithm::MD5 => write!(f, "md5"),
+            DigestAlgorithm::SHA1 => write!(f, "sha1"),
+            DigestAlgorithm::SHA256 => write!(f, "sha256"),
+            DigestAlgorithm::SHA384 => write!(f, "sha384"),
+            DigestAlgorithm::SHA512 => write!(f, "sha512"),
+            DigestAlgorithm::Other(oid) => write!(f, "{oid}"),
+        }
+    }
+}
+
+/// An X.509 certificate embedded in the signature.
+///
+/// Names are formatted like `C=US, O=Microsoft Corporation, CN=Microsoft
+/// Windows`, in the order they're encoded, and serial numbers as hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    pub subject:       String,
+    pub issuer:        String,
+    pub serial_number: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signer {
+    /// the issuer and serial number identify the signer's certificate.
+    pub issuer:           String,
+    pub serial_number:    String,
+    /// the subject of the signer's certificate, when it's embedded.
+    pub subject:          Option<String>,
+    pub digest_algorithm: DigestAlgorithm,
+    /// from the signing time attribute of the signer, or of its
+    /// countersignature or RFC 3161 timestamp.
+    pub signing_time:     Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub digest_algorithm: DigestAlgorithm,
+    /// the Authenticode hash of the file, as signed.
+    pub digest:           Vec<u8>,
+    pub signers:          Vec<Signer>,
+    pub certificates:     Vec<Certificate>,
+}
+
+impl Signature {
+    /// Compare the signed digest with the Authenticode hash of the file,
+    /// or `None` when the digest algorithm isn't supported.
+    pub fn verify_digest(&self, pe: &PE) -> Result<Option<bool>> {
+        Ok(compute_authenticode_hash(pe, &self.digest_algorithm)?.map(|hash| hash == self.digest))
+    }
+}
+
+/// Parse the Authenticode signatures of the file, including those nested
+/// within other signatures, such as a SHA-256 signature alongside a SHA-1 one.
+///
+/// Entries that aren't PKCS#7 SignedData, or that fail to parse, are skipped.
+pub fn read_signatures(pe: &PE) -> Result<Vec<Signature>> {
+    let mut signatures = vec![];
+
+    for certificate in read_certificates(pe)?.iter() {
+        if certificate.certificate_type != WIN_CERT_TYPE_PKCS_SIGNED_DATA {
+            debug!(
+                "authenticode: skipping certificate type: {:#x}",
+                certificate.certificate_type
+            );
+            continue;
+        }
+
+        match parse_signatures(&certificate.data) {
+            Ok(found) => signatures.extend(found),
+            Err(e) => debug!("authenticode: failed to parse signature: {:?}", e),
+        }
+    }
+
+    Ok(signatures)
+}
+
+fn hash<'a, D: Digest>(ranges: impl Iterator<Item = &'a [u8]>) -> Vec<u8> {
+    let mut hasher = D::new();
+    for range in ranges {
+        hasher.update(range);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Compute the Authenticode hash of the file: the hash of its contents,
+/// excluding the checksum, the security data directory, and the certificate
+/// table. Returns `None` when the digest algorithm isn't supported.
+pub fn compute_authenticode_hash(pe: &PE, algorithm: &DigestAlgorithm) -> Result<Option<Vec<u8>>> {
+    let goblin_pe = pe.pe()?;
+
+    // the goblin iterator assumes the headers and section data are within the file.
+    let Some(opt_header) = goblin_pe.header.optional_header else {
+        return Ok(None);
+    };
+    if opt_header.windows_fields.size_of_headers as usize > pe.buf.len()
+        || goblin_pe
+            .sections
+            .iter()
+            .any(|section| section.pointer_to_raw_data as usize + section.size_of_raw_data as usize > pe.buf.len())
+    {
+        return Err(malformed("section data beyond end of file"));
+    }
+
+    let ranges = goblin_pe.authenticode_ranges();
+    Ok(match algorithm {
+        DigestAlgorithm::MD5 => Some(hash::<md5::Md5>(ranges)),
+        DigestAlgorithm::SHA1 => Some(hash::<sha1::Sha1>(ranges)),
+        DigestAlgorithm::SHA256 => Some(hash::<sha2::Sha256>(ranges)),
+        DigestAlgorithm::SHA384 => Some(hash::<sha2::Sha384>(ranges)),
+        DigestAlgorithm::SHA512 => Some(hash::<sha2::Sha512>(ranges)),
+        _ => None,
+    })
+}
+
+fn malformed(message: &str) -> anyhow::Error {
+    PEError::MalformedPEFile(format!("authenticode: {message}")).into()
+}
+
+/// A DER element, with single-byte tags and definite lengths.
+struct Tlv<'a> {
+    tag:     u8,
+    content: &'a [u8],
+}
+
+/// Reads consecutive DER elements from a buffer, such as the content of a
+/// SEQUENCE.
+struct DerReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn new(buf: &'a [u8]) -> DerReader<'a> {
+        DerReader { buf }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn read_any(&mut self) -> Result<Tlv<'a>> {
+        let (&tag, rest) = self
+            .buf
+            .split_first()
+            .ok_or_else(|| malformed("unexpected end of data"))?;
+        if tag & 0x1F == 0x1F {
+            return Err(malformed("unsupported multi-byte tag"));
+        }
+
+        let (&length, rest) = rest.split_first().ok_or_else(|| malformed("unexpected end of data"))?;
+        let (length, rest) = if length & 0x80 == 0 {
+            (length as usize, rest)
+        } else {
+            // zero indicates an indefinite length, which BER allows but DER doesn't.
+            let count = (length & 0x7F) as usize;
+            if count == 0 || count > 4 || count > rest.len() {
+                return Err(malformed("unsupported length"));
+            }
+            let length = rest[..count].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            (length, &rest[count..])
+        };
+
+        if length > rest.len() {
+            return Err(malformed("length exceeds data"));
+        }
+
+        self.buf = &rest[length..];
+        Ok(Tlv {
+            tag,
+            content: &rest[..length],
+        })
+    }
+
+    /// read the element with the given tag, returning its content.
+    fn read(&mut self, tag: u8) -> Result<&'a [u8]> {
+        let tlv = self.read_any()?;
+        if tlv.tag != tag {
+            return Err(malformed(&format!("expected tag {:#x}, found {:#x}", tag, tlv.tag)));
+        }
+        Ok(tlv.content)
+    }
+
+    fn read_optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>> {
+        if self.buf.first() == Some(&tag) {
+            Ok(Some(self.read(tag)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_sequence(&mut self) -> Result<DerReader<'a>> {
+        Ok(DerReader::new(self.read(TAG_SEQUENCE)?))
+    }
+
+    fn read_oid(&mut self) -> Result<String> {
+        decode_oid(self.read(TAG_OID)?)
+    }
+
+    /// read an AlgorithmIdentifier, ignoring its parameters.
+    fn read_digest_algorithm(&mut self) -> Result<DigestAlgorithm> {
+        Ok(DigestAlgorithm::from_oid(&self.read_sequence()?.read_oid()?))
+    }
+}
+
+fn decode_oid(buf: &[u8]) -> Result<String> {
+    let mut arcs: Vec<u64> = vec![];
+    let mut value: u64 = 0;
+    for (i, &b) in buf.iter().enumerate() {
+        if value > (u64::MAX >> 7) {
+            return Err(malformed("OID component too large"));
+        }
+        value = (value << 7) | (b & 0x7F) as u64;
+
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                // the first component encodes the first two arcs.
+                let first = std::cmp::min(value / 40, 2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        } else if i == buf.len() - 1 {
+            return Err(malformed("truncated OID"));
+        }
+    }
+
+    if arcs.is_empty() {
+        return Err(malformed("empty OID"));
+    }
+
+    Ok(arcs.iter().map(|arc| arc.to_string()).collect::<Vec<_>>().join("."))
+}
+
+/// format an INTEGER as hex, without the padding byte that keeps it positive.
+fn format_serial_number(buf: &[u8]) -> String {
+    let buf = match buf {
+        [0x00, rest @ ..] if !rest.is_empty() => rest,
+        _ => buf,
+    };
+    buf.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_string(tlv: &Tlv) -> String {
+    match tlv.tag {
+        TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_IA5_STRING | TAG_T61_STRING => {
+            String::from_utf8_lossy(tlv.content).into_owned()
+        }
+        TAG_BMP_STRING => {
+            let chars = tlv
+                .content
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<u16>>();
+            String::from_utf16_lossy(&chars)
+        }
+        TAG_UNIVERSAL_STRING => tlv
+            .content
+            .chunks_exact(4)
+            .map(|c| char::from_u32(u32::from_be_bytes([c[0], c[1], c[2], c[3]])).unwrap_or('\u{FFFD}'))
+            .collect(),
+        _ => tlv.content.iter().map(|b| format!("{b:02x}")).collect(),
+    }
+}
+
+fn attribute_type_name(oid: &str) -> &str {
+    match oid {
+        "2.5.4.3" => "CN",
+        "2.5.4.5" => "serialNumber",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.9" => "street",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "1.2.840.113549.1.9.1" => "emailAddress",
+        _ => oid,
+    }
+}
+
+/// format a Name, which is a SEQUENCE of SETs of (type, value) pairs.
+fn format_name(buf: &[u8]) -> Result<String> {
+    let mut parts = vec![];
+
+    let mut rdns = DerReader::new(buf);
+    while !rdns.is_empty() {
+        let mut rdn = DerReader::new(rdns.read(TAG_SET)?);
+        while !rdn.is_empty() {
+            let mut pair = rdn.read_sequence()?;
+            let ty = pair.read_oid()?;
+            let value = pair.read_any()?;
+            parts.push(format!("{}={}", attribute_type_name(&ty), decode_string(&value)));
+        }
+    }
+
+    Ok(parts.join(", "))
+}
+
+/// parse a UTCTime or GeneralizedTime, ignoring fractional seconds.
+fn parse_time(tlv: &Tlv) -> Option<DateTime<Utc>> {
+    let s = std::str::from_utf8(tlv.content).ok()?.strip_suffix('Z')?;
+
+    let (year, rest) = match tlv.tag {
+        TAG_UTC_TIME => {
+            let year: i32 = s.get(0..2)?.parse().ok()?;
+            (if year < 50 { 2000 + year } else { 1900 + year }, s.get(2..)?)
+        }
+        TAG_GENERALIZED_TIME => (s.get(0..4)?.parse().ok()?, s.get(4..)?),
+        _ => return None,
+    };
+
+    let field = |i: usize| rest.get(i..i + 2)?.parse::<u32>().ok();
+    let date = NaiveDate::from_ymd_opt(year, field(0)?, field(2)?)?;
+    Some(date.and_hms_opt(field(4)?, field(6)?, field(8)?)?.and_utc())
+}
+
+/// parse the attributes of a SignerInfo, which are (type, SET of values) pairs.
+fn parse_attributes(buf: &[u8]) -> Result<Vec<(String, Vec<Tlv<'_>>)>> {
+    let mut attributes = vec![];
+
+    let mut reader = DerReader::new(buf);
+    while !reader.is_empty() {
+        let mut attribute = reader.read_sequence()?;
+        let ty = attribute.read_oid()?;
+
+        let mut values = vec![];
+        let mut set = DerReader::new(attribute.read(TAG_SET)?);
+        while !set.is_empty() {
+            values.push(set.read_any()?);
+        }
+
+        attributes.push((ty, values));
+    }
+
+    Ok(attributes)
+}
+
+fn parse_certificate(buf: &[u8]) -> Result<Certificate> {
+    let mut tbs = DerReader::new(buf).read_sequence()?;
+    let _version = tbs.read_optional(TAG_CONTEXT_0)?;
+    let serial_number = format_serial_number(tbs.read(TAG_INTEGER)?);
+    let _signature = tbs.read(TAG_SEQUENCE)?;
+    let issuer = format_name(tbs.read(TAG_SEQUENCE)?)?;
+    let _validity = tbs.read(TAG_SEQUENCE)?;
+    let subject = format_name(tbs.read(TAG_SEQUENCE)?)?;
+
+    Ok(Certificate {
+        subject,
+        issuer,
+        serial_number,
+    })
+}
+
+/// parse an RFC 3161 timestamp token, which is a ContentInfo whose SignedData
+/// encapsulates a TSTInfo, returning the time at which it was generated.
+fn parse_timestamp_token(buf: &[u8]) -> Result<Option<DateTime<Utc>>> {
+    let mut content_info = DerReader::new(buf);
+    if content_info.read_oid()? != OID_SIGNED_DATA {
+        return Ok(None);
+    }
+
+    let mut signed_data = DerReader::new(content_info.read(TAG_CONTEXT_0)?).read_sequence()?;
+    let _version = signed_data.read(TAG_INTEGER)?;
+    let _digest_algorithms = signed_data.read(TAG_SET)?;
+
+    let mut encapsulated = signed_data.read_sequence()?;
+    if encapsulated.read_oid()? != OID_TST_INFO {
+        return Ok(None);
+    }
+    let tst_info = DerReader::new(encapsulated.read(TAG_CONTEXT_0)?).read(TAG_OCTET_STRING)?;
+
+    let mut tst_info = DerReader::new(tst_info).read_sequence()?;
+    let _version = tst_info.read(TAG_INTEGER)?;
+    let _policy = tst_info.read(TAG_OID)?;
+    let _message_imprint = tst_info.read(TAG_SEQUENCE)?;
+    let _serial_number = tst_info.read(TAG_INTEGER)?;
+
+    Ok(parse_time(&tst_info.read_any()?))
+}
+
+/// parse a SignerInfo, along with the signatures nested in its unauthenticated
+/// attributes, up to `MAX_NESTING_DEPTH` levels deep.
+fn parse_signer_info(buf: &[u8], certificates: &[Certificate], depth: usize) -> Result<(Signer, Vec<Signature>)> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(malformed("signatures nested too deeply"));
+    }
+
+    let mut signer_info = DerReader::new(buf);
+    let _version = signer_info.read(TAG_INTEGER)?;
+
+    let mut issuer_and_serial_number = signer_info.read_sequence()?;
+    let issuer = format_name(issuer_and_serial_number.read(TAG_SEQUENCE)?)?;
+    let serial_number = format_serial_number(issuer_and_serial_number.read(TAG_INTEGER)?);
+
+    let digest_algorithm = signer_info.read_digest_algorithm()?;
+    let authenticated_attributes = signer_info.read_optional(TAG_CONTEXT_0)?;
+    let _digest_encryption_algorithm = signer_info.read(TAG_SEQUENCE)?;
+    let _encrypted_digest = signer_info.read(TAG_OCTET_STRING)?;
+    let unauthenticated_attributes = signer_info.read_optional(TAG_CONTEXT_1)?;
+
+    let mut signing_time = None;
+    if let Some(attributes) = authenticated_attributes {
+        for (ty, values) in parse_attributes(attributes)?.iter() {
+            if ty == OID_SIGNING_TIME {
+                signing_time = values.first().and_then(parse_time);
+            }
+        }
+    }
+
+    let mut nested = vec![];
+    if let Some(attributes) = unauthenticated_attributes {
+        for (ty, values) in parse_attributes(attributes)?.iter() {
+            for value in values.iter() {
+                match ty.as_str() {
+                    OID_COUNTERSIGNATURE if signing_time.is_none() => {
+                        signing_time = parse_signer_info(value.content, &[], depth + 1)?.0.signing_time;
+                    }
+                    OID_RFC3161_TIMESTAMP if signing_time.is_none() => {
+                        signing_time = parse_timestamp_token(value.content)?;
+                    }
+                    OID_NESTED_SIGNATURE => {
+                        nested.extend(parse_content_info(value.content, depth + 1)?);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let subject = certificates
+        .iter()
+        .find(|certificate| certificate.issuer == issuer && certificate.serial_number == serial_number)
+        .map(|certificate| certificate.subject.clone());
+
+    Ok((
+        Signer {
+            issuer,
+            serial_number,
+            subject,
+            digest_algorithm,
+            signing_time,
+        },
+        nested,
+    ))
+}
+
+/// parse the content of a ContentInfo that contains an Authenticode SignedData,
+/// returning its signature followed by any nested signatures.
+fn parse_content_info(buf: &[u8], depth: usize) -> Result<Vec<Signature>> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(malformed("signatures nested too deeply"));
+    }
+
+    let mut content_info = DerReader::new(buf);
+    if content_info.read_oid()? != OID_SIGNED_DATA {
+        return Err(malformed("expected PKCS#7 SignedData"));
+    }
+
+    let mut signed_data = DerReader::new(content_info.read(TAG_CONTEXT_0)?).read_sequence()?;
+    let _version = signed_data.read(TAG_INTEGER)?;
+    let _digest_algorithms = signed_data.read(TAG_SET)?;
+
+    let mut encapsulated = signed_data.read_sequence()?;
+    if encapsulated.read_oid()? != OID_SPC_INDIRECT_DATA {
+        return Err(malformed("expected SpcIndirectDataContent"));
+    }
+    // SpcIndirectDataContent: SEQUENCE { data, messageDigest: DigestInfo }
+    let mut indirect_data = DerReader::new(encapsulated.read(TAG_CONTEXT_0)?).read_sequence()?;
+    let _data = indirect_data.read(TAG_SEQUENCE)?;
+    let mut digest_info = indirect_data.read_sequence()?;
+    let digest_algorithm = digest_info.read_digest_algorithm()?;
+    let digest = digest_info.read(TAG_OCTET_STRING)?.to_vec();
+
+    let mut certificates = vec![];
+    if let Some(buf) = signed_data.read_optional(TAG_CONTEXT_0)? {
+        let mut reader = DerReader::new(buf);
+        while !reader.is_empty() {
+            let tlv = reader.read_any()?;
+            if tlv.tag != TAG_SEQUENCE {
+                // such as an attribute certificate.
+                continue;
+            }
+            certificates.push(parse_certificate(tlv.content)?);
+        }
+    }
+    let _crls = signed_data.read_optional(TAG_CONTEXT_1)?;
+
+    let mut signers = vec![];
+    let mut nested = vec![];
+    let mut signer_infos = DerReader::new(signed_data.read(TAG_SET)?);
+    while !signer_infos.is_empty() {
+        let (signer, signatures) = parse_signer_info(signer_infos.read(TAG_SEQUENCE)?, &certificates, depth)?;
+        signers.push(signer);
+        nested.extend(signatures);
+    }
+
+    let mut signatures = vec![Signature {
+        digest_algorithm,
+        digest,
+        signers,
+        certificates,
+    }];
+    signatures.extend(nested);
+
+    Ok(signatures)
+}
+
+/// Parse a DER-encoded PKCS#7 ContentInfo that contains an Authenticode
+/// SignedData, such as `bCertificate` from a `WIN_CERTIFICATE`,
+/// returning its signature followed by any nested signatures.
+pub fn parse_signatures(buf: &[u8]) -> Result<Vec<Signature>> {
+    parse_content_info(DerReader::new(buf).read(TAG_SEQUENCE)?, 0)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use sha2::Digest;
+
+    use super::*;
+    use crate::loader::pe::{
+        tests::{add_data_directory, build_mapped_pe, data_directory_offset},
+        unmap_image, IMAGE_DIRECTORY_ENTRY_SECURITY,
+    };
+
+    pub const SIGNER_SERIAL_NUMBER: &str = "80c0ffee";
+    pub const SIGNER_SUBJECT: &str = "C=US, O=Contoso, CN=Contoso Code Signing";
+    pub const ISSUER: &str = "C=US, O=Contoso, CN=Contoso Root CA";
+
+    pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut buf = vec![tag];
+        if content.len() < 0x80 {
+            buf.push(content.len() as u8);
+        } else {
+            let length = (content.len() as u32).to_be_bytes();
+            let length = &length[length.iter().position(|&b| b != 0).unwrap()..];
+            buf.push(0x80 | length.len() as u8);
+            buf.extend_from_slice(length);
+        }
+        buf.extend_from_slice(content);
+        buf
+    }
+
+    pub fn oid(oid: &str) -> Vec<u8> {
+        let arcs = oid
+            .split('.')
+            .map(|arc| arc.parse::<u64>().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut buf = vec![];
+        for arc in std::iter::once(arcs[0] * 40 + arcs[1]).chain(arcs[2..].iter().cloned()) {
+            let mut bytes = vec![(arc & 0x7F) as u8];
+            let mut arc = arc >> 7;
+            while arc != 0 {
+                bytes.push(0x80 | (arc & 0x7F) as u8);
+                arc >>= 7;
+            }
+            bytes.reverse();
+            buf.extend(bytes);
+        }
+        tlv(TAG_OID, &buf)
+    }
+
+    fn sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+        tlv(TAG_SEQUENCE, &elements.concat())
+    }
+
+    fn set(elements: &[Vec<u8>]) -> Vec<u8> {
+        tlv(TAG_SET, &elements.concat())
+    }
+
+    fn name(cn: &str) -> Vec<u8> {
+        sequence(&[
+            set(&[sequence(&[oid("2.5.4.6"), tlv(TAG_PRINTABLE_STRING, b"US")])]),
+            set(&[sequence(&[oid("2.5.4.10"), tlv(TAG_UTF8_STRING, b"Contoso")])]),
+            set(&[sequence(&[oid("2.5.4.3"), tlv(TAG_UTF8_STRING, cn.as_bytes())])]),
+        ])
+    }
+
+    fn algorithm(algorithm: &str) -> Vec<u8> {
+        sequence(&[oid(algorithm), tlv(0x05, &[])])
+    }
+
+    fn certificate(serial_number: &[u8], issuer: &str, subject: &str) -> Vec<u8> {
+        let validity = sequence(&[tlv(TAG_UTC_TIME, b"200101000000Z"), tlv(TAG_UTC_TIME, b"300101000000Z")]);
+        let tbs = sequence(&[
+            tlv(TAG_CONTEXT_0, &tlv(TAG_INTEGER, &[2])),
+            tlv(TAG_INTEGER, serial_number),
+            algorithm("1.2.840.113549.1.1.11"),
+            name(issuer),
+            validity,
+            name(subject),
+            sequence(&[]), // SubjectPublicKeyInfo
+        ]);
+        sequence(&[tbs, algorithm("1.2.840.113549.1.1.11"), tlv(0x03, &[0])])
+    }
+
+    /// Build a ContentInfo with an Authenticode SignedData over `digest`,
+    /// from a signer whose certificate is embedded,
+    /// with the given unauthenticated attributes.
+    fn build_signed_data(digest_algorithm: &str, digest: &[u8], unauthenticated_attributes: &[Vec<u8>]) -> Vec<u8> {
+        let indirect_data = sequence(&[
+            // SpcAttributeTypeAndOptionalValue: SPC_PE_IMAGE_DATA
+            sequence(&[oid("1.3.6.1.4.1.311.2.1.15"), sequence(&[])]),
+            sequence(&[algorithm(digest_algorithm), tlv(TAG_OCTET_STRING, digest)]),
+        ]);
+
+        let serial_number = [0x00, 0x80, 0xC0, 0xFF, 0xEE];
+        let mut signer_info = vec![
+            tlv(TAG_INTEGER, &[1]),
+            sequence(&[name("Contoso Root CA"), tlv(TAG_INTEGER, &serial_number)]),
+            algorithm(digest_algorithm),
+            tlv(
+                TAG_CONTEXT_0,
+                &sequence(&[oid("1.2.840.113549.1.9.3"), set(&[oid(OID_SPC_INDIRECT_DATA)])]),
+            ),
+            algorithm("1.2.840.113549.1.1.1"),
+            tlv(TAG_OCTET_STRING, &[0xAA; 16]),
+        ];
+        if !unauthenticated_attributes.is_empty() {
+            signer_info.push(tlv(TAG_CONTEXT_1, &unauthenticated_attributes.concat()));
+        }
+
+        let signed_data = sequence(&[
+            tlv(TAG_INTEGER, &[1]),
+            set(&[algorithm(digest_algorithm)]),
+            sequence(&[oid(OID_SPC_INDIRECT_DATA), tlv(TAG_CONTEXT_0, &indirect_data)]),
+            tlv(
+                TAG_CONTEXT_0,
+                &[
+                    certificate(&[0x01], "Contoso Root CA", "Contoso Root CA"),
+                    certificate(&serial_number, "Contoso Root CA", "Contoso Code Signing"),
+                ]
+                .concat(),
+            ),
+            set(&[sequence(&signer_info)]),
+        ]);
+
+        sequence(&[oid(OID_SIGNED_DATA), tlv(TAG_CONTEXT_0, &signed_data)])
+    }
+
+    /// A countersignature from a timestamping authority, at `time` (UTCTime).
+    fn countersignature(time: &str) -> Vec<u8> {
+        sequence(&[
+            oid(OID_COUNTERSIGNATURE),
+            set(&[sequence(&[
+                tlv(TAG_INTEGER, &[1]),
+                sequence(&[name("Contoso Timestamping CA"), tlv(TAG_INTEGER, &[0x02])]),
+                algorithm("1.3.14.3.2.26"),
+                tlv(
+                    TAG_CONTEXT_0,
+                    &sequence(&[oid(OID_SIGNING_TIME), set(&[tlv(TAG_UTC_TIME, time.as_bytes())])]),
+                ),
+                algorithm("1.2.840.113549.1.1.1"),
+                tlv(TAG_OCTET_STRING, &[0xBB; 16]),
+            ])]),
+        ])
+    }
+
+    /// the size of `.text` in `build_unsigned_pe`:
+    /// `unmap_image` sets its raw size to its virtual size.
+    const UNSIGNED_TEXT_SIZE: u32 = 0x1D0;
+
+    /// The unsigned PE file: `build_mapped_pe` converted into its file layout.
+    pub fn build_unsigned_pe() -> Vec<u8> {
+        unmap_image(&build_mapped_pe(), None).unwrap()
+    }
+
+    /// Hash the PE file as Authenticode does, assuming it has no certificate
+    /// table yet: skip the checksum and the security data directory.
+    pub fn authenticode_hash<D: Digest>(buf: &[u8]) -> Vec<u8> {
+        let checksum = 0x80 + 4 + 20 + 64;
+        let directory = data_directory_offset(IMAGE_DIRECTORY_ENTRY_SECURITY);
+        let mut hasher = D::new();
+        hasher.update(&buf[..checksum]);
+        hasher.update(&buf[checksum + 4..directory]);
+        hasher.update(&buf[directory + 8..]);
+        hasher.finalize().to_vec()
+    }
+
+    /// Append a certificate table to the PE file, containing the given
+    /// PKCS#7 SignedData, and reference it from the security data directory.
+    pub fn sign_pe(mut buf: Vec<u8>, signed_data: &[u8]) -> Vec<u8> {
+        let offset = buf.len();
+
+        // WIN_CERTIFICATE, padded to an 8-byte boundary.
+        let length = 8 + signed_data.len();
+        buf.write_u32::<LittleEndian>(length as u32).unwrap();
+        buf.write_u16::<LittleEndian>(WIN_CERT_REVISION_2_0).unwrap();
+        buf.write_u16::<LittleEndian>(WIN_CERT_TYPE_PKCS_SIGNED_DATA).unwrap();
+        buf.extend_from_slice(signed_data);
+        buf.resize(offset + crate::util::align(length as u64, 8) as usize, 0);
+
+        let size = buf.len() - offset;
+        add_data_directory(
+            &mut buf,
+            IMAGE_DIRECTORY_ENTRY_SECURITY,
+            offset as u64,
+            size as u32,
+            UNSIGNED_TEXT_SIZE,
+        );
+
+        buf
+    }
+
+    /// Build a PE file with a SHA-256 Authenticode signature over its contents,
+    /// timestamped by a countersignature,
+    /// with a nested SHA-1 signature that has an RFC 3161 timestamp.
+    pub fn build_signed_pe() -> Vec<u8> {
+        let buf = build_unsigned_pe();
+        let sha256 = authenticode_hash::<sha2::Sha256>(&buf);
+        let sha1 = authenticode_hash::<sha1::Sha1>(&buf);
+
+        let tst_info = sequence(&[
+            tlv(TAG_INTEGER, &[1]),
+            oid("1.2.3.4"),
+            sequence(&[algorithm("1.3.14.3.2.26"), tlv(TAG_OCTET_STRING, &[0xCC; 20])]),
+            tlv(TAG_INTEGER, &[0x03]),
+            tlv(TAG_GENERALIZED_TIME, b"20210304050607.5Z"),
+        ]);
+        let timestamp_token = sequence(&[
+            oid(OID_SIGNED_DATA),
+            tlv(
+                TAG_CONTEXT_0,
+                &sequence(&[
+                    tlv(TAG_INTEGER, &[3]),
+                    set(&[algorithm("1.3.14.3.2.26")]),
+                    sequence(&[oid(OID_TST_INFO), tlv(TAG_CONTEXT_0, &tlv(TAG_OCTET_STRING, &tst_info))]),
+                    set(&[]),
+                ]),
+            ),
+        ]);
+        let nested = build_signed_data(
+            "1.3.14.3.2.26",
+            &sha1,
+            &[sequence(&[oid(OID_RFC3161_TIMESTAMP), set(&[timestamp_token])])],
+        );
+
+        let signed_data = build_signed_data(
+            "2.16.840.1.101.3.4.2.1",
+            &sha256,
+            &[
+                countersignature("200203040506Z"),
+                sequence(&[oid(OID_NESTED_SIGNATURE), set(&[nested])]),
+            ],
+        );
+
+        sign_pe(buf, &signed_data)
+    }
+
+    #[test]
+    fn oids() -> Result<()> {
+        for s in [
+            "1.2.840.113549.1.7.2",
+            "1.3.6.1.4.1.311.2.1.4",
+            "2.16.840.1.101.3.4.2.1",
+            "2.999.1",
+        ] {
+            let buf = oid(s);
+            assert_eq!(s, DerReader::new(&buf).read_oid()?);
+        }
+
+        assert!(decode_oid(&[]).is_err());
+        assert!(decode_oid(&[0x2A, 0x86]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn times() {
+        let time = |tag, s: &str| {
+            parse_time(&Tlv {
+                tag,
+                content: s.as_bytes(),
+            })
+            .map(|t| t.to_rfc3339())
+        };
+
+        assert_eq!(
+            Some("2020-02-03T04:05:06+00:00".to_string()),
+            time(TAG_UTC_TIME, "200203040506Z")
+        );
+        assert_eq!(
+            Some("1999-12-31T23:59:59+00:00".to_string()),
+            time(TAG_UTC_TIME, "991231235959Z")
+        );
+        assert_eq!(
+            Some("2021-03-04T05:06:07+00:00".to_string()),
+            time(TAG_GENERALIZED_TIME, "20210304050607.5Z")
+        );
+        assert_eq!(None, time(TAG_UTC_TIME, "200203040506"));
+        assert_eq!(None, time(TAG_UTC_TIME, "201303040506Z"));
+    }
+
+    #[test]
+    fn signatures() -> Result<()> {
+        let pe = PE::from_bytes(&build_signed_pe())?;
+
+        let certificates = read_certificates(&pe)?;
+        assert_eq!(1, certificates.len());
+        assert_eq!(WIN_CERT_REVISION_2_0, certificates[0].revision);
+        assert_eq!(WIN_CERT_TYPE_PKCS_SIGNED_DATA, certificates[0].certificate_type);
+
+        let signatures = pe.signatures()?;
+        assert_eq!(2, signatures.len());
+
+        let signature = &signatures[0];
+        assert_eq!(DigestAlgorithm::SHA256, signature.digest_algorithm);
+        assert_eq!(
+            authenticode_hash::<sha2::Sha256>(&build_unsigned_pe()),
+            signature.digest
+        );
+        assert_eq!(2, signature.certificates.len());
+        assert_eq!(SIGNER_SUBJECT, signature.certificates[1].subject);
+        assert_eq!(ISSUER, signature.certificates[1].issuer);
+        assert_eq!(SIGNER_SERIAL_NUMBER, signature.certificates[1].serial_number);
+
+        assert_eq!(1, signature.signers.len());
+        let signer = &signature.signers[0];
+        assert_eq!(ISSUER, signer.issuer);
+        assert_eq!(SIGNER_SERIAL_NUMBER, signer.serial_number);
+        assert_eq!(Some(SIGNER_SUBJECT), signer.subject.as_deref());
+        assert_eq!(DigestAlgorithm::SHA256, signer.digest_algorithm);
+        assert_eq!("2020-02-03T04:05:06+00:00", signer.signing_time.unwrap().to_rfc3339());
+        assert_eq!(Some(true), signature.verify_digest(&pe)?);
+
+        let nested = &signatures[1];
+        assert_eq!(DigestAlgorithm::SHA1, nested.digest_algorithm);
+        assert_eq!(
+            "2021-03-04T05:06:07+00:00",
+            nested.signers[0].signing_time.unwrap().to_rfc3339()
+        );
+        assert_eq!(Some(true), nested.verify_digest(&pe)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn md5() -> Result<()> {
+        let buf = build_unsigned_pe();
+        let digest = authenticode_hash::<md5::Md5>(&buf);
+        let pe = PE::from_bytes(&sign_pe(buf, &build_signed_data("1.2.840.113549.2.5", &digest, &[])))?;
+
+        let signatures = pe.signatures()?;
+        assert_eq!(DigestAlgorithm::MD5, signatures[0].digest_algorithm);
+        assert_eq!(Some(true), signatures[0].verify_digest(&pe)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tampered() -> Result<()> {
+        let mut buf = build_signed_pe();
+        // patch the entry point instruction.
+        buf[0x1005] = 0xCC;
+        let pe = PE::from_bytes(&buf)?;
+
+        for signature in pe.signatures()?.iter() {
+            assert_eq!(Some(false), signature.verify_digest(&pe)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn nesting_depth() -> Result<()> {
+        let nest = |depth: usize| {
+            let mut signed_data = build_signed_data("1.3.14.3.2.26", &[0xCC; 20], &[]);
+            for _ in 0..depth {
+                signed_data = build_signed_data(
+                    "1.3.14.3.2.26",
+                    &[0xCC; 20],
+                    &[sequence(&[oid(OID_NESTED_SIGNATURE), set(&[signed_data])])],
+                );
+            }
+            signed_data
+        };
+
+        assert_eq!(MAX_NESTING_DEPTH + 1, parse_signatures(&nest(MAX_NESTING_DEPTH))?.len());
+        assert!(parse_signatures(&nest(MAX_NESTING_DEPTH + 1)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsigned() -> Result<()> {
+        let pe = PE::from_bytes(&build_unsigned_pe())?;
+        assert!(read_certificates(&pe)?.is_empty());
+        assert!(pe.signatures()?.is_empty());
+
+        Ok(())
+    }
+}
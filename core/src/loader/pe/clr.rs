@@ -0,0 +1,815 @@
+//! Parse the CLR header and metadata tables of .NET assemblies.
+//!
+//! Only the tables needed to locate and name methods are decoded:
+//! TypeDef, MethodDef, ModuleRef, and ImplMap (P/Invoke declarations).
+//! The remaining tables are only measured, so that these can be found.
+//!
+//! ref: ECMA-335, Partition II, sections 22 (tables) and 24 (physical layout).
+#![allow(non_camel_case_types)]
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use log::debug;
+use thiserror::Error;
+
+use crate::{
+    aspace::AddressSpace,
+    loader::pe::{IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR, PE},
+    RVA,
+};
+
+#[derive(Error, Debug)]
+pub enum ClrError {
+    #[error("invalid CLR header")]
+    InvalidHeader,
+
+    #[error("invalid metadata: {0}")]
+    InvalidMetadata(String),
+}
+
+/// The image contains only IL code, so has no native code besides maybe the
+/// stub at the entry point that calls into `mscoree.dll`.
+pub const COMIMAGE_FLAGS_ILONLY: u32 = 0x0000_0001;
+pub const COMIMAGE_FLAGS_32BITREQUIRED: u32 = 0x0000_0002;
+pub const COMIMAGE_FLAGS_STRONGNAMESIGNED: u32 = 0x0000_0008;
+/// The `entry_point` field is the RVA of a native function,
+/// rather than a MethodDef or File token.
+pub const COMIMAGE_FLAGS_NATIVE_ENTRYPOINT: u32 = 0x0000_0010;
+
+// MethodDef.ImplFlags
+pub const METHOD_IMPL_CODE_TYPE_MASK: u16 = 0x0003;
+pub const METHOD_IMPL_IL: u16 = 0x0000;
+pub const METHOD_IMPL_NATIVE: u16 = 0x0001;
+pub const METHOD_IMPL_OPTIL: u16 = 0x0002;
+pub const METHOD_IMPL_RUNTIME: u16 = 0x0003;
+pub const METHOD_IMPL_UNMANAGED: u16 = 0x0004;
+
+// MethodDef.Flags
+pub const METHOD_ATTRIBUTE_PINVOKE_IMPL: u16 = 0x2000;
+
+pub const TABLE_MODULE: u8 = 0x00;
+pub const TABLE_TYPE_REF: u8 = 0x01;
+pub const TABLE_TYPE_DEF: u8 = 0x02;
+pub const TABLE_FIELD: u8 = 0x04;
+pub const TABLE_METHOD_DEF: u8 = 0x06;
+pub const TABLE_MODULE_REF: u8 = 0x1A;
+pub const TABLE_IMPL_MAP: u8 = 0x1C;
+
+/// The fields are the same for 32- and 64-bit images.
+#[derive(Debug, Clone)]
+pub struct IMAGE_COR20_HEADER {
+    pub cb: u32,
+    pub major_runtime_version: u16,
+    pub minor_runtime_version: u16,
+    pub metadata_rva: RVA,
+    pub metadata_size: u32,
+    pub flags: u32,
+    /// a MethodDef token, or an RVA when `COMIMAGE_FLAGS_NATIVE_ENTRYPOINT` is
+    /// set.
+    pub entry_point: u32,
+    pub resources_rva: RVA,
+    pub resources_size: u32,
+    pub strong_name_signature_rva: RVA,
+    pub strong_name_signature_size: u32,
+    pub vtable_fixups_rva: RVA,
+    pub vtable_fixups_size: u32,
+}
+
+impl IMAGE_COR20_HEADER {
+    pub fn is_il_only(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_ILONLY != 0
+    }
+
+    pub fn has_native_entry_point(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_NATIVE_ENTRYPOINT != 0
+    }
+}
+
+/// fetch the CLR header, which is present when the PE is a .NET assembly.
+pub fn read_cor20_header(pe: &PE) -> Result<Option<IMAGE_COR20_HEADER>> {
+    let Some(directory) = pe.get_data_directory(IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR)? else {
+        return Ok(None);
+    };
+    if directory.size == 0 {
+        return Ok(None);
+    }
+
+    let aspace = &pe.module.address_space;
+    let va = directory.address;
+
+    let header = IMAGE_COR20_HEADER {
+        cb: aspace.read_u32(va)?,
+        major_runtime_version: aspace.read_u16(va + 4)?,
+        minor_runtime_version: aspace.read_u16(va + 6)?,
+        metadata_rva: aspace.read_u32(va + 8)? as RVA,
+        metadata_size: aspace.read_u32(va + 12)?,
+        flags: aspace.read_u32(va + 16)?,
+        entry_point: aspace.read_u32(va + 20)?,
+        resources_rva: aspace.read_u32(va + 24)? as RVA,
+        resources_size: aspace.read_u32(va + 28)?,
+        strong_name_signature_rva: aspace.read_u32(va + 32)? as RVA,
+        strong_name_signature_size: aspace.read_u32(va + 36)?,
+        vtable_fixups_rva: aspace.read_u32(va + 48)? as RVA,
+        vtable_fixups_size: aspace.read_u32(va + 52)?,
+    };
+
+    if header.cb < 72 || header.metadata_rva == 0 {
+        return Err(ClrError::InvalidHeader.into());
+    }
+
+    debug!(
+        "clr: runtime {}.{}, flags: {:#x}",
+        header.major_runtime_version, header.minor_runtime_version, header.flags
+    );
+    Ok(Some(header))
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeDef {
+    pub flags:       u32,
+    pub name:        String,
+    pub namespace:   String,
+    /// the first MethodDef row (1-based) owned by this type,
+    /// which runs until the first row owned by the next type.
+    pub method_list: u32,
+}
+
+impl TypeDef {
+    /// like `Namespace.Name`, or just `Name` when there's no namespace.
+    pub fn full_name(&self) -> String {
+        if self.namespace.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.namespace, self.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodDef {
+    /// the method body, either IL or native code depending on `impl_flags`,
+    /// or zero when there's no body, such as for abstract and P/Invoke
+    /// methods.
+    pub rva:        RVA,
+    pub impl_flags: u16,
+    pub flags:      u16,
+    pub name:       String,
+}
+
+impl MethodDef {
+    pub fn is_native(&self) -> bool {
+        self.impl_flags & METHOD_IMPL_CODE_TYPE_MASK == METHOD_IMPL_NATIVE
+    }
+
+    pub fn is_il(&self) -> bool {
+        matches!(
+            self.impl_flags & METHOD_IMPL_CODE_TYPE_MASK,
+            METHOD_IMPL_IL | METHOD_IMPL_OPTIL
+        )
+    }
+
+    pub fn is_pinvoke(&self) -> bool {
+        self.flags & METHOD_ATTRIBUTE_PINVOKE_IMPL != 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleRef {
+    pub name: String,
+}
+
+/// Describes how a P/Invoke method is found in a native module.
+#[derive(Debug, Clone)]
+pub struct ImplMap {
+    pub flags:            u16,
+    /// the token of the Field or MethodDef that is forwarded,
+    /// like `0x06000003`.
+    pub member_forwarded: u32,
+    pub import_name:      String,
+    /// the ModuleRef row (1-based) of the native module.
+    pub import_scope:     u32,
+}
+
+/// The decoded metadata tables of a .NET assembly.
+///
+/// Rows are stored 0-based, while references between tables are 1-based,
+/// as found in the file.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    /// the version of the runtime the assembly targets, like `v4.0.30319`.
+    pub version:     String,
+    pub type_defs:   Vec<TypeDef>,
+    pub method_defs: Vec<MethodDef>,
+    pub module_refs: Vec<ModuleRef>,
+    pub impl_maps:   Vec<ImplMap>,
+}
+
+impl Metadata {
+    pub fn from_pe(pe: &PE) -> Result<Option<Metadata>> {
+        let Some(header) = read_cor20_header(pe)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Metadata::from_header(pe, &header)?))
+    }
+
+    /// Parse the metadata referenced by the given CLR header.
+    pub fn from_header(pe: &PE, header: &IMAGE_COR20_HEADER) -> Result<Metadata> {
+        let buf = pe
+            .module
+            .address_space
+            .relative
+            .read_bytes(header.metadata_rva, header.metadata_size as usize)?;
+        Metadata::from_bytes(&buf)
+    }
+
+    /// Parse the metadata root (`BSJB`) and its streams.
+    pub fn from_bytes(buf: &[u8]) -> Result<Metadata> {
+        if buf.len() < 16 || LittleEndian::read_u32(buf) != 0x424A_5342 {
+            return Err(ClrError::InvalidMetadata("missing BSJB signature".to_string()).into());
+        }
+
+        let version_length = LittleEndian::read_u32(&buf[12..]) as usize;
+        let version = read_str(buf, 16, version_length)?;
+
+        let mut offset = 16 + version_length;
+        let header = get_slice(buf, offset, 4)?;
+        let stream_count = LittleEndian::read_u16(&header[2..]);
+        offset += 4;
+
+        let mut tables: Option<&[u8]> = None;
+        let mut strings: &[u8] = &[];
+        for _ in 0..stream_count {
+            let header = get_slice(buf, offset, 8)?;
+            let stream_offset = LittleEndian::read_u32(header) as usize;
+            let stream_size = LittleEndian::read_u32(&header[4..]) as usize;
+
+            // the name is null-terminated and padded to a multiple of four bytes.
+            let name = read_str(buf, offset + 8, 32)?;
+            offset += 8 + ((name.len() + 4) & !3);
+
+            let stream = get_slice(buf, stream_offset, stream_size)?;
+            match name.as_str() {
+                "#~" | "#-" => tables = Some(stream),
+                "#Strings" => strings = stream,
+                _ => {}
+            }
+        }
+
+        let Some(tables) = tables else {
+            return Err(ClrError::InvalidMetadata("missing tables stream".to_string()).into());
+        };
+        let tables = Tables::from_bytes(tables, strings)?;
+
+        let mut metadata = Metadata {
+            version,
+            ..Default::default()
+        };
+
+        for row in 1..=tables.rows[TABLE_TYPE_DEF as usize] {
+            let values = tables.read_row(TABLE_TYPE_DEF, row)?;
+            metadata.type_defs.push(TypeDef {
+                flags:       values[0],
+                name:        tables.read_string(values[1])?,
+                namespace:   tables.read_string(values[2])?,
+                method_list: values[5],
+            });
+        }
+
+        for row in 1..=tables.rows[TABLE_METHOD_DEF as usize] {
+            let values = tables.read_row(TABLE_METHOD_DEF, row)?;
+            metadata.method_defs.push(MethodDef {
+                rva:        values[0] as RVA,
+                impl_flags: values[1] as u16,
+                flags:      values[2] as u16,
+                name:       tables.read_string(values[3])?,
+            });
+        }
+
+        for row in 1..=tables.rows[TABLE_MODULE_REF as usize] {
+            let values = tables.read_row(TABLE_MODULE_REF, row)?;
+            metadata.module_refs.push(ModuleRef {
+                name: tables.read_string(values[0])?,
+            });
+        }
+
+        for row in 1..=tables.rows[TABLE_IMPL_MAP as usize] {
+            let values = tables.read_row(TABLE_IMPL_MAP, row)?;
+            // MemberForwarded: Field or MethodDef, tagged by the low bit.
+            let table = if values[1] & 1 == 0 {
+                TABLE_FIELD
+            } else {
+                TABLE_METHOD_DEF
+            };
+            metadata.impl_maps.push(ImplMap {
+                flags:            values[0] as u16,
+                member_forwarded: (table as u32) << 24 | values[1] >> 1,
+                import_name:      tables.read_string(values[2])?,
+                import_scope:     values[3],
+            });
+        }
+
+        debug!(
+            "clr: metadata {}: {} types, {} methods, {} P/Invokes",
+            metadata.version,
+            metadata.type_defs.len(),
+            metadata.method_defs.len(),
+            metadata.impl_maps.len()
+        );
+        Ok(metadata)
+    }
+
+    /// Find the type that owns the given MethodDef row (1-based).
+    pub fn get_declaring_type(&self, method: u32) -> Option<&TypeDef> {
+        self.type_defs
+            .iter()
+            .enumerate()
+            .find(|(i, type_def)| {
+                let end = self
+                    .type_defs
+                    .get(i + 1)
+                    .map(|next| next.method_list)
+                    .unwrap_or(u32::MAX);
+                (type_def.method_list..end).contains(&method)
+            })
+            .map(|(_, type_def)| type_def)
+    }
+
+    /// Render the name of the given MethodDef row (1-based), like
+    /// `Namespace.Type::Method`.
+    ///
+    /// Global methods, owned by the `<Module>` type, such as the native
+    /// functions of a C++/CLI assembly, are rendered without a type.
+    pub fn get_method_name(&self, method: u32) -> Option<String> {
+        let method_def = self.method_defs.get(method.checked_sub(1)? as usize)?;
+        match self.get_declaring_type(method) {
+            Some(type_def) if type_def.name != "<Module>" => {
+                Some(format!("{}::{}", type_def.full_name(), method_def.name))
+            }
+            _ => Some(method_def.name.clone()),
+        }
+    }
+}
+
+fn get_slice(buf: &[u8], offset: usize, size: usize) -> Result<&[u8]> {
+    buf.get(offset..offset.saturating_add(size))
+        .ok_or_else(|| ClrError::InvalidMetadata(format!("out of bounds: {offset:#x}")).into())
+}
+
+/// read a null-terminated UTF-8 string of at most `max_length` bytes.
+fn read_str(buf: &[u8], offset: usize, max_length: usize) -> Result<String> {
+    let buf = buf
+        .get(offset..)
+        .ok_or_else(|| ClrError::InvalidMetadata(format!("out of bounds: {offset:#x}")))?;
+    let buf = &buf[..std::cmp::min(max_length, buf.len())];
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..end]).into_owned())
+}
+
+#[derive(Clone, Copy)]
+enum Column {
+    /// a constant of the given size.
+    Fixed(usize),
+    Str,
+    Guid,
+    Blob,
+    /// a row of the given table.
+    Index(u8),
+    /// a row of one of the given tables, tagged in the low bits.
+    /// `0xFF` marks an unused tag.
+    Coded(&'static [u8]),
+}
+
+use Column::*;
+
+const TYPE_DEF_OR_REF: &[u8] = &[0x02, 0x01, 0x1B];
+const HAS_CONSTANT: &[u8] = &[0x04, 0x08, 0x17];
+const HAS_CUSTOM_ATTRIBUTE: &[u8] = &[
+    0x06, 0x04, 0x01, 0x02, 0x08, 0x09, 0x0A, 0x00, 0x0E, 0x17, 0x14, 0x11, 0x1A, 0x1B, 0x20, 0x23, 0x26, 0x27, 0x28,
+    0x2A, 0x2C, 0x2B,
+];
+const HAS_FIELD_MARSHAL: &[u8] = &[0x04, 0x08];
+const HAS_DECL_SECURITY: &[u8] = &[0x02, 0x06, 0x20];
+const MEMBER_REF_PARENT: &[u8] = &[0x02, 0x01, 0x1A, 0x06, 0x1B];
+const HAS_SEMANTICS: &[u8] = &[0x14, 0x17];
+const METHOD_DEF_OR_REF: &[u8] = &[0x06, 0x0A];
+const MEMBER_FORWARDED: &[u8] = &[0x04, 0x06];
+const CUSTOM_ATTRIBUTE_TYPE: &[u8] = &[0xFF, 0xFF, 0x06, 0x0A, 0xFF];
+const RESOLUTION_SCOPE: &[u8] = &[0x00, 0x1A, 0x23, 0x01];
+
+/// The columns of each table, up to and including ImplMap.
+/// The tables are stored in this order, so these are all that's needed to
+/// find the ones we decode.
+const SCHEMA: [&[Column]; TABLE_IMPL_MAP as usize + 1] = [
+    // 0x00 Module: Generation, Name, Mvid, EncId, EncBaseId
+    &[Fixed(2), Str, Guid, Guid, Guid],
+    // 0x01 TypeRef: ResolutionScope, TypeName, TypeNamespace
+    &[Coded(RESOLUTION_SCOPE), Str, Str],
+    // 0x02 TypeDef: Flags, TypeName, TypeNamespace, Extends, FieldList, MethodList
+    &[Fixed(4), Str, Str, Coded(TYPE_DEF_OR_REF), Index(0x04), Index(0x06)],
+    // 0x03 FieldPtr
+    &[Index(0x04)],
+    // 0x04 Field: Flags, Name, Signature
+    &[Fixed(2), Str, Blob],
+    // 0x05 MethodPtr
+    &[Index(0x06)],
+    // 0x06 MethodDef: RVA, ImplFlags, Flags, Name, Signature, ParamList
+    &[Fixed(4), Fixed(2), Fixed(2), Str, Blob, Index(0x08)],
+    // 0x07 ParamPtr
+    &[Index(0x08)],
+    // 0x08 Param: Flags, Sequence, Name
+    &[Fixed(2), Fixed(2), Str],
+    // 0x09 InterfaceImpl: Class, Interface
+    &[Index(0x02), Coded(TYPE_DEF_OR_REF)],
+    // 0x0A MemberRef: Class, Name, Signature
+    &[Coded(MEMBER_REF_PARENT), Str, Blob],
+    // 0x0B Constant: Type (and padding), Parent, Value
+    &[Fixed(2), Coded(HAS_CONSTANT), Blob],
+    // 0x0C CustomAttribute: Parent, Type, Value
+    &[Coded(HAS_CUSTOM_ATTRIBUTE), Coded(CUSTOM_ATTRIBUTE_TYPE), Blob],
+    // 0x0D FieldMarshal: Parent, NativeType
+    &[Coded(HAS_FIELD_MARSHAL), Blob],
+    // 0x0E DeclSecurity: Action, Parent, PermissionSet
+    &[Fixed(2), Coded(HAS_DECL_SECURITY), Blob],
+    // 0x0F ClassLayout: PackingSize, ClassSize, Parent
+    &[Fixed(2), Fixed(4), Index(0x02)],
+    // 0x10 FieldLayout: Offset, Field
+    &[Fixed(4), Index(0x04)],
+    // 0x11 StandAloneSig: Signature
+    &[Blob],
+    // 0x12 EventMap: Parent, EventList
+    &[Index(0x02), Index(0x14)],
+    // 0x13 EventPtr
+    &[Index(0x14)],
+    // 0x14 Event: EventFlags, Name, EventType
+    &[Fixed(2), Str, Coded(TYPE_DEF_OR_REF)],
+    // 0x15 PropertyMap: Parent, PropertyList
+    &[Index(0x02), Index(0x17)],
+    // 0x16 PropertyPtr
+    &[Index(0x17)],
+    // 0x17 Property: Flags, Name, Type
+    &[Fixed(2), Str, Blob],
+    // 0x18 MethodSemantics: Semantics, Method, Association
+    &[Fixed(2), Index(0x06), Coded(HAS_SEMANTICS)],
+    // 0x19 MethodImpl: Class, MethodBody, MethodDeclaration
+    &[Index(0x02), Coded(METHOD_DEF_OR_REF), Coded(METHOD_DEF_OR_REF)],
+    // 0x1A ModuleRef: Name
+    &[Str],
+    // 0x1B TypeSpec: Signature
+    &[Blob],
+    // 0x1C ImplMap: MappingFlags, MemberForwarded, ImportName, ImportScope
+    &[Fixed(2), Coded(MEMBER_FORWARDED), Str, Index(0x1A)],
+];
+
+/// The `#~` stream, which contains the metadata tables.
+struct Tables<'a> {
+    buf:        &'a [u8],
+    strings:    &'a [u8],
+    heap_sizes: u8,
+    /// the number of rows in each table, indexed by table number.
+    rows:       [u32; 64],
+    /// the offset of each decodable table within `buf`.
+    offsets:    [usize; TABLE_IMPL_MAP as usize + 1],
+}
+
+impl<'a> Tables<'a> {
+    fn from_bytes(buf: &'a [u8], strings: &'a [u8]) -> Result<Tables<'a>> {
+        let header = get_slice(buf, 0, 24)?;
+        let heap_sizes = header[6];
+        let valid = LittleEndian::read_u64(&header[8..]);
+
+        let mut rows = [0u32; 64];
+        let mut offset = 24;
+        for (table, count) in rows.iter_mut().enumerate() {
+            if valid & (1 << table) != 0 {
+                *count = LittleEndian::read_u32(get_slice(buf, offset, 4)?);
+                offset += 4;
+            }
+        }
+
+        // some obfuscators set this flag, and follow the row counts with four extra
+        // bytes.
+        if heap_sizes & 0x40 != 0 {
+            offset += 4;
+        }
+
+        let mut tables = Tables {
+            buf,
+            strings,
+            heap_sizes,
+            rows,
+            offsets: Default::default(),
+        };
+
+        for table in 0..tables.offsets.len() {
+            tables.offsets[table] = offset;
+            offset += tables.row_size(table as u8) * tables.rows[table] as usize;
+        }
+
+        Ok(tables)
+    }
+
+    fn column_size(&self, column: Column) -> usize {
+        match column {
+            Fixed(size) => size,
+            Str => {
+                if self.heap_sizes & 0x01 != 0 {
+                    4
+                } else {
+                    2
+                }
+            }
+            Guid => {
+                if self.heap_sizes & 0x02 != 0 {
+                    4
+                } else {
+                    2
+                }
+            }
+            Blob => {
+                if self.heap_sizes & 0x04 != 0 {
+                    4
+                } else {
+                    2
+                }
+            }
+            Index(table) => {
+                if self.rows[table as usize] < 0x1_0000 {
+                    2
+                } else {
+                    4
+                }
+            }
+            Coded(tables) => {
+                let tag_bits = (tables.len() as u32).next_power_of_two().trailing_zeros();
+                let max_rows = tables
+                    .iter()
+                    .filter(|&&table| table != 0xFF)
+                    .map(|&table| self.rows[table as usize])
+                    .max()
+                    .unwrap_or(0);
+                if max_rows < 1 << (16 - tag_bits) {
+                    2
+                } else {
+                    4
+                }
+            }
+        }
+    }
+
+    fn row_size(&self, table: u8) -> usize {
+        SCHEMA[table as usize]
+            .iter()
+            .map(|&column| self.column_size(column))
+            .sum()
+    }
+
+    /// read the columns of the given row (1-based).
+    fn read_row(&self, table: u8, row: u32) -> Result<Vec<u32>> {
+        let row_size = self.row_size(table);
+        let mut offset = self.offsets[table as usize] + row_size * (row as usize - 1);
+
+        let mut values = vec![];
+        for &column in SCHEMA[table as usize].iter() {
+            let size = self.column_size(column);
+            let buf = get_slice(self.buf, offset, size)?;
+            values.push(match size {
+                1 => buf[0] as u32,
+                2 => LittleEndian::read_u16(buf) as u32,
+                _ => LittleEndian::read_u32(buf),
+            });
+            offset += size;
+        }
+
+        Ok(values)
+    }
+
+    fn read_string(&self, index: u32) -> Result<String> {
+        read_str(self.strings, index as usize, usize::MAX)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::{
+        loader::pe::tests::{add_data_directory, build_mapped_pe, HELPER_RVA},
+        rsrc::*,
+    };
+
+    pub const COR20_HEADER_RVA: u64 = 0x1200;
+    pub const METADATA_RVA: u64 = 0x1300;
+    /// the tiny IL body of `Program::Main`: `ldc.i4.0; ret`.
+    pub const IL_METHOD_RVA: u64 = 0x1600;
+
+    fn build_metadata() -> Vec<u8> {
+        // #Strings
+        let mut strings = vec![0u8];
+        let mut string = |s: &str| -> u16 {
+            let index = strings.len() as u16;
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+            index
+        };
+        let module = string("example.exe");
+        let global = string("<Module>");
+        let program = string("Program");
+        let namespace = string("Example");
+        let main = string("Main");
+        let helper = string("native_helper");
+        let message_box = string("MessageBox");
+        let user32 = string("user32");
+        let message_box_a = string("MessageBoxA");
+        while strings.len() % 4 != 0 {
+            strings.push(0);
+        }
+
+        // #~
+        let mut tables = vec![];
+        tables.write_u32::<LittleEndian>(0).unwrap(); // Reserved
+        tables.write_u8(2).unwrap(); // MajorVersion
+        tables.write_u8(0).unwrap(); // MinorVersion
+        tables.write_u8(0).unwrap(); // HeapSizes
+        tables.write_u8(1).unwrap(); // Reserved
+        let valid: u64 = (1 << TABLE_MODULE)
+            | (1 << TABLE_TYPE_DEF)
+            | (1 << TABLE_METHOD_DEF)
+            | (1 << TABLE_MODULE_REF)
+            | (1 << TABLE_IMPL_MAP);
+        tables.write_u64::<LittleEndian>(valid).unwrap(); // Valid
+        tables.write_u64::<LittleEndian>(0).unwrap(); // Sorted
+        for rows in [1, 2, 3, 1, 1] {
+            tables.write_u32::<LittleEndian>(rows).unwrap();
+        }
+
+        // Module
+        tables.write_u16::<LittleEndian>(0).unwrap();
+        tables.write_u16::<LittleEndian>(module).unwrap();
+        tables.extend_from_slice(&[0u8; 6]);
+
+        // TypeDef: <Module> owns native_helper, Example.Program owns MessageBox and
+        // Main.
+        for (name, namespace, method_list) in [(global, 0, 1), (program, namespace, 2)] {
+            tables.write_u32::<LittleEndian>(0).unwrap(); // Flags
+            tables.write_u16::<LittleEndian>(name).unwrap();
+            tables.write_u16::<LittleEndian>(namespace).unwrap();
+            tables.write_u16::<LittleEndian>(0).unwrap(); // Extends
+            tables.write_u16::<LittleEndian>(1).unwrap(); // FieldList
+            tables.write_u16::<LittleEndian>(method_list).unwrap(); // MethodList
+        }
+
+        // MethodDef
+        for (rva, impl_flags, flags, name) in [
+            (
+                HELPER_RVA as u32,
+                METHOD_IMPL_NATIVE | METHOD_IMPL_UNMANAGED,
+                0x0013,
+                helper,
+            ),
+            (0, 0x0080, METHOD_ATTRIBUTE_PINVOKE_IMPL | 0x0016, message_box),
+            (IL_METHOD_RVA as u32, METHOD_IMPL_IL, 0x0016, main),
+        ] {
+            tables.write_u32::<LittleEndian>(rva).unwrap();
+            tables.write_u16::<LittleEndian>(impl_flags).unwrap();
+            tables.write_u16::<LittleEndian>(flags).unwrap();
+            tables.write_u16::<LittleEndian>(name).unwrap();
+            tables.write_u16::<LittleEndian>(0).unwrap(); // Signature
+            tables.write_u16::<LittleEndian>(1).unwrap(); // ParamList
+        }
+
+        // ModuleRef
+        tables.write_u16::<LittleEndian>(user32).unwrap();
+
+        // ImplMap: MessageBox -> user32!MessageBoxA
+        tables.write_u16::<LittleEndian>(0x0100).unwrap(); // MappingFlags
+        tables.write_u16::<LittleEndian>(2 << 1 | 1).unwrap(); // MemberForwarded: MethodDef 2
+        tables.write_u16::<LittleEndian>(message_box_a).unwrap(); // ImportName
+        tables.write_u16::<LittleEndian>(1).unwrap(); // ImportScope
+        while tables.len() % 4 != 0 {
+            tables.push(0);
+        }
+
+        // metadata root
+        let version = b"v4.0.30319\x00\x00";
+        let streams_offset = 16 + version.len() + 4 + 12 + 20;
+        let mut root = vec![];
+        root.write_u32::<LittleEndian>(0x424A_5342).unwrap(); // Signature
+        root.write_u16::<LittleEndian>(1).unwrap(); // MajorVersion
+        root.write_u16::<LittleEndian>(1).unwrap(); // MinorVersion
+        root.write_u32::<LittleEndian>(0).unwrap(); // Reserved
+        root.write_u32::<LittleEndian>(version.len() as u32).unwrap(); // Length
+        root.extend_from_slice(version);
+        root.write_u16::<LittleEndian>(0).unwrap(); // Flags
+        root.write_u16::<LittleEndian>(2).unwrap(); // Streams
+        root.write_u32::<LittleEndian>(streams_offset as u32).unwrap();
+        root.write_u32::<LittleEndian>(tables.len() as u32).unwrap();
+        root.extend_from_slice(b"#~\x00\x00");
+        root.write_u32::<LittleEndian>((streams_offset + tables.len()) as u32)
+            .unwrap();
+        root.write_u32::<LittleEndian>(strings.len() as u32).unwrap();
+        root.extend_from_slice(b"#Strings\x00\x00\x00\x00");
+        assert_eq!(streams_offset, root.len());
+
+        root.extend_from_slice(&tables);
+        root.extend_from_slice(&strings);
+        root
+    }
+
+    /// Build a minimal x64 mixed-mode assembly (see `build_mapped_pe`),
+    /// with a CLR header at RVA 0x1200 and metadata at RVA 0x1300
+    /// that declares:
+    ///
+    /// ```text
+    ///   native_helper                 native code at HELPER_RVA
+    ///   Example.Program::MessageBox   P/Invoke to user32!MessageBoxA
+    ///   Example.Program::Main         IL at IL_METHOD_RVA
+    /// ```
+    pub fn build_mapped_pe_with_clr() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        let metadata = build_metadata();
+        let offset = METADATA_RVA as usize;
+        buf[offset..offset + metadata.len()].copy_from_slice(&metadata);
+
+        // IMAGE_COR20_HEADER
+        let mut header = vec![];
+        header.write_u32::<LittleEndian>(72).unwrap(); // cb
+        header.write_u16::<LittleEndian>(2).unwrap(); // MajorRuntimeVersion
+        header.write_u16::<LittleEndian>(5).unwrap(); // MinorRuntimeVersion
+        header.write_u32::<LittleEndian>(METADATA_RVA as u32).unwrap(); // MetaData
+        header.write_u32::<LittleEndian>(metadata.len() as u32).unwrap();
+        header.write_u32::<LittleEndian>(0).unwrap(); // Flags: mixed-mode
+        header.write_u32::<LittleEndian>(0x0600_0003).unwrap(); // EntryPointToken: Main
+        header.resize(72, 0);
+        let offset = COR20_HEADER_RVA as usize;
+        buf[offset..offset + header.len()].copy_from_slice(&header);
+
+        // ldc.i4.0; ret
+        let offset = IL_METHOD_RVA as usize;
+        buf[offset..offset + 3].copy_from_slice(&[0x02 | (2 << 2), 0x16, 0x2A]);
+
+        add_data_directory(
+            &mut buf,
+            IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR,
+            COR20_HEADER_RVA,
+            72,
+            0x800,
+        );
+
+        buf
+    }
+
+    #[test]
+    fn metadata() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_clr(), None)?;
+
+        let header = pe.clr_header()?.unwrap();
+        assert_eq!(METADATA_RVA, header.metadata_rva);
+        assert!(!header.is_il_only());
+
+        let metadata = Metadata::from_pe(&pe)?.unwrap();
+        assert_eq!("v4.0.30319", metadata.version);
+        assert_eq!(2, metadata.type_defs.len());
+        assert_eq!("Example.Program", metadata.type_defs[1].full_name());
+        assert_eq!(3, metadata.method_defs.len());
+        assert!(metadata.method_defs[0].is_native());
+        assert!(metadata.method_defs[1].is_pinvoke());
+        assert!(metadata.method_defs[2].is_il());
+
+        assert_eq!("native_helper", metadata.get_method_name(1).unwrap());
+        assert_eq!("Example.Program::MessageBox", metadata.get_method_name(2).unwrap());
+        assert_eq!("Example.Program::Main", metadata.get_method_name(3).unwrap());
+        assert!(metadata.get_method_name(4).is_none());
+
+        assert_eq!("user32", metadata.module_refs[0].name);
+        assert_eq!(1, metadata.impl_maps.len());
+        assert_eq!(0x0600_0002, metadata.impl_maps[0].member_forwarded);
+        assert_eq!("MessageBoxA", metadata.impl_maps[0].import_name);
+        assert_eq!(1, metadata.impl_maps[0].import_scope);
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_managed() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe(), None)?;
+        assert!(pe.clr_header()?.is_none());
+        assert!(Metadata::from_pe(&pe)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn k32() -> Result<()> {
+        let buf = get_buf(Rsrc::K32);
+        let pe = PE::from_bytes(&buf)?;
+
+        assert!(read_cor20_header(&pe)?.is_none());
+        assert!(Metadata::from_pe(&pe)?.is_none());
+
+        Ok(())
+    }
+}
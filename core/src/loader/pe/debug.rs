@@ -0,0 +1,150 @@
+//! Parse the CodeView record from the debug directory,
+//! which identifies the PDB that describes the build.
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use log::debug;
+
+use crate::loader::pe::PE;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeViewRecord {
+    /// the path of the PDB when the PE was linked, like `C:\build\foo.pdb`.
+    pub path: String,
+    pub guid: [u8; 16],
+    pub age:  u32,
+}
+
+impl CodeViewRecord {
+    /// Parse the `RSDS` CodeView record, if present.
+    pub fn from_pe(pe: &PE) -> Result<Option<CodeViewRecord>> {
+        let Some(info) = pe.pe()?.debug_data.and_then(|data| data.codeview_pdb70_debug_info) else {
+            debug!("debug: no CodeView record");
+            return Ok(None);
+        };
+
+        let path = String::from_utf8_lossy(info.filename)
+            .trim_end_matches('\0')
+            .to_string();
+
+        debug!("debug: CodeView record: {} age: {}", path, info.age);
+
+        Ok(Some(CodeViewRecord {
+            path,
+            guid: info.signature,
+            age: info.age,
+        }))
+    }
+
+    /// The file name of the PDB, without its directory.
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit(['\\', '/']).next().unwrap_or(&self.path)
+    }
+
+    /// The key that symbol servers use to store the PDB,
+    /// at `<file name>/<key>/<file name>`: the GUID, formatted like
+    /// `{00000000-0000-0000-0000-000000000000}` without punctuation,
+    /// followed by the age in hex.
+    pub fn symbol_store_key(&self) -> String {
+        let guid = &self.guid;
+        format!(
+            "{:08X}{:04X}{:04X}{}{:X}",
+            LittleEndian::read_u32(&guid[0..4]),
+            LittleEndian::read_u16(&guid[4..6]),
+            LittleEndian::read_u16(&guid[6..8]),
+            guid[8..].iter().map(|b| format!("{b:02X}")).collect::<String>(),
+            self.age
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::{
+        loader::{
+            pdb::tests::{PDB_AGE, PDB_GUID},
+            pe::tests::{add_data_directory, build_mapped_pe},
+        },
+        rsrc::*,
+    };
+
+    pub const PDB_PATH: &str = "C:\\build\\test.pdb";
+
+    /// Build a minimal x64 PE image in its memory layout (see
+    /// `build_mapped_pe`) with a debug directory at RVA 0x1200 that
+    /// references `PDB_PATH`, with the GUID and age of `build_pdb`.
+    pub fn build_mapped_pe_with_codeview() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        let mut record = vec![];
+        record.extend_from_slice(b"RSDS");
+        record.extend_from_slice(&PDB_GUID);
+        record.write_u32::<LittleEndian>(PDB_AGE).unwrap();
+        record.extend_from_slice(PDB_PATH.as_bytes());
+        record.push(0);
+        buf[0x1220..0x1220 + record.len()].copy_from_slice(&record);
+
+        // IMAGE_DEBUG_DIRECTORY
+        let mut directory = vec![];
+        directory.write_u32::<LittleEndian>(0).unwrap(); // Characteristics
+        directory.write_u32::<LittleEndian>(0).unwrap(); // TimeDateStamp
+        directory.write_u16::<LittleEndian>(0).unwrap(); // MajorVersion
+        directory.write_u16::<LittleEndian>(0).unwrap(); // MinorVersion
+        directory.write_u32::<LittleEndian>(2).unwrap(); // Type: IMAGE_DEBUG_TYPE_CODEVIEW
+        directory.write_u32::<LittleEndian>(record.len() as u32).unwrap(); // SizeOfData
+        directory.write_u32::<LittleEndian>(0x1220).unwrap(); // AddressOfRawData
+        directory.write_u32::<LittleEndian>(0x1220).unwrap(); // PointerToRawData
+        buf[0x1200..0x1200 + directory.len()].copy_from_slice(&directory);
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_DEBUG,
+            0x1200,
+            directory.len() as u32,
+            0x400,
+        );
+
+        buf
+    }
+
+    #[test]
+    fn codeview() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_codeview(), None)?;
+
+        let codeview = CodeViewRecord::from_pe(&pe)?.unwrap();
+        assert_eq!(PDB_PATH, codeview.path);
+        assert_eq!(PDB_GUID, codeview.guid);
+        assert_eq!(PDB_AGE, codeview.age);
+        assert_eq!("test.pdb", codeview.file_name());
+        assert_eq!("123456781234567801020304050607082", codeview.symbol_store_key());
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_codeview() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe(), None)?;
+        assert!(CodeViewRecord::from_pe(&pe)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn k32() -> Result<()> {
+        let buf = get_buf(Rsrc::K32);
+        let pe = PE::from_bytes(&buf)?;
+
+        let codeview = CodeViewRecord::from_pe(&pe)?.unwrap();
+        assert_eq!("kernel32.pdb", codeview.path);
+        assert_eq!("63816243EC704DC091BC31470BAC48A31", codeview.symbol_store_key());
+
+        // mimikatz is linked without debug info.
+        let buf = get_buf(Rsrc::MIMI);
+        let pe = PE::from_bytes(&buf)?;
+        assert!(CodeViewRecord::from_pe(&pe)?.is_none());
+
+        Ok(())
+    }
+}
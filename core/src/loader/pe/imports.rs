@@ -220,3 +220,237 @@ pub fn read_image_import_by_name(pe: &PE, va: VA) -> Result<IMAGE_IMPORT_BY_NAME
         name: pe.module.address_space.read_ascii(va + 2u64, 1)?,
     })
 }
+
+const sizeof_IMAGE_DELAYLOAD_DESCRIPTOR: usize = 0x20;
+
+/// when set, the fields of the descriptor are RVAs.
+/// otherwise, they're VAs, as emitted by older (VC6) linkers.
+const dlattrRva: u32 = 0x1;
+
+// ```
+//  0x0                       0x20
+//  +----------------------------+      +---------------------+
+//  | IMAGE_DELAYLOAD_DESCRIPTOR | ---> | IAT: ptr to stub    | -----> __imp_load_X:
+//  +----------------------------+ \    +---------------------+            lea  rax, [IAT entry]
+//  | ...                        |  |   | ...                 |            jmp  __tailMerge_X
+//  +----------------------------+  |   +---------------------+              -> __delayLoadHelper2
+//  | 00 00 00 00 00 00 00 00    |  |
+//  +----------------------------+  +-> +---------------------+
+//                                      | INT: IMAGE_THUNK_DATA | -> IMAGE_IMPORT_BY_NAME
+//                                      +---------------------+
+// ```
+//
+// the IAT entries initially point to stubs that resolve the import on first
+// use, via `__delayLoadHelper2`, and then overwrite the IAT entry.
+//
+// the fields are normalized to RVAs when read.
+#[derive(Clone)]
+pub struct IMAGE_DELAYLOAD_DESCRIPTOR {
+    pub attributes:                 u32,
+    pub dll_name:                   RVA,
+    pub module_handle:              RVA,
+    pub import_address_table:       RVA,
+    pub import_name_table:          RVA,
+    pub bound_import_address_table: RVA,
+    pub unload_information_table:   RVA,
+    pub time_date_stamp:            u32,
+}
+
+impl IMAGE_DELAYLOAD_DESCRIPTOR {
+    pub fn is_rva_based(&self) -> bool {
+        self.attributes & dlattrRva == dlattrRva
+    }
+
+    /// read the name of the DLL into a String.
+    pub fn read_name(&self, pe: &PE) -> Result<String> {
+        pe.module
+            .address_space
+            .read_ascii(pe.module.address_space.base_address + self.dll_name, 1)
+    }
+}
+
+impl std::fmt::Debug for IMAGE_DELAYLOAD_DESCRIPTOR {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "IMAGE_DELAYLOAD_DESCRIPTOR(IAT: {:#x} INT: {:#x} name: {:#x})",
+            self.import_address_table, self.import_name_table, self.dll_name
+        )
+    }
+}
+
+/// fetch the VA of the delay-load import directory, if it exists.
+pub fn get_delay_import_directory(pe: &PE) -> Result<Option<VA>> {
+    Ok(pe
+        .get_data_directory(crate::loader::pe::IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT)?
+        .filter(|directory| directory.size > 0)
+        .map(|directory| directory.address))
+}
+
+/// convert a field of a VA-based descriptor to an RVA.
+/// zero remains zero, as it indicates an absent table.
+fn delayload_va_to_rva(pe: &PE, va: VA) -> Result<RVA> {
+    if va == 0 {
+        return Ok(0);
+    }
+
+    va.checked_sub(pe.module.address_space.base_address).ok_or_else(|| {
+        crate::loader::pe::PEError::MalformedPEFile(format!("invalid delay-load descriptor address: {va:#x}")).into()
+    })
+}
+
+pub fn read_image_delayload_descriptor(pe: &PE, va: VA) -> Result<IMAGE_DELAYLOAD_DESCRIPTOR> {
+    let buf = pe
+        .module
+        .address_space
+        .read_bytes(va, sizeof_IMAGE_DELAYLOAD_DESCRIPTOR)?;
+
+    // these fields are all u32, even on 64-bit
+    let entries: Vec<u32> = buf.chunks_exact(0x4).map(LittleEndian::read_u32).collect();
+
+    let attributes = entries[0];
+    let to_rva = |field: u32| -> Result<RVA> {
+        if attributes & dlattrRva == dlattrRva {
+            Ok(field as RVA)
+        } else {
+            delayload_va_to_rva(pe, field as VA)
+        }
+    };
+
+    Ok(IMAGE_DELAYLOAD_DESCRIPTOR {
+        attributes,
+        dll_name: to_rva(entries[1])?,
+        module_handle: to_rva(entries[2])?,
+        import_address_table: to_rva(entries[3])?,
+        import_name_table: to_rva(entries[4])?,
+        bound_import_address_table: to_rva(entries[5])?,
+        unload_information_table: to_rva(entries[6])?,
+        time_date_stamp: entries[7],
+    })
+}
+
+pub fn read_delayload_descriptors(
+    pe: &PE,
+    delay_import_directory: VA,
+) -> impl Iterator<Item = IMAGE_DELAYLOAD_DESCRIPTOR> + '_ {
+    (0..usize::MAX)
+        .map(move |i| delay_import_directory + (i * sizeof_IMAGE_DELAYLOAD_DESCRIPTOR) as RVA)
+        .map(move |va| read_image_delayload_descriptor(pe, va))
+        .take_while(|desc| match desc {
+            // the table is terminated by an all-zero descriptor,
+            // though only the name is reliably zero.
+            Ok(desc) => desc.dll_name != 0x0,
+            Err(_) => false,
+        })
+        .map(|desc| desc.unwrap())
+}
+
+/// read the entries of the import name table (INT) of a delay-load
+/// descriptor, along with the address of the corresponding IAT entry,
+/// which is what code references.
+///
+/// for VA-based descriptors, the name pointers are converted to RVAs.
+pub fn read_delayload_thunks<'a>(
+    pe: &'a PE,
+    descriptor: &'a IMAGE_DELAYLOAD_DESCRIPTOR,
+) -> impl Iterator<Item = (VA, IMAGE_THUNK_DATA)> + 'a {
+    let base_address = pe.module.address_space.base_address;
+    let psize = pe.module.arch.pointer_size();
+
+    (0..usize::MAX)
+        .map(move |i| {
+            (
+                base_address + descriptor.import_address_table + (i * psize) as RVA,
+                read_image_thunk_data(pe, base_address + descriptor.import_name_table + (i * psize) as RVA),
+            )
+        })
+        .take_while(|(_, thunk)| !matches!(thunk, Err(_) | Ok(IMAGE_THUNK_DATA::Function(0x0))))
+        .map(move |(iat, thunk)| match thunk.unwrap() {
+            IMAGE_THUNK_DATA::Function(name) if !descriptor.is_rva_based() => {
+                (iat, IMAGE_THUNK_DATA::Function(name.wrapping_sub(base_address)))
+            }
+            thunk => (iat, thunk),
+        })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::loader::pe::tests::{add_data_directory, build_mapped_pe, IMAGE_BASE};
+
+    pub const DELAY_IMPORT_IAT_RVA: u64 = 0x1260;
+    pub const DELAY_IMPORT_STUB_RVA: u64 = 0x1020;
+
+    /// Build a minimal x64 PE image in its memory layout (see
+    /// `build_mapped_pe`) with an RVA-based delay-load import of
+    /// `USER32.dll!MessageBoxA`:
+    ///
+    /// ```text
+    ///   0x1020: lea rax, [0x1260]   ; __imp_load_MessageBoxA
+    ///   0x1027: jmp 0x1030
+    ///   0x1030: ret                 ; __tailMerge_USER32_dll
+    ///   0x1200: IMAGE_DELAYLOAD_DESCRIPTOR
+    ///   0x1240: "USER32.dll"
+    ///   0x1260: IAT, pointing to the stub at 0x1020
+    ///   0x1280: INT, pointing to the name at 0x12A0
+    /// ```
+    pub fn build_mapped_pe_with_delay_imports() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        let stub = DELAY_IMPORT_STUB_RVA as usize;
+        buf[stub..stub + 7].copy_from_slice(&[0x48, 0x8D, 0x05, 0x39, 0x02, 0x00, 0x00]);
+        buf[stub + 7..stub + 12].copy_from_slice(&[0xE9, 0x04, 0x00, 0x00, 0x00]);
+        buf[stub + 0x10] = 0xC3;
+
+        let mut descriptor = vec![];
+        descriptor.write_u32::<LittleEndian>(dlattrRva).unwrap(); // Attributes
+        descriptor.write_u32::<LittleEndian>(0x1240).unwrap(); // DllNameRVA
+        descriptor.write_u32::<LittleEndian>(0x1250).unwrap(); // ModuleHandleRVA
+        descriptor
+            .write_u32::<LittleEndian>(DELAY_IMPORT_IAT_RVA as u32)
+            .unwrap(); // ImportAddressTableRVA
+        descriptor.write_u32::<LittleEndian>(0x1280).unwrap(); // ImportNameTableRVA
+        descriptor.write_u32::<LittleEndian>(0).unwrap(); // BoundImportAddressTableRVA
+        descriptor.write_u32::<LittleEndian>(0).unwrap(); // UnloadInformationTableRVA
+        descriptor.write_u32::<LittleEndian>(0).unwrap(); // TimeDateStamp
+        buf[0x1200..0x1200 + descriptor.len()].copy_from_slice(&descriptor);
+
+        buf[0x1240..0x124A].copy_from_slice(b"USER32.dll");
+
+        let iat = DELAY_IMPORT_IAT_RVA as usize;
+        buf[iat..iat + 8].copy_from_slice(&(IMAGE_BASE + DELAY_IMPORT_STUB_RVA).to_le_bytes());
+        buf[0x1280..0x1288].copy_from_slice(&0x12A0u64.to_le_bytes());
+        buf[0x12A2..0x12AD].copy_from_slice(b"MessageBoxA");
+
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT,
+            0x1200,
+            0x40,
+            0x400,
+        );
+
+        buf
+    }
+
+    #[test]
+    fn delay_imports() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_delay_imports(), None)?;
+
+        let directory = get_delay_import_directory(&pe)?.unwrap();
+        let descriptors = read_delayload_descriptors(&pe, directory).collect::<Vec<_>>();
+        assert_eq!(1, descriptors.len());
+        assert!(descriptors[0].is_rva_based());
+        assert_eq!("USER32.dll", descriptors[0].read_name(&pe)?);
+
+        let thunks = read_delayload_thunks(&pe, &descriptors[0]).collect::<Vec<_>>();
+        assert_eq!(1, thunks.len());
+        assert_eq!(IMAGE_BASE + DELAY_IMPORT_IAT_RVA, thunks[0].0);
+        assert!(matches!(thunks[0].1, IMAGE_THUNK_DATA::Function(0x12A0)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,526 @@
+//! Parse the Load Config directory, which describes the security features
+//! of the image: the security cookie, SafeSEH handlers, and the Control Flow
+//! Guard (CFG) tables.
+//!
+//! The directory grows with each release of Windows, and its first field is
+//! its size, so fields beyond the size are treated as absent.
+//! The pointers it contains are VAs covered by base relocations,
+//! so they're read from the loaded image.
+//!
+//! references:
+//!   - https://docs.microsoft.com/en-us/windows/desktop/debug/pe-format#load-configuration-directory
+//!   - https://docs.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-image_load_config_directory32
+//!   - https://docs.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-image_load_config_directory64
+//!   - https://lucasg.github.io/2017/02/05/Control-Flow-Guard/
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use log::debug;
+
+use crate::{arch::Arch, aspace::AddressSpace, loader::pe::PE, RVA, VA};
+
+pub const IMAGE_GUARD_CF_INSTRUMENTED: u32 = 0x0000_0100;
+pub const IMAGE_GUARD_CFW_INSTRUMENTED: u32 = 0x0000_0200;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT: u32 = 0x0000_0400;
+pub const IMAGE_GUARD_SECURITY_COOKIE_UNUSED: u32 = 0x0000_0800;
+pub const IMAGE_GUARD_PROTECT_DELAYLOAD_IAT: u32 = 0x0000_1000;
+pub const IMAGE_GUARD_DELAYLOAD_IAT_IN_ITS_OWN_SECTION: u32 = 0x0000_2000;
+pub const IMAGE_GUARD_CF_EXPORT_SUPPRESSION_INFO_PRESENT: u32 = 0x0000_4000;
+pub const IMAGE_GUARD_CF_ENABLE_EXPORT_SUPPRESSION: u32 = 0x0000_8000;
+pub const IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT: u32 = 0x0001_0000;
+pub const IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT: u32 = 0x0040_0000;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK: u32 = 0xF000_0000;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT: u32 = 28;
+
+/// the target may not be called indirectly.
+pub const IMAGE_GUARD_FLAG_FID_SUPPRESSED: u8 = 0x01;
+/// the target is an export that may not be called indirectly
+/// until it's resolved via `GetProcAddress`.
+pub const IMAGE_GUARD_FLAG_EXPORT_SUPPRESSED: u8 = 0x02;
+pub const IMAGE_GUARD_FLAG_FID_LANGEXCPTHANDLER: u8 = 0x04;
+pub const IMAGE_GUARD_FLAG_FID_XFG: u8 = 0x08;
+
+/// An entry in one of the CFG tables: an RVA, followed by optional metadata
+/// whose size is given by the guard flags (the "stride").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuardEntry {
+    pub address: VA,
+    /// the `IMAGE_GUARD_FLAG_*` flags, or zero if the stride is zero.
+    pub flags:   u8,
+}
+
+impl GuardEntry {
+    pub fn is_suppressed(&self) -> bool {
+        self.flags & (IMAGE_GUARD_FLAG_FID_SUPPRESSED | IMAGE_GUARD_FLAG_EXPORT_SUPPRESSED) != 0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadConfig {
+    pub size: u32,
+
+    /// the address of the `__security_cookie` global.
+    pub security_cookie: Option<VA>,
+
+    /// the SafeSEH exception handlers (x32 only).
+    pub se_handler_table: Vec<VA>,
+
+    /// the address of the `__guard_check_icall_fptr` global,
+    /// which points to the routine that validates indirect call targets.
+    pub guard_cf_check_function_pointer:    Option<VA>,
+    /// the address of the `__guard_dispatch_icall_fptr` global,
+    /// which points to the routine that validates and makes indirect calls.
+    pub guard_cf_dispatch_function_pointer: Option<VA>,
+
+    /// the `IMAGE_GUARD_*` flags.
+    pub guard_flags: u32,
+
+    /// the valid indirect call targets.
+    pub guard_cf_function_table:             Vec<GuardEntry>,
+    /// the IAT entries of imports whose address is taken,
+    /// which may also be called indirectly.
+    pub guard_address_taken_iat_entry_table: Vec<GuardEntry>,
+    /// the valid `longjmp` targets.
+    pub guard_long_jump_target_table:        Vec<GuardEntry>,
+    /// the valid exception handler continuation targets.
+    pub guard_eh_continuation_table:         Vec<GuardEntry>,
+
+    /// the address of the dynamic value relocation table,
+    /// such as for import control transfers (retpoline).
+    pub dynamic_value_reloc_table: Option<VA>,
+    /// the address of the hybrid (CHPE/ARM64X) metadata.
+    pub chpe_metadata_pointer:     Option<VA>,
+}
+
+/// The offsets of the fields, which differ by architecture
+/// due to the pointer-sized fields.
+struct Layout {
+    security_cookie: RVA,
+    se_handler_table: RVA,
+    se_handler_count: RVA,
+    guard_cf_check_function_pointer: RVA,
+    guard_cf_dispatch_function_pointer: RVA,
+    guard_cf_function_table: RVA,
+    guard_cf_function_count: RVA,
+    guard_flags: RVA,
+    guard_address_taken_iat_entry_table: RVA,
+    guard_address_taken_iat_entry_count: RVA,
+    guard_long_jump_target_table: RVA,
+    guard_long_jump_target_count: RVA,
+    dynamic_value_reloc_table: RVA,
+    chpe_metadata_pointer: RVA,
+    guard_eh_continuation_table: RVA,
+    guard_eh_continuation_count: RVA,
+}
+
+const LAYOUT_32: Layout = Layout {
+    security_cookie: 0x3C,
+    se_handler_table: 0x40,
+    se_handler_count: 0x44,
+    guard_cf_check_function_pointer: 0x48,
+    guard_cf_dispatch_function_pointer: 0x4C,
+    guard_cf_function_table: 0x50,
+    guard_cf_function_count: 0x54,
+    guard_flags: 0x58,
+    guard_address_taken_iat_entry_table: 0x68,
+    guard_address_taken_iat_entry_count: 0x6C,
+    guard_long_jump_target_table: 0x70,
+    guard_long_jump_target_count: 0x74,
+    dynamic_value_reloc_table: 0x78,
+    chpe_metadata_pointer: 0x7C,
+    guard_eh_continuation_table: 0xA4,
+    guard_eh_continuation_count: 0xA8,
+};
+
+const LAYOUT_64: Layout = Layout {
+    security_cookie: 0x58,
+    se_handler_table: 0x60,
+    se_handler_count: 0x68,
+    guard_cf_check_function_pointer: 0x70,
+    guard_cf_dispatch_function_pointer: 0x78,
+    guard_cf_function_table: 0x80,
+    guard_cf_function_count: 0x88,
+    guard_flags: 0x90,
+    guard_address_taken_iat_entry_table: 0xA0,
+    guard_address_taken_iat_entry_count: 0xA8,
+    guard_long_jump_target_table: 0xB0,
+    guard_long_jump_target_count: 0xB8,
+    dynamic_value_reloc_table: 0xC0,
+    chpe_metadata_pointer: 0xC8,
+    guard_eh_continuation_table: 0x108,
+    guard_eh_continuation_count: 0x110,
+};
+
+struct LoadConfigReader<'a> {
+    pe:      &'a PE,
+    address: VA,
+    size:    u32,
+}
+
+impl LoadConfigReader<'_> {
+    fn contains(&self, offset: RVA, length: usize) -> bool {
+        offset + length as RVA <= self.size as RVA
+    }
+
+    fn read_u32(&self, offset: RVA) -> Result<Option<u32>> {
+        if !self.contains(offset, 4) {
+            return Ok(None);
+        }
+        Ok(Some(self.pe.module.address_space.read_u32(self.address + offset)?))
+    }
+
+    /// read a pointer-sized field, like a VA or count.
+    fn read_pointer(&self, offset: RVA) -> Result<Option<u64>> {
+        let arch = self.pe.module.arch;
+        if !self.contains(offset, arch.pointer_size()) {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.pe.module.address_space.read_pointer(arch, self.address + offset)?,
+        ))
+    }
+
+    /// read a pointer-sized VA field, treating zero as absent.
+    fn read_va(&self, offset: RVA) -> Result<Option<VA>> {
+        Ok(self.read_pointer(offset)?.filter(|&va| va != 0))
+    }
+
+    /// read the table referenced by the pointer and count at the given offsets,
+    /// with entries of a u32 RVA followed by `stride` bytes of metadata.
+    fn read_table(&self, name: &str, table: RVA, count: RVA, stride: usize) -> Vec<GuardEntry> {
+        let (Ok(Some(table)), Ok(Some(count))) = (self.read_va(table), self.read_pointer(count)) else {
+            return vec![];
+        };
+        debug!("load config: {}: {:#x} count: {:#x}", name, table, count);
+
+        let entry_size = 4 + stride;
+        // the count is untrusted, so don't read beyond the section containing the
+        // table.
+        let Some(section) = self
+            .pe
+            .module
+            .sections
+            .iter()
+            .find(|section| section.virtual_range.contains(&table))
+        else {
+            debug!("load config: {}: table not in a section", name);
+            return vec![];
+        };
+        let max_count = (section.virtual_range.end - table) / entry_size as u64;
+        if count > max_count {
+            debug!("load config: {}: count exceeds section: {:#x}", name, count);
+        }
+        let count = count.min(max_count);
+
+        // read the table once up front, rather than each entry,
+        // which is much faster for large tables.
+        let Ok(buf) = self
+            .pe
+            .module
+            .address_space
+            .read_bytes(table, count as usize * entry_size)
+        else {
+            debug!("load config: {}: failed to read table", name);
+            return vec![];
+        };
+
+        let base_address = self.pe.module.address_space.base_address;
+        buf.chunks_exact(entry_size)
+            .map(|entry| GuardEntry {
+                address: base_address + LittleEndian::read_u32(entry) as RVA,
+                flags:   if stride > 0 { entry[4] } else { 0 },
+            })
+            .collect()
+    }
+}
+
+impl LoadConfig {
+    /// Parse the Load Config directory, if present.
+    pub fn from_pe(pe: &PE) -> Result<Option<LoadConfig>> {
+        let Some(directory) = pe.get_data_directory(crate::loader::pe::IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG)? else {
+            return Ok(None);
+        };
+        if directory.size == 0 {
+            return Ok(None);
+        }
+        debug!("load config directory: {:#x}", directory.address);
+
+        // according to IDA, the first DWORD is `Size` not `Characteristics` (unused).
+        let size = pe.module.address_space.read_u32(directory.address)?;
+        debug!("load config: size: {:#x}", size);
+
+        let r = LoadConfigReader {
+            pe,
+            address: directory.address,
+            size,
+        };
+
+        let layout = match pe.module.arch {
+            Arch::X32 => &LAYOUT_32,
+            Arch::X64 => &LAYOUT_64,
+        };
+
+        let guard_flags = r.read_u32(layout.guard_flags)?.unwrap_or_default();
+        debug!("load config: guard flags: {:#x}", guard_flags);
+
+        let stride = ((guard_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK)
+            >> IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT) as usize;
+        if stride > 8 {
+            // stride should really be 1, but we'll accept up to 8 for future compatibility.
+            debug!("load config: unexpected CF guard stride: {:#x}", stride);
+            return Ok(Some(LoadConfig {
+                size,
+                guard_flags,
+                ..Default::default()
+            }));
+        }
+
+        let guard_cf_function_table = if guard_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT > 0 {
+            r.read_table(
+                "guard CF function table",
+                layout.guard_cf_function_table,
+                layout.guard_cf_function_count,
+                stride,
+            )
+        } else {
+            vec![]
+        };
+
+        // the table is present alongside the export suppression info,
+        // though its count is zero when no import's address is taken.
+        let guard_address_taken_iat_entry_table = r.read_table(
+            "guard address taken IAT entry table",
+            layout.guard_address_taken_iat_entry_table,
+            layout.guard_address_taken_iat_entry_count,
+            stride,
+        );
+
+        let guard_long_jump_target_table = if guard_flags & IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT > 0 {
+            r.read_table(
+                "guard longjmp target table",
+                layout.guard_long_jump_target_table,
+                layout.guard_long_jump_target_count,
+                stride,
+            )
+        } else {
+            vec![]
+        };
+
+        let guard_eh_continuation_table = if guard_flags & IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT > 0 {
+            r.read_table(
+                "guard EH continuation table",
+                layout.guard_eh_continuation_table,
+                layout.guard_eh_continuation_count,
+                stride,
+            )
+        } else {
+            vec![]
+        };
+
+        // the SafeSEH table has no metadata.
+        let se_handler_table = r
+            .read_table("SafeSEH table", layout.se_handler_table, layout.se_handler_count, 0)
+            .into_iter()
+            .map(|entry| entry.address)
+            .collect();
+
+        Ok(Some(LoadConfig {
+            size,
+            security_cookie: r.read_va(layout.security_cookie)?,
+            se_handler_table,
+            guard_cf_check_function_pointer: r.read_va(layout.guard_cf_check_function_pointer)?,
+            guard_cf_dispatch_function_pointer: r.read_va(layout.guard_cf_dispatch_function_pointer)?,
+            guard_flags,
+            guard_cf_function_table,
+            guard_address_taken_iat_entry_table,
+            guard_long_jump_target_table,
+            guard_eh_continuation_table,
+            dynamic_value_reloc_table: r.read_va(layout.dynamic_value_reloc_table)?,
+            chpe_metadata_pointer: r.read_va(layout.chpe_metadata_pointer)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::{loader::pe::tests::*, rsrc::*};
+
+    pub const SECURITY_COOKIE_RVA: u64 = 0x1320;
+    pub const GUARD_CHECK_ICALL_FPTR_RVA: u64 = 0x1328;
+
+    /// Build a minimal x64 PE image in its memory layout (see
+    /// `build_mapped_pe`) with a Load Config directory at RVA 0x1200 that
+    /// contains:
+    ///
+    /// ```text
+    ///   security cookie:          0x1320
+    ///   guard check icall fptr:   0x1328 -> HELPER_RVA
+    ///   guard CF function table:  ENTRY_RVA, HELPER_RVA (suppressed)
+    ///   address taken IAT table:  IAT_RVA
+    ///   longjmp target table:     0x1005
+    ///   EH continuation table:    0x1011
+    /// ```
+    pub fn build_mapped_pe_with_load_config() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        // entries have one byte of metadata: the stride.
+        let guard_flags = IMAGE_GUARD_CF_INSTRUMENTED
+            | IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT
+            | IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT
+            | IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT
+            | (1 << IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT);
+
+        let mut write_table = |rva: usize, entries: &[(u64, u8)]| {
+            for (i, &(address, flags)) in entries.iter().enumerate() {
+                let entry = rva + i * 5;
+                buf[entry..entry + 4].copy_from_slice(&(address as u32).to_le_bytes());
+                buf[entry + 4] = flags;
+            }
+        };
+        write_table(0x1340, &[(ENTRY_RVA, 0), (HELPER_RVA, IMAGE_GUARD_FLAG_FID_SUPPRESSED)]);
+        write_table(0x1380, &[(0x1005, 0)]);
+        write_table(0x13A0, &[(0x1011, 0)]);
+        write_table(0x13C0, &[(IAT_RVA, 0)]);
+
+        let fptr = GUARD_CHECK_ICALL_FPTR_RVA as usize;
+        buf[fptr..fptr + 8].copy_from_slice(&(IMAGE_BASE + HELPER_RVA).to_le_bytes());
+
+        // IMAGE_LOAD_CONFIG_DIRECTORY64
+        let mut directory = vec![0u8; 0x118];
+        let mut set = |offset: usize, value: u64| {
+            (&mut directory[offset..offset + 8])
+                .write_u64::<LittleEndian>(value)
+                .unwrap();
+        };
+        set(0x58, IMAGE_BASE + SECURITY_COOKIE_RVA); // SecurityCookie
+        set(0x70, IMAGE_BASE + GUARD_CHECK_ICALL_FPTR_RVA); // GuardCFCheckFunctionPointer
+        set(0x80, IMAGE_BASE + 0x1340); // GuardCFFunctionTable
+        set(0x88, 2); // GuardCFFunctionCount
+        set(0xA0, IMAGE_BASE + 0x13C0); // GuardAddressTakenIatEntryTable
+        set(0xA8, 1); // GuardAddressTakenIatEntryCount
+        set(0xB0, IMAGE_BASE + 0x1380); // GuardLongJumpTargetTable
+        set(0xB8, 1); // GuardLongJumpTargetCount
+        set(0x108, IMAGE_BASE + 0x13A0); // GuardEHContinuationTable
+        set(0x110, 1); // GuardEHContinuationCount
+        directory[0x0..0x4].copy_from_slice(&0x118u32.to_le_bytes()); // Size
+        directory[0x90..0x94].copy_from_slice(&guard_flags.to_le_bytes()); // GuardFlags
+        buf[0x1200..0x1200 + directory.len()].copy_from_slice(&directory);
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG,
+            0x1200,
+            directory.len() as u32,
+            0x400,
+        );
+
+        buf
+    }
+
+    #[test]
+    fn load_config() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_load_config(), None)?;
+        let load_config = pe.load_config()?.unwrap();
+
+        assert_eq!(0x118, load_config.size);
+        assert_eq!(Some(IMAGE_BASE + SECURITY_COOKIE_RVA), load_config.security_cookie);
+        assert_eq!(
+            Some(IMAGE_BASE + GUARD_CHECK_ICALL_FPTR_RVA),
+            load_config.guard_cf_check_function_pointer
+        );
+        assert_eq!(None, load_config.guard_cf_dispatch_function_pointer);
+        assert!(load_config.se_handler_table.is_empty());
+
+        assert_eq!(
+            vec![
+                GuardEntry {
+                    address: IMAGE_BASE + ENTRY_RVA,
+                    flags:   0,
+                },
+                GuardEntry {
+                    address: IMAGE_BASE + HELPER_RVA,
+                    flags:   IMAGE_GUARD_FLAG_FID_SUPPRESSED,
+                },
+            ],
+            load_config.guard_cf_function_table
+        );
+        assert!(load_config.guard_cf_function_table[1].is_suppressed());
+        assert_eq!(IMAGE_BASE + 0x1005, load_config.guard_long_jump_target_table[0].address);
+        assert_eq!(IMAGE_BASE + 0x1011, load_config.guard_eh_continuation_table[0].address);
+        assert_eq!(
+            vec![GuardEntry {
+                address: IMAGE_BASE + IAT_RVA,
+                flags:   0,
+            }],
+            load_config.guard_address_taken_iat_entry_table
+        );
+        assert_eq!(None, load_config.dynamic_value_reloc_table);
+        assert_eq!(None, load_config.chpe_metadata_pointer);
+
+        Ok(())
+    }
+
+    #[test]
+    fn table_count() -> Result<()> {
+        let mut buf = build_mapped_pe_with_load_config();
+        // GuardCFFunctionCount
+        buf[0x1200 + 0x88..0x1200 + 0x90].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        // the table is read up to the end of .text.
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+        let load_config = pe.load_config()?.unwrap();
+        let table = IMAGE_BASE + 0x1340;
+        let text = pe
+            .module
+            .sections
+            .iter()
+            .find(|section| section.virtual_range.contains(&table))
+            .unwrap();
+        assert_eq!(
+            ((text.virtual_range.end - table) / 5) as usize,
+            load_config.guard_cf_function_table.len()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_load_config() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe(), None)?;
+        assert!(pe.load_config()?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn k32() -> Result<()> {
+        let buf = get_buf(Rsrc::K32);
+        let pe = PE::from_bytes(&buf)?;
+        let load_config = pe.load_config()?.unwrap();
+
+        assert_eq!(Some(0x1_800A_81F0), load_config.security_cookie);
+        assert_eq!(Some(0x1_8007_9B00), load_config.guard_cf_check_function_pointer);
+        assert_eq!(0x1001_7500, load_config.guard_flags);
+        assert_eq!(1500, load_config.guard_cf_function_table.len());
+        assert_eq!(2, load_config.guard_address_taken_iat_entry_table.len());
+        assert!(load_config.se_handler_table.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn nop() -> Result<()> {
+        let buf = get_buf(Rsrc::NOP);
+        let pe = PE::from_bytes(&buf)?;
+        let load_config = pe.load_config()?.unwrap();
+
+        assert_eq!(Some(0x40_8420), load_config.security_cookie);
+        assert_eq!(2, load_config.se_handler_table.len());
+        assert_eq!(0, load_config.guard_flags);
+        assert!(load_config.guard_cf_function_table.is_empty());
+
+        Ok(())
+    }
+}
@@ -4,9 +4,15 @@ use anyhow::Result;
 use log::debug;
 use thiserror::Error;
 
+pub mod authenticode;
+pub mod clr;
+pub mod debug;
 pub mod imports;
+pub mod load_config;
 pub mod reloc;
+pub mod rich;
 pub mod rsrc;
+pub mod tls;
 
 use crate::{
     arch::Arch,
@@ -63,7 +69,43 @@ pub struct PE {
 
 impl PE {
     pub fn from_bytes(buf: &[u8]) -> Result<PE> {
-        load_pe(buf)
+        load_pe(buf, None)
+    }
+
+    /// Load the PE at `base_address` rather than its preferred image base,
+    /// applying the base relocations, like the Windows loader does when ASLR
+    /// moves the image.
+    pub fn from_bytes_at(buf: &[u8], base_address: VA) -> Result<PE> {
+        load_pe(buf, Some(base_address))
+    }
+
+    /// Load a PE image that's in its memory layout, such as one dumped from a
+    /// process by a sandbox or debugger, where each section is found at its
+    /// RVA rather than at `PointerToRawData`.
+    ///
+    /// When provided, `base_address` is the address at which the image was
+    /// found, which overrides the image base in the header. Relocations are
+    /// not applied, since the Windows loader already did that.
+    ///
+    /// The `buf` field of the result contains the image converted into a file
+    /// layout (see `unmap_image`), so that `PE.pe()` works as usual.
+    pub fn from_mapped_bytes(buf: &[u8], base_address: Option<VA>) -> Result<PE> {
+        load_pe(&unmap_image(buf, base_address)?, None)
+    }
+
+    /// Move the module to `base_address`, applying the base relocations so
+    /// that embedded pointers match the new location.
+    ///
+    /// The optional header continues to describe the file,
+    /// so use `module.address_space.base_address` to find where the image is
+    /// loaded.
+    pub fn rebase(&mut self, base_address: VA) -> Result<()> {
+        if base_address == self.module.address_space.base_address {
+            return Ok(());
+        }
+
+        *self = load_pe(&self.buf, Some(base_address))?;
+        Ok(())
     }
 
     pub fn executable_sections<'b>(&'b self) -> Box<dyn Iterator<Item = &'b Section> + 'b> {
@@ -79,6 +121,26 @@ impl PE {
         get_pe(&self.buf)
     }
 
+    /// Parse the Load Config directory, if present.
+    pub fn load_config(&self) -> Result<Option<load_config::LoadConfig>> {
+        load_config::LoadConfig::from_pe(self)
+    }
+
+    /// Decode the Rich header, if present.
+    pub fn rich_header(&self) -> Result<Option<rich::RichHeader>> {
+        rich::RichHeader::from_pe(self)
+    }
+
+    /// Parse the CLR header, which is present when the PE is a .NET assembly.
+    pub fn clr_header(&self) -> Result<Option<clr::IMAGE_COR20_HEADER>> {
+        clr::read_cor20_header(self)
+    }
+
+    /// Parse the Authenticode signatures, if any.
+    pub fn signatures(&self) -> Result<Vec<authenticode::Signature>> {
+        authenticode::read_signatures(self)
+    }
+
     pub fn get_data_directory(&self, data_directory: usize) -> Result<Option<DataDirectory>> {
         assert!(data_directory <= IMAGE_DIRECTORY_MAX);
 
@@ -110,11 +172,6 @@ fn get_pe(buf: &[u8]) -> Result<goblin::pe::PE> {
         }
     };
 
-    if let Some(opt) = pe.header.optional_header {
-        if opt.data_directories.get_clr_runtime_header().is_some() {
-            return Err(PEError::FormatNotSupported(".NET assembly".to_string()).into());
-        }
-    }
     Ok(pe)
 }
 
@@ -222,8 +279,126 @@ fn load_pe_section(
     })
 }
 
+/// Convert a PE image in its memory layout into one that can be parsed as a
+/// file, by pointing each section's raw data at its virtual address, so that
+/// file offsets and RVAs are the same.
+///
+/// Dumped images often have headers with wiped or stale sizes (for example,
+/// raw sizes from the file on disk, or zeros from a packer), so sizes are
+/// re-derived from the section table and the size of the buffer:
+///   - a section's size is its virtual size, falling back to its raw size,
+///     falling back to the distance to the next section,
+///   - the header size is the distance to the first section.
+///
+/// When provided, `base_address` is written into the image base field.
+pub fn unmap_image(buf: &[u8], base_address: Option<VA>) -> Result<Vec<u8>> {
+    use byteorder::{ByteOrder, LittleEndian};
+    use goblin::pe::{header, optional_header::MAGIC_64, section_table::SIZEOF_SECTION_TABLE};
+
+    let hdr = header::Header::parse(buf).map_err(|e| PEError::MalformedPEFile(e.to_string()))?;
+    let Some(opt) = hdr.optional_header else {
+        return Err(PEError::FormatNotSupported("no optional header".to_string()).into());
+    };
+
+    let optional_header_offset = hdr.dos_header.pe_pointer as usize + 4 + header::SIZEOF_COFF_HEADER;
+    let section_table_offset = optional_header_offset + hdr.coff_header.size_of_optional_header as usize;
+    let section_count = hdr.coff_header.number_of_sections as usize;
+    if section_table_offset + section_count * SIZEOF_SECTION_TABLE > buf.len() {
+        return Err(PEError::MalformedPEFile("section table out of bounds".to_string()).into());
+    }
+
+    let mut image = buf.to_vec();
+
+    if let Some(base_address) = base_address {
+        if opt.standard_fields.magic == MAGIC_64 {
+            LittleEndian::write_u64(&mut image[optional_header_offset + 24..], base_address);
+        } else {
+            LittleEndian::write_u32(&mut image[optional_header_offset + 28..], base_address as u32);
+        }
+    }
+
+    // the remaining data directories contain RVAs, except for:
+    //   - the certificate table, which contains a file offset and isn't mapped into
+    //     memory,
+    //   - the debug directory entries, which contain both an RVA and a file offset.
+    let data_directories_offset = optional_header_offset + if opt.standard_fields.magic == MAGIC_64 { 112 } else { 96 };
+    if opt.data_directories.get_certificate_table().is_some() {
+        let offset = data_directories_offset + IMAGE_DIRECTORY_ENTRY_SECURITY * 8;
+        image[offset..offset + 8].fill(0);
+    }
+    if let Some(debug) = opt.data_directories.get_debug_table() {
+        const SIZEOF_DEBUG_DIRECTORY: u64 = 28;
+        for i in 0..(debug.size as u64 / SIZEOF_DEBUG_DIRECTORY) {
+            let offset = (debug.virtual_address as u64 + i * SIZEOF_DEBUG_DIRECTORY) as usize;
+            if offset + SIZEOF_DEBUG_DIRECTORY as usize > image.len() {
+                break;
+            }
+            // PointerToRawData <- AddressOfRawData
+            let address = LittleEndian::read_u32(&image[offset + 20..]);
+            LittleEndian::write_u32(&mut image[offset + 24..], address);
+        }
+    }
+
+    let section_offsets = (0..section_count)
+        .map(|i| section_table_offset + i * SIZEOF_SECTION_TABLE)
+        .collect::<Vec<usize>>();
+    let mut section_addresses = section_offsets
+        .iter()
+        .map(|&offset| LittleEndian::read_u32(&image[offset + 12..]) as u64)
+        .collect::<Vec<u64>>();
+    section_addresses.sort_unstable();
+
+    let image_end = image.len() as u64;
+
+    // SizeOfHeaders
+    let headers_size = section_addresses.first().cloned().unwrap_or(PAGE_SIZE);
+    let headers_size = std::cmp::min(headers_size, image_end);
+    if opt.windows_fields.size_of_headers as u64 != headers_size {
+        debug!(
+            "pe: unmap: header size: {:#x} -> {:#x}",
+            opt.windows_fields.size_of_headers, headers_size
+        );
+        LittleEndian::write_u32(&mut image[optional_header_offset + 60..], headers_size as u32);
+    }
+
+    for &offset in section_offsets.iter() {
+        let virtual_size = LittleEndian::read_u32(&image[offset + 8..]) as u64;
+        let virtual_address = LittleEndian::read_u32(&image[offset + 12..]) as u64;
+        let raw_size = LittleEndian::read_u32(&image[offset + 16..]) as u64;
+
+        let next_address = section_addresses
+            .iter()
+            .cloned()
+            .find(|&address| address > virtual_address)
+            .unwrap_or(image_end);
+
+        let size = if virtual_size != 0 {
+            virtual_size
+        } else if raw_size != 0 {
+            raw_size
+        } else {
+            next_address.saturating_sub(virtual_address)
+        };
+        let size = std::cmp::min(size, image_end.saturating_sub(virtual_address));
+
+        debug!(
+            "pe: unmap: section at {:#x}: size: {:#x} (virtual: {:#x} raw: {:#x})",
+            virtual_address, size, virtual_size, raw_size
+        );
+
+        if virtual_size == 0 {
+            LittleEndian::write_u32(&mut image[offset + 8..], size as u32);
+        }
+        // SizeOfRawData, PointerToRawData
+        LittleEndian::write_u32(&mut image[offset + 16..], size as u32);
+        LittleEndian::write_u32(&mut image[offset + 20..], virtual_address as u32);
+    }
+
+    Ok(image)
+}
+
 // lots of further detail here: https://github.com/corkami/docs/blob/master/PE/PE.md
-fn load_pe(buf: &[u8]) -> Result<PE> {
+fn load_pe(buf: &[u8], requested_base_address: Option<VA>) -> Result<PE> {
     let pe = get_pe(buf)?;
 
     let arch = match pe.is_64 {
@@ -232,7 +407,7 @@ fn load_pe(buf: &[u8]) -> Result<PE> {
     };
     debug!("pe: arch: {:?}", arch);
 
-    let (base_address, section_alignment) = match pe.header.optional_header {
+    let (image_base, section_alignment) = match pe.header.optional_header {
         Some(opt) => (
             opt.windows_fields.image_base,
             opt.windows_fields.section_alignment as u64,
@@ -242,6 +417,22 @@ fn load_pe(buf: &[u8]) -> Result<PE> {
             (0x40_0000, PAGE_SIZE)
         }
     };
+
+    let base_address = match requested_base_address {
+        None => image_base,
+        Some(base_address) if base_address == image_base => base_address,
+        Some(_)
+            if pe.header.coff_header.characteristics & goblin::pe::characteristic::IMAGE_FILE_RELOCS_STRIPPED != 0 =>
+        {
+            return Err(PEError::FormatNotSupported("relocations stripped, cannot rebase".to_string()).into());
+        }
+        Some(base_address) if aspace::page_offset(base_address) != 0 => {
+            return Err(
+                PEError::FormatNotSupported(format!("base address not page aligned: {base_address:#x}")).into(),
+            );
+        }
+        Some(base_address) => base_address,
+    };
     debug!("pe: base address: {:#x}", base_address);
 
     let mut sections = vec![load_pe_header(buf, &pe, base_address)?];
@@ -316,25 +507,160 @@ fn load_pe(buf: &[u8]) -> Result<PE> {
     let module = Module {
         arch,
         sections,
+        section_headers: Default::default(),
         address_space: address_space.into_absolute(base_address)?,
     };
 
-    debug!("pe: loaded");
-    Ok(PE {
+    let mut pe = PE {
         buf: buf.to_vec(),
         module,
         optional_header: pe.header.optional_header,
-    })
+    };
+
+    // no-op unless loaded away from the image base.
+    reloc::apply_relocations(&mut pe)?;
+
+    debug!("pe: loaded");
+    Ok(pe)
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     #![allow(clippy::identity_op)]
 
     use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
 
     use crate::{aspace::AddressSpace, rsrc::*};
 
+    pub const IMAGE_BASE: u64 = 0x1_4000_0000;
+    pub const IMAGE_SIZE: u64 = 0x2000;
+    pub const ENTRY_RVA: u64 = 0x1000;
+    pub const HELPER_RVA: u64 = 0x1010;
+    pub const IMPORT_DESCRIPTOR_RVA: u64 = 0x1100;
+    pub const IAT_RVA: u64 = 0x1180;
+    /// the address of `KERNEL32.dll!ExitProcess` in the dumped process.
+    pub const RESOLVED_IMPORT: u64 = 0x7FFA_0000_1234;
+
+    /// Build a minimal x64 PE image in its memory layout, as if dumped from a
+    /// process, with a `.text` section at RVA 0x1000 containing:
+    ///
+    /// ```text
+    ///   0x1000: call 0x1010
+    ///   0x1005: ret
+    ///   0x1010: nop
+    ///   0x1011: ret
+    ///   0x1100: IMAGE_IMPORT_DESCRIPTOR for KERNEL32.dll, without OFT
+    ///   0x1180: IAT, resolved to RESOLVED_IMPORT
+    ///   0x11C0: "KERNEL32.dll"
+    /// ```
+    ///
+    /// The section header still contains the file layout, with the section
+    /// at raw offset 0x400.
+    pub fn build_mapped_pe() -> Vec<u8> {
+        let mut buf = vec![0u8; IMAGE_SIZE as usize];
+
+        // IMAGE_DOS_HEADER
+        buf[0..2].copy_from_slice(b"MZ");
+        buf[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+
+        let mut hdr = vec![];
+        hdr.extend_from_slice(b"PE\x00\x00");
+        // IMAGE_FILE_HEADER
+        hdr.write_u16::<LittleEndian>(0x8664).unwrap(); // Machine
+        hdr.write_u16::<LittleEndian>(1).unwrap(); // NumberOfSections
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // TimeDateStamp
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // PointerToSymbolTable
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // NumberOfSymbols
+        hdr.write_u16::<LittleEndian>(0xF0).unwrap(); // SizeOfOptionalHeader
+        hdr.write_u16::<LittleEndian>(0x22).unwrap(); // Characteristics: EXECUTABLE | LARGE_ADDRESS_AWARE
+                                                      // IMAGE_OPTIONAL_HEADER64
+        hdr.write_u16::<LittleEndian>(0x20B).unwrap(); // Magic
+        hdr.write_u16::<LittleEndian>(0).unwrap(); // linker version
+        hdr.write_u32::<LittleEndian>(0x200).unwrap(); // SizeOfCode
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // SizeOfInitializedData
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // SizeOfUninitializedData
+        hdr.write_u32::<LittleEndian>(ENTRY_RVA as u32).unwrap(); // AddressOfEntryPoint
+        hdr.write_u32::<LittleEndian>(0x1000).unwrap(); // BaseOfCode
+        hdr.write_u64::<LittleEndian>(IMAGE_BASE).unwrap(); // ImageBase
+        hdr.write_u32::<LittleEndian>(0x1000).unwrap(); // SectionAlignment
+        hdr.write_u32::<LittleEndian>(0x200).unwrap(); // FileAlignment
+        hdr.write_u16::<LittleEndian>(6).unwrap(); // MajorOperatingSystemVersion
+        hdr.write_u16::<LittleEndian>(0).unwrap();
+        hdr.write_u16::<LittleEndian>(0).unwrap(); // image version
+        hdr.write_u16::<LittleEndian>(0).unwrap();
+        hdr.write_u16::<LittleEndian>(6).unwrap(); // MajorSubsystemVersion
+        hdr.write_u16::<LittleEndian>(0).unwrap();
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // Win32VersionValue
+        hdr.write_u32::<LittleEndian>(IMAGE_SIZE as u32).unwrap(); // SizeOfImage
+        hdr.write_u32::<LittleEndian>(0x400).unwrap(); // SizeOfHeaders
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // CheckSum
+        hdr.write_u16::<LittleEndian>(3).unwrap(); // Subsystem: console
+        hdr.write_u16::<LittleEndian>(0x8160).unwrap(); // DllCharacteristics
+        hdr.write_u64::<LittleEndian>(0x10_0000).unwrap(); // SizeOfStackReserve
+        hdr.write_u64::<LittleEndian>(0x1000).unwrap(); // SizeOfStackCommit
+        hdr.write_u64::<LittleEndian>(0x10_0000).unwrap(); // SizeOfHeapReserve
+        hdr.write_u64::<LittleEndian>(0x1000).unwrap(); // SizeOfHeapCommit
+        hdr.write_u32::<LittleEndian>(0).unwrap(); // LoaderFlags
+        hdr.write_u32::<LittleEndian>(16).unwrap(); // NumberOfRvaAndSizes
+                                                    // DataDirectory
+        for i in 0..16 {
+            let (address, size) = match i {
+                super::IMAGE_DIRECTORY_ENTRY_IMPORT => (IMPORT_DESCRIPTOR_RVA, 0x28),
+                super::IMAGE_DIRECTORY_ENTRY_IAT => (IAT_RVA, 0x10),
+                _ => (0, 0),
+            };
+            hdr.write_u32::<LittleEndian>(address as u32).unwrap();
+            hdr.write_u32::<LittleEndian>(size).unwrap();
+        }
+        // IMAGE_SECTION_HEADER
+        hdr.extend_from_slice(b".text\x00\x00\x00");
+        hdr.write_u32::<LittleEndian>(0x1D0).unwrap(); // VirtualSize
+        hdr.write_u32::<LittleEndian>(0x1000).unwrap(); // VirtualAddress
+        hdr.write_u32::<LittleEndian>(0x200).unwrap(); // SizeOfRawData
+        hdr.write_u32::<LittleEndian>(0x400).unwrap(); // PointerToRawData
+        hdr.extend_from_slice(&[0u8; 12]); // relocations, line numbers
+        hdr.write_u32::<LittleEndian>(0x6000_0020).unwrap(); // Characteristics: CODE | EXECUTE | READ
+
+        buf[0x80..0x80 + hdr.len()].copy_from_slice(&hdr);
+
+        let text = 0x1000usize;
+        buf[text..text + 6].copy_from_slice(&[0xE8, 0x0B, 0x00, 0x00, 0x00, 0xC3]);
+        buf[text + 0x10..text + 0x12].copy_from_slice(&[0x90, 0xC3]);
+
+        let descriptor = IMPORT_DESCRIPTOR_RVA as usize;
+        buf[descriptor + 12..descriptor + 16].copy_from_slice(&0x11C0u32.to_le_bytes()); // Name
+        buf[descriptor + 16..descriptor + 20].copy_from_slice(&(IAT_RVA as u32).to_le_bytes()); // FirstThunk
+
+        let iat = IAT_RVA as usize;
+        buf[iat..iat + 8].copy_from_slice(&RESOLVED_IMPORT.to_le_bytes());
+
+        buf[0x11C0..0x11CC].copy_from_slice(b"KERNEL32.dll");
+
+        buf
+    }
+
+    /// The offset of the data directory entry at `index` in the headers of
+    /// `build_mapped_pe`.
+    pub fn data_directory_offset(index: usize) -> usize {
+        0x80 + 4 + 20 + 112 + 8 * index
+    }
+
+    /// Extend `build_mapped_pe` with a data directory: point the entry at
+    /// `index` to `rva` with `size`, and grow `.text` to `text_size` bytes,
+    /// so that it covers the directory and any data that it references.
+    pub fn add_data_directory(buf: &mut [u8], index: usize, rva: u64, size: u32, text_size: u32) {
+        // IMAGE_SECTION_HEADER: VirtualSize, SizeOfRawData
+        let section = 0x80 + 4 + 20 + 0xF0;
+        buf[section + 8..section + 12].copy_from_slice(&text_size.to_le_bytes());
+        buf[section + 16..section + 20].copy_from_slice(&text_size.to_le_bytes());
+
+        // IMAGE_DATA_DIRECTORY
+        let entry = data_directory_offset(index);
+        buf[entry..entry + 4].copy_from_slice(&(rva as u32).to_le_bytes());
+        buf[entry + 4..entry + 8].copy_from_slice(&size.to_le_bytes());
+    }
+
     #[test]
     fn base_address() -> Result<()> {
         let buf = get_buf(Rsrc::K32);
@@ -423,4 +749,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn mapped() -> Result<()> {
+        let buf = build_mapped_pe();
+
+        // as a file, the code is expected at raw offset 0x400, which is empty.
+        let pe = crate::loader::pe::PE::from_bytes(&buf)?;
+        assert_eq!(0x00, pe.module.address_space.read_u8(IMAGE_BASE + ENTRY_RVA)?);
+
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+        assert_eq!(IMAGE_BASE, pe.module.address_space.base_address);
+        assert_eq!(0xE8, pe.module.address_space.read_u8(IMAGE_BASE + ENTRY_RVA)?);
+        assert_eq!(0x90, pe.module.address_space.read_u8(IMAGE_BASE + HELPER_RVA)?);
+
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, Some(0x7FF6_0000_0000))?;
+        assert_eq!(0x7FF6_0000_0000, pe.module.address_space.base_address);
+        assert_eq!(0xE8, pe.module.address_space.read_u8(0x7FF6_0000_0000 + ENTRY_RVA)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mapped_wiped_sizes() -> Result<()> {
+        let mut buf = build_mapped_pe();
+
+        // SizeOfHeaders
+        buf[0x80 + 4 + 20 + 60..][..4].fill(0);
+        // .text VirtualSize, SizeOfRawData
+        let section = 0x80 + 4 + 20 + 0xF0;
+        buf[section + 8..section + 12].fill(0);
+        buf[section + 16..section + 20].fill(0);
+
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+        assert_eq!(0x4D, pe.module.address_space.read_u8(IMAGE_BASE)?);
+        assert_eq!(0xE8, pe.module.address_space.read_u8(IMAGE_BASE + ENTRY_RVA)?);
+
+        let text = pe.module.sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(IMAGE_BASE + 0x1000..IMAGE_BASE + 0x2000, text.virtual_range);
+
+        Ok(())
+    }
 }
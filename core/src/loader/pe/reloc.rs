@@ -106,6 +106,11 @@ impl RelocSectionData {
             const header_size: usize = 8;
             const entry_size: usize = 2;
 
+            if size < header_size {
+                debug!("reloc: invalid block size: {size:#x}");
+                break;
+            }
+
             let entry_count = (size - header_size) / entry_size;
             for entry_index in 0..entry_count {
                 let entry = self.read_u16(offset + header_size + (entry_index * entry_size))?;
@@ -177,7 +182,7 @@ pub fn apply_relocations(pe: &mut PE) -> Result<()> {
         return Ok(());
     };
 
-    let delta = (found as i64) - (wanted as i64);
+    let delta = (found as i64).wrapping_sub(wanted as i64);
 
     debug!("reloc: applying {} relocations", relocations.len());
 
@@ -188,22 +193,22 @@ pub fn apply_relocations(pe: &mut PE) -> Result<()> {
             ImageRelocationType::IMAGE_REL_BASED_DIR64 => {
                 debug!("reloc: applying DIR64 at {:x}", relocation.address);
                 let existing = pe.module.address_space.read_u64(relocation.address)?;
-                let updated = existing as i64 + delta;
+                let updated = (existing as i64).wrapping_add(delta);
                 pe.module.address_space.write_u64(relocation.address, updated as u64)?;
             }
             ImageRelocationType::IMAGE_REL_BASED_HIGHLOW => {
                 let existing = pe.module.address_space.read_u32(relocation.address)?;
-                let updated = existing + (delta & 0xFFFF_FFFF) as u32;
+                let updated = existing.wrapping_add((delta & 0xFFFF_FFFF) as u32);
                 pe.module.address_space.write_u32(relocation.address, updated)?;
             }
             ImageRelocationType::IMAGE_REL_BASED_HIGH => {
                 let existing = pe.module.address_space.read_u16(relocation.address)?;
-                let updated = existing + (((delta >> 16) & 0xFFFF) as u16);
+                let updated = existing.wrapping_add(((delta >> 16) & 0xFFFF) as u16);
                 pe.module.address_space.write_u16(relocation.address, updated)?;
             }
             ImageRelocationType::IMAGE_REL_BASED_LOW => {
                 let existing = pe.module.address_space.read_u16(relocation.address)?;
-                let updated = existing + ((delta & 0xFFFF) as u16);
+                let updated = existing.wrapping_add((delta & 0xFFFF) as u16);
                 pe.module.address_space.write_u16(relocation.address, updated)?;
             }
             _ => unimplemented!(),
@@ -245,7 +250,57 @@ mod tests {
 
     #[test]
     fn apply_relocations() -> Result<()> {
-        // haven't tested a mapping at the non-preferred base address yet.
+        use crate::{
+            aspace::AddressSpace,
+            loader::pe::tests::{add_data_directory, build_mapped_pe, HELPER_RVA, IMAGE_BASE},
+        };
+
+        const POINTER_RVA: u64 = 0x1080;
+        const RELOC_RVA: u64 = 0x1090;
+
+        let mut buf = build_mapped_pe();
+        // a pointer to the helper routine, with a DIR64 base relocation.
+        buf[POINTER_RVA as usize..][..8].copy_from_slice(&(IMAGE_BASE + HELPER_RVA).to_le_bytes());
+        let block = RELOC_RVA as usize;
+        buf[block..block + 4].copy_from_slice(&0x1000u32.to_le_bytes()); // VirtualAddress
+        buf[block + 4..block + 8].copy_from_slice(&0xCu32.to_le_bytes()); // SizeOfBlock
+        buf[block + 8..block + 10].copy_from_slice(&((10u16 << 12) | 0x080).to_le_bytes());
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_BASERELOC,
+            RELOC_RVA,
+            0xC,
+            0x200,
+        );
+
+        let mut pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+        assert_eq!(
+            IMAGE_BASE + HELPER_RVA,
+            pe.module.address_space.read_u64(IMAGE_BASE + POINTER_RVA)?
+        );
+
+        const NEW_BASE: u64 = 0x7FF6_0000_0000;
+        pe.rebase(NEW_BASE)?;
+        assert_eq!(NEW_BASE, pe.module.address_space.base_address);
+        assert_eq!(
+            NEW_BASE + HELPER_RVA,
+            pe.module.address_space.read_u64(NEW_BASE + POINTER_RVA)?
+        );
+        let text = pe.module.sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(NEW_BASE + 0x1000, text.virtual_range.start);
+        assert_eq!(
+            vec![NEW_BASE + 0x1000],
+            crate::analysis::pe::entrypoints::find_pe_entrypoint(&pe)?
+        );
+
+        // rebasing again applies the relocations relative to the image base in the
+        // header.
+        pe.rebase(0x1000_0000)?;
+        assert_eq!(
+            0x1000_0000 + HELPER_RVA,
+            pe.module.address_space.read_u64(0x1000_0000 + POINTER_RVA)?
+        );
+
         Ok(())
     }
 }
@@ -0,0 +1,397 @@
+//! Decode the Rich header, which the Microsoft linker writes between the DOS
+//! stub and the PE header. It records the tools that produced the objects
+//! that were linked, by product ID and build number, along with the count of
+//! objects from each.
+//!
+//! The header is obfuscated by XOR with a key, which is also a checksum over
+//! the DOS header and the entries:
+//!
+//! ```text
+//!   "DanS" ^ key, 0 ^ key, 0 ^ key, 0 ^ key
+//!   (comp.id ^ key, count ^ key) ...
+//!   "Rich", key
+//! ```
+//!
+//! ref: http://bytepointer.com/articles/the_microsoft_rich_header.htm
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use log::debug;
+
+use crate::loader::pe::PE;
+
+const DANS: u32 = 0x536E_6144; // "DanS"
+const RICH: u32 = 0x6863_6952; // "Rich"
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    /// counts the imported symbols, rather than objects.
+    Import,
+    Linker,
+    CCompiler,
+    CPPCompiler,
+    /// such as the compiler for MSIL, or the Phoenix backend.
+    OtherCompiler,
+    Assembler,
+    /// `cvtres`, which converts resources into an object.
+    Resource,
+    /// `lib /def`, which produces the export object for a DLL.
+    Export,
+    /// `lib`, which produces import libraries.
+    ImportLibrary,
+    /// such as `aliasobj` and `cvtpgd`.
+    Other,
+}
+
+/// The tool identified by a product ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Product {
+    pub kind:    ToolKind,
+    /// the Visual Studio version that shipped the tool, as (major, minor),
+    /// like (14, 0) for Visual Studio 2015 and later,
+    /// which is also the version of its linker.
+    /// the compiler is five major versions ahead, like 19.00.
+    pub version: (u16, u16),
+}
+
+/// Layout of the blocks of product IDs assigned per release since Visual
+/// Studio 2010, each starting with the tools, followed by the compilers.
+const BLOCK_TOOLS: [ToolKind; 7] = [
+    ToolKind::Other,         // aliasobj
+    ToolKind::Other,         // cvtpgd
+    ToolKind::Resource,      // cvtres
+    ToolKind::Export,        // export
+    ToolKind::ImportLibrary, // implib
+    ToolKind::Linker,        // linker
+    ToolKind::Assembler,     // masm
+];
+const BLOCK_COMPILERS: [ToolKind; 11] = [
+    ToolKind::CCompiler,     // utc_C
+    ToolKind::CPPCompiler,   // utc_CPP
+    ToolKind::CCompiler,     // utc_CVTCIL_C
+    ToolKind::CPPCompiler,   // utc_CVTCIL_CPP
+    ToolKind::CCompiler,     // utc_LTCG_C
+    ToolKind::CPPCompiler,   // utc_LTCG_CPP
+    ToolKind::OtherCompiler, // utc_LTCG_MSIL
+    ToolKind::CCompiler,     // utc_POGO_I_C
+    ToolKind::CPPCompiler,   // utc_POGO_I_CPP
+    ToolKind::CCompiler,     // utc_POGO_O_C
+    ToolKind::CPPCompiler,   // utc_POGO_O_CPP
+];
+/// (first product ID, version) of each block,
+/// for which the compilers immediately follow the tools.
+const BLOCKS: [(u16, (u16, u16)); 5] = [
+    (0x00B5, (10, 10)),
+    (0x00C7, (11, 0)),
+    (0x00D9, (12, 0)),
+    (0x00EB, (12, 10)),
+    (0x00FD, (14, 0)),
+];
+
+impl Product {
+    /// Identify the tool by its product ID, as assigned by the Microsoft
+    /// toolchain from Visual Studio 6.0 through Visual Studio 2022.
+    pub fn from_product_id(product_id: u16) -> Option<Product> {
+        use ToolKind::*;
+
+        let product = |kind, version| Some(Product { kind, version });
+
+        for &(start, version) in BLOCKS.iter() {
+            let Some(index) = product_id.checked_sub(start).map(|index| index as usize) else {
+                continue;
+            };
+            if index < BLOCK_TOOLS.len() {
+                return product(BLOCK_TOOLS[index], version);
+            } else if index < BLOCK_TOOLS.len() + BLOCK_COMPILERS.len() {
+                return product(BLOCK_COMPILERS[index - BLOCK_TOOLS.len()], version);
+            }
+        }
+
+        match product_id {
+            0x0001 => product(Import, (0, 0)),
+
+            // Visual Studio 6.0
+            0x0004 => product(Linker, (6, 0)),
+            0x000A | 0x0015 | 0x0017 => product(CCompiler, (6, 0)),
+            0x000B | 0x0016 | 0x0018 => product(CPPCompiler, (6, 0)),
+            0x000D => product(OtherCompiler, (6, 0)), // Visual Basic 6.0
+
+            // Visual Studio .NET 2002
+            0x0019 => product(ImportLibrary, (7, 0)),
+            0x001C | 0x002B | 0x0039 | 0x0041 | 0x0043 => product(CCompiler, (7, 0)),
+            0x001D | 0x002C | 0x003A | 0x0042 | 0x0044 => product(CPPCompiler, (7, 0)),
+            0x003D => product(Linker, (7, 0)),
+            0x003F => product(Export, (7, 0)),
+            0x0040 => product(Assembler, (7, 0)),
+            0x0045 => product(Resource, (7, 0)),
+
+            // Visual Studio .NET 2003
+            0x000F => product(Assembler, (7, 10)),
+            0x005A => product(Linker, (7, 10)),
+            0x005C => product(Export, (7, 10)),
+            0x005D => product(ImportLibrary, (7, 10)),
+            0x005E => product(Resource, (7, 10)),
+            0x005F..=0x0068 if product_id % 2 == 1 => product(CCompiler, (7, 10)),
+            0x005F..=0x0068 => product(CPPCompiler, (7, 10)),
+
+            // Visual Studio 2005
+            0x006D..=0x0076 if product_id % 2 == 1 => product(CCompiler, (8, 0)),
+            0x006D..=0x0076 => product(CPPCompiler, (8, 0)),
+            0x0078 => product(Linker, (8, 0)),
+            0x007A => product(Export, (8, 0)),
+            0x007B => product(ImportLibrary, (8, 0)),
+            0x007C => product(Resource, (8, 0)),
+            0x007D => product(Assembler, (8, 0)),
+            0x0080 => product(CCompiler, (8, 0)),
+            0x0081 => product(CPPCompiler, (8, 0)),
+            0x0082 => product(OtherCompiler, (8, 0)),
+
+            // Visual Studio 2008
+            0x0083..=0x008A if product_id % 2 == 1 => product(CCompiler, (9, 0)),
+            0x0083..=0x008A => product(CPPCompiler, (9, 0)),
+            0x008B => product(OtherCompiler, (9, 0)),
+            0x008C | 0x008E => product(CCompiler, (9, 0)),
+            0x008D | 0x008F => product(CPPCompiler, (9, 0)),
+            0x0091 => product(Linker, (9, 0)),
+            0x0092 => product(Export, (9, 0)),
+            0x0093 => product(ImportLibrary, (9, 0)),
+            0x0094 => product(Resource, (9, 0)),
+            0x0095 => product(Assembler, (9, 0)),
+
+            0x0097 => product(Resource, (0, 0)),
+
+            // Visual Studio 2010, with the Phoenix backend between the tools and compilers.
+            0x0098..=0x009E => product(BLOCK_TOOLS[(product_id - 0x0098) as usize], (10, 0)),
+            0x009F..=0x00A9 => product(OtherCompiler, (10, 0)),
+            0x00AA..=0x00B4 => product(BLOCK_COMPILERS[(product_id - 0x00AA) as usize], (10, 0)),
+
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RichEntry {
+    pub product_id: u16,
+    pub build:      u16,
+    /// the number of objects produced by the tool,
+    /// or the number of imported symbols.
+    pub count:      u32,
+}
+
+impl RichEntry {
+    /// the `@comp.id`, which combines the product ID and build number.
+    pub fn comp_id(&self) -> u32 {
+        ((self.product_id as u32) << 16) | self.build as u32
+    }
+
+    pub fn product(&self) -> Option<Product> {
+        Product::from_product_id(self.product_id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RichHeader {
+    /// the file offset of the decoded `DanS` marker.
+    pub offset:   usize,
+    /// the number of bytes from `DanS` up to, but excluding, `Rich`.
+    pub size:     usize,
+    pub key:      u32,
+    pub entries:  Vec<RichEntry>,
+    /// the checksum recomputed from the DOS header and entries,
+    /// which matches the key unless the header was modified.
+    pub checksum: u32,
+}
+
+impl RichHeader {
+    /// Decode the Rich header, if present.
+    pub fn from_pe(pe: &PE) -> Result<Option<RichHeader>> {
+        let pe_pointer = pe.pe()?.header.dos_header.pe_pointer as usize;
+        Ok(RichHeader::from_buf(&pe.buf[..std::cmp::min(pe_pointer, pe.buf.len())]))
+    }
+
+    /// Decode the Rich header found in the given data that precedes the PE
+    /// header.
+    fn from_buf(buf: &[u8]) -> Option<RichHeader> {
+        let dwords = buf.chunks_exact(4).map(LittleEndian::read_u32).collect::<Vec<u32>>();
+
+        // skip the DOS header.
+        let rich = (0x40 / 4..dwords.len().saturating_sub(1)).find(|&i| dwords[i] == RICH)?;
+        let key = dwords[rich + 1];
+
+        let Some(dans) = (0x40 / 4..rich).rev().find(|&i| dwords[i] ^ key == DANS) else {
+            debug!("rich: found Rich marker without DanS");
+            return None;
+        };
+
+        // DanS is followed by three zero dwords, then the entries.
+        let entries = dwords[std::cmp::min(dans + 4, rich)..rich]
+            .chunks_exact(2)
+            .map(|entry| {
+                let comp_id = entry[0] ^ key;
+                RichEntry {
+                    product_id: (comp_id >> 16) as u16,
+                    build:      comp_id as u16,
+                    count:      entry[1] ^ key,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let offset = dans * 4;
+        let checksum = compute_checksum(&buf[..offset], &entries);
+
+        debug!(
+            "rich: found {} entries, key: {:#x}, checksum: {:#x}",
+            entries.len(),
+            key,
+            checksum
+        );
+
+        Some(RichHeader {
+            offset,
+            size: (rich - dans) * 4,
+            key,
+            entries,
+            checksum,
+        })
+    }
+
+    pub fn is_checksum_valid(&self) -> bool {
+        self.checksum == self.key
+    }
+}
+
+/// the checksum covers the bytes before `DanS`, excluding `e_lfanew`,
+/// and the entries, each rotated by their position or count.
+fn compute_checksum(dos: &[u8], entries: &[RichEntry]) -> u32 {
+    let mut checksum = dos.len() as u32;
+
+    for (i, &b) in dos.iter().enumerate() {
+        if (0x3C..0x40).contains(&i) {
+            continue;
+        }
+        checksum = checksum.wrapping_add((b as u32).rotate_left(i as u32));
+    }
+
+    for entry in entries.iter() {
+        checksum = checksum.wrapping_add(entry.comp_id().rotate_left(entry.count));
+    }
+
+    checksum
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::loader::pe::tests::build_mapped_pe;
+
+    /// (product ID, build, count): the C++ compiler and linker of
+    /// Visual Studio 2019, and imports.
+    pub const RICH_ENTRIES: [(u16, u16, u32); 3] = [(0x0105, 30133, 12), (0x0102, 30133, 1), (0x0001, 0, 42)];
+
+    /// Insert a Rich header with `RICH_ENTRIES` into the DOS stub of the
+    /// given image, at 0x40, and set the linker version to 14.29.
+    pub fn add_rich_header(buf: &mut [u8]) {
+        let entries = RICH_ENTRIES
+            .iter()
+            .map(|&(product_id, build, count)| RichEntry {
+                product_id,
+                build,
+                count,
+            })
+            .collect::<Vec<_>>();
+        let key = compute_checksum(&buf[..0x40], &entries);
+
+        let mut dwords = vec![DANS ^ key, key, key, key];
+        for entry in entries.iter() {
+            dwords.push(entry.comp_id() ^ key);
+            dwords.push(entry.count ^ key);
+        }
+        dwords.push(RICH);
+        dwords.push(key);
+
+        for (i, dword) in dwords.iter().enumerate() {
+            buf[0x40 + i * 4..0x40 + i * 4 + 4].copy_from_slice(&dword.to_le_bytes());
+        }
+
+        // MajorLinkerVersion, MinorLinkerVersion
+        buf[0x80 + 4 + 20 + 2] = 14;
+        buf[0x80 + 4 + 20 + 3] = 29;
+    }
+
+    #[test]
+    fn rich_header() -> Result<()> {
+        let mut buf = build_mapped_pe();
+        add_rich_header(&mut buf);
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+
+        let rich = RichHeader::from_pe(&pe)?.unwrap();
+        assert_eq!(0x40, rich.offset);
+        assert_eq!(0x28, rich.size);
+        assert!(rich.is_checksum_valid());
+        assert_eq!(
+            vec![
+                RichEntry {
+                    product_id: 0x0105,
+                    build:      30133,
+                    count:      12,
+                },
+                RichEntry {
+                    product_id: 0x0102,
+                    build:      30133,
+                    count:      1,
+                },
+                RichEntry {
+                    product_id: 0x0001,
+                    build:      0,
+                    count:      42,
+                },
+            ],
+            rich.entries
+        );
+        assert_eq!(0x010575B5, rich.entries[0].comp_id());
+
+        // tamper with the count of objects.
+        let count = 0x40 + 16 + 4;
+        buf[count] ^= 1;
+        let pe = PE::from_mapped_bytes(&buf, None)?;
+        let rich = RichHeader::from_pe(&pe)?.unwrap();
+        assert_eq!(13, rich.entries[0].count);
+        assert!(!rich.is_checksum_valid());
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_rich_header() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe(), None)?;
+        assert!(RichHeader::from_pe(&pe)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn products() {
+        let product = |product_id| Product::from_product_id(product_id).map(|p| (p.kind, p.version));
+
+        // Visual Studio 2015 and later.
+        assert_eq!(Some((ToolKind::Linker, (14, 0))), product(0x0102));
+        assert_eq!(Some((ToolKind::Assembler, (14, 0))), product(0x0103));
+        assert_eq!(Some((ToolKind::CCompiler, (14, 0))), product(0x0104));
+        assert_eq!(Some((ToolKind::CPPCompiler, (14, 0))), product(0x0105));
+        assert_eq!(Some((ToolKind::CPPCompiler, (14, 0))), product(0x010E));
+        // Visual Studio 2013
+        assert_eq!(Some((ToolKind::Linker, (12, 0))), product(0x00DE));
+        assert_eq!(Some((ToolKind::CPPCompiler, (12, 0))), product(0x00E1));
+        // Visual Studio 2010
+        assert_eq!(Some((ToolKind::Linker, (10, 0))), product(0x009D));
+        assert_eq!(Some((ToolKind::CCompiler, (10, 0))), product(0x00AA));
+        // Visual Studio 2008
+        assert_eq!(Some((ToolKind::CPPCompiler, (9, 0))), product(0x0084));
+        // Visual Studio 6.0
+        assert_eq!(Some((ToolKind::CCompiler, (6, 0))), product(0x000A));
+        assert_eq!(Some((ToolKind::Import, (0, 0))), product(0x0001));
+        assert_eq!(None, product(0x0FFF));
+    }
+}
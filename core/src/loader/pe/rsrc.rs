@@ -8,11 +8,18 @@
 #![allow(non_camel_case_types)]
 #![allow(non_upper_case_globals)]
 
+use std::collections::BTreeMap;
+
 use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
 use log::debug;
 
-use crate::{aspace::AddressSpace, loader::pe::PE, RVA};
+use crate::{
+    aspace::AddressSpace,
+    loader::pe::{PEError, PE},
+    util::align,
+    RVA,
+};
 
 pub struct ResourceSectionData {
     buf: Vec<u8>,
@@ -68,6 +75,74 @@ impl ResourceSectionData {
 
         Ok(Some(ResourceSectionData { buf }))
     }
+
+    /// Collect the resources of the given type, along with their name and
+    /// language, in directory order.
+    pub fn get_resources(
+        &self,
+        ty: ResourceDataType,
+    ) -> Result<Vec<(NodeIdentifier, NodeIdentifier, ResourceDataDescriptor)>> {
+        let mut ret = vec![];
+
+        let Some(NodeChild::Node(type_node)) = self.root()?.get_child_by_id(self, ty as u32)? else {
+            return Ok(ret);
+        };
+
+        for (name_entry, name_child) in type_node.children(self)? {
+            let NodeChild::Node(name_node) = name_child else {
+                continue;
+            };
+
+            for (language_entry, language_child) in name_node.children(self)? {
+                let NodeChild::Data(data) = language_child else {
+                    continue;
+                };
+
+                ret.push((name_entry.id(self)?, language_entry.id(self)?, data));
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Parse the first RT_VERSION resource, if present.
+    pub fn version_info(&self, pe: &PE) -> Result<Option<VersionInfo>> {
+        match self.get_resources(ResourceDataType::RT_VERSION)?.first() {
+            Some((_, _, data)) => Ok(Some(VersionInfo::from_bytes(&data.data(pe)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse the first RT_MANIFEST resource, if present.
+    pub fn manifest(&self, pe: &PE) -> Result<Option<Manifest>> {
+        match self.get_resources(ResourceDataType::RT_MANIFEST)?.first() {
+            Some((_, _, data)) => Ok(Some(Manifest::from_bytes(&data.data(pe)?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse the RT_STRING resources into a map from string ID to string.
+    /// When a string is provided in multiple languages, the first is used.
+    pub fn string_table(&self, pe: &PE) -> Result<BTreeMap<u32, String>> {
+        let mut strings: BTreeMap<u32, String> = Default::default();
+
+        for (name, _, data) in self.get_resources(ResourceDataType::RT_STRING)? {
+            let NodeIdentifier::ID(block) = name else {
+                continue;
+            };
+
+            match parse_string_table_block(block, &data.data(pe)?) {
+                Ok(block_strings) => {
+                    for (id, string) in block_strings {
+                        strings.entry(id).or_insert(string);
+                    }
+                }
+                Err(e) => debug!("rsrc: string table block {block}: {e}"),
+            }
+        }
+
+        Ok(strings)
+    }
 }
 
 struct ResourceNodeHeader {
@@ -210,7 +285,7 @@ impl ResourceNode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ResourceDataType {
     RT_CURSOR       = 1,
     RT_BITMAP       = 2,
@@ -312,3 +387,607 @@ pub enum NodeChild {
     Node(ResourceNode),
     Data(ResourceDataDescriptor),
 }
+
+fn malformed(message: &str) -> anyhow::Error {
+    PEError::MalformedPEFile(format!("resource: {message}")).into()
+}
+
+/// Read a NUL-terminated UTF-16 string from the start of `buf`,
+/// returning the string and the number of bytes consumed, including the NUL.
+fn read_utf16z(buf: &[u8]) -> (String, usize) {
+    let chars: Vec<u16> = buf
+        .chunks_exact(2)
+        .map(LittleEndian::read_u16)
+        .take_while(|&c| c != 0)
+        .collect();
+    let length = (chars.len() + 1) * 2;
+    (String::from_utf16_lossy(&chars), length)
+}
+
+/// The version numbers are split across two u32s,
+/// like `1.2.3.4` is `0x0001_0002`, `0x0003_0004`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version(pub u16, pub u16, pub u16, pub u16);
+
+impl Version {
+    fn from_parts(ms: u32, ls: u32) -> Version {
+        Version((ms >> 16) as u16, ms as u16, (ls >> 16) as u16, ls as u16)
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.0, self.1, self.2, self.3)
+    }
+}
+
+const VS_FFI_SIGNATURE: u32 = 0xFEEF_04BD;
+
+#[derive(Debug, Clone)]
+pub struct VS_FIXEDFILEINFO {
+    pub file_version:    Version,
+    pub product_version: Version,
+    pub file_flags:      u32,
+    pub file_os:         u32,
+    pub file_type:       u32,
+    pub file_subtype:    u32,
+}
+
+impl VS_FIXEDFILEINFO {
+    fn from_bytes(buf: &[u8]) -> Result<VS_FIXEDFILEINFO> {
+        if buf.len() < 0x34 {
+            return Err(malformed("VS_FIXEDFILEINFO too small"));
+        }
+
+        let fields: Vec<u32> = buf[..0x34].chunks_exact(4).map(LittleEndian::read_u32).collect();
+        if fields[0] != VS_FFI_SIGNATURE {
+            return Err(malformed("invalid VS_FIXEDFILEINFO signature"));
+        }
+
+        Ok(VS_FIXEDFILEINFO {
+            file_version:    Version::from_parts(fields[2], fields[3]),
+            product_version: Version::from_parts(fields[4], fields[5]),
+            file_flags:      fields[7] & fields[6],
+            file_os:         fields[8],
+            file_type:       fields[9],
+            file_subtype:    fields[10],
+        })
+    }
+}
+
+/// The common structure of the nodes in a VS_VERSIONINFO resource:
+///
+/// ```text
+///   u16     wLength       of the node, including children
+///   u16     wValueLength  in bytes (binary) or characters (text)
+///   u16     wType         0: binary, 1: text
+///   wchar[] szKey         NUL-terminated
+///   padding to 32 bits
+///   Value
+///   padding to 32 bits
+///   Children
+/// ```
+struct VersionNode<'a> {
+    key:      String,
+    value:    &'a [u8],
+    is_text:  bool,
+    children: Vec<VersionNode<'a>>,
+}
+
+impl<'a> VersionNode<'a> {
+    /// parse the node at the start of `buf`, which must be 32-bit aligned
+    /// relative to the resource.
+    fn parse(buf: &'a [u8]) -> Result<VersionNode<'a>> {
+        if buf.len() < 6 {
+            return Err(malformed("version node too small"));
+        }
+
+        let length = LittleEndian::read_u16(&buf[0..2]) as usize;
+        let value_length = LittleEndian::read_u16(&buf[2..4]) as usize;
+        let is_text = LittleEndian::read_u16(&buf[4..6]) == 1;
+        if length < 6 || length > buf.len() {
+            return Err(malformed("invalid version node length"));
+        }
+        let buf = &buf[..length];
+
+        let (key, key_length) = read_utf16z(&buf[6..]);
+        let value_offset = align(6 + key_length as u64, 4) as usize;
+
+        // text lengths are in characters, though some compilers emit bytes,
+        // so rely on the NUL terminator and bounds instead.
+        let value_length = if is_text { value_length * 2 } else { value_length };
+        let value_end = std::cmp::min(value_offset + value_length, length);
+        let value = buf.get(value_offset..value_end).unwrap_or_default();
+
+        let mut children = vec![];
+        let mut offset = align(value_end as u64, 4) as usize;
+        while offset + 6 <= length {
+            let child = VersionNode::parse(&buf[offset..])?;
+            let child_length = LittleEndian::read_u16(&buf[offset..offset + 2]) as usize;
+            children.push(child);
+            offset = align((offset + child_length) as u64, 4) as usize;
+        }
+
+        Ok(VersionNode {
+            key,
+            value,
+            is_text,
+            children,
+        })
+    }
+
+    fn text(&self) -> String {
+        read_utf16z(self.value).0
+    }
+}
+
+/// The contents of a VS_VERSIONINFO (RT_VERSION) resource.
+#[derive(Debug, Clone, Default)]
+pub struct VersionInfo {
+    pub fixed:   Option<VS_FIXEDFILEINFO>,
+    /// the StringFileInfo tables, by language and codepage, like `040904B0`,
+    /// each containing entries like `CompanyName` or `OriginalFilename`.
+    pub strings: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl VersionInfo {
+    pub fn from_bytes(buf: &[u8]) -> Result<VersionInfo> {
+        let root = VersionNode::parse(buf)?;
+        if root.key != "VS_VERSION_INFO" {
+            return Err(malformed("invalid VS_VERSIONINFO key"));
+        }
+
+        let fixed = if root.value.is_empty() {
+            None
+        } else {
+            Some(VS_FIXEDFILEINFO::from_bytes(root.value)?)
+        };
+
+        let mut strings: BTreeMap<String, BTreeMap<String, String>> = Default::default();
+        for string_file_info in root.children.iter().filter(|child| child.key == "StringFileInfo") {
+            for table in string_file_info.children.iter() {
+                let entries = strings.entry(table.key.clone()).or_default();
+                for string in table.children.iter().filter(|string| string.is_text) {
+                    entries.insert(string.key.clone(), string.text());
+                }
+            }
+        }
+
+        Ok(VersionInfo { fixed, strings })
+    }
+
+    /// Fetch an entry from the first string table that contains it,
+    /// like `CompanyName`.
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        self.strings
+            .values()
+            .find_map(|table| table.get(key))
+            .map(|value| value.as_str())
+    }
+
+    pub fn company_name(&self) -> Option<&str> {
+        self.get_string("CompanyName")
+    }
+
+    pub fn product_name(&self) -> Option<&str> {
+        self.get_string("ProductName")
+    }
+
+    pub fn original_filename(&self) -> Option<&str> {
+        self.get_string("OriginalFilename")
+    }
+
+    /// The file version from the string table, which may contain extra text
+    /// like `10.0.19041.1 (WinBuild.160101.0800)`, or from the fixed info.
+    pub fn file_version(&self) -> Option<String> {
+        self.get_string("FileVersion")
+            .map(|version| version.to_string())
+            .or_else(|| self.fixed.as_ref().map(|fixed| fixed.file_version.to_string()))
+    }
+}
+
+/// The contents of an RT_MANIFEST resource, the XML application manifest.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub xml: String,
+}
+
+impl Manifest {
+    pub fn from_bytes(buf: &[u8]) -> Manifest {
+        let buf = buf.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(buf);
+        Manifest {
+            xml: String::from_utf8_lossy(buf).trim_end_matches('\0').to_string(),
+        }
+    }
+
+    /// Find the value of an attribute of the first element with the given
+    /// name, like `<requestedExecutionLevel level="asInvoker"/>`.
+    ///
+    /// This isn't a real XML parser, but manifests are simple.
+    fn get_attribute(&self, element: &str, attribute: &str) -> Option<&str> {
+        // the element may be namespaced, like `<ms_asmv2:requestedExecutionLevel`.
+        let start = self.xml.find(element)?;
+        let tag = &self.xml[start..];
+        let mut rest = &tag[..tag.find('>')?];
+
+        while let Some(index) = rest.find(attribute) {
+            // ensure we matched a whole attribute name, like ` level=`.
+            let is_name = rest[..index].ends_with(char::is_whitespace);
+            let after = rest[index + attribute.len()..].trim_start();
+            rest = &rest[index + attribute.len()..];
+
+            let Some(after) = after.strip_prefix('=').filter(|_| is_name) else {
+                continue;
+            };
+
+            let after = after.trim_start();
+            let quote = after.chars().next().filter(|&c| c == '"' || c == '\'')?;
+            let value = &after[1..];
+            return Some(&value[..value.find(quote)?]);
+        }
+
+        None
+    }
+
+    /// Like `asInvoker`, `highestAvailable`, or `requireAdministrator`.
+    pub fn requested_execution_level(&self) -> Option<&str> {
+        self.get_attribute("requestedExecutionLevel", "level")
+    }
+
+    pub fn ui_access(&self) -> Option<bool> {
+        self.get_attribute("requestedExecutionLevel", "uiAccess")
+            .map(|value| value.eq_ignore_ascii_case("true"))
+    }
+}
+
+/// Parse an RT_STRING resource, which contains a block of 16 strings,
+/// each prefixed by its length in characters.
+/// Block N contains the strings with IDs (N - 1) * 16 through (N - 1) * 16 +
+/// 15. Empty strings are not returned.
+pub fn parse_string_table_block(block: u32, buf: &[u8]) -> Result<Vec<(u32, String)>> {
+    // string IDs are 16 bits, so valid blocks are 1..=4096.
+    let Some(first_id) = block
+        .checked_sub(1)
+        .and_then(|index| index.checked_mul(16))
+        .filter(|&first_id| first_id <= u16::MAX as u32)
+    else {
+        return Err(malformed("invalid string table block"));
+    };
+
+    let mut strings = vec![];
+    let mut offset = 0usize;
+    for i in 0..16u32 {
+        let Some(length) = buf.get(offset..offset + 2).map(LittleEndian::read_u16) else {
+            break;
+        };
+        offset += 2;
+
+        let length = length as usize * 2;
+        let Some(chars) = buf.get(offset..offset + length) else {
+            return Err(malformed("string table entry too large"));
+        };
+        offset += length;
+
+        if length > 0 {
+            let chars: Vec<u16> = chars.chunks_exact(2).map(LittleEndian::read_u16).collect();
+            strings.push((first_id + i, String::from_utf16_lossy(&chars)));
+        }
+    }
+
+    Ok(strings)
+}
+
+/// The resources commonly needed when triaging a PE.
+#[derive(Debug, Clone, Default)]
+pub struct Resources {
+    pub version_info: Option<VersionInfo>,
+    pub manifest:     Option<Manifest>,
+    pub strings:      BTreeMap<u32, String>,
+}
+
+impl Resources {
+    /// Parse the version info, manifest, and string table resources.
+    /// Resources that fail to parse are logged and skipped.
+    pub fn from_pe(pe: &PE) -> Result<Option<Resources>> {
+        let Some(rsrc) = ResourceSectionData::from_pe(pe)? else {
+            return Ok(None);
+        };
+
+        let version_info = rsrc.version_info(pe).unwrap_or_else(|e| {
+            debug!("rsrc: failed to parse version info: {:?}", e);
+            None
+        });
+
+        let manifest = rsrc.manifest(pe).unwrap_or_else(|e| {
+            debug!("rsrc: failed to parse manifest: {:?}", e);
+            None
+        });
+
+        let strings = rsrc.string_table(pe).unwrap_or_else(|e| {
+            debug!("rsrc: failed to parse string table: {:?}", e);
+            Default::default()
+        });
+
+        Ok(Some(Resources {
+            version_info,
+            manifest,
+            strings,
+        }))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::{
+        loader::pe::tests::{add_data_directory, build_mapped_pe},
+        rsrc::*,
+    };
+
+    pub const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+  <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
+    <security>
+      <requestedPrivileges>
+        <requestedExecutionLevel level="requireAdministrator" uiAccess="false"></requestedExecutionLevel>
+      </requestedPrivileges>
+    </security>
+  </trustInfo>
+</assembly>"#;
+
+    fn utf16z(s: &str) -> Vec<u8> {
+        s.encode_utf16().chain([0]).flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    fn pad(buf: &mut Vec<u8>) {
+        buf.resize(align(buf.len() as u64, 4) as usize, 0);
+    }
+
+    fn build_version_node(key: &str, value: &[u8], value_length: u16, is_text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = vec![0u8; 6];
+        buf.extend(utf16z(key));
+        pad(&mut buf);
+        buf.extend_from_slice(value);
+        for child in children {
+            pad(&mut buf);
+            buf.extend_from_slice(child);
+        }
+
+        let length = buf.len() as u16;
+        buf[0..2].copy_from_slice(&length.to_le_bytes());
+        buf[2..4].copy_from_slice(&value_length.to_le_bytes());
+        buf[4..6].copy_from_slice(&(is_text as u16).to_le_bytes());
+        buf
+    }
+
+    /// Build a VS_VERSIONINFO resource for version 1.2.3.4
+    /// with a single string table.
+    pub fn build_version_info(strings: &[(&str, &str)]) -> Vec<u8> {
+        let mut fixed = vec![];
+        for field in [
+            VS_FFI_SIGNATURE,
+            0x0001_0000, // dwStrucVersion
+            0x0001_0002, // dwFileVersionMS
+            0x0003_0004, // dwFileVersionLS
+            0x0001_0002, // dwProductVersionMS
+            0x0003_0004, // dwProductVersionLS
+            0x3F,        // dwFileFlagsMask
+            0x0,         // dwFileFlags
+            0x0004_0004, // dwFileOS: VOS_NT_WINDOWS32
+            0x1,         // dwFileType: VFT_APP
+            0x0,         // dwFileSubtype
+            0x0,         // dwFileDateMS
+            0x0,         // dwFileDateLS
+        ] {
+            fixed.write_u32::<LittleEndian>(field).unwrap();
+        }
+
+        let strings = strings
+            .iter()
+            .map(|(key, value)| {
+                let length = value.encode_utf16().count() as u16 + 1;
+                build_version_node(key, &utf16z(value), length, true, &[])
+            })
+            .collect::<Vec<_>>();
+        let table = build_version_node("040904B0", &[], 0, true, &strings);
+        let string_file_info = build_version_node("StringFileInfo", &[], 0, true, &[table]);
+
+        build_version_node(
+            "VS_VERSION_INFO",
+            &fixed,
+            fixed.len() as u16,
+            false,
+            &[string_file_info],
+        )
+    }
+
+    /// Build an RT_STRING block that contains the given (index, string) pairs.
+    pub fn build_string_table_block(strings: &[(usize, &str)]) -> Vec<u8> {
+        let mut buf = vec![];
+        for i in 0..16 {
+            let string = strings
+                .iter()
+                .find(|(index, _)| *index == i)
+                .map(|(_, s)| *s)
+                .unwrap_or("");
+            buf.write_u16::<LittleEndian>(string.encode_utf16().count() as u16)
+                .unwrap();
+            for c in string.encode_utf16() {
+                buf.write_u16::<LittleEndian>(c).unwrap();
+            }
+        }
+        buf
+    }
+
+    /// Build a resource directory at the given RVA with one resource per
+    /// (type, name) pair, each in language 0x409.
+    pub fn build_resource_directory(rva: u32, resources: &[(ResourceDataType, u32, &[u8])]) -> Vec<u8> {
+        let count = resources.len();
+        // root node, then per resource: type node, name node, data descriptor.
+        let node = |i: usize| 16 + 8 * count + i * 64;
+        let mut buf = vec![0u8; node(count)];
+
+        let write_node = |buf: &mut Vec<u8>, offset: usize, entries: &[(u32, u32)]| {
+            buf[offset + 14..offset + 16].copy_from_slice(&(entries.len() as u16).to_le_bytes());
+            for (i, &(id, child)) in entries.iter().enumerate() {
+                let entry = offset + 16 + i * 8;
+                buf[entry..entry + 4].copy_from_slice(&id.to_le_bytes());
+                buf[entry + 4..entry + 8].copy_from_slice(&child.to_le_bytes());
+            }
+        };
+
+        let root = resources
+            .iter()
+            .enumerate()
+            .map(|(i, &(ty, _, _))| (ty as u32, 0x8000_0000 | node(i) as u32))
+            .collect::<Vec<_>>();
+        write_node(&mut buf, 0, &root);
+
+        for (i, &(_, name, data)) in resources.iter().enumerate() {
+            write_node(&mut buf, node(i), &[(name, 0x8000_0000 | (node(i) + 24) as u32)]);
+            write_node(&mut buf, node(i) + 24, &[(0x409, (node(i) + 48) as u32)]);
+
+            let offset = buf.len();
+            let descriptor = node(i) + 48;
+            buf[descriptor..descriptor + 4].copy_from_slice(&(rva + offset as u32).to_le_bytes());
+            buf[descriptor + 4..descriptor + 8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+
+            buf.extend_from_slice(data);
+            pad(&mut buf);
+        }
+
+        buf
+    }
+
+    /// Build a minimal x64 PE image in its memory layout (see
+    /// `build_mapped_pe`) with a resource directory at RVA 0x1400 that
+    /// contains version info, a manifest, and a string table.
+    pub fn build_mapped_pe_with_resources() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        let version_info = build_version_info(&[("CompanyName", "Lancelot"), ("OriginalFilename", "test.exe")]);
+        let strings = build_string_table_block(&[(1, "hello")]);
+        let rsrc = build_resource_directory(
+            0x1400,
+            &[
+                (ResourceDataType::RT_STRING, 1, &strings),
+                (ResourceDataType::RT_VERSION, 1, &version_info),
+                (ResourceDataType::RT_MANIFEST, 1, MANIFEST.as_bytes()),
+            ],
+        );
+        buf[0x1400..0x1400 + rsrc.len()].copy_from_slice(&rsrc);
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_RESOURCE,
+            0x1400,
+            rsrc.len() as u32,
+            0x800,
+        );
+
+        buf
+    }
+
+    #[test]
+    fn version_info() -> Result<()> {
+        let buf = build_version_info(&[
+            ("CompanyName", "Lancelot"),
+            ("ProductName", "lancelot test"),
+            ("FileVersion", "1.2.3.4 (test)"),
+        ]);
+        let version_info = VersionInfo::from_bytes(&buf)?;
+
+        assert_eq!(Version(1, 2, 3, 4), version_info.fixed.as_ref().unwrap().file_version);
+        assert_eq!(Some("Lancelot"), version_info.company_name());
+        assert_eq!(Some("lancelot test"), version_info.product_name());
+        assert_eq!(None, version_info.original_filename());
+        assert_eq!(Some("1.2.3.4 (test)".to_string()), version_info.file_version());
+        assert_eq!(3, version_info.strings["040904B0"].len());
+
+        assert!(VersionInfo::from_bytes(&buf[..4]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn manifest() -> Result<()> {
+        let manifest = Manifest::from_bytes(MANIFEST.as_bytes());
+        assert_eq!(Some("requireAdministrator"), manifest.requested_execution_level());
+        assert_eq!(Some(false), manifest.ui_access());
+
+        let manifest = Manifest::from_bytes(b"<assembly></assembly>");
+        assert_eq!(None, manifest.requested_execution_level());
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_table_block() -> Result<()> {
+        let buf = build_string_table_block(&[(0, "first"), (15, "last")]);
+        assert_eq!(
+            vec![(32, "first".to_string()), (47, "last".to_string())],
+            parse_string_table_block(3, &buf)?
+        );
+
+        // there's no block 0, and block IDs beyond the 16-bit string IDs are invalid.
+        assert!(parse_string_table_block(0, &buf).is_err());
+        assert!(parse_string_table_block(u32::MAX, &buf).is_err());
+        assert!(parse_string_table_block(4096, &buf).is_ok());
+        assert!(parse_string_table_block(4097, &buf).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn resources() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_resources(), None)?;
+        let resources = Resources::from_pe(&pe)?.unwrap();
+
+        let version_info = resources.version_info.unwrap();
+        assert_eq!(Some("Lancelot"), version_info.company_name());
+        assert_eq!(Some("test.exe"), version_info.original_filename());
+        assert_eq!(Some("1.2.3.4".to_string()), version_info.file_version());
+
+        assert_eq!(
+            Some("requireAdministrator"),
+            resources.manifest.unwrap().requested_execution_level()
+        );
+
+        assert_eq!(1, resources.strings.len());
+        assert_eq!("hello", resources.strings[&1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mimi() -> Result<()> {
+        let buf = get_buf(Rsrc::MIMI);
+        let pe = PE::from_bytes(&buf)?;
+        let resources = Resources::from_pe(&pe)?.unwrap();
+
+        let version_info = resources.version_info.unwrap();
+        assert_eq!(Some("gentilkiwi (Benjamin DELPY)"), version_info.company_name());
+        assert_eq!(Some("mimikatz"), version_info.product_name());
+        assert_eq!(Some("mimikatz.exe"), version_info.original_filename());
+        assert_eq!(Some("2.2.0.0".to_string()), version_info.file_version());
+        assert!(resources.manifest.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cpp1() -> Result<()> {
+        let buf = get_buf(Rsrc::CPP1);
+        let pe = PE::from_bytes(&buf)?;
+        let resources = Resources::from_pe(&pe)?.unwrap();
+
+        assert!(resources.version_info.is_none());
+        let manifest = resources.manifest.unwrap();
+        assert_eq!(Some("asInvoker"), manifest.requested_execution_level());
+        assert_eq!(Some(false), manifest.ui_access());
+
+        Ok(())
+    }
+}
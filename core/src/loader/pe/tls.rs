@@ -0,0 +1,154 @@
+//! Parse the TLS directory, which may contain callbacks that run before the
+//! entry point of the image.
+#![allow(non_camel_case_types)]
+
+use anyhow::Result;
+use log::debug;
+
+use crate::{aspace::AddressSpace, loader::pe::PE, RVA, VA};
+
+/// The fields that contain addresses are pointer-sized,
+/// so this is `IMAGE_TLS_DIRECTORY32` or `IMAGE_TLS_DIRECTORY64`.
+///
+/// The addresses are VAs, which are covered by base relocations,
+/// so they're read from the loaded image.
+#[derive(Debug, Clone)]
+pub struct IMAGE_TLS_DIRECTORY {
+    pub start_address_of_raw_data: VA,
+    pub end_address_of_raw_data:   VA,
+    pub address_of_index:          VA,
+    pub address_of_callbacks:      VA,
+    pub size_of_zero_fill:         u32,
+    pub characteristics:           u32,
+}
+
+/// fetch the TLS directory, if it exists.
+pub fn read_tls_directory(pe: &PE) -> Result<Option<IMAGE_TLS_DIRECTORY>> {
+    let Some(directory) = pe.get_data_directory(crate::loader::pe::IMAGE_DIRECTORY_ENTRY_TLS)? else {
+        return Ok(None);
+    };
+    if directory.size == 0 {
+        return Ok(None);
+    }
+
+    let arch = pe.module.arch;
+    let psize = arch.pointer_size() as RVA;
+    let aspace = &pe.module.address_space;
+    let va = directory.address;
+
+    Ok(Some(IMAGE_TLS_DIRECTORY {
+        start_address_of_raw_data: aspace.read_pointer(arch, va)?,
+        end_address_of_raw_data:   aspace.read_pointer(arch, va + psize)?,
+        address_of_index:          aspace.read_pointer(arch, va + 2 * psize)?,
+        address_of_callbacks:      aspace.read_pointer(arch, va + 3 * psize)?,
+        size_of_zero_fill:         aspace.read_u32(va + 4 * psize)?,
+        characteristics:           aspace.read_u32(va + 4 * psize + 4)?,
+    }))
+}
+
+/// read the null-terminated array of TLS callback addresses, if any.
+pub fn read_tls_callbacks(pe: &PE) -> Result<Vec<VA>> {
+    let Some(directory) = read_tls_directory(pe)? else {
+        return Ok(vec![]);
+    };
+
+    let mut callbacks = vec![];
+    if directory.address_of_callbacks == 0 {
+        return Ok(callbacks);
+    }
+
+    let arch = pe.module.arch;
+    let psize = arch.pointer_size() as RVA;
+    for i in 0.. {
+        match pe
+            .module
+            .address_space
+            .read_pointer(arch, directory.address_of_callbacks + i * psize)
+        {
+            Ok(0) => break,
+            Ok(callback) => callbacks.push(callback),
+            Err(e) => {
+                debug!("tls: error reading callback: {}", e);
+                break;
+            }
+        }
+    }
+
+    Ok(callbacks)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use anyhow::Result;
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::*;
+    use crate::{
+        loader::pe::tests::{add_data_directory, build_mapped_pe, HELPER_RVA, IMAGE_BASE},
+        rsrc::*,
+    };
+
+    pub const TLS_CALLBACKS_RVA: u64 = 0x1240;
+
+    /// Build a minimal x64 PE image in its memory layout (see
+    /// `build_mapped_pe`) with a TLS directory at RVA 0x1200 that registers
+    /// the function at `HELPER_RVA` as its only callback.
+    pub fn build_mapped_pe_with_tls() -> Vec<u8> {
+        let mut buf = build_mapped_pe();
+
+        // IMAGE_TLS_DIRECTORY64
+        let mut directory = vec![];
+        directory.write_u64::<LittleEndian>(IMAGE_BASE + 0x1280).unwrap(); // StartAddressOfRawData
+        directory.write_u64::<LittleEndian>(IMAGE_BASE + 0x1288).unwrap(); // EndAddressOfRawData
+        directory.write_u64::<LittleEndian>(IMAGE_BASE + 0x1290).unwrap(); // AddressOfIndex
+        directory
+            .write_u64::<LittleEndian>(IMAGE_BASE + TLS_CALLBACKS_RVA)
+            .unwrap(); // AddressOfCallBacks
+        directory.write_u32::<LittleEndian>(0).unwrap(); // SizeOfZeroFill
+        directory.write_u32::<LittleEndian>(0).unwrap(); // Characteristics
+        buf[0x1200..0x1200 + directory.len()].copy_from_slice(&directory);
+        add_data_directory(
+            &mut buf,
+            crate::loader::pe::IMAGE_DIRECTORY_ENTRY_TLS,
+            0x1200,
+            directory.len() as u32,
+            0x400,
+        );
+
+        let callbacks = TLS_CALLBACKS_RVA as usize;
+        buf[callbacks..callbacks + 8].copy_from_slice(&(IMAGE_BASE + HELPER_RVA).to_le_bytes());
+
+        buf
+    }
+
+    #[test]
+    fn tls_callbacks() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_tls(), None)?;
+
+        let directory = read_tls_directory(&pe)?.unwrap();
+        assert_eq!(IMAGE_BASE + TLS_CALLBACKS_RVA, directory.address_of_callbacks);
+        assert_eq!(vec![IMAGE_BASE + HELPER_RVA], read_tls_callbacks(&pe)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_tls() -> Result<()> {
+        let pe = PE::from_mapped_bytes(&build_mapped_pe(), None)?;
+        assert!(read_tls_directory(&pe)?.is_none());
+        assert!(read_tls_callbacks(&pe)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn k32() -> Result<()> {
+        let buf = get_buf(Rsrc::K32);
+        let pe = PE::from_bytes(&buf)?;
+
+        assert!(read_tls_directory(&pe)?.is_none());
+        assert!(read_tls_callbacks(&pe)?.is_empty());
+
+        Ok(())
+    }
+}
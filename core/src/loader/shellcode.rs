@@ -0,0 +1,117 @@
+//! Load raw code, like shellcode or blobs carved from memory or files.
+//!
+//! There's no header, so the caller provides the architecture and the address
+//! at which the code was (or would be) found. The whole buffer is mapped as a
+//! single RWX section.
+use anyhow::Result;
+use log::debug;
+use thiserror::Error;
+
+use crate::{
+    arch::Arch,
+    aspace::{self, RelativeAddressSpace},
+    module::{Module, Permissions, Section},
+    util, VA,
+};
+
+#[derive(Error, Debug)]
+pub enum ShellcodeError {
+    #[error("format not supported: {0}")]
+    FormatNotSupported(String),
+}
+
+const PAGE_SIZE: u64 = 0x1000;
+
+pub fn load_shellcode(arch: Arch, buf: &[u8], base_address: VA) -> Result<Module> {
+    if aspace::page_offset(base_address) != 0 {
+        return Err(ShellcodeError::FormatNotSupported(format!(
+            "base address must be page aligned: {base_address:#x}"
+        ))
+        .into());
+    }
+
+    if buf.is_empty() {
+        return Err(ShellcodeError::FormatNotSupported("empty buffer".to_string()).into());
+    }
+
+    let size = util::align(buf.len() as u64, PAGE_SIZE);
+    if base_address.checked_add(size).is_none() {
+        return Err(ShellcodeError::FormatNotSupported(format!(
+            "buffer extends beyond the address space: {base_address:#x} + {size:#x}"
+        ))
+        .into());
+    }
+
+    let mut address_space = RelativeAddressSpace::with_capacity(size);
+
+    let mut padded = buf.to_vec();
+    padded.resize(size as usize, 0);
+    address_space.map.write(0x0, &padded)?;
+
+    debug!(
+        "shellcode: mapped {:#x} - {:#x} {:?}",
+        base_address,
+        base_address + buf.len() as u64,
+        arch
+    );
+
+    Ok(Module {
+        arch,
+        sections: vec![Section {
+            name:           "shellcode".to_string(),
+            permissions:    Permissions::RWX,
+            physical_range: std::ops::Range {
+                start: 0x0,
+                end:   buf.len() as u64,
+            },
+            virtual_range:  std::ops::Range {
+                start: base_address,
+                end:   base_address + buf.len() as u64,
+            },
+        }],
+        section_headers: Default::default(),
+        address_space: address_space.into_absolute(base_address)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{arch::Arch, aspace::AddressSpace};
+
+    #[test]
+    fn base_address() -> Result<()> {
+        let module = super::load_shellcode(Arch::X32, &[0x90, 0xC3], 0x40_0000)?;
+
+        assert_eq!(0x40_0000, module.address_space.base_address);
+        assert_eq!(0x90, module.address_space.read_u8(0x40_0000)?);
+        assert_eq!(0xC3, module.address_space.read_u8(0x40_0001)?);
+        assert!(module.probe_va(0x40_0001, crate::module::Permissions::X));
+        assert!(!module.probe_va(0x40_0002, crate::module::Permissions::X));
+
+        assert!(super::load_shellcode(Arch::X32, &[0x90, 0xC3], 0x40_0010).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn page_sized() -> Result<()> {
+        let module = super::load_shellcode(Arch::X64, &[0xCC; 0x1000], 0x0)?;
+
+        assert_eq!(0xCC, module.address_space.read_u8(0xFFF)?);
+        assert!(module.address_space.read_u8(0x1000).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn overflow() -> Result<()> {
+        assert!(super::load_shellcode(Arch::X64, &[0x90; 0x10], 0xFFFF_FFFF_FFFF_F000).is_err());
+
+        let module = super::load_shellcode(Arch::X64, &[0x90; 0x10], 0xFFFF_FFFF_FFFF_E000)?;
+        assert_eq!(0x90, module.address_space.read_u8(0xFFFF_FFFF_FFFF_E00F)?);
+
+        Ok(())
+    }
+}
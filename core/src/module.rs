@@ -54,6 +54,27 @@ pub struct Section {
     pub name:           String,
 }
 
+/// A named region described by the section headers of a file format that
+/// maps memory by other means, like an ELF section within a loadable segment.
+///
+/// These don't contribute to the address space, and may overlap
+/// `Module.sections`.
+#[derive(Debug, Clone)]
+pub struct SectionHeader {
+    // source data, relative to file start.
+    // empty for sections without data in the file, like `.bss`.
+    pub physical_range: std::ops::Range<RVA>,
+    // as mapped into memory with absolute addresses.
+    pub virtual_range:  std::ops::Range<VA>,
+    pub permissions:    Permissions,
+    pub name:           String,
+    /// the format-specific flags, like the ELF `sh_flags`.
+    pub flags:          u64,
+    /// the size of each entry of sections that hold a table, like the ELF
+    /// `sh_entsize`, or zero.
+    pub entry_size:     u64,
+}
+
 /// An address space, as a file would be loaded into memory.
 ///
 /// This has an associated architecture (e.g. x32 or x64),
@@ -61,9 +82,14 @@ pub struct Section {
 /// This is the information that we'd expect to be common across formats.
 #[derive(Clone)]
 pub struct Module {
-    pub arch:          Arch,
-    pub sections:      Vec<Section>,
-    pub address_space: AbsoluteAddressSpace,
+    pub arch:            Arch,
+    /// the regions mapped into the address space,
+    /// like PE sections or ELF loadable segments.
+    pub sections:        Vec<Section>,
+    /// the named regions within `sections`, when the format describes these
+    /// separately, like ELF section headers. Otherwise, empty.
+    pub section_headers: Vec<SectionHeader>,
+    pub address_space:   AbsoluteAddressSpace,
 }
 
 impl Module {
@@ -106,6 +132,27 @@ impl Module {
         self.probe_va(va, perm)
     }
 
+    /// Find the section header with the given name, like `.plt`.
+    pub fn get_section_header(&self, name: &str) -> Option<&SectionHeader> {
+        self.section_headers.iter().find(|header| header.name == name)
+    }
+
+    /// Find the name of the region that contains the given VA,
+    /// preferring the section headers, like `.text`, over the mapped
+    /// sections, like `segment_text_0`.
+    pub fn get_section_name(&self, va: VA) -> Option<&str> {
+        self.section_headers
+            .iter()
+            .find(|header| header.virtual_range.contains(&va))
+            .map(|header| header.name.as_str())
+            .or_else(|| {
+                self.sections
+                    .iter()
+                    .find(|section| section.virtual_range.contains(&va))
+                    .map(|section| section.name.as_str())
+            })
+    }
+
     /// Is the memory at the given VA backed by data in the module?
     pub fn is_in_image(&self, offset: VA) -> bool {
         if let Ok(file_offset) = self.file_offset(offset) {
@@ -1,9 +1,9 @@
 //! Helpers that are useful for tests and doctests.
 use crate::{
     arch::Arch,
-    aspace::{AddressSpace, RelativeAddressSpace},
-    module::{Module, Permissions, Section},
-    RVA, VA,
+    aspace::AddressSpace,
+    module::{Module, Permissions},
+    VA,
 };
 
 /// configure a global logger at level==DEBUG.
@@ -32,25 +32,7 @@ pub fn init_logging() {
 
 /// this is for testing, so will panic on error.
 pub fn load_shellcode(arch: Arch, buf: &[u8]) -> Module {
-    let mut address_space = RelativeAddressSpace::with_capacity(buf.len() as u64);
-    address_space.map.writezx(0x0, buf).unwrap();
-
-    Module {
-        arch,
-        sections: vec![Section {
-            name:           "shellcode".to_string(),
-            permissions:    Permissions::RWX,
-            physical_range: std::ops::Range {
-                start: 0x0,
-                end:   buf.len() as RVA,
-            },
-            virtual_range:  std::ops::Range {
-                start: 0x0,
-                end:   buf.len() as RVA,
-            },
-        }],
-        address_space: address_space.into_absolute(0x0).unwrap(),
-    }
+    crate::loader::shellcode::load_shellcode(arch, buf, 0x0).unwrap()
 }
 
 /// this is for testing, so will panic on error.
@@ -1,50 +1,154 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 use anyhow::Result;
+use log::debug;
 
-use crate::VA;
+use crate::{
+    analysis::{
+        pe::{apiset::ApiSetSchema, ordinals::OrdinalNames, Import},
+        toolchain::Toolchain,
+    },
+    loader::pe::debug::CodeViewRecord,
+    VA,
+};
 use lancelot_flirt::{FlirtSignature, FlirtSignatureSet};
 
 pub trait Configuration: Send {
     /// provide the FLIRT signatures to be used to recognize known code.
     fn get_sigs(&self) -> Result<FlirtSignatureSet>;
 
+    /// provide the FLIRT signatures to be used for a module that was likely
+    /// built by the given toolchain. by default, all the signatures.
+    fn get_sigs_for_toolchain(&self, _toolchain: &Toolchain) -> Result<FlirtSignatureSet> {
+        self.get_sigs()
+    }
+
     /// provide the addresses known to be functions.
     fn get_function_hints(&self) -> Result<Vec<VA>>;
 
+    /// provide the address at which to load PE and ELF files,
+    /// such as where ASLR placed the module at runtime,
+    /// or `None` to use the preferred base address.
+    fn get_base_address(&self) -> Result<Option<VA>> {
+        Ok(None)
+    }
+
+    /// is a PE in its memory layout, such as one dumped from a process,
+    /// where each section is found at its RVA? (see `PE::from_mapped_bytes`)
+    fn is_mapped_image(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// provide the symbols exported by the other modules loaded into the same
+    /// process, indexed by address, used to resolve the IAT of a PE dumped
+    /// from memory (see `get_resolved_imports`). by default, none.
+    fn get_process_exports(&self) -> Result<BTreeMap<VA, Import>> {
+        Ok(Default::default())
+    }
+
+    /// locate a local copy of the PDB referenced by the CodeView record of a
+    /// PE. the caller validates that its GUID and age match.
+    fn get_pdb_path(&self, _codeview: &CodeViewRecord) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    /// provide the names of the symbols that DLLs export by ordinal,
+    /// used to name the imports of a PE. by default, the built-in tables.
+    /// implementations that read files should do so once, and cache the result.
+    fn get_ordinal_names(&self) -> Result<OrdinalNames> {
+        Ok(Default::default())
+    }
+
+    /// provide the API set schema used to find the DLLs that implement the
+    /// contracts imported by a PE. by default, the built-in table.
+    /// implementations that read files should do so once, and cache the result.
+    fn get_apiset_schema(&self) -> Result<ApiSetSchema> {
+        Ok(Default::default())
+    }
+
     fn clone(&self) -> Box<dyn Configuration>;
 }
 
+/// Search the directory for the PDB referenced by the CodeView record,
+/// either laid out like a symbol store, at `<name>/<key>/<name>`,
+/// or directly within the directory, at `<name>`.
+fn find_pdb(directory: &Path, codeview: &CodeViewRecord) -> Option<PathBuf> {
+    let name = codeview.file_name();
+
+    [
+        directory.join(name).join(codeview.symbol_store_key()).join(name),
+        directory.join(name),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Read the FLIRT signatures from the files ending with .sig or .pat
+/// directly within the directory.
+fn read_sigs(directory: &Path) -> Result<Vec<FlirtSignature>> {
+    let mut sigs: Vec<FlirtSignature> = Default::default();
+
+    for entry in directory.read_dir()?.flatten() {
+        if let Ok(filename) = entry.file_name().into_string() {
+            if filename.ends_with(".sig") {
+                let buf = std::fs::read(entry.path())?;
+                sigs.extend(lancelot_flirt::sig::parse(&buf)?);
+            } else if filename.ends_with(".pat") {
+                let buf = String::from_utf8(std::fs::read(entry.path())?)?;
+                sigs.extend(lancelot_flirt::pat::parse(&buf)?);
+            }
+        }
+    }
+
+    Ok(sigs)
+}
+
 /// Directory that contains:
-///   - sigs/  FLIRT signatures, ending with .sig, .pat, .sig.gz, .pat.gz
+///   - sigs/  FLIRT signatures, ending with .sig, .pat, .sig.gz, .pat.gz, and
+///     subdirectories of signatures that apply to a toolchain, like sigs/msvc/
+///     and sigs/msvc/vs2019/ (see `Toolchain::sig_directories`)
+///   - pdbs/  PDB files, optionally laid out like a symbol store
+///   - ordinals/  reference DLLs, and map files with lines like
+///     `ws2_32.dll,115,WSAStartup`, used to name imports by ordinal
+///   - apisetschema.dll  the API set schema of the system that ran the module
 pub struct FileSystemConfiguration {
-    path: PathBuf,
+    path:          PathBuf,
+    ordinal_names: OnceLock<OrdinalNames>,
+    apisets:       OnceLock<ApiSetSchema>,
 }
 
 impl FileSystemConfiguration {
     pub fn from_path(path: &Path) -> FileSystemConfiguration {
         FileSystemConfiguration {
-            path: path.to_path_buf(),
+            path:          path.to_path_buf(),
+            ordinal_names: Default::default(),
+            apisets:       Default::default(),
         }
     }
 }
 
 impl Configuration for FileSystemConfiguration {
     fn get_sigs(&self) -> Result<FlirtSignatureSet> {
-        let mut sigs: Vec<FlirtSignature> = Default::default();
+        let mut path = self.path.clone();
+        path.push("sigs");
+
+        Ok(FlirtSignatureSet::with_signatures(read_sigs(&path)?))
+    }
 
+    fn get_sigs_for_toolchain(&self, toolchain: &Toolchain) -> Result<FlirtSignatureSet> {
         let mut path = self.path.clone();
         path.push("sigs");
 
-        for entry in path.read_dir()?.flatten() {
-            if let Ok(filename) = entry.file_name().into_string() {
-                if filename.ends_with(".sig") {
-                    let buf = std::fs::read(entry.path())?;
-                    sigs.extend(lancelot_flirt::sig::parse(&buf)?);
-                } else if filename.ends_with(".pat") {
-                    let buf = String::from_utf8(std::fs::read(entry.path())?)?;
-                    sigs.extend(lancelot_flirt::pat::parse(&buf)?);
-                }
+        let mut sigs = read_sigs(&path)?;
+        for directory in toolchain.sig_directories() {
+            let path = path.join(directory);
+            if path.is_dir() {
+                debug!("config: reading signatures from {}", path.display());
+                sigs.extend(read_sigs(&path)?);
             }
         }
 
@@ -55,9 +159,51 @@ impl Configuration for FileSystemConfiguration {
         Ok(vec![])
     }
 
+    fn get_pdb_path(&self, codeview: &CodeViewRecord) -> Result<Option<PathBuf>> {
+        let mut path = self.path.clone();
+        path.push("pdbs");
+
+        Ok(find_pdb(&path, codeview))
+    }
+
+    fn get_ordinal_names(&self) -> Result<OrdinalNames> {
+        if let Some(names) = self.ordinal_names.get() {
+            return Ok(names.clone());
+        }
+
+        let mut path = self.path.clone();
+        path.push("ordinals");
+
+        let mut names: OrdinalNames = Default::default();
+        if path.is_dir() {
+            names.add_path(&path)?;
+        }
+
+        Ok(self.ordinal_names.get_or_init(|| names).clone())
+    }
+
+    fn get_apiset_schema(&self) -> Result<ApiSetSchema> {
+        if let Some(apisets) = self.apisets.get() {
+            return Ok(apisets.clone());
+        }
+
+        let mut path = self.path.clone();
+        path.push("apisetschema.dll");
+
+        let apisets = if path.is_file() {
+            ApiSetSchema::from_path(&path)?
+        } else {
+            Default::default()
+        };
+
+        Ok(self.apisets.get_or_init(|| apisets).clone())
+    }
+
     fn clone(&self) -> Box<dyn Configuration> {
         Box::new(FileSystemConfiguration {
-            path: self.path.clone(),
+            path:          self.path.clone(),
+            ordinal_names: self.ordinal_names.clone(),
+            apisets:       self.apisets.clone(),
         })
     }
 }
@@ -66,6 +212,13 @@ impl Configuration for FileSystemConfiguration {
 pub struct DynamicConfiguration {
     sig_paths:      Vec<PathBuf>,
     function_hints: Vec<VA>,
+    base_address:   Option<VA>,
+    mapped_image:   bool,
+    pdb_paths:      Vec<PathBuf>,
+    ordinal_paths:  Vec<PathBuf>,
+    apiset_schema:  Option<PathBuf>,
+    ordinal_names:  OnceLock<OrdinalNames>,
+    apisets:        OnceLock<ApiSetSchema>,
 }
 
 impl DynamicConfiguration {
@@ -83,6 +236,40 @@ impl DynamicConfiguration {
         self.function_hints.extend_from_slice(function_hints);
         self
     }
+
+    pub fn with_base_address(mut self, base_address: VA) -> DynamicConfiguration {
+        self.base_address = Some(base_address);
+        self
+    }
+
+    /// Load a PE in its memory layout, such as one dumped from a process.
+    pub fn with_mapped_image(mut self) -> DynamicConfiguration {
+        self.mapped_image = true;
+        self
+    }
+
+    /// Provide a PDB file, which is used when its name matches the CodeView
+    /// record, or a directory to search for PDB files.
+    pub fn with_pdb_path(mut self, pdb_path: &Path) -> DynamicConfiguration {
+        self.pdb_paths.push(pdb_path.to_path_buf());
+        self
+    }
+
+    /// Provide a reference DLL, a map file with lines like
+    /// `ws2_32.dll,115,WSAStartup`, or a directory of them, used to name the
+    /// symbols that a PE imports by ordinal.
+    pub fn with_ordinal_path(mut self, ordinal_path: &Path) -> DynamicConfiguration {
+        self.ordinal_paths.push(ordinal_path.to_path_buf());
+        self.ordinal_names = Default::default();
+        self
+    }
+
+    /// Provide the `apisetschema.dll` of the system that ran the module.
+    pub fn with_apiset_schema_path(mut self, apiset_schema_path: &Path) -> DynamicConfiguration {
+        self.apiset_schema = Some(apiset_schema_path.to_path_buf());
+        self.apisets = Default::default();
+        self
+    }
 }
 
 impl Configuration for DynamicConfiguration {
@@ -108,10 +295,68 @@ impl Configuration for DynamicConfiguration {
         Ok(self.function_hints.clone())
     }
 
+    fn get_base_address(&self) -> Result<Option<VA>> {
+        Ok(self.base_address)
+    }
+
+    fn is_mapped_image(&self) -> Result<bool> {
+        Ok(self.mapped_image)
+    }
+
+    fn get_pdb_path(&self, codeview: &CodeViewRecord) -> Result<Option<PathBuf>> {
+        for pdb_path in self.pdb_paths.iter() {
+            if pdb_path.is_dir() {
+                if let Some(path) = find_pdb(pdb_path, codeview) {
+                    return Ok(Some(path));
+                }
+            } else if pdb_path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(codeview.file_name()))
+            {
+                return Ok(Some(pdb_path.clone()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn get_ordinal_names(&self) -> Result<OrdinalNames> {
+        if let Some(names) = self.ordinal_names.get() {
+            return Ok(names.clone());
+        }
+
+        let mut names: OrdinalNames = Default::default();
+        for ordinal_path in self.ordinal_paths.iter() {
+            names.add_path(ordinal_path)?;
+        }
+
+        Ok(self.ordinal_names.get_or_init(|| names).clone())
+    }
+
+    fn get_apiset_schema(&self) -> Result<ApiSetSchema> {
+        if let Some(apisets) = self.apisets.get() {
+            return Ok(apisets.clone());
+        }
+
+        let apisets = match &self.apiset_schema {
+            Some(path) => ApiSetSchema::from_path(path)?,
+            None => Default::default(),
+        };
+
+        Ok(self.apisets.get_or_init(|| apisets).clone())
+    }
+
     fn clone(&self) -> Box<dyn Configuration> {
         Box::new(DynamicConfiguration {
             sig_paths:      self.sig_paths.clone(),
             function_hints: self.function_hints.clone(),
+            base_address:   self.base_address,
+            mapped_image:   self.mapped_image,
+            pdb_paths:      self.pdb_paths.clone(),
+            ordinal_paths:  self.ordinal_paths.clone(),
+            apiset_schema:  self.apiset_schema.clone(),
+            ordinal_names:  self.ordinal_names.clone(),
+            apisets:        self.apisets.clone(),
         })
     }
 }
@@ -119,3 +364,124 @@ impl Configuration for DynamicConfiguration {
 pub fn empty() -> Box<dyn Configuration> {
     Box::new(DynamicConfiguration::default())
 }
+
+/// Wraps another configuration, overriding or extending what it provides with
+/// facts learned about the module from elsewhere, such as:
+///   - function hints, like the program counters of threads in a memory dump
+///   - the runtime address of the module, such as from a debugger or sandbox
+///     report
+///   - that a PE is in its memory layout, such as when dumped from a process
+///   - the symbols exported by the other modules loaded into the same process,
+///     such as in a minidump
+///
+/// Fields that aren't set defer to the inner configuration.
+struct OverridesConfiguration {
+    inner:          Box<dyn Configuration>,
+    function_hints: Vec<VA>,
+    base_address:   Option<VA>,
+    mapped_image:   Option<bool>,
+    exports:        BTreeMap<VA, Import>,
+}
+
+impl OverridesConfiguration {
+    fn from_config(config: Box<dyn Configuration>) -> OverridesConfiguration {
+        OverridesConfiguration {
+            inner:          config,
+            function_hints: Default::default(),
+            base_address:   None,
+            mapped_image:   None,
+            exports:        Default::default(),
+        }
+    }
+}
+
+impl Configuration for OverridesConfiguration {
+    fn get_sigs(&self) -> Result<FlirtSignatureSet> {
+        self.inner.get_sigs()
+    }
+
+    fn get_sigs_for_toolchain(&self, toolchain: &Toolchain) -> Result<FlirtSignatureSet> {
+        self.inner.get_sigs_for_toolchain(toolchain)
+    }
+
+    fn get_function_hints(&self) -> Result<Vec<VA>> {
+        let mut function_hints = self.inner.get_function_hints()?;
+        function_hints.extend_from_slice(&self.function_hints);
+        Ok(function_hints)
+    }
+
+    fn get_base_address(&self) -> Result<Option<VA>> {
+        match self.base_address {
+            Some(base_address) => Ok(Some(base_address)),
+            None => self.inner.get_base_address(),
+        }
+    }
+
+    fn is_mapped_image(&self) -> Result<bool> {
+        match self.mapped_image {
+            Some(mapped_image) => Ok(mapped_image),
+            None => self.inner.is_mapped_image(),
+        }
+    }
+
+    fn get_pdb_path(&self, codeview: &CodeViewRecord) -> Result<Option<PathBuf>> {
+        self.inner.get_pdb_path(codeview)
+    }
+
+    fn get_ordinal_names(&self) -> Result<OrdinalNames> {
+        self.inner.get_ordinal_names()
+    }
+
+    fn get_apiset_schema(&self) -> Result<ApiSetSchema> {
+        self.inner.get_apiset_schema()
+    }
+
+    fn get_process_exports(&self) -> Result<BTreeMap<VA, Import>> {
+        let mut exports = self.inner.get_process_exports()?;
+        exports.extend(self.exports.clone());
+        Ok(exports)
+    }
+
+    fn clone(&self) -> Box<dyn Configuration> {
+        Box::new(OverridesConfiguration {
+            inner:          self.inner.clone(),
+            function_hints: self.function_hints.clone(),
+            base_address:   self.base_address,
+            mapped_image:   self.mapped_image,
+            exports:        self.exports.clone(),
+        })
+    }
+}
+
+/// Add the given function hints to those provided by the configuration.
+pub fn with_function_hints(config: Box<dyn Configuration>, function_hints: &[VA]) -> Box<dyn Configuration> {
+    Box::new(OverridesConfiguration {
+        function_hints: function_hints.to_vec(),
+        ..OverridesConfiguration::from_config(config)
+    })
+}
+
+/// Override the address at which to load the module.
+pub fn with_base_address(config: Box<dyn Configuration>, base_address: VA) -> Box<dyn Configuration> {
+    Box::new(OverridesConfiguration {
+        base_address: Some(base_address),
+        ..OverridesConfiguration::from_config(config)
+    })
+}
+
+/// Load a PE in its memory layout.
+pub fn with_mapped_image(config: Box<dyn Configuration>) -> Box<dyn Configuration> {
+    Box::new(OverridesConfiguration {
+        mapped_image: Some(true),
+        ..OverridesConfiguration::from_config(config)
+    })
+}
+
+/// Add the symbols exported by the other modules loaded into the same process
+/// to those provided by the configuration.
+pub fn with_process_exports(config: Box<dyn Configuration>, exports: BTreeMap<VA, Import>) -> Box<dyn Configuration> {
+    Box::new(OverridesConfiguration {
+        exports,
+        ..OverridesConfiguration::from_config(config)
+    })
+}
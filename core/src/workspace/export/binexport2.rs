@@ -14,7 +14,7 @@ use crate::{
     analysis::{
         cfg::{self, flow::Flow, thunk::get_thunk_target},
         dis::{self, Target},
-        pe::ImportedSymbol,
+        pe::{Import, ImportedSymbol},
     },
     arch::Arch,
     aspace::AddressSpace,
@@ -155,15 +155,45 @@ fn add_reg(
     expression
 }
 
+fn add_symbol(expressions: &mut ExpressionIndex, expression_indexes: &mut Vec<i32>, symbol: &str, parent: i32) -> i32 {
+    let expression = expressions.add(pb::bin_export2::Expression {
+        r#type:        Some(pb::bin_export2::expression::Type::Symbol.into()),
+        symbol:        Some(symbol.into()),
+        immediate:     None,
+        parent_index:  Some(parent),
+        is_relocation: Some(false),
+    });
+    expression_indexes.push(expression);
+    expression
+}
+
+fn get_import_name(import: &Import) -> String {
+    match &import.symbol {
+        ImportedSymbol::Ordinal(ord) => format!("#{}", ord),
+        ImportedSymbol::Name(name) => name.clone(),
+    }
+}
+
+/// The DLL that implements the import, following API set contracts,
+/// so that the same API is found in the same library across modules.
+fn get_import_library<'a>(ws: &'a dyn Workspace, import: &'a Import) -> &'a str {
+    ws.analysis()
+        .canonical_imports
+        .get(&import.address)
+        .map(|canonical| canonical.dll.as_str())
+        .unwrap_or(&import.dll)
+}
+
 fn collect_instruction_call_targets(
     ws: &dyn Workspace,
     bb: &cfg::BasicBlock,
     insn_va: VA,
+    insn: &dis::zydis::DecodedInstruction,
     vertex_index_by_address: &BTreeMap<u64, usize>,
     call_targets_by_basic_block: &mut BTreeMap<u64, Vec<u64>>,
 ) -> Vec<u64> {
     // The list of all functions (tail-)called by this instruction.
-    let call_targets = ws
+    let mut call_targets = ws
         .cfg()
         .flows
         .flows_by_src
@@ -188,6 +218,16 @@ fn collect_instruction_call_targets(
         })
         .unwrap_or_default();
 
+    // Calls relative to the i386 GOT base, like `call [ebx+puts@GOT]`,
+    // don't have a target in the CFG, so link them to the import they reference.
+    if matches!(insn.mnemonic, dis::zydis::Mnemonic::CALL | dis::zydis::Mnemonic::JMP) {
+        if let Some(import) = ws.analysis().import_references.get(&insn_va) {
+            if vertex_index_by_address.contains_key(&import.address) && !call_targets.contains(&import.address) {
+                call_targets.push(import.address);
+            }
+        }
+    }
+
     call_targets_by_basic_block
         .entry(bb.address)
         .or_default()
@@ -310,23 +350,19 @@ fn collect_vertices(
     }));
 
     // Pass 2: Imported functions.
-    vertices.extend(
-        ws.analysis()
-            .imports
-            .iter()
-            .map(|(&address, imp)| pb::bin_export2::call_graph::Vertex {
-                address:       Some(address),
-                r#type:        Some(pb::bin_export2::call_graph::vertex::Type::Imported.into()),
-                mangled_name:  Some(match &imp.symbol {
-                    ImportedSymbol::Ordinal(ord) => format!("#{}", ord),
-                    ImportedSymbol::Name(name) => name.clone(),
-                }),
-                library_index: library_index_by_name.get(&imp.dll).map(|&v| v as i32),
-
-                demangled_name: None,
-                module_index:   None,
-            }),
-    );
+    vertices.extend(ws.analysis().imports.iter().map(|(&address, imp)| {
+        pb::bin_export2::call_graph::Vertex {
+            address:       Some(address),
+            r#type:        Some(pb::bin_export2::call_graph::vertex::Type::Imported.into()),
+            mangled_name:  Some(get_import_name(imp)),
+            library_index: library_index_by_name
+                .get(get_import_library(ws, imp))
+                .map(|&v| v as i32),
+
+            demangled_name: None,
+            module_index:   None,
+        }
+    }));
 
     // BinExport2 requires that Vertices to be sorted by address.
     vertices.sort_by_key(|v| v.address.unwrap());
@@ -643,10 +679,18 @@ fn collect_instruction_operands(
                             ("+", op.mem.disp.displacement as u64)
                         };
 
-                        let a_expr =
-                            add_operator(expressions, &mut expression_indexes, a_sym, current_expression.unwrap());
-                        add_reg(expressions, &mut expression_indexes, op.mem.base, a_expr);
-                        add_int(expressions, &mut expression_indexes, disp, a_expr);
+                        // like `[ebx+puts]`, for the slot of an import relative to the i386 GOT base.
+                        if let Some(import) = ws.analysis().import_references.get(&insn_va) {
+                            let a_expr =
+                                add_operator(expressions, &mut expression_indexes, "+", current_expression.unwrap());
+                            add_reg(expressions, &mut expression_indexes, op.mem.base, a_expr);
+                            add_symbol(expressions, &mut expression_indexes, &get_import_name(import), a_expr);
+                        } else {
+                            let a_expr =
+                                add_operator(expressions, &mut expression_indexes, a_sym, current_expression.unwrap());
+                            add_reg(expressions, &mut expression_indexes, op.mem.base, a_expr);
+                            add_int(expressions, &mut expression_indexes, disp, a_expr);
+                        }
                     }
 
                     (_, false, true, _) => unimplemented!("scale with no index"),
@@ -851,7 +895,7 @@ pub fn export_workspace_to_binexport2(
         .analysis()
         .imports
         .values()
-        .map(|import| import.dll.clone())
+        .map(|import| get_import_library(ws, import).to_string())
         .collect::<BTreeSet<String>>()
         .into_iter()
         .map(|dll| pb::bin_export2::Library {
@@ -920,6 +964,7 @@ pub fn export_workspace_to_binexport2(
                     ws,
                     bb,
                     va,
+                    &insn,
                     &vertex_index_by_address,
                     &mut call_targets_by_basic_block,
                 );
@@ -9,6 +9,7 @@ use super::Workspace;
 #[derive(Default, Clone)]
 struct OriginalHooks {
     print_address_abs: Option<zydis::Hook>,
+    print_disp:        Option<zydis::Hook>,
     print_mnemonic:    Option<zydis::Hook>,
     pre_instruction:   Option<zydis::Hook>,
 }
@@ -141,15 +142,9 @@ impl Formatter {
 
                     let va = ctx.runtime_address;
 
-                    if let Some(sec) = userdata
-                        .ws
-                        .module()
-                        .sections
-                        .iter()
-                        .find(|&sec| sec.virtual_range.contains(&va))
-                    {
+                    if let Some(name) = userdata.ws.module().get_section_name(va) {
                         buf.append(TOKEN_USER_SYMBOLNAME)?;
-                        buf.get_string()?.append(&sec.name)?;
+                        buf.get_string()?.append(name)?;
                     } else {
                         buf.append(zydis::TOKEN_INVALID)?;
                         buf.get_string()?.append("???")?;
@@ -275,6 +270,67 @@ impl Formatter {
             .unwrap();
         orig.print_address_abs = Some(f);
 
+        let f = inner
+            .set_print_disp(Box::new(
+                |formatter: &zydis::Formatter,
+                 buf: &mut zydis::FormatterBuffer,
+                 ctx: &mut zydis::FormatterContext,
+                 userdata: Option<&mut dyn core::any::Any>|
+                 -> zydis::Result<()> {
+                    // programming error: userdata must be provided. this is guaranteed within
+                    // Formatter.
+                    let userdata = userdata.expect("no userdata");
+
+                    // programming error: userdata must be a Box<UserData>. this is guaranteed
+                    // within Formatter.
+                    let userdata = userdata.downcast_ref::<UserData>().expect("incorrect userdata");
+
+                    let has_base = unsafe {
+                        // safety: the operand comes from zydis, so we assume it contains valid data.
+                        let op: &zydis::DecodedOperand = &*ctx.operand;
+                        op.mem.base != zydis::Register::NONE
+                    };
+
+                    // the slot of an import relative to the i386 GOT base, like `[ebx+puts@GOT]`,
+                    // is named like the import.
+                    let name = userdata
+                        .ws
+                        .analysis()
+                        .import_references
+                        .get(&ctx.runtime_address)
+                        .filter(|_| has_base)
+                        .and_then(|import| userdata.ws.analysis().names.names_by_address.get(&import.address));
+
+                    if let Some(name) = name {
+                        buf.append(zydis::TOKEN_DELIMITER)?;
+                        buf.get_string()?.append("+")?;
+
+                        buf.append(TOKEN_USER_SYMBOLNAME)?;
+                        buf.get_string()?.append(name)
+                    } else {
+                        // programming error: the original hook must be recorded. this is guaranteed
+                        // within Formatter.
+                        let orig = userdata.orig.print_disp.as_ref().expect("no original hook");
+
+                        if let zydis::Hook::PrintDisp(Some(f)) = orig {
+                            // safety: zydis::Formatter <-> zydis::ffi::ZydisFormatter is safe according to
+                            // here: https://docs.rs/zydis/3.1.2/src/zydis/formatter.rs.html#306
+                            let status =
+                                unsafe { f(formatter as *const _ as *const zydis::ffi::ZydisFormatter, buf, ctx) };
+                            if status.is_error() {
+                                Err(status)
+                            } else {
+                                Ok(())
+                            }
+                        } else {
+                            panic!("unexpected original hook");
+                        }
+                    }
+                },
+            ))
+            .unwrap();
+        orig.print_disp = Some(f);
+
         let f = inner
             .set_print_mnemonic(Box::new(
                 |formatter: &zydis::Formatter,
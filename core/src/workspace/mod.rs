@@ -8,19 +8,21 @@ use std::{
 use anyhow::Result;
 use bitflags::bitflags;
 use goblin::elf;
+use lancelot_flirt::FlirtSignatureSet;
 use log::{debug, warn};
 use thiserror::Error;
 
 use crate::{
     analysis::{
         cfg::{flow::Flow, InstructionIndex, CFG},
-        pe::{Import, ImportedSymbol},
+        pe::{Import, ImportedSymbol, ThunkTarget},
     },
+    arch::Arch,
     loader::{
-        coff::{SymbolKind, COFF}, elf::ELF, pe::PE
+        coff::{SymbolKind, COFF}, elf::{relocatable::ELFObject, ELF}, macho::MachO, pdb::PDB, pe::{debug::CodeViewRecord, PE}
     },
     module::Module,
-    VA,
+    RVA, VA,
 };
 
 pub mod config;
@@ -31,6 +33,8 @@ pub mod formatter;
 pub enum WorkspaceError {
     #[error("buffer too small")]
     BufferTooSmall,
+    #[error("archives contain multiple objects, use `workspaces_from_archive`")]
+    Archive,
     #[error("format not supported")]
     FormatNotSupported {
         // we want the following:
@@ -47,6 +51,10 @@ bitflags! {
     pub struct FunctionFlags: u8 {
         const NORET = 0b0000_0001;
         const THUNK = 0b0000_0010;
+        /// registered in the TLS directory, so runs before the entry point.
+        const TLS_CALLBACK = 0b0000_0100;
+        /// permitted to be called indirectly, such as by Control Flow Guard.
+        const INDIRECT_CALL_TARGET = 0b0000_1000;
     }
 }
 
@@ -67,6 +75,12 @@ impl NameIndex {
         self.addresses_by_name.insert(name, va);
     }
 
+    /// Add another name by which the address can be found,
+    /// while keeping the name displayed for it.
+    pub fn insert_alias(&mut self, va: VA, name: String) {
+        self.addresses_by_name.entry(name).or_insert(va);
+    }
+
     pub fn contains_address(&self, va: VA) -> bool {
         self.names_by_address.contains_key(&va)
     }
@@ -87,6 +101,20 @@ pub struct WorkspaceAnalysis {
     //   - file format analysis pass: pe::get_imports()
     pub imports: BTreeMap<VA, Import>,
 
+    // derived from:
+    //   - imports, and the instructions that reference their slots
+    //     relative to the i386 GOT base, like `call [ebx+puts@GOT]`.
+    // keyed by the address of the instruction.
+    // only populated for ELF files.
+    pub import_references: BTreeMap<VA, Import>,
+
+    // derived from:
+    //   - imports, following API set contracts to the DLLs that implement them
+    // keyed like `imports`, which keeps the names found in the module.
+    // the canonical names are also aliases in `names`.
+    // only populated for PE files.
+    pub canonical_imports: BTreeMap<VA, Import>,
+
     pub externs: BTreeMap<VA, String>,
 
     // derived from:
@@ -109,6 +137,81 @@ pub struct PEWorkspace {
     pub pe:       PE,
     pub cfg:      CFG,
     pub analysis: WorkspaceAnalysis,
+    /// the CLR header and metadata, when the PE is a .NET assembly.
+    pub assembly: Option<crate::analysis::pe::clr::Assembly>,
+}
+
+/// Load the PDB referenced by the CodeView record of the PE,
+/// if the configuration can locate it and its GUID and age match.
+fn load_pdb(config: &dyn config::Configuration, pe: &PE) -> Result<Option<PDB>> {
+    let Some(codeview) = CodeViewRecord::from_pe(pe)? else {
+        return Ok(None);
+    };
+
+    let Some(path) = config.get_pdb_path(&codeview)? else {
+        debug!("workspace: pdb: not found: {}", codeview.path);
+        return Ok(None);
+    };
+
+    let pdb = match std::fs::read(&path).map_err(anyhow::Error::from).and_then(|buf| PDB::from_bytes(&buf)) {
+        Ok(pdb) => pdb,
+        Err(e) => {
+            warn!("workspace: pdb: failed to load {}: {:?}", path.display(), e);
+            return Ok(None);
+        }
+    };
+
+    if !pdb.matches(&codeview.guid, codeview.age) {
+        warn!("workspace: pdb: GUID or age mismatch: {}", path.display());
+        return Ok(None);
+    }
+
+    debug!("workspace: pdb: loaded {}: {} symbols", path.display(), pdb.symbols.len());
+    Ok(Some(pdb))
+}
+
+/// Name the functions that match exactly one FLIRT signature.
+///
+/// Functions that are already named, such as by symbols or a PDB, keep their
+/// names. Colliding matches are skipped, since the name can't be determined.
+fn apply_flirt_names(
+    module: &Module,
+    sigs: &FlirtSignatureSet,
+    function_starts: &BTreeSet<VA>,
+    names: &mut NameIndex,
+) -> Result<()> {
+    for &function in function_starts.iter() {
+        if names.contains_address(function) {
+            continue;
+        }
+
+        let matches = crate::analysis::flirt::match_flirt(module, sigs, function)?;
+
+        match matches.len().cmp(&1) {
+            std::cmp::Ordering::Less => {
+                // no matches
+                continue;
+            }
+            std::cmp::Ordering::Equal => {
+                // exactly one match: perfect.
+                if let Some(name) = matches[0].get_name() {
+                    log::info!("FLIRT match: {:#x}: {}", function, name);
+                    names.insert(function, name.to_string());
+                } else {
+                    // no associated name, just know its a library function
+                    continue;
+                }
+            }
+            std::cmp::Ordering::Greater => {
+                // colliding matches, can't determine the name.
+                // TODO: maybe check for special case that all names are the same?
+                log::info!("FLIRT match: {:#x}: {} collisions", function, matches.len());
+                continue;
+            }
+        }
+    }
+
+    Ok(())
 }
 
 impl PEWorkspace {
@@ -118,18 +221,46 @@ impl PEWorkspace {
 
         function_starts.extend(config.get_function_hints()?);
 
-        function_starts.extend(crate::analysis::pe::find_function_starts(&pe)?);
+        let pdb = load_pdb(&*config, &pe)?;
+        if let Some(pdb) = &pdb {
+            function_starts.extend(crate::analysis::pe::pdb::find_pdb_function_starts(&pe, pdb)?);
+        }
+
+        // parsed once, and shared by the analyses of managed code below.
+        let assembly = crate::analysis::pe::clr::Assembly::from_pe(&pe);
+        // likewise, shared by the SafeSEH and CFG analyses below.
+        let load_config = pe.load_config()?;
+
+        function_starts.extend(crate::analysis::pe::find_function_starts(
+            &pe,
+            assembly.as_ref(),
+            load_config.as_ref(),
+        )?);
 
         for &function in function_starts.iter() {
             insns.build_index(&pe.module, function)?;
         }
 
+        // code within functions, like longjmp targets, that may only be reached indirectly.
+        for va in crate::analysis::pe::control_flow_guard::find_pe_cfguard_code_targets(&pe, load_config.as_ref()) {
+            insns.build_index(&pe.module, va)?;
+        }
+
+        // IL and metadata of .NET assemblies, which is never native code.
+        let managed_regions = match &assembly {
+            Some(assembly) => crate::analysis::pe::clr::find_pe_managed_regions(&pe, assembly)?,
+            None => vec![],
+        };
+
         // heuristic that we trust:
         //   - find_new_code_references: existing instruction operands that reference
         //     likely code.
 
         loop {
-            let new_code = crate::analysis::cfg::code_references::find_new_code_references(&pe.module, &insns)?;
+            let new_code = crate::analysis::cfg::code_references::find_new_code_references(&pe.module, &insns)?
+                .into_iter()
+                .filter(|va| managed_regions.iter().any(|region| region.contains(va)).not())
+                .collect::<Vec<VA>>();
             if new_code.is_empty() {
                 break;
             }
@@ -170,7 +301,33 @@ impl PEWorkspace {
             .collect::<BTreeSet<VA>>();
         function_starts.extend(call_targets);
 
-        let imports = crate::analysis::pe::get_imports(&pe)?;
+        // name the symbols imported by ordinal, when they're known,
+        // and those found in an IAT resolved by the loader of a dumped process.
+        let ordinal_names = config.get_ordinal_names()?;
+        let imports = crate::analysis::pe::get_resolved_imports(&pe, &config.get_process_exports()?)?
+            .values()
+            .map(|import| (import.address, ordinal_names.resolve(import)))
+            .collect::<BTreeMap<VA, Import>>();
+
+        // the schema only improves the names of imports, so don't fail the workspace without it.
+        let apisets = config.get_apiset_schema().unwrap_or_else(|e| {
+            warn!("failed to read API set schema: {:?}", e);
+            Default::default()
+        });
+        let canonical_imports = imports
+            .values()
+            .map(|import| {
+                let dll = apisets.resolve(&import.dll).unwrap_or_else(|| import.dll.clone());
+                (
+                    import.address,
+                    Import {
+                        address: import.address,
+                        dll,
+                        symbol: import.symbol.clone(),
+                    },
+                )
+            })
+            .collect::<BTreeMap<VA, Import>>();
 
         let mut names: NameIndex = Default::default();
         for import in imports.values() {
@@ -182,33 +339,72 @@ impl PEWorkspace {
             names.insert(import.address, name);
         }
 
-        let sigs = config.get_sigs()?;
-        for &function in function_starts.iter() {
-            let matches = crate::analysis::flirt::match_flirt(&pe.module, &sigs, function)?;
+        // so that `api-ms-win-core-synch-l1-2-0.dll!Sleep` is also found as `kernelbase.dll!Sleep`.
+        for import in canonical_imports.values() {
+            names.insert_alias(import.address, import.to_string());
+        }
 
-            match matches.len().cmp(&1) {
-                std::cmp::Ordering::Less => {
-                    // no matches
-                    continue;
+        // forwarded exports, by the export that implements them,
+        // like `kernelbase.dll!WaitOnAddress`.
+        for forward in crate::analysis::pe::exports::get_pe_forwarded_exports(&pe)? {
+            if names.contains_address(forward.address).not() {
+                names.insert(forward.address, forward.canonical_target(&apisets).to_string());
+            }
+        }
+
+        // stubs that resolve delay-load imports on first use, via __delayLoadHelper2.
+        let delay_import_thunks = crate::analysis::pe::find_delay_import_thunks(&pe, &imports)?;
+        for thunk in delay_import_thunks.values() {
+            if let ThunkTarget::Import(import) = &thunk.target {
+                names.insert(thunk.address, format!("__imp_load_{import}"));
+            }
+        }
+
+        if let Some(pdb) = &pdb {
+            for (va, name) in crate::analysis::pe::pdb::get_pdb_names(&pe, pdb)? {
+                if !names.contains_address(va) {
+                    names.insert(va, name);
                 }
-                std::cmp::Ordering::Equal => {
-                    // exactly one match: perfect.
-                    if let Some(name) = matches[0].get_name() {
-                        log::info!("FLIRT match: {:#x}: {}", function, name);
-                        names.insert(function, name.to_string());
-                    } else {
-                        // no associated name, just know its a library function
-                        continue;
-                    }
+            }
+        }
+
+        // native methods of mixed-mode assemblies, named by the metadata.
+        if let Some(assembly) = &assembly {
+            for (va, name) in crate::analysis::pe::clr::get_pe_native_method_names(&pe, assembly) {
+                if !names.contains_address(va) {
+                    names.insert(va, name);
                 }
-                std::cmp::Ordering::Greater => {
-                    // colliding matches, can't determine the name.
-                    // TODO: maybe check for special case that all names are the same?
-                    log::info!("FLIRT match: {:#x}: {} collisions", function, matches.len());
-                    continue;
+            }
+        }
+
+        let toolchain = crate::analysis::toolchain::Toolchain::from_pe(&pe).unwrap_or_else(|e| {
+            warn!("failed to identify toolchain: {:?}", e);
+            Default::default()
+        });
+        let sigs = config.get_sigs_for_toolchain(&toolchain)?;
+        // prefer names from the PDB and metadata.
+        apply_flirt_names(&pe.module, &sigs, &function_starts, &mut names)?;
+
+        // globals and routines referenced by the Load Config directory.
+        if let Some(load_config) = &load_config {
+            use crate::analysis::pe::control_flow_guard::{get_guard_check_icall, get_guard_dispatch_icall};
+
+            for (va, name) in [
+                (load_config.security_cookie, "__security_cookie"),
+                (load_config.guard_cf_check_function_pointer, "__guard_check_icall_fptr"),
+                (load_config.guard_cf_dispatch_function_pointer, "__guard_dispatch_icall_fptr"),
+                (get_guard_check_icall(&pe, load_config), "_guard_check_icall"),
+                (get_guard_dispatch_icall(&pe, load_config), "_guard_dispatch_icall"),
+            ] {
+                if let Some(va) = va {
+                    if names.contains_address(va).not() && names.contains_name(name).not() {
+                        names.insert(va, name.to_string());
+                    }
                 }
             }
         }
+        let mut indirect_call_targets =
+            crate::analysis::pe::control_flow_guard::find_pe_cfguard_indirect_call_targets(load_config.as_ref());
 
         for name in [
             "kernel32.dll!ExitProcess",
@@ -224,7 +420,25 @@ impl PEWorkspace {
             }
         }
 
-        let thunks = crate::analysis::cfg::thunk::find_thunks(&cfg, function_starts.iter());
+        let mut thunks = crate::analysis::cfg::thunk::find_thunks(&cfg, function_starts.iter());
+        thunks.extend(delay_import_thunks.keys());
+
+        // the targets include the IAT entries of imports whose address is taken,
+        // so the thunks that jump through these entries may be called indirectly, too.
+        for thunk in crate::analysis::pe::find_thunks(&pe, &imports, &thunks)?.values() {
+            if let ThunkTarget::Import(import) = &thunk.target {
+                if indirect_call_targets.contains(&import.address) {
+                    indirect_call_targets.insert(thunk.address);
+                }
+            }
+        }
+
+        let tls_callbacks = crate::analysis::pe::entrypoints::find_pe_tls_callbacks(&pe)?;
+        for (i, &callback) in tls_callbacks.iter().enumerate() {
+            if names.contains_address(callback).not() {
+                names.insert(callback, format!("tls_callback_{i}"));
+            }
+        }
 
         let mut functions: BTreeMap<VA, FunctionAnalysis> = Default::default();
         for va in function_starts {
@@ -238,6 +452,14 @@ impl PEWorkspace {
                 flags.set(FunctionFlags::THUNK, true);
             }
 
+            if tls_callbacks.contains(&va) {
+                flags.set(FunctionFlags::TLS_CALLBACK, true);
+            }
+
+            if indirect_call_targets.contains(&va) {
+                flags.set(FunctionFlags::INDIRECT_CALL_TARGET, true);
+            }
+
             functions.insert(va, FunctionAnalysis { flags });
         }
 
@@ -251,14 +473,44 @@ impl PEWorkspace {
             config,
             pe,
             cfg,
+            assembly,
             analysis: WorkspaceAnalysis {
                 functions,
                 imports,
+                import_references: Default::default(),
+                canonical_imports,
                 externs: Default::default(),
                 names,
             },
         })
     }
+
+    /// Identify the compiler, linker, and runtime that likely built the PE.
+    pub fn toolchain(&self) -> Result<crate::analysis::toolchain::Toolchain> {
+        crate::analysis::toolchain::Toolchain::from_pe(&self.pe)
+    }
+
+    /// Whether the PE is a .NET assembly, which may also contain native code
+    /// when it's mixed-mode.
+    pub fn is_managed(&self) -> bool {
+        matches!(self.pe.clr_header(), Ok(Some(_)))
+    }
+
+    /// The native symbols imported by the P/Invoke declarations of a .NET
+    /// assembly. These have no import address, since the runtime resolves
+    /// them on first use.
+    pub fn pinvokes(&self) -> Vec<crate::analysis::pe::clr::PInvoke> {
+        match &self.assembly {
+            Some(assembly) => crate::analysis::pe::clr::get_pe_pinvokes(assembly),
+            None => vec![],
+        }
+    }
+
+    /// Parse the version info, manifest, and string table resources,
+    /// which are commonly needed to triage a PE.
+    pub fn resources(&self) -> Result<Option<crate::loader::pe::rsrc::Resources>> {
+        crate::loader::pe::rsrc::Resources::from_pe(&self.pe)
+    }
 }
 
 impl Workspace for PEWorkspace {
@@ -350,6 +602,10 @@ impl COFFWorkspace {
             .collect::<BTreeSet<VA>>();
         function_starts.extend(call_targets);
 
+        // static functions have no symbols, so may be recognized by FLIRT.
+        let sigs = config.get_sigs()?;
+        apply_flirt_names(&coff.module, &sigs, &function_starts, &mut names)?;
+
         let mut noret: BTreeSet<VA> = Default::default();
 
         for name in [
@@ -396,6 +652,8 @@ impl COFFWorkspace {
             analysis: WorkspaceAnalysis {
                 functions,
                 imports: Default::default(),
+                import_references: Default::default(),
+                canonical_imports: Default::default(),
                 externs,
                 names,
             },
@@ -464,40 +722,35 @@ impl ELFWorkspace {
 
         let elf_imports = crate::analysis::elf::get_imports(&elf)?;
         
-        let goblin_elf = elf::Elf::parse(&elf.buf)?;
-        let base_address = elf.module.address_space.base_address;
-        
         let mut plt_ranges: Vec<(VA, VA)> = Vec::new();
-        for section in goblin_elf.section_headers.iter() {
-            if let Some(name) = goblin_elf.shdr_strtab.get_at(section.sh_name) {
-                if name == ".plt" {
-                    // skip the first entry to avoid filtering out the PLT resolver
-                    let plt_entry_size = if elf.module.arch.pointer_size() == 8 { 16u64 } else { 16u64 };
-                    let start = section.sh_addr + base_address + plt_entry_size;
-                    let end = section.sh_addr + base_address + section.sh_size;
-                    if start < end {
-                        debug!("Found PLT section '{}': {:#x} - {:#x} (excluding header at {:#x})", 
-                               name, start, end, section.sh_addr + base_address);
-                        plt_ranges.push((start, end));
-                    }
-                } else if name == ".plt.got" || name == ".plt.sec" {
-                    let start = section.sh_addr + base_address;
-                    let end = start + section.sh_size;
-                    debug!("Found PLT section '{}': {:#x} - {:#x} (size: {:#x})", 
-                           name, start, end, section.sh_size);
+        for section in elf.module.section_headers.iter() {
+            if section.name == ".plt" {
+                // skip the first entry to avoid filtering out the PLT resolver
+                let start = section.virtual_range.start + crate::analysis::elf::plt::PLT_HEADER_SIZE;
+                let end = section.virtual_range.end;
+                if start < end {
+                    debug!("Found PLT section '{}': {:#x} - {:#x} (excluding header at {:#x})", 
+                           section.name, start, end, section.virtual_range.start);
                     plt_ranges.push((start, end));
                 }
+            } else if section.name == ".plt.got" || section.name == ".plt.sec" {
+                let start = section.virtual_range.start;
+                let end = section.virtual_range.end;
+                debug!("Found PLT section '{}': {:#x} - {:#x} (size: {:#x})", 
+                       section.name, start, end, end - start);
+                plt_ranges.push((start, end));
             }
         }
         
         // convert ELF imports to the common Import format
         let mut imports: BTreeMap<VA, Import> = Default::default();
         for (_, elf_import) in elf_imports.iter() {
-            let address = elf_import.address + base_address;
+            let address = elf_import.address;
             let import = Import {
                 address,
                 dll: elf_import.library.clone(),
-                symbol: ImportedSymbol::Name(elf_import.symbol.name.clone()),
+                // with the version, like `memcpy@GLIBC_2.14`
+                symbol: ImportedSymbol::Name(elf_import.symbol.versioned_name()),
             };
             
             imports.insert(address, import);
@@ -538,12 +791,17 @@ impl ELFWorkspace {
 
         // parse ELF for symbol names
         let goblin_elf = elf::Elf::parse(&elf.buf)?;
-        for sym in goblin_elf.dynsyms.iter() {
+        for (idx, sym) in goblin_elf.dynsyms.iter().enumerate() {
             if sym.st_value != 0 {
-                let addr = sym.st_value + elf.module.address_space.base_address;
+                let addr = sym.st_value.wrapping_add(elf.load_bias());
                 if let Some(name) = goblin_elf.dynstrtab.get_at(sym.st_name) {
                     if !name.is_empty() && !names.contains_address(addr) {
-                        names.insert(addr, name.to_string());
+                        // the default version keeps the plain name, like `memcpy`,
+                        // while older versions are distinguished, like `memcpy@GLIBC_2.2.5`.
+                        match elf.symbol_versions.get(idx).cloned().flatten() {
+                            Some(version) if version.hidden => names.insert(addr, format!("{}@{}", name, version.name)),
+                            _ => names.insert(addr, name.to_string()),
+                        }
                     }
                 }
             }
@@ -552,7 +810,7 @@ impl ELFWorkspace {
         // add symbols from symtab
         for sym in goblin_elf.syms.iter() {
             if sym.st_value != 0 {
-                let addr = sym.st_value + elf.module.address_space.base_address;
+                let addr = sym.st_value.wrapping_add(elf.load_bias());
                 if let Some(name) = goblin_elf.strtab.get_at(sym.st_name) {
                     if !name.is_empty() && !names.contains_address(addr) {
                         names.insert(addr, name.to_string());
@@ -561,21 +819,53 @@ impl ELFWorkspace {
             }
         }
 
+        // statically linked binaries are often stripped,
+        // so FLIRT may recognize the runtime and library functions.
+        let toolchain = crate::analysis::toolchain::Toolchain::from_elf(&elf).unwrap_or_else(|e| {
+            warn!("failed to identify toolchain: {:?}", e);
+            Default::default()
+        });
+        let sigs = config.get_sigs_for_toolchain(&toolchain)?;
+        apply_flirt_names(&elf.module, &sigs, &function_starts, &mut names)?;
+
         // name the entry point if it doesn't have a name
-        let entry_point = goblin_elf.header.e_entry + elf.module.address_space.base_address;
+        let entry_point = goblin_elf.header.e_entry.wrapping_add(elf.load_bias());
         if names.contains_address(entry_point).not() {
             names.insert(entry_point, "<entry_point>".to_string());
         }
 
-        let sigs = config.get_sigs()?;
-        for &function in function_starts.iter() {
-            let matches = crate::analysis::flirt::match_flirt(&elf.module, &sigs, function)?;
-            if !matches.is_empty() {
-                log::debug!("FLIRT matches for {:#x}: {}", function, matches.len());
+        // i386 PIC code computes the GOT base via `__x86.get_pc_thunk.*`,
+        // and then calls imports relative to it.
+        let pc_thunks = crate::analysis::elf::plt::find_elf_pc_thunks(&elf.module, function_starts.iter());
+        for (&thunk, &reg) in pc_thunks.iter() {
+            if names.contains_address(thunk).not() {
+                names.insert(thunk, crate::analysis::elf::plt::get_pc_thunk_name(reg));
+            }
+        }
+
+        let imports_by_slot = elf_imports
+            .values()
+            .filter_map(|elf_import| Some((elf_import.symbol.got_address?, &imports[&elf_import.address])))
+            .collect::<BTreeMap<VA, &Import>>();
+        let import_references = crate::analysis::elf::plt::find_elf_got_references(&elf, &cfg, &pc_thunks)?
+            .into_iter()
+            .filter_map(|(va, slot)| Some((va, imports_by_slot.get(&slot).copied()?.clone())))
+            .collect::<BTreeMap<VA, Import>>();
+
+        // name the initializers and IFUNC resolvers that don't have symbols
+        for function in crate::analysis::elf::init::find_elf_init_functions(&elf)? {
+            if names.contains_address(function.address).not() {
+                names.insert(function.address, function.name());
+            }
+        }
+        for (i, resolver) in crate::analysis::elf::init::find_elf_ifunc_resolvers(&elf)?.into_iter().enumerate() {
+            if names.contains_address(resolver).not() {
+                names.insert(resolver, format!("ifunc_resolver_{i}"));
             }
         }
 
-        // mark noret functions based on known exit functions
+        // mark noret functions based on known exit functions,
+        // and propagate to the functions that always call them.
         for name in [
             "exit",
             "_exit",
@@ -584,7 +874,9 @@ impl ELFWorkspace {
             "abort",
         ] {
             if let Some(&va) = names.addresses_by_name.get(name) {
+                log::info!("noret via name: {}: {:#x}", name, va);
                 noret.insert(va);
+                noret.extend(crate::analysis::cfg::noret::cfg_mark_noret(&elf.module, &mut cfg, va)?);
             }
         }
 
@@ -620,12 +912,19 @@ impl ELFWorkspace {
             analysis: WorkspaceAnalysis {
                 functions,
                 imports,
+                import_references,
+                canonical_imports: Default::default(),
                 externs: BTreeMap::new(),
                 names,
             },
         })
 
     }
+
+    /// Identify the compiler, linker, and runtime that likely built the ELF.
+    pub fn toolchain(&self) -> Result<crate::analysis::toolchain::Toolchain> {
+        crate::analysis::toolchain::Toolchain::from_elf(&self.elf)
+    }
 }
 
 impl Workspace for ELFWorkspace {
@@ -646,106 +945,728 @@ impl Workspace for ELFWorkspace {
     }
 }
 
-pub fn workspace_from_bytes(config: Box<dyn config::Configuration>, buf: &[u8]) -> Result<Box<dyn Workspace>> {
-    if buf.len() < 2 {
-        return Err(WorkspaceError::BufferTooSmall.into());
-    }
+pub struct ELFObjectWorkspace {
+    pub config:   Box<dyn config::Configuration>,
+    pub obj:      ELFObject,
+    pub cfg:      CFG,
+    pub analysis: WorkspaceAnalysis,
+}
 
-    // TODO: move this tasting to the loaders?
-    match (buf[1] as u16) << 8u16 | buf[0] as u16 {
-        0x5A4D => {
-            let pe = crate::loader::pe::PE::from_bytes(buf)?;
-            Ok(Box::new(PEWorkspace::from_pe(config, pe)?))
+impl ELFObjectWorkspace {
+    pub fn from_elf_object(config: Box<dyn config::Configuration>, obj: ELFObject) -> Result<ELFObjectWorkspace> {
+        let mut insns: InstructionIndex = Default::default();
+        let mut function_starts: BTreeSet<VA> = Default::default();
+
+        function_starts.extend(config.get_function_hints()?);
+
+        let mut names: NameIndex = Default::default();
+        for (name, symbol) in obj.symbols.by_name.iter() {
+            if let SymbolKind::Text = symbol.kind {
+                function_starts.insert(symbol.address);
+                names.insert(symbol.address, name.clone());
+            }
         }
-        0x14C => {
-            // coff.Machine == IMAGE_FILE_MACHINE_I386
-            // from msvcrt libcpmt.lib 0a783ea78e08268f9ead780da0368409
-            let coff = crate::loader::coff::COFF::from_bytes(buf)?;
-            Ok(Box::new(COFFWorkspace::from_coff(config, coff)?))
+        // each address may have multiple associated names.
+        // so prefer function names, and then fill  in anything else.
+        for (name, symbol) in obj.symbols.by_name.iter() {
+            if names.contains_address(symbol.address).not() {
+                names.insert(symbol.address, name.clone());
+            }
         }
-        0x8664 => {
-            // coff.Machine == IMAGE_FILE_MACHINE_AMD64
-            // from static libs built via MSVC 2019
-            let coff = crate::loader::coff::COFF::from_bytes(buf)?;
-            Ok(Box::new(COFFWorkspace::from_coff(config, coff)?))
+
+        let externs: BTreeMap<VA, String> = obj.externs.iter().map(|(name, &va)| (va, name.clone())).collect();
+        for (&va, name) in externs.iter() {
+            names.insert(va, name.clone());
         }
-        _ => {
-            // check for elf 
-            if buf.len() >= 4 && &buf[0..4] == b"\x7FELF" {
-                let elf = crate::loader::elf::ELF::from_bytes(buf)?;
-                return Ok(Box::new(ELFWorkspace::from_elf(config, elf)?));
-            }
-            warn!("workspace: unknown file format: magic: {:02x} {:02x}", buf[0], buf[1]);
-            Err(WorkspaceError::FormatNotSupported {
-                source: anyhow::anyhow!("unknown magic"),
-            }
-            .into())
+
+        for &function in function_starts.iter() {
+            insns.build_index(&obj.module, function)?;
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::rsrc::*;
+        loop {
+            let new_code = crate::analysis::cfg::code_references::find_new_code_references(&obj.module, &insns)?;
+            if new_code.is_empty() {
+                break;
+            }
 
-    #[test]
-    fn nop() -> Result<()> {
-        //crate::test::init_logging();
+            for &function in new_code.iter() {
+                insns.build_index(&obj.module, function)?;
 
-        let buf = get_buf(Rsrc::NOP);
-        let pe = crate::loader::pe::PE::from_bytes(&buf)?;
-        let config = get_config();
+                // see note in PE workspace about whether this is the right idea or note.
+                function_starts.insert(function);
+            }
+        }
 
-        let ws = PEWorkspace::from_pe(config, pe)?;
+        let mut cfg = CFG::from_instructions(&obj.module, insns)?;
 
-        // entry point
-        assert!(ws.analysis.functions.contains_key(&0x401081));
+        let mut function_starts = function_starts
+            .into_iter()
+            .filter(|va| cfg.insns.insns_by_address.contains_key(va))
+            .collect::<BTreeSet<VA>>();
+        let call_targets = cfg
+            .basic_blocks
+            .blocks_by_address
+            .keys()
+            .cloned()
+            .filter(|bb| {
+                cfg.flows.flows_by_dst[bb]
+                    .iter()
+                    .any(|flow| matches!(flow, Flow::Call(_)))
+            })
+            .collect::<BTreeSet<VA>>();
+        function_starts.extend(call_targets);
 
-        // main
-        assert!(ws.analysis.functions.contains_key(&0x401000));
+        // local functions may be unnamed, such as when the object was stripped,
+        // so FLIRT may recognize them. prefer names from the symbols.
+        let sigs = config.get_sigs()?;
+        apply_flirt_names(&obj.module, &sigs, &function_starts, &mut names)?;
 
-        assert!(ws.analysis.imports.contains_key(&0x40600C));
-        assert!(ws
-            .analysis
-            .names
-            .contains_name(&String::from("kernel32.dll!ExitProcess")));
-        assert!(ws.analysis.names.contains_address(0x40600C));
+        let mut noret: BTreeSet<VA> = Default::default();
 
-        // 0x401C4E: void __cdecl __noreturn __crtExitProcess(UINT uExitCode)
-        assert!(ws.analysis.functions[&0x401C4E].flags.intersects(FunctionFlags::NORET));
+        for name in ["exit", "_exit", "__exit", "_Exit", "abort", "__stack_chk_fail", "__assert_fail"] {
+            if let Some(&va) = names.addresses_by_name.get(name) {
+                log::info!("noret via name: {}: {:#x}", name, va);
+                noret.extend(crate::analysis::cfg::noret::cfg_mark_noret(&obj.module, &mut cfg, va)?);
+            }
+        }
 
-        // ```
-        //     .text:00405F42  ; void __stdcall RtlUnwind(PVOID TargetFrame, PVOID TargetIp, PEXCEPTION_RECORD ExceptionRecord, PVOID ReturnValue)
-        //     .text:00405F42  RtlUnwind       proc near               ; CODE XREF: __global_unwind2+13↑p
-        //     .text:00405F42
-        //     .text:00405F42  TargetFrame     = dword ptr  4
-        //     .text:00405F42  TargetIp        = dword ptr  8
-        //     .text:00405F42  ExceptionRecord = dword ptr  0Ch
-        //     .text:00405F42  ReturnValue     = dword ptr  10h
-        //     .text:00405F42
-        //     .text:00405F42 000 FF 25 7C 60 40 00  jmp     ds:__imp_RtlUnwind
-        //     .text:00405F42  RtlUnwind       endp
-        // ```
-        assert!(ws.analysis.functions[&0x405F42].flags.intersects(FunctionFlags::THUNK));
+        let thunks = crate::analysis::cfg::thunk::find_thunks(&cfg, function_starts.iter());
 
-        // via FLIRT 0x401da9: _exit
-        assert!(ws.analysis.functions.contains_key(&0x401da9));
-        assert!(ws.analysis.names.contains_name(&String::from("_exit")));
-        assert!(ws.analysis.functions[&0x401da9].flags.intersects(FunctionFlags::NORET));
+        let mut functions: BTreeMap<VA, FunctionAnalysis> = Default::default();
+        for va in function_starts {
+            let mut flags = FunctionFlags::empty();
 
-        Ok(())
-    }
+            if noret.contains(&va) {
+                flags.set(FunctionFlags::NORET, true);
+            }
 
-    #[test]
-    fn pe() -> Result<()> {
-        let buf = get_buf(Rsrc::NOP);
-        let config = get_config();
-        let ws = workspace_from_bytes(config, &buf)?;
+            if thunks.contains(&va) {
+                flags.set(FunctionFlags::THUNK, true);
+            }
 
-        assert!(ws.analysis().functions.contains_key(&0x401081));
+            functions.insert(va, FunctionAnalysis { flags });
+        }
 
-        Ok(())
+        for &function in functions.keys() {
+            if names.contains_address(function).not() {
+                names.insert(function, format!("sub_{function:x}"));
+            }
+        }
+
+        Ok(ELFObjectWorkspace {
+            config,
+            obj,
+            cfg,
+            analysis: WorkspaceAnalysis {
+                functions,
+                imports: Default::default(),
+                import_references: Default::default(),
+                canonical_imports: Default::default(),
+                externs,
+                names,
+            },
+        })
+    }
+}
+
+impl Workspace for ELFObjectWorkspace {
+    fn config(&self) -> &Box<dyn config::Configuration> {
+        &self.config
+    }
+
+    fn cfg(&self) -> &CFG {
+        &self.cfg
+    }
+
+    fn analysis(&self) -> &WorkspaceAnalysis {
+        &self.analysis
+    }
+
+    fn module(&self) -> &Module {
+        &self.obj.module
+    }
+}
+
+pub struct MachOWorkspace {
+    pub config:   Box<dyn config::Configuration>,
+    pub macho:    MachO,
+    pub cfg:      CFG,
+    pub analysis: WorkspaceAnalysis,
+}
+
+impl MachOWorkspace {
+    pub fn from_macho(config: Box<dyn config::Configuration>, macho: MachO) -> Result<MachOWorkspace> {
+        let mut insns: InstructionIndex = Default::default();
+        let mut function_starts: BTreeSet<VA> = Default::default();
+
+        function_starts.extend(config.get_function_hints()?);
+
+        function_starts.extend(crate::analysis::macho::find_function_starts(&macho)?);
+
+        for &function in function_starts.iter() {
+            insns.build_index(&macho.module, function)?;
+        }
+
+        loop {
+            let new_code = crate::analysis::cfg::code_references::find_new_code_references(&macho.module, &insns)?;
+            if new_code.is_empty() {
+                break;
+            }
+
+            for &function in new_code.iter() {
+                insns.build_index(&macho.module, function)?;
+
+                // see note in PE workspace about whether this is the right idea or note.
+                function_starts.insert(function);
+            }
+        }
+
+        let mut cfg = CFG::from_instructions(&macho.module, insns)?;
+
+        let mut noret = crate::analysis::macho::noret_imports::cfg_prune_noret_imports(&macho, &mut cfg)?;
+
+        let mut function_starts = function_starts
+            .into_iter()
+            .filter(|va| cfg.insns.insns_by_address.contains_key(va))
+            .collect::<BTreeSet<VA>>();
+        let call_targets = cfg
+            .basic_blocks
+            .blocks_by_address
+            .keys()
+            .cloned()
+            .filter(|bb| {
+                cfg.flows.flows_by_dst[bb]
+                    .iter()
+                    .any(|flow| matches!(flow, Flow::Call(_)))
+            })
+            .collect::<BTreeSet<VA>>();
+        function_starts.extend(call_targets);
+
+        let imports = crate::analysis::macho::get_imports(&macho)?;
+
+        let mut names: NameIndex = Default::default();
+        for import in imports.values() {
+            names.insert(import.address, import.to_string());
+        }
+
+        // stubs are named like the symbol they jump to, such as `_exit`,
+        // which is how they're referenced by the code that calls them.
+        for (&va, stub) in crate::analysis::macho::get_stubs(&macho)?.iter() {
+            if let ImportedSymbol::Name(name) = &stub.symbol {
+                names.insert(va, name.clone());
+            }
+        }
+
+        for (&va, name) in crate::analysis::macho::symbols::find_macho_symbols(&macho)?.iter() {
+            if names.contains_address(va).not() {
+                names.insert(va, name.clone());
+            }
+        }
+
+        // prefer names from the symbols.
+        let sigs = config.get_sigs()?;
+        apply_flirt_names(&macho.module, &sigs, &function_starts, &mut names)?;
+
+        for name in ["_exit", "__exit", "_abort"] {
+            if let Some(&va) = names.addresses_by_name.get(name) {
+                log::info!("noret via name: {}: {:#x}", name, va);
+                noret.extend(crate::analysis::cfg::noret::cfg_mark_noret(&macho.module, &mut cfg, va)?);
+            }
+        }
+
+        let thunks = crate::analysis::cfg::thunk::find_thunks(&cfg, function_starts.iter());
+
+        let mut functions: BTreeMap<VA, FunctionAnalysis> = Default::default();
+        for va in function_starts {
+            let mut flags = FunctionFlags::empty();
+
+            if noret.contains(&va) {
+                flags.set(FunctionFlags::NORET, true);
+            }
+
+            if thunks.contains(&va) {
+                flags.set(FunctionFlags::THUNK, true);
+            }
+
+            functions.insert(va, FunctionAnalysis { flags });
+        }
+
+        for &function in functions.keys() {
+            if names.contains_address(function).not() {
+                names.insert(function, format!("sub_{function:x}"));
+            }
+        }
+
+        Ok(MachOWorkspace {
+            config,
+            macho,
+            cfg,
+            analysis: WorkspaceAnalysis {
+                functions,
+                imports,
+                import_references: Default::default(),
+                canonical_imports: Default::default(),
+                externs: Default::default(),
+                names,
+            },
+        })
+    }
+}
+
+impl Workspace for MachOWorkspace {
+    fn config(&self) -> &Box<dyn config::Configuration> {
+        &self.config
+    }
+
+    fn cfg(&self) -> &CFG {
+        &self.cfg
+    }
+
+    fn analysis(&self) -> &WorkspaceAnalysis {
+        &self.analysis
+    }
+
+    fn module(&self) -> &Module {
+        &self.macho.module
+    }
+}
+
+pub struct ShellcodeWorkspace {
+    pub config:   Box<dyn config::Configuration>,
+    pub module:   Module,
+    pub cfg:      CFG,
+    pub analysis: WorkspaceAnalysis,
+}
+
+impl ShellcodeWorkspace {
+    /// Analyze raw code, like shellcode or a carved blob, loaded at the given base address.
+    /// `entry_points` are offsets into the buffer at which code is known to start.
+    pub fn from_shellcode(
+        config: Box<dyn config::Configuration>,
+        buf: &[u8],
+        arch: Arch,
+        base_address: VA,
+        entry_points: &[RVA],
+    ) -> Result<ShellcodeWorkspace> {
+        let module = crate::loader::shellcode::load_shellcode(arch, buf, base_address)?;
+
+        let entry_points = entry_points
+            .iter()
+            .map(|&offset| {
+                base_address.checked_add(offset).ok_or_else(|| {
+                    anyhow::Error::from(WorkspaceError::FormatNotSupported {
+                        source: anyhow::anyhow!("entry point beyond the address space: {offset:#x}"),
+                    })
+                })
+            })
+            .collect::<Result<Vec<VA>>>()?;
+
+        ShellcodeWorkspace::from_module(config, module, &entry_points)
+    }
+
+    /// Analyze code that's already mapped into a module but has no file format to describe it,
+    /// like the memory of a process captured in a core dump.
+    /// `entry_points` are addresses at which code is known to start.
+    pub fn from_module(
+        config: Box<dyn config::Configuration>,
+        module: Module,
+        entry_points: &[VA],
+    ) -> Result<ShellcodeWorkspace> {
+        let mut insns: InstructionIndex = Default::default();
+        let mut function_starts: BTreeSet<VA> = Default::default();
+
+        function_starts.extend(config.get_function_hints()?);
+        function_starts.extend(crate::analysis::shellcode::find_function_starts(&module, entry_points)?);
+
+        for &function in function_starts.iter() {
+            insns.build_index(&module, function)?;
+        }
+
+        loop {
+            let new_code = crate::analysis::cfg::code_references::find_new_code_references(&module, &insns)?;
+            if new_code.is_empty() {
+                break;
+            }
+
+            for &function in new_code.iter() {
+                insns.build_index(&module, function)?;
+
+                // see note in PE workspace about whether this is the right idea or note.
+                function_starts.insert(function);
+            }
+        }
+
+        let cfg = CFG::from_instructions(&module, insns)?;
+
+        let mut function_starts = function_starts
+            .into_iter()
+            .filter(|va| cfg.insns.insns_by_address.contains_key(va))
+            .collect::<BTreeSet<VA>>();
+        let call_targets = cfg
+            .basic_blocks
+            .blocks_by_address
+            .keys()
+            .cloned()
+            .filter(|bb| {
+                cfg.flows.flows_by_dst[bb]
+                    .iter()
+                    .any(|flow| matches!(flow, Flow::Call(_)))
+            })
+            .collect::<BTreeSet<VA>>();
+        function_starts.extend(call_targets);
+
+        let thunks = crate::analysis::cfg::thunk::find_thunks(&cfg, function_starts.iter());
+
+        let mut functions: BTreeMap<VA, FunctionAnalysis> = Default::default();
+        for va in function_starts {
+            let mut flags = FunctionFlags::empty();
+
+            if thunks.contains(&va) {
+                flags.set(FunctionFlags::THUNK, true);
+            }
+
+            functions.insert(va, FunctionAnalysis { flags });
+        }
+
+        let mut names: NameIndex = Default::default();
+        for &function in functions.keys() {
+            names.insert(function, format!("sub_{function:x}"));
+        }
+
+        Ok(ShellcodeWorkspace {
+            config,
+            module,
+            cfg,
+            analysis: WorkspaceAnalysis {
+                functions,
+                imports: Default::default(),
+                import_references: Default::default(),
+                canonical_imports: Default::default(),
+                externs: Default::default(),
+                names,
+            },
+        })
+    }
+}
+
+impl Workspace for ShellcodeWorkspace {
+    fn config(&self) -> &Box<dyn config::Configuration> {
+        &self.config
+    }
+
+    fn cfg(&self) -> &CFG {
+        &self.cfg
+    }
+
+    fn analysis(&self) -> &WorkspaceAnalysis {
+        &self.analysis
+    }
+
+    fn module(&self) -> &Module {
+        &self.module
+    }
+}
+
+pub fn workspace_from_bytes(config: Box<dyn config::Configuration>, buf: &[u8]) -> Result<Box<dyn Workspace>> {
+    if buf.len() < 2 {
+        return Err(WorkspaceError::BufferTooSmall.into());
+    }
+
+    if crate::loader::ar::is_archive(buf) {
+        return Err(WorkspaceError::Archive.into());
+    }
+
+    // TODO: move this tasting to the loaders?
+    match (buf[1] as u16) << 8u16 | buf[0] as u16 {
+        0x5A4D => {
+            let pe = match (config.is_mapped_image()?, config.get_base_address()?) {
+                (true, base_address) => crate::loader::pe::PE::from_mapped_bytes(buf, base_address)?,
+                (false, Some(base_address)) => crate::loader::pe::PE::from_bytes_at(buf, base_address)?,
+                (false, None) => crate::loader::pe::PE::from_bytes(buf)?,
+            };
+            Ok(Box::new(PEWorkspace::from_pe(config, pe)?))
+        }
+        0x14C => {
+            // coff.Machine == IMAGE_FILE_MACHINE_I386
+            // from msvcrt libcpmt.lib 0a783ea78e08268f9ead780da0368409
+            let coff = crate::loader::coff::COFF::from_bytes(buf)?;
+            Ok(Box::new(COFFWorkspace::from_coff(config, coff)?))
+        }
+        0x8664 => {
+            // coff.Machine == IMAGE_FILE_MACHINE_AMD64
+            // from static libs built via MSVC 2019
+            let coff = crate::loader::coff::COFF::from_bytes(buf)?;
+            Ok(Box::new(COFFWorkspace::from_coff(config, coff)?))
+        }
+        _ => {
+            // check for elf 
+            if buf.len() >= 4 && &buf[0..4] == b"\x7FELF" {
+                if crate::loader::elf::coredump::is_elf_core(buf) {
+                    let core = crate::loader::elf::coredump::ELFCore::from_bytes(buf)?;
+                    return Ok(Box::new(workspace_from_elf_core(config, &core)?));
+                }
+                if crate::loader::elf::relocatable::is_elf_object(buf) {
+                    let obj = ELFObject::from_bytes(buf)?;
+                    return Ok(Box::new(ELFObjectWorkspace::from_elf_object(config, obj)?));
+                }
+                let elf = match config.get_base_address()? {
+                    Some(base_address) => crate::loader::elf::ELF::from_bytes_at(buf, base_address)?,
+                    None => crate::loader::elf::ELF::from_bytes(buf)?,
+                };
+                return Ok(Box::new(ELFWorkspace::from_elf(config, elf)?));
+            }
+            // check for mach-o: MH_MAGIC_64, MH_MAGIC, or FAT_MAGIC (universal binary)
+            // but not java class files, which share FAT_MAGIC.
+            if crate::loader::macho::is_macho(buf) {
+                let macho = crate::loader::macho::MachO::from_bytes(buf)?;
+                return Ok(Box::new(MachOWorkspace::from_macho(config, macho)?));
+            }
+            // check for windows minidump, and analyze the first module, the main executable.
+            if buf.starts_with(crate::loader::minidump::MINIDUMP_SIGNATURE) {
+                let dump = crate::loader::minidump::Minidump::from_bytes(buf)?;
+                let Some(module) = dump.modules.first() else {
+                    return Err(WorkspaceError::FormatNotSupported {
+                        source: anyhow::anyhow!("minidump contains no modules"),
+                    }
+                    .into());
+                };
+                let exports = get_minidump_exports(&dump);
+                return Ok(Box::new(workspace_from_minidump_module(config, &dump, module, &exports)?));
+            }
+            warn!("workspace: unknown file format: magic: {:02x} {:02x}", buf[0], buf[1]);
+            Err(WorkspaceError::FormatNotSupported {
+                source: anyhow::anyhow!("unknown magic"),
+            }
+            .into())
+        }
+    }
+}
+
+/// Create a workspace for raw code, like shellcode or a carved blob,
+/// which has no header to describe its architecture or load address.
+/// See `ShellcodeWorkspace::from_shellcode`.
+pub fn workspace_from_shellcode(
+    config: Box<dyn config::Configuration>,
+    buf: &[u8],
+    arch: Arch,
+    base_address: VA,
+    entry_points: &[RVA],
+) -> Result<Box<dyn Workspace>> {
+    Ok(Box::new(ShellcodeWorkspace::from_shellcode(
+        config,
+        buf,
+        arch,
+        base_address,
+        entry_points,
+    )?))
+}
+
+/// Create a workspace for each object member of the given archive,
+/// such as a static library (`.a`, `.lib`) from GCC, Clang, or MSVC.
+///
+/// Each item is the member name along with the result of loading it,
+/// so that one malformed object doesn't prevent analysis of the rest.
+/// Workspaces are created lazily, as the iterator is consumed.
+/// Members without code, like the short-format entries of import libraries,
+/// are skipped; use `loader::ar::read_archive` to inspect these.
+pub fn workspaces_from_archive<'a>(
+    config: Box<dyn config::Configuration>,
+    buf: &'a [u8],
+) -> Result<impl Iterator<Item = (String, Result<Box<dyn Workspace>>)> + 'a> {
+    use crate::loader::ar::MemberKind;
+
+    let members = crate::loader::ar::read_archive(buf)?;
+
+    Ok(members.into_iter().filter_map(move |member| {
+        let ws: Result<Box<dyn Workspace>> = match member.kind {
+            MemberKind::Coff => COFF::from_bytes(member.buf)
+                .and_then(|coff| COFFWorkspace::from_coff(config.clone(), coff))
+                .map(|ws| Box::new(ws) as Box<dyn Workspace>),
+            MemberKind::Elf => ELFObject::from_bytes(member.buf)
+                .and_then(|obj| ELFObjectWorkspace::from_elf_object(config.clone(), obj))
+                .map(|ws| Box::new(ws) as Box<dyn Workspace>),
+            _ => {
+                debug!("workspace: archive: skipping member: {}: {:?}", member.name, member.kind);
+                return None;
+            }
+        };
+
+        Some((member.name, ws))
+    }))
+}
+
+/// Collect the exports of the modules loaded into the process captured by the
+/// given minidump, which the IATs of the modules point into.
+///
+/// This is built once per dump, since each module is reconstructed and parsed.
+fn get_minidump_exports(dump: &crate::loader::minidump::Minidump) -> BTreeMap<VA, Import> {
+    let mut exports: BTreeMap<VA, Import> = Default::default();
+    for module in dump.modules.iter() {
+        match dump
+            .load_pe(module)
+            .and_then(|pe| crate::analysis::pe::exports::get_pe_exported_symbols(&pe))
+        {
+            Ok(symbols) => exports.extend(symbols),
+            Err(e) => debug!("workspace: minidump: {}: failed to read exports: {:?}", module.name, e),
+        }
+    }
+    debug!("workspace: minidump: {} process exports", exports.len());
+
+    exports
+}
+
+/// `exports` are the exports of the process, from `get_minidump_exports`.
+fn workspace_from_minidump_module(
+    config: Box<dyn config::Configuration>,
+    dump: &crate::loader::minidump::Minidump,
+    module: &crate::loader::minidump::MinidumpModule,
+    exports: &BTreeMap<VA, Import>,
+) -> Result<PEWorkspace> {
+    let pe = dump.load_pe(module)?;
+
+    // threads were executing here, so it's code, though not necessarily the start of a function.
+    let hints = dump
+        .threads
+        .iter()
+        .filter_map(|thread| thread.pc)
+        .filter(|&pc| pe.module.probe_va(pc, crate::module::Permissions::X))
+        .collect::<Vec<VA>>();
+    debug!("workspace: minidump: {}: {} thread hints", module.name, hints.len());
+
+    // the exports of the other modules of the process, which the IAT may point into.
+    let image = module.base_address..module.base_address.saturating_add(module.size);
+    let exports = exports
+        .iter()
+        .filter(|(va, _)| image.contains(va).not())
+        .map(|(&va, import)| (va, import.clone()))
+        .collect::<BTreeMap<VA, Import>>();
+    debug!("workspace: minidump: {}: {} process exports", module.name, exports.len());
+
+    let config = config::with_function_hints(config, &hints);
+    PEWorkspace::from_pe(config::with_process_exports(config, exports), pe)
+}
+
+/// Analyze the program captured by the given Linux core dump,
+/// mapping the memory backed by the executable,
+/// or, if it can't be found, by the file the crashing thread was executing.
+///
+/// Code is often missing from core dumps, since only the first page of each
+/// file-backed mapping is dumped by default, so there's no file format analysis;
+/// only the entry point and the program counters of the threads are followed.
+fn workspace_from_elf_core(
+    config: Box<dyn config::Configuration>,
+    core: &crate::loader::elf::coredump::ELFCore,
+) -> Result<ShellcodeWorkspace> {
+    let pc = core.crashing_thread().and_then(|thread| thread.pc());
+    let file = core.executable().or_else(|| {
+        pc.and_then(|pc| core.mapping_by_address(pc))
+            .and_then(|mapping| mapping.file.as_deref())
+    });
+    let Some(file) = file else {
+        return Err(WorkspaceError::FormatNotSupported {
+            source: anyhow::anyhow!("core dump: executable not found"),
+        }
+        .into());
+    };
+    let module = core.load_file(file)?;
+
+    // threads were executing here, so it's code, though not necessarily the start of a function.
+    let entry_points = core
+        .entry_point()
+        .into_iter()
+        .chain(core.threads.iter().filter_map(|thread| thread.pc()))
+        .filter(|&va| module.probe_va(va, crate::module::Permissions::X))
+        .collect::<Vec<VA>>();
+    debug!("workspace: core: {}: {} entry points", file, entry_points.len());
+
+    ShellcodeWorkspace::from_module(config, module, &entry_points)
+}
+
+/// Create a workspace for each module loaded into the process captured by the
+/// given Windows minidump. Each module image is reconstructed from memory as
+/// a PE at its real base address, and the program counters of threads within
+/// the image are used as function hints.
+///
+/// Each item is the module path along with the result of loading it,
+/// since some images may be only partially captured.
+/// Workspaces are created lazily, as the iterator is consumed.
+pub fn workspaces_from_minidump(
+    config: Box<dyn config::Configuration>,
+    buf: &[u8],
+) -> Result<impl Iterator<Item = (String, Result<Box<dyn Workspace>>)>> {
+    let dump = crate::loader::minidump::Minidump::from_bytes(buf)?;
+    let exports = get_minidump_exports(&dump);
+
+    Ok((0..dump.modules.len()).map(move |i| {
+        let module = &dump.modules[i];
+        let ws = workspace_from_minidump_module(config.clone(), &dump, module, &exports)
+            .map(|ws| Box::new(ws) as Box<dyn Workspace>);
+        (module.name.clone(), ws)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsrc::*;
+
+    #[test]
+    fn nop() -> Result<()> {
+        //crate::test::init_logging();
+
+        let buf = get_buf(Rsrc::NOP);
+        let pe = crate::loader::pe::PE::from_bytes(&buf)?;
+        let config = get_config();
+
+        let ws = PEWorkspace::from_pe(config, pe)?;
+
+        // entry point
+        assert!(ws.analysis.functions.contains_key(&0x401081));
+
+        // main
+        assert!(ws.analysis.functions.contains_key(&0x401000));
+
+        assert!(ws.analysis.imports.contains_key(&0x40600C));
+        assert!(ws
+            .analysis
+            .names
+            .contains_name(&String::from("kernel32.dll!ExitProcess")));
+        assert!(ws.analysis.names.contains_address(0x40600C));
+
+        // 0x401C4E: void __cdecl __noreturn __crtExitProcess(UINT uExitCode)
+        assert!(ws.analysis.functions[&0x401C4E].flags.intersects(FunctionFlags::NORET));
+
+        // ```
+        //     .text:00405F42  ; void __stdcall RtlUnwind(PVOID TargetFrame, PVOID TargetIp, PEXCEPTION_RECORD ExceptionRecord, PVOID ReturnValue)
+        //     .text:00405F42  RtlUnwind       proc near               ; CODE XREF: __global_unwind2+13↑p
+        //     .text:00405F42
+        //     .text:00405F42  TargetFrame     = dword ptr  4
+        //     .text:00405F42  TargetIp        = dword ptr  8
+        //     .text:00405F42  ExceptionRecord = dword ptr  0Ch
+        //     .text:00405F42  ReturnValue     = dword ptr  10h
+        //     .text:00405F42
+        //     .text:00405F42 000 FF 25 7C 60 40 00  jmp     ds:__imp_RtlUnwind
+        //     .text:00405F42  RtlUnwind       endp
+        // ```
+        assert!(ws.analysis.functions[&0x405F42].flags.intersects(FunctionFlags::THUNK));
+
+        // via FLIRT 0x401da9: _exit
+        assert!(ws.analysis.functions.contains_key(&0x401da9));
+        assert!(ws.analysis.names.contains_name(&String::from("_exit")));
+        assert!(ws.analysis.functions[&0x401da9].flags.intersects(FunctionFlags::NORET));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pe() -> Result<()> {
+        let buf = get_buf(Rsrc::NOP);
+        let config = get_config();
+        let ws = workspace_from_bytes(config, &buf)?;
+
+        assert!(ws.analysis().functions.contains_key(&0x401081));
+
+        Ok(())
     }
 
     #[test]
@@ -762,6 +1683,293 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn macho() -> Result<()> {
+        use crate::loader::macho::tests::*;
+
+        let buf = build_fat(&build_macho());
+        let config = get_config();
+        let ws = workspace_from_bytes(config, &buf)?;
+
+        // LC_MAIN
+        assert!(ws.analysis().functions.contains_key(&(TEXT_VMADDR + MAIN_OFFSET)));
+        // LC_FUNCTION_STARTS
+        assert!(ws.analysis().functions.contains_key(&(TEXT_VMADDR + HELPER_OFFSET)));
+
+        // __stubs
+        let stub = TEXT_VMADDR + STUB_OFFSET;
+        assert!(ws.analysis().functions[&stub].flags.intersects(FunctionFlags::THUNK));
+        assert!(ws.analysis().functions[&stub].flags.intersects(FunctionFlags::NORET));
+        assert_eq!(ws.analysis().names.addresses_by_name.get("_exit").unwrap(), &stub);
+
+        // __la_symbol_ptr
+        assert_eq!(
+            ws.analysis()
+                .names
+                .addresses_by_name
+                .get("libSystem.B.dylib!_exit")
+                .unwrap(),
+            &(TEXT_VMADDR + LAZY_POINTER_OFFSET)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn elf_object() -> Result<()> {
+        use crate::loader::elf::relocatable::tests::*;
+
+        let buf = build_elf_object();
+        let config = get_config();
+        let ws = workspace_from_bytes(config, &buf)?;
+
+        assert!(ws.analysis().functions.contains_key(&TEXT_ADDRESS));
+        assert!(ws.analysis().functions.contains_key(&(TEXT_ADDRESS + HELPER_OFFSET)));
+        assert_eq!(ws.analysis().names.addresses_by_name.get("main").unwrap(), &TEXT_ADDRESS);
+        assert_eq!(ws.analysis().externs.get(&EXTERN_ADDRESS).unwrap(), "puts");
+
+        Ok(())
+    }
+
+    #[test]
+    fn archive() -> Result<()> {
+        use crate::loader::{ar::tests::*, elf::relocatable::tests::*};
+
+        let obj = build_elf_object();
+        let import = build_import_member("KERNEL32.dll", "CreateFileW");
+        let buf = build_archive(&[("a.o", &obj), ("KERNEL32.dll", &import), ("b.o", &obj)]);
+
+        assert!(workspace_from_bytes(get_config(), &buf).is_err());
+
+        let workspaces = workspaces_from_archive(get_config(), &buf)?.collect::<Vec<_>>();
+        let names = workspaces.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec!["a.o", "b.o"], names);
+
+        let ws = workspaces[1].1.as_ref().unwrap();
+        assert!(ws.analysis().functions.contains_key(&TEXT_ADDRESS));
+
+        Ok(())
+    }
+
+    #[test]
+    fn minidump() -> Result<()> {
+        use crate::loader::minidump::tests::*;
+
+        let buf = build_minidump();
+        let ws = workspace_from_bytes(get_config(), &buf)?;
+
+        assert_eq!(MODULE_BASE, ws.module().address_space.base_address);
+        // entry point
+        assert!(ws.analysis().functions.contains_key(&(MODULE_BASE + ENTRY_RVA)));
+        // call target, also the thread's program counter
+        assert!(ws.analysis().functions.contains_key(&(MODULE_BASE + THREAD_PC_RVA)));
+
+        let workspaces = workspaces_from_minidump(get_config(), &buf)?.collect::<Vec<_>>();
+        assert_eq!(1, workspaces.len());
+        assert_eq!("C:\\test.exe", workspaces[0].0);
+        assert!(workspaces[0].1.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn elf_core() -> Result<()> {
+        use crate::loader::elf::coredump::tests::*;
+
+        let buf = build_elf_core();
+        let ws = workspace_from_bytes(get_config(), &buf)?;
+
+        // the mappings of the executable.
+        assert_eq!(TEXT_ADDRESS, ws.module().address_space.base_address);
+        assert_eq!(EXECUTABLE, ws.module().sections[0].name);
+        // entry point
+        assert!(ws.analysis().functions.contains_key(&TEXT_ADDRESS));
+        // the crashing thread's program counter
+        assert!(ws.cfg().insns.insns_by_address.contains_key(&(TEXT_ADDRESS + CRASH_OFFSET)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shellcode() -> Result<()> {
+        // 0x0: call 0x10
+        // 0x5: ret
+        // 0x10: ret
+        let mut buf = vec![0xCCu8; 0x20];
+        buf[0x0..0x6].copy_from_slice(&[0xE8, 0x0B, 0x00, 0x00, 0x00, 0xC3]);
+        buf[0x10] = 0xC3;
+
+        let ws = workspace_from_shellcode(get_config(), &buf, Arch::X64, 0x1000, &[0x0])?;
+
+        assert!(ws.analysis().functions.contains_key(&0x1000));
+        assert!(ws.analysis().functions.contains_key(&0x1010));
+        assert_eq!(ws.analysis().names.addresses_by_name.get("sub_1010").unwrap(), &0x1010);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shellcode_overflow() -> Result<()> {
+        let buf = [0xC3u8];
+
+        assert!(workspace_from_shellcode(get_config(), &buf, Arch::X64, 0xFFFF_FFFF_FFFF_F000, &[0x0]).is_err());
+        assert!(workspace_from_shellcode(get_config(), &buf, Arch::X64, 0x1000, &[u64::MAX]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn base_address() -> Result<()> {
+        use crate::loader::elf::tests::*;
+
+        const BASE_ADDRESS: u64 = 0x5555_5555_4000;
+
+        let buf = build_pie();
+        let config = config::with_base_address(get_config(), BASE_ADDRESS);
+        let ws = workspace_from_bytes(config, &buf)?;
+
+        assert_eq!(BASE_ADDRESS, ws.module().address_space.base_address);
+        assert!(ws.analysis().functions.contains_key(&(BASE_ADDRESS + PIE_ENTRY)));
+        assert_eq!(
+            ws.analysis().names.addresses_by_name.get("<entry_point>").unwrap(),
+            &(BASE_ADDRESS + PIE_ENTRY)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn mapped_image() -> Result<()> {
+        use crate::loader::pe::tests::*;
+
+        const BASE_ADDRESS: u64 = 0x7FF6_0000_0000;
+
+        let buf = build_mapped_pe();
+        let config = config::with_base_address(config::with_mapped_image(get_config()), BASE_ADDRESS);
+        let ws = workspace_from_bytes(config, &buf)?;
+
+        assert_eq!(BASE_ADDRESS, ws.module().address_space.base_address);
+        assert!(ws.analysis().functions.contains_key(&(BASE_ADDRESS + ENTRY_RVA)));
+
+        // the import names have been wiped, so the IAT is resolved via the process exports.
+        let mut exports: BTreeMap<VA, Import> = Default::default();
+        exports.insert(
+            RESOLVED_IMPORT,
+            Import {
+                address: RESOLVED_IMPORT,
+                dll:     "kernel32.dll".to_string(),
+                symbol:  ImportedSymbol::Name("ExitProcess".to_string()),
+            },
+        );
+        let config = config::with_process_exports(config::with_mapped_image(get_config()), exports);
+        let ws = workspace_from_bytes(config, &buf)?;
+        assert_eq!(
+            ws.analysis().names.addresses_by_name.get("kernel32.dll!ExitProcess").unwrap(),
+            &(IMAGE_BASE + IAT_RVA)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn elf_flirt() -> Result<()> {
+        use crate::loader::elf::tests::*;
+
+        // 0x100: call 0x110
+        // 0x105: ret
+        // 0x110: xor edi, edi; mov eax, 60; syscall; ret
+        let mut buf = build_pie();
+        buf[0x100..0x106].copy_from_slice(&[0xE8, 0x0B, 0x00, 0x00, 0x00, 0xC3]);
+        buf[0x110..0x11A].copy_from_slice(&[0x31, 0xFF, 0xB8, 0x3C, 0x00, 0x00, 0x00, 0x0F, 0x05, 0xC3]);
+
+        let path = std::env::temp_dir().join(format!("lancelot-elf-flirt-{}.pat", std::process::id()));
+        std::fs::write(&path, "31FFB83C0000000F05C3 00 0000 000A :0000 exit\n---\n")?;
+        let config = config::DynamicConfiguration::default().with_sig_path(&path);
+        let elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        let ws = ELFWorkspace::from_elf(Box::new(config), elf);
+        std::fs::remove_file(&path)?;
+        let ws = ws?;
+
+        assert_eq!(ws.analysis.names.addresses_by_name.get("exit").unwrap(), &0x110);
+        assert!(ws.analysis.functions[&0x110].flags.intersects(FunctionFlags::NORET));
+        // the entry point always calls exit, so never returns, either.
+        assert!(ws.analysis.functions[&PIE_ENTRY].flags.intersects(FunctionFlags::NORET));
+
+        Ok(())
+    }
+
+    #[test]
+    fn elf_got_references() -> Result<()> {
+        use crate::analysis::elf::plt::tests::*;
+
+        let buf = build_pie32_with_got_references();
+        let elf = crate::loader::elf::ELF::from_bytes(&buf)?;
+        let ws = ELFWorkspace::from_elf(get_config(), elf)?;
+
+        assert_eq!(ws.analysis.names.addresses_by_name.get("__x86.get_pc_thunk.bx").unwrap(), &0x120);
+
+        // 0x10C: call [ebx+0xC]
+        let import = &ws.analysis.import_references[&PIE32_GOT_REFERENCE];
+        assert_eq!(import.dll, "libc.so.6");
+        assert_eq!(import.symbol, ImportedSymbol::Name("puts".to_string()));
+
+        let fmt = formatter::Formatter::with_options()
+            .with_colors(false)
+            .with_hex_column_size(0)
+            .build();
+        let insn = crate::test::read_insn(&ws.elf.module, PIE32_GOT_REFERENCE);
+        assert!(fmt
+            .format_instruction(&ws, &insn, PIE32_GOT_REFERENCE)?
+            .ends_with("call    [ebx+libc.so.6!puts]"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn forwarded_exports() -> Result<()> {
+        use crate::loader::pe::tests::IMAGE_BASE;
+
+        let buf = crate::analysis::pe::exports::tests::build_pe_with_forwarders();
+        let pe = crate::loader::pe::PE::from_mapped_bytes(&buf, None)?;
+        let ws = PEWorkspace::from_pe(config::empty(), pe)?;
+
+        // the forwarder to the API set contract is named by the DLL that implements it.
+        assert_eq!(
+            "kernelbase.dll!WaitOnAddress",
+            ws.analysis.names.names_by_address[&(IMAGE_BASE + 0x12C0)]
+        );
+        assert_eq!(
+            "example.dll!#2",
+            ws.analysis.names.names_by_address[&(IMAGE_BASE + 0x12F0)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pdb() -> Result<()> {
+        use crate::loader::{
+            pdb::tests::build_pdb,
+            pe::{debug::tests::build_mapped_pe_with_codeview, tests::*},
+        };
+
+        let directory = std::env::temp_dir().join(format!("lancelot-pdb-{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        std::fs::write(directory.join("test.pdb"), build_pdb())?;
+
+        let config = config::DynamicConfiguration::default().with_pdb_path(&directory);
+        let pe = PE::from_mapped_bytes(&build_mapped_pe_with_codeview(), None)?;
+        let ws = PEWorkspace::from_pe(Box::new(config), pe);
+        std::fs::remove_dir_all(&directory)?;
+        let ws = ws?;
+
+        assert!(ws.analysis.functions.contains_key(&(IMAGE_BASE + HELPER_RVA)));
+        assert_eq!("helper", ws.analysis.names.names_by_address[&(IMAGE_BASE + HELPER_RVA)]);
+        assert_eq!("g_counter", ws.analysis.names.names_by_address[&(IMAGE_BASE + 0x1080)]);
+
+        Ok(())
+    }
+
     #[test]
     fn ws_thunks() -> Result<()> {
         //crate::test::init_logging();
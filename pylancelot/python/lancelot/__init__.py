@@ -1,14 +1,101 @@
+from typing import List, Optional
+
 from ._lib import binexport2_from_bytes as _binexport2_bytes_from_bytes
+from ._lib import binexport2_from_shellcode as _binexport2_bytes_from_shellcode
+from ._lib import get_fingerprints as _get_fingerprints
+from ._lib import get_pe_resources as _get_pe_resources
+from ._lib import get_pe_signatures as _get_pe_signatures
 from .be2utils.binexport2_pb2 import BinExport2
 
 
-def get_binexport2_bytes_from_bytes(buf: bytes, sig_paths=None, function_hints=None) -> bytes:
+def get_binexport2_bytes_from_bytes(
+    buf: bytes,
+    sig_paths=None,
+    function_hints=None,
+    base_address=None,
+    mapped=False,
+    pdb_paths=None,
+    ordinal_paths=None,
+) -> bytes:
     """Get the Lancelot workspace as a BinExport2-encoded buffer"""
-    return _binexport2_bytes_from_bytes(buf, sig_paths=sig_paths, function_hints=function_hints)
+    return _binexport2_bytes_from_bytes(
+        buf,
+        sig_paths=sig_paths,
+        function_hints=function_hints,
+        base_address=base_address,
+        mapped=mapped,
+        pdb_paths=pdb_paths,
+        ordinal_paths=ordinal_paths,
+    )
 
 
-def get_binexport2_from_bytes(buf: bytes, sig_paths=None, function_hints=None) -> BinExport2:
+def get_binexport2_from_bytes(
+    buf: bytes,
+    sig_paths=None,
+    function_hints=None,
+    base_address=None,
+    mapped=False,
+    pdb_paths=None,
+    ordinal_paths=None,
+) -> BinExport2:
     """Get the Lancelot workspace as a BinExport2 instance"""
     be2: BinExport2 = BinExport2()
-    be2.ParseFromString(get_binexport2_bytes_from_bytes(buf, sig_paths=sig_paths, function_hints=function_hints))
+    be2.ParseFromString(
+        get_binexport2_bytes_from_bytes(
+            buf,
+            sig_paths=sig_paths,
+            function_hints=function_hints,
+            base_address=base_address,
+            mapped=mapped,
+            pdb_paths=pdb_paths,
+            ordinal_paths=ordinal_paths,
+        )
+    )
+    return be2
+
+
+def get_binexport2_bytes_from_shellcode(
+    buf: bytes, arch: str, base_address=0, entry_points=None, sig_paths=None, function_hints=None
+) -> bytes:
+    """Get the Lancelot workspace for raw code (arch: "x32" or "x64") as a BinExport2-encoded buffer"""
+    return _binexport2_bytes_from_shellcode(
+        buf,
+        arch,
+        base_address=base_address,
+        entry_points=entry_points,
+        sig_paths=sig_paths,
+        function_hints=function_hints,
+    )
+
+
+def get_binexport2_from_shellcode(
+    buf: bytes, arch: str, base_address=0, entry_points=None, sig_paths=None, function_hints=None
+) -> BinExport2:
+    """Get the Lancelot workspace for raw code (arch: "x32" or "x64") as a BinExport2 instance"""
+    be2: BinExport2 = BinExport2()
+    be2.ParseFromString(
+        get_binexport2_bytes_from_shellcode(
+            buf,
+            arch,
+            base_address=base_address,
+            entry_points=entry_points,
+            sig_paths=sig_paths,
+            function_hints=function_hints,
+        )
+    )
     return be2
+
+
+def get_pe_resources(buf: bytes) -> Optional[dict]:
+    """Get the version info, manifest, and string table resources of a PE file"""
+    return _get_pe_resources(buf)
+
+
+def get_pe_signatures(buf: bytes) -> List[dict]:
+    """Get the Authenticode signatures of a PE file, and whether their digests match it"""
+    return _get_pe_signatures(buf)
+
+
+def get_fingerprints(buf: bytes) -> dict:
+    """Get the imphash, export hash, and Rich hash of a PE or ELF file"""
+    return _get_fingerprints(buf)
@@ -3,7 +3,13 @@
 #![allow(clippy::useless_conversion)] // something to do with PyErr conversion, try to remove again eventually
 
 use ::lancelot::{
-    loader::{coff::COFFError, pe::PEError},
+    analysis::fingerprints::Fingerprints,
+    arch::Arch,
+    loader::{
+        coff::COFFError,
+        pe::{rsrc::Resources, PEError, PE},
+        shellcode::ShellcodeError,
+    },
     module::ModuleError,
     pagemap::PageMapError,
     util::UtilError,
@@ -26,6 +32,7 @@ fn to_py_err(e: Error) -> PyErr {
     #[allow(clippy::single_match)]
     match e.downcast_ref::<WorkspaceError>() {
         Some(WorkspaceError::BufferTooSmall) => return to_value_error(e),
+        Some(WorkspaceError::Archive) => return to_value_error(e),
         Some(WorkspaceError::FormatNotSupported { source: _ }) => return to_value_error(e),
         None => (),
     };
@@ -36,6 +43,12 @@ fn to_py_err(e: Error) -> PyErr {
         None => (),
     };
 
+    #[allow(clippy::single_match)]
+    match e.downcast_ref::<ShellcodeError>() {
+        Some(ShellcodeError::FormatNotSupported(_)) => return to_value_error(e),
+        None => (),
+    };
+
     match e.downcast_ref::<COFFError>() {
         Some(COFFError::FormatNotSupported(_)) => return to_value_error(e),
         Some(COFFError::MalformedCOFFFile(_)) => return to_value_error(e),
@@ -63,6 +76,43 @@ fn to_py_err(e: Error) -> PyErr {
     to_value_error(e)
 }
 
+fn get_config(
+    sig_paths: Option<Vec<String>>,
+    function_hints: Option<Vec<u64>>,
+    base_address: Option<u64>,
+    mapped: bool,
+    pdb_paths: Option<Vec<String>>,
+    ordinal_paths: Option<Vec<String>>,
+) -> Box<dyn Configuration> {
+    let mut config: DynamicConfiguration = Default::default();
+    if let Some(sig_paths) = sig_paths {
+        let sig_paths: Vec<_> = sig_paths.iter().map(PathBuf::from).collect();
+        config = config.with_sig_paths(&sig_paths);
+    }
+
+    if let Some(function_hints) = function_hints {
+        config = config.with_function_hints(&function_hints);
+    }
+
+    if let Some(base_address) = base_address {
+        config = config.with_base_address(base_address);
+    }
+
+    if mapped {
+        config = config.with_mapped_image();
+    }
+
+    for pdb_path in pdb_paths.unwrap_or_default() {
+        config = config.with_pdb_path(&PathBuf::from(pdb_path));
+    }
+
+    for ordinal_path in ordinal_paths.unwrap_or_default() {
+        config = config.with_ordinal_path(&PathBuf::from(ordinal_path));
+    }
+
+    Box::new(config)
+}
+
 /// analyze the given bytes with Lancelot and emit a BinExport2 protobuf.
 ///
 /// Args:
@@ -70,36 +120,225 @@ fn to_py_err(e: Error) -> PyErr {
 ///   executable_id (Optional[str]): name of the file, if known
 ///   sig_paths (Optional[list[str]]): paths to FLIRT signature files
 ///   function_hints (Optional[list[int]]): known function virtual addresses
+///   base_address (Optional[int]): the address at which to load a PE or ELF
+///     file, like where it was found at runtime, applying relocations
+///   mapped (bool): the buffer is a PE file in its memory layout, like one
+///     dumped from a process, rather than its file layout
+///   pdb_paths (Optional[list[str]]): PDB files, or directories to search, for
+///     the symbols of a PE file
+///   ordinal_paths (Optional[list[str]]): reference DLLs, map files with lines
+///     like "ws2_32.dll,115,WSAStartup", or directories of them, used to name
+///     the symbols that a PE file imports by ordinal
 ///
 /// Returns: bytes
 #[pyfunction]
-#[pyo3(signature = (buf, executable_id=None, sig_paths=None, function_hints=None))]
+#[pyo3(signature = (buf, executable_id=None, sig_paths=None, function_hints=None, base_address=None, mapped=false, pdb_paths=None, ordinal_paths=None))]
+#[allow(clippy::too_many_arguments)]
 pub fn binexport2_from_bytes(
     py: Python,
     buf: &Bound<'_, PyBytes>,
     executable_id: Option<String>,
     sig_paths: Option<Vec<String>>,
     function_hints: Option<Vec<u64>>,
+    base_address: Option<u64>,
+    mapped: bool,
+    pdb_paths: Option<Vec<String>>,
+    ordinal_paths: Option<Vec<String>>,
 ) -> PyResult<Py<PyBytes>> {
-    let mut config: DynamicConfiguration = Default::default();
-    if let Some(sig_paths) = sig_paths {
-        let sig_paths: Vec<_> = sig_paths.iter().map(PathBuf::from).collect();
-        config = config.with_sig_paths(&sig_paths);
-    }
+    let config = get_config(
+        sig_paths,
+        function_hints,
+        base_address,
+        mapped,
+        pdb_paths,
+        ordinal_paths,
+    );
 
-    if let Some(function_hints) = function_hints {
-        config = config.with_function_hints(&function_hints);
-    }
+    let ws = ::lancelot::workspace::workspace_from_bytes(config, buf.as_bytes()).map_err(to_py_err)?;
+    let hash = sha256::digest(buf.as_bytes());
+    export_workspace_to_binexport2(&*ws, hash, executable_id)
+        .map(|buf| PyBytes::new_bound(py, &buf).into())
+        .map_err(to_py_err)
+}
+
+/// analyze the given raw code, like shellcode, with Lancelot and emit a
+/// BinExport2 protobuf.
+///
+/// Args:
+///   buf (bytes): the raw code
+///   arch (str): the architecture of the code, either "x32" or "x64"
+///   base_address (int): the address at which to load the code, page aligned
+///   entry_points (Optional[list[int]]): offsets of known functions
+///   executable_id (Optional[str]): name of the file, if known
+///   sig_paths (Optional[list[str]]): paths to FLIRT signature files
+///   function_hints (Optional[list[int]]): known function virtual addresses
+///
+/// Returns: bytes
+#[pyfunction]
+#[pyo3(signature = (buf, arch, base_address=0, entry_points=None, executable_id=None, sig_paths=None, function_hints=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn binexport2_from_shellcode(
+    py: Python,
+    buf: &Bound<'_, PyBytes>,
+    arch: &str,
+    base_address: u64,
+    entry_points: Option<Vec<u64>>,
+    executable_id: Option<String>,
+    sig_paths: Option<Vec<String>>,
+    function_hints: Option<Vec<u64>>,
+) -> PyResult<Py<PyBytes>> {
+    let arch = match arch {
+        "x32" => Arch::X32,
+        "x64" => Arch::X64,
+        _ => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "unsupported architecture: {arch}"
+            )))
+        }
+    };
 
-    let config = config.clone();
+    let config = get_config(sig_paths, function_hints, None, false, None, None);
 
-    let ws = ::lancelot::workspace::workspace_from_bytes(config, buf.as_bytes()).map_err(to_py_err)?;
+    let ws = ::lancelot::workspace::workspace_from_shellcode(
+        config,
+        buf.as_bytes(),
+        arch,
+        base_address,
+        &entry_points.unwrap_or_default(),
+    )
+    .map_err(to_py_err)?;
     let hash = sha256::digest(buf.as_bytes());
     export_workspace_to_binexport2(&*ws, hash, executable_id)
         .map(|buf| PyBytes::new_bound(py, &buf).into())
         .map_err(to_py_err)
 }
 
+/// parse the resources of the given PE file that are commonly needed for
+/// triage: version info, application manifest, and string table.
+///
+/// Args:
+///   buf (bytes): the raw bytes of a PE file
+///
+/// Returns: Optional[dict], with keys:
+///   version_info (Optional[dict]): file_version, product_version,
+///     company_name, product_name, original_filename, and strings, the
+///     string tables by language and codepage, like "040904B0"
+///   manifest (Optional[dict]): xml, requested_execution_level, ui_access
+///   strings (dict[int, str]): the RT_STRING entries by ID
+#[pyfunction]
+pub fn get_pe_resources(py: Python, buf: &Bound<'_, PyBytes>) -> PyResult<PyObject> {
+    let pe = PE::from_bytes(buf.as_bytes()).map_err(to_py_err)?;
+    let Some(resources) = Resources::from_pe(&pe).map_err(to_py_err)? else {
+        return Ok(py.None());
+    };
+
+    let version_info = match &resources.version_info {
+        None => py.None(),
+        Some(version_info) => {
+            let d = PyDict::new_bound(py);
+            let fixed = version_info.fixed.as_ref();
+            d.set_item("file_version", version_info.file_version())?;
+            d.set_item("product_version", fixed.map(|fixed| fixed.product_version.to_string()))?;
+            d.set_item("company_name", version_info.company_name())?;
+            d.set_item("product_name", version_info.product_name())?;
+            d.set_item("original_filename", version_info.original_filename())?;
+            d.set_item("strings", &version_info.strings)?;
+            d.into()
+        }
+    };
+
+    let manifest = match &resources.manifest {
+        None => py.None(),
+        Some(manifest) => {
+            let d = PyDict::new_bound(py);
+            d.set_item("xml", &manifest.xml)?;
+            d.set_item("requested_execution_level", manifest.requested_execution_level())?;
+            d.set_item("ui_access", manifest.ui_access())?;
+            d.into()
+        }
+    };
+
+    let d = PyDict::new_bound(py);
+    d.set_item("version_info", version_info)?;
+    d.set_item("manifest", manifest)?;
+    d.set_item("strings", &resources.strings)?;
+    Ok(d.into())
+}
+
+/// parse the Authenticode signatures of the given PE file, including nested
+/// signatures, and check the signed digests against the file.
+/// certificate chains are not validated.
+///
+/// Args:
+///   buf (bytes): the raw bytes of a PE file
+///
+/// Returns: list[dict], with keys:
+///   digest_algorithm (str): like "sha256"
+///   digest (str): the signed Authenticode hash, as hex
+///   digest_matches (Optional[bool]): whether the digest matches the file,
+///     or None when the digest algorithm isn't supported
+///   signers (list[dict]): issuer, serial_number, subject, digest_algorithm,
+///     and signing_time, in RFC 3339 format
+///   certificates (list[dict]): subject, issuer, serial_number
+#[pyfunction]
+pub fn get_pe_signatures(py: Python, buf: &Bound<'_, PyBytes>) -> PyResult<PyObject> {
+    let pe = PE::from_bytes(buf.as_bytes()).map_err(to_py_err)?;
+
+    let signatures = PyList::empty_bound(py);
+    for signature in pe.signatures().map_err(to_py_err)?.iter() {
+        let signers = PyList::empty_bound(py);
+        for signer in signature.signers.iter() {
+            let d = PyDict::new_bound(py);
+            d.set_item("issuer", &signer.issuer)?;
+            d.set_item("serial_number", &signer.serial_number)?;
+            d.set_item("subject", &signer.subject)?;
+            d.set_item("digest_algorithm", signer.digest_algorithm.to_string())?;
+            d.set_item("signing_time", signer.signing_time.map(|time| time.to_rfc3339()))?;
+            signers.append(d)?;
+        }
+
+        let certificates = PyList::empty_bound(py);
+        for certificate in signature.certificates.iter() {
+            let d = PyDict::new_bound(py);
+            d.set_item("subject", &certificate.subject)?;
+            d.set_item("issuer", &certificate.issuer)?;
+            d.set_item("serial_number", &certificate.serial_number)?;
+            certificates.append(d)?;
+        }
+
+        let d = PyDict::new_bound(py);
+        d.set_item("digest_algorithm", signature.digest_algorithm.to_string())?;
+        d.set_item(
+            "digest",
+            signature.digest.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+        )?;
+        d.set_item("digest_matches", signature.verify_digest(&pe).map_err(to_py_err)?)?;
+        d.set_item("signers", signers)?;
+        d.set_item("certificates", certificates)?;
+        signatures.append(d)?;
+    }
+
+    Ok(signatures.into())
+}
+
+/// Compute the fingerprints of the given PE or ELF file.
+///
+/// Returns a dict with the following keys, each an MD5 hex digest or None:
+///   imphash: for PE files, compatible with pefile;
+///     for ELF files, over the imported functions, selected like telfhash
+///   export_hash: over the names of the exported symbols
+///   rich_hash: over the decoded Rich header of a PE file
+#[pyfunction]
+pub fn get_fingerprints(py: Python, buf: &Bound<'_, PyBytes>) -> PyResult<PyObject> {
+    let fingerprints = Fingerprints::from_bytes(buf.as_bytes()).map_err(to_py_err)?;
+
+    let d = PyDict::new_bound(py);
+    d.set_item("imphash", fingerprints.imphash)?;
+    d.set_item("export_hash", fingerprints.export_hash)?;
+    d.set_item("rich_hash", fingerprints.rich_hash)?;
+    Ok(d.into())
+}
+
 #[pymodule(name = "_lib")]
 fn lancelot(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     pyo3_log::init();
@@ -107,6 +346,10 @@ fn lancelot(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     // note that these are re-exported by:
     // pylancelot/python/lancelot/__init__.py
     m.add_function(wrap_pyfunction!(binexport2_from_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(binexport2_from_shellcode, m)?)?;
+    m.add_function(wrap_pyfunction!(get_pe_resources, m)?)?;
+    m.add_function(wrap_pyfunction!(get_pe_signatures, m)?)?;
+    m.add_function(wrap_pyfunction!(get_fingerprints, m)?)?;
 
     Ok(())
 }
@@ -34,3 +34,42 @@ def test_hint_function(k32):
     # 7dd70e00  8bff               mov     edi, edi
     # 7dd70e02  55                 push    ebp {__saved_ebp}
     lancelot.get_binexport2_bytes_from_bytes(k32, function_hints=[0x7DD70E02])
+
+
+def test_load_shellcode():
+    # 0x1000: call 0x1010
+    # 0x1005: ret
+    # 0x1010: ret
+    buf = b"\xE8\x0B\x00\x00\x00\xC3" + b"\xCC" * 10 + b"\xC3"
+    be2 = lancelot.get_binexport2_from_shellcode(buf, "x64", base_address=0x1000, entry_points=[0x0])
+    assert len(be2.flow_graph) == 2
+
+    with pytest.raises(ValueError):
+        lancelot.get_binexport2_bytes_from_shellcode(buf, "arm")
+
+    with pytest.raises(ValueError):
+        # base address must be page aligned
+        lancelot.get_binexport2_bytes_from_shellcode(buf, "x64", base_address=0x1001)
+
+
+def test_pe_resources(k32):
+    resources = lancelot.get_pe_resources(k32)
+    assert resources is not None
+
+    version_info = resources["version_info"]
+    assert version_info["company_name"] == "Microsoft Corporation"
+    assert version_info["original_filename"].lower().startswith("kernel32")
+
+
+def test_pe_signatures(k32):
+    # the file is unmodified, so any embedded signature must match it.
+    for signature in lancelot.get_pe_signatures(k32):
+        assert signature["digest_matches"] in (True, None)
+        for signer in signature["signers"]:
+            assert signer["issuer"]
+
+
+def test_fingerprints(k32):
+    fingerprints = lancelot.get_fingerprints(k32)
+    assert len(fingerprints["imphash"]) == 32
+    assert len(fingerprints["export_hash"]) == 32